@@ -1,6 +1,6 @@
 use criterion::{BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
 use dictv::index::IndexManager;
-use dictv::models::{Language, SearchMode};
+use dictv::models::{LanguagePair, SearchMode};
 use dictv::search::SearchEngine;
 use std::sync::OnceLock;
 use tempfile::TempDir;
@@ -46,7 +46,13 @@ fn bench_exact_search(c: &mut Criterion) {
     c.bench_function("exact_search_haus", |b| {
         b.iter(|| {
             engine
-                .search(black_box("Haus"), SearchMode::Exact, Language::DeEn, 2, 10)
+                .search(
+                    black_box("Haus"),
+                    SearchMode::Exact,
+                    LanguagePair::DE_EN,
+                    2,
+                    10,
+                )
                 .unwrap()
         })
     });
@@ -58,7 +64,13 @@ fn bench_fuzzy_search(c: &mut Criterion) {
     c.bench_function("fuzzy_search_hauss_distance_1", |b| {
         b.iter(|| {
             engine
-                .search(black_box("Hauss"), SearchMode::Fuzzy, Language::DeEn, 1, 10)
+                .search(
+                    black_box("Hauss"),
+                    SearchMode::Fuzzy,
+                    LanguagePair::DE_EN,
+                    1,
+                    10,
+                )
                 .unwrap()
         })
     });
@@ -66,7 +78,13 @@ fn bench_fuzzy_search(c: &mut Criterion) {
     c.bench_function("fuzzy_search_haaus_distance_2", |b| {
         b.iter(|| {
             engine
-                .search(black_box("Haaus"), SearchMode::Fuzzy, Language::DeEn, 2, 10)
+                .search(
+                    black_box("Haaus"),
+                    SearchMode::Fuzzy,
+                    LanguagePair::DE_EN,
+                    2,
+                    10,
+                )
                 .unwrap()
         })
     });
@@ -78,7 +96,13 @@ fn bench_prefix_search(c: &mut Criterion) {
     c.bench_function("prefix_search_ha", |b| {
         b.iter(|| {
             engine
-                .search(black_box("Ha"), SearchMode::Prefix, Language::DeEn, 2, 10)
+                .search(
+                    black_box("Ha"),
+                    SearchMode::Prefix,
+                    LanguagePair::DE_EN,
+                    2,
+                    10,
+                )
                 .unwrap()
         })
     });
@@ -95,7 +119,7 @@ fn bench_search_modes(c: &mut Criterion) {
             |b, &mode| {
                 b.iter(|| {
                     engine
-                        .search(black_box("Haus"), mode, Language::DeEn, 2, 10)
+                        .search(black_box("Haus"), mode, LanguagePair::DE_EN, 2, 10)
                         .unwrap()
                 })
             },
@@ -113,7 +137,13 @@ fn bench_varying_query_lengths(c: &mut Criterion) {
         group.bench_with_input(BenchmarkId::from_parameter(query), query, |b, &query| {
             b.iter(|| {
                 engine
-                    .search(black_box(query), SearchMode::Fuzzy, Language::DeEn, 2, 10)
+                    .search(
+                        black_box(query),
+                        SearchMode::Fuzzy,
+                        LanguagePair::DE_EN,
+                        2,
+                        10,
+                    )
                     .unwrap()
             })
         });
@@ -131,7 +161,7 @@ fn bench_diacritic_search(c: &mut Criterion) {
                 .search(
                     black_box("grussen"),
                     SearchMode::Fuzzy,
-                    Language::DeEn,
+                    LanguagePair::DE_EN,
                     2,
                     10,
                 )
@@ -15,6 +15,8 @@ export interface SearchResponse {
   results: SearchResult[];
   query_time_ms: number;
   total_results: number;
+  index_generation?: string; // Changes when the server's index contents change
+  snapshot?: string; // Token of the pinned index snapshot, with pin=true
 }
 
 export interface Preferences {
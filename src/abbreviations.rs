@@ -0,0 +1,141 @@
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Abbreviations common in German-English dictionary definitions
+const BUILTIN: &[(&str, &str)] = &[
+    ("Abk.", "Abkürzung"),
+    ("Am.", "American English"),
+    ("Br.", "British English"),
+    ("bzw.", "beziehungsweise"),
+    ("coll.", "colloquial"),
+    ("etw.", "etwas"),
+    ("fig.", "figurativ"),
+    ("geh.", "gehoben"),
+    ("jd.", "jemand"),
+    ("jdm.", "jemandem"),
+    ("jdn.", "jemanden"),
+    ("jds.", "jemandes"),
+    ("o.ä.", "oder ähnlich"),
+    ("österr.", "österreichisch"),
+    ("pej.", "pejorativ"),
+    ("pl.", "Plural"),
+    ("schweiz.", "schweizerisch"),
+    ("sb.", "somebody"),
+    ("sb.'s", "somebody's"),
+    ("sg.", "Singular"),
+    ("sl.", "slang"),
+    ("sth.", "something"),
+    ("techn.", "technisch"),
+    ("ugs.", "umgangssprachlich"),
+    ("usw.", "und so weiter"),
+    ("vulg.", "vulgär"),
+    ("z.B.", "zum Beispiel"),
+];
+
+/// Abbreviation glossary used to explain and expand definitions
+#[derive(Debug, Clone)]
+pub struct Abbreviations {
+    table: BTreeMap<String, String>,
+}
+
+impl Default for Abbreviations {
+    fn default() -> Self {
+        Self {
+            table: BUILTIN
+                .iter()
+                .map(|(abbreviation, expansion)| (abbreviation.to_string(), expansion.to_string()))
+                .collect(),
+        }
+    }
+}
+
+impl Abbreviations {
+    /// Add entries from a file of `abbreviation<TAB>expansion` lines, if it exists.
+    /// Entries override built-in ones with the same abbreviation.
+    pub fn with_file<P: AsRef<Path>>(mut self, path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(self);
+        }
+        let content = fs::read_to_string(path)
+            .context(format!("Failed to read abbreviations file: {:?}", path))?;
+
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (abbreviation, expansion) = line
+                .split_once('\t')
+                .context(format!("Invalid abbreviation line in {:?}: {}", path, line))?;
+            self.table.insert(
+                abbreviation.trim().to_string(),
+                expansion.trim().to_string(),
+            );
+        }
+
+        Ok(self)
+    }
+
+    /// All abbreviations and their expansions, sorted by abbreviation
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.table.iter().map(|(a, e)| (a.as_str(), e.as_str()))
+    }
+
+    /// Replace known abbreviations in `text`, keeping surrounding brackets and punctuation
+    pub fn expand(&self, text: &str) -> String {
+        text.split(' ')
+            .map(|token| {
+                let start = token.len() - token.trim_start_matches(['(', '[', '{', '<']).len();
+                let end = token
+                    .trim_end_matches([',', ';', ':', ')', ']', '}', '>'])
+                    .len();
+                if start >= end {
+                    return token.to_string();
+                }
+                match self.table.get(&token[start..end]) {
+                    Some(expansion) => {
+                        format!("{}{}{}", &token[..start], expansion, &token[end..])
+                    }
+                    None => token.to_string(),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_expand_keeps_brackets_and_punctuation() {
+        let abbreviations = Abbreviations::default();
+
+        assert_eq!(
+            abbreviations.expand("jdn. grüßen [ugs.], etw. z.B."),
+            "jemanden grüßen [umgangssprachlich], etwas zum Beispiel"
+        );
+        assert_eq!(abbreviations.expand("to greet sb."), "to greet somebody");
+        assert_eq!(abbreviations.expand("house"), "house");
+    }
+
+    #[test]
+    fn test_with_file_overrides_builtin() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "# custom glossary\nugs.\tcolloquial\nBot.\tBotanik").unwrap();
+
+        let abbreviations = Abbreviations::default().with_file(file.path()).unwrap();
+
+        assert_eq!(
+            abbreviations.expand("[ugs.] [Bot.]"),
+            "[colloquial] [Botanik]"
+        );
+        assert!(abbreviations.entries().any(|(a, _)| a == "Bot."));
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::models::QueryCount;
+
+/// Distinct queries kept before the rarest ones are dropped
+const MAX_TRACKED_QUERIES: usize = 10_000;
+
+#[derive(Debug, Clone, Copy)]
+struct QueryStats {
+    count: u64,
+    last_seen_ms: u64,
+}
+
+/// Counts searches per language and normalized query, e.g. those that
+/// returned no results
+#[derive(Debug, Default)]
+pub struct QueryCounter {
+    queries: Mutex<HashMap<(String, String), QueryStats>>,
+}
+
+impl QueryCounter {
+    /// Number of distinct queries counted
+    pub fn tracked(&self) -> usize {
+        self.queries.lock().map_or(0, |queries| queries.len())
+    }
+
+    /// Record a search for `query`
+    pub fn record(&self, language: &str, query: &str) {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return;
+        }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+
+        let Ok(mut queries) = self.queries.lock() else {
+            return;
+        };
+        let key = (language.to_string(), query);
+        if !queries.contains_key(&key)
+            && queries.len() >= MAX_TRACKED_QUERIES
+            && let Some(rarest) = queries
+                .iter()
+                .min_by_key(|(_, stats)| (stats.count, stats.last_seen_ms))
+                .map(|(key, _)| key.clone())
+        {
+            queries.remove(&rarest);
+        }
+        let stats = queries.entry(key).or_insert(QueryStats {
+            count: 0,
+            last_seen_ms: now,
+        });
+        stats.count += 1;
+        stats.last_seen_ms = now;
+    }
+
+    /// Most frequent queries, optionally for one language
+    pub fn top(&self, language: Option<&str>, limit: usize) -> Vec<QueryCount> {
+        self.ranked(language, "", limit)
+    }
+
+    /// Most frequent queries starting with `prefix`, the most recent first among
+    /// equally frequent ones
+    pub fn with_prefix(
+        &self,
+        language: Option<&str>,
+        prefix: &str,
+        limit: usize,
+    ) -> Vec<QueryCount> {
+        self.ranked(language, &prefix.trim().to_lowercase(), limit)
+    }
+
+    fn ranked(&self, language: Option<&str>, prefix: &str, limit: usize) -> Vec<QueryCount> {
+        let Ok(queries) = self.queries.lock() else {
+            return Vec::new();
+        };
+        let mut entries: Vec<QueryCount> = queries
+            .iter()
+            .filter(|((lang, query), _)| {
+                language.is_none_or(|l| l == lang) && query.starts_with(prefix)
+            })
+            .map(|((lang, query), stats)| QueryCount {
+                query: query.clone(),
+                language: lang.clone(),
+                count: stats.count,
+                last_seen_ms: stats.last_seen_ms,
+            })
+            .collect();
+        entries.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| b.last_seen_ms.cmp(&a.last_seen_ms))
+                .then_with(|| a.query.cmp(&b.query))
+        });
+        entries.truncate(limit);
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_counter_counts_normalized_queries() {
+        let tracker = QueryCounter::default();
+        tracker.record("de-en", "Hauss");
+        tracker.record("de-en", " hauss ");
+        tracker.record("de-en", "Xyz");
+        tracker.record("en-de", "hauss");
+
+        let top = tracker.top(Some("de-en"), 10);
+        assert_eq!(top.len(), 2);
+        assert_eq!((top[0].query.as_str(), top[0].count), ("hauss", 2));
+        assert_eq!(tracker.top(None, 1).len(), 1);
+    }
+
+    #[test]
+    fn test_query_counter_prefix_matches() {
+        let tracker = QueryCounter::default();
+        tracker.record("de-en", "Haus");
+        tracker.record("de-en", "Hallo");
+        tracker.record("de-en", "hallo");
+        tracker.record("de-en", "Auto");
+        tracker.record("en-de", "hat");
+
+        let matches = tracker.with_prefix(Some("de-en"), "HA", 10);
+        let queries: Vec<&str> = matches.iter().map(|q| q.query.as_str()).collect();
+        assert_eq!(queries, ["hallo", "haus"]);
+        assert_eq!(tracker.with_prefix(None, "ha", 10).len(), 3);
+    }
+}
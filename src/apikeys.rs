@@ -0,0 +1,140 @@
+use axum::http::HeaderMap;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::models::{LanguagePair, SearchOptions};
+
+/// A client key and the data it may search
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApiKey {
+    pub key: String,
+    /// Client name, used in logs
+    #[serde(default)]
+    pub name: String,
+    /// Language pairs the key may search (all when empty)
+    #[serde(default)]
+    pub languages: Vec<LanguagePair>,
+    /// Dictionaries the key may search (all when empty)
+    #[serde(default)]
+    pub sources: Vec<String>,
+    /// Searches allowed per UTC day (unlimited when unset)
+    #[serde(default)]
+    pub daily_quota: Option<u64>,
+    /// May read `/admin` endpoints
+    #[serde(default)]
+    pub admin: bool,
+}
+
+impl ApiKey {
+    /// Name used in usage reports, never the key itself
+    pub fn label(&self) -> String {
+        if self.name.is_empty() {
+            format!("{}…", self.key.chars().take(4).collect::<String>())
+        } else {
+            self.name.clone()
+        }
+    }
+
+    /// Limit `options` to what this key may see.
+    /// Fails with a message when the request asks for data outside the key's scope.
+    pub fn restrict(&self, options: &mut SearchOptions) -> Result<(), String> {
+        if !self.languages.is_empty() && !self.languages.contains(&options.language) {
+            return Err(format!(
+                "API key may not search {}",
+                options.language.as_str()
+            ));
+        }
+        if !self.languages.is_empty() && !self.languages.contains(&options.language.reverse()) {
+            options.flip_direction = false;
+        }
+
+        if self.sources.is_empty() {
+            return Ok(());
+        }
+        if options.sources.is_empty() {
+            options.sources = self.sources.clone();
+        } else if let Some(source) = options.sources.iter().find(|s| !self.sources.contains(s)) {
+            return Err(format!("API key may not search {}", source));
+        }
+        Ok(())
+    }
+}
+
+/// Configured API keys. With none configured, the API is open.
+#[derive(Debug, Clone, Default)]
+pub struct ApiKeys {
+    keys: HashMap<String, ApiKey>,
+}
+
+impl ApiKeys {
+    pub fn new(keys: Vec<ApiKey>) -> Self {
+        Self {
+            keys: keys.into_iter().map(|k| (k.key.clone(), k)).collect(),
+        }
+    }
+
+    /// Whether requests need a key
+    pub fn required(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&ApiKey> {
+        self.keys.get(key)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ApiKey> {
+        self.keys.values()
+    }
+
+    /// Look up the key sent in an `X-API-Key` header
+    pub fn from_headers(&self, headers: &HeaderMap) -> Option<&ApiKey> {
+        headers
+            .get("x-api-key")
+            .and_then(|key| key.to_str().ok())
+            .and_then(|key| self.get(key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(languages: Vec<LanguagePair>, sources: &[&str]) -> ApiKey {
+        ApiKey {
+            key: "secret".to_string(),
+            name: "app".to_string(),
+            languages,
+            sources: sources.iter().map(|s| s.to_string()).collect(),
+            daily_quota: None,
+            admin: false,
+        }
+    }
+
+    #[test]
+    fn test_restrict_enforces_scope() {
+        let scoped = key(vec![LanguagePair::DE_EN], &["freedict-deu-eng"]);
+
+        let mut options = SearchOptions::default();
+        scoped.restrict(&mut options).unwrap();
+        assert_eq!(options.sources, vec!["freedict-deu-eng"]);
+        // Flipping to en-de would leave the key's scope
+        assert!(!options.flip_direction);
+
+        let mut options = SearchOptions {
+            sources: vec!["dictcc".to_string()],
+            ..SearchOptions::default()
+        };
+        assert!(scoped.restrict(&mut options).is_err());
+
+        let mut options = SearchOptions {
+            language: LanguagePair::EN_DE,
+            ..SearchOptions::default()
+        };
+        assert!(scoped.restrict(&mut options).is_err());
+
+        let mut options = SearchOptions::default();
+        key(Vec::new(), &[]).restrict(&mut options).unwrap();
+        assert!(options.sources.is_empty());
+    }
+}
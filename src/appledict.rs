@@ -0,0 +1,348 @@
+use anyhow::{Context, Result};
+use flate2::read::ZlibDecoder;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+use crate::models::DictionaryEntry;
+use crate::parser::{self, ParseStats};
+use crate::xml::{self, Token};
+
+/// Offset of the field holding the end of the compressed chunks, minus 0x40
+const CHUNKS_END_FIELD: usize = 0x40;
+/// Offset of the first compressed chunk
+const FIRST_CHUNK: usize = 0x60;
+
+/// A parsed macOS Dictionary.app bundle
+#[derive(Debug, Clone)]
+pub struct AppleDictionary {
+    pub name: Option<String>,
+    /// Language pair from the bundle's Info.plist, e.g. "de-en"
+    pub language: Option<String>,
+    pub entries: Vec<DictionaryEntry>,
+}
+
+/// Parse a compiled `.dictionary` bundle, as installed under
+/// `~/Library/Dictionaries` or by Dictionary.app in
+/// `/System/Library/AssetsV2`.
+///
+/// The articles live in `Contents/Resources/Body.data` (`Contents/Body.data`
+/// in older bundles): zlib-compressed chunks of `<d:entry d:title="...">`
+/// XHTML articles. Each article becomes an entry for its title, with the
+/// headword and markup removed from the definition. `path` may be the bundle
+/// or its `Body.data`.
+pub fn parse_apple_dictionary<P: AsRef<Path>>(
+    path: P,
+    language: &str,
+    stats: &mut ParseStats,
+) -> Result<AppleDictionary> {
+    let path = path.as_ref();
+    let (bundle, body_path) = locate_body(path)?;
+    let body =
+        std::fs::read(&body_path).context(format!("Failed to read Body.data: {:?}", body_path))?;
+
+    let info = bundle
+        .map(|bundle| bundle.join("Contents").join("Info.plist"))
+        .and_then(|plist| std::fs::read_to_string(plist).ok())
+        .unwrap_or_default();
+    let mut dictionary = AppleDictionary {
+        name: plist_string(&info, "CFBundleDisplayName")
+            .or_else(|| plist_string(&info, "CFBundleName")),
+        language: plist_language(&info),
+        entries: Vec::new(),
+    };
+    if let Some(detected) = &dictionary.language
+        && detected != language
+    {
+        warn!(
+            "Dictionary bundle declares {}, ignoring requested {}",
+            detected, language
+        );
+    }
+    stats.detected_language = dictionary.language.clone();
+    let entry_language = dictionary
+        .language
+        .as_deref()
+        .unwrap_or(language)
+        .to_string();
+
+    for chunk in chunks(&body)? {
+        let mut text = Vec::new();
+        if ZlibDecoder::new(chunk).read_to_end(&mut text).is_err() {
+            stats.record_malformed(&format!("compressed chunk of {} bytes", chunk.len()));
+            continue;
+        }
+        let text = String::from_utf8_lossy(&text);
+        for article in articles(&text) {
+            match parse_article(article) {
+                Some((title, definition)) => dictionary.entries.push(DictionaryEntry::new(
+                    title,
+                    definition,
+                    entry_language.clone(),
+                )),
+                None => stats.record_malformed(excerpt(article)),
+            }
+        }
+    }
+    Ok(dictionary)
+}
+
+/// The first 80 characters of an article, for reporting it as malformed
+fn excerpt(article: &str) -> &str {
+    match article.char_indices().nth(80) {
+        Some((end, _)) => &article[..end],
+        None => article,
+    }
+}
+
+/// The bundle directory, when there is one, and its Body.data file
+fn locate_body(path: &Path) -> Result<(Option<PathBuf>, PathBuf)> {
+    if path.is_file() {
+        // Body.data sits in Contents/Resources or Contents of the bundle
+        let bundle = path
+            .ancestors()
+            .find(|ancestor| ancestor.extension().is_some_and(|e| e == "dictionary"))
+            .map(Path::to_path_buf);
+        return Ok((bundle, path.to_path_buf()));
+    }
+    let contents = path.join("Contents");
+    [
+        contents.join("Resources").join("Body.data"),
+        contents.join("Body.data"),
+    ]
+    .into_iter()
+    .find(|body| body.is_file())
+    .map(|body| (Some(path.to_path_buf()), body))
+    .context(format!("No Contents/Resources/Body.data in {:?}", path))
+}
+
+/// Compressed chunks of Body.data. Each one is a little-endian length, two
+/// more 32-bit sizes and the zlib stream.
+fn chunks(body: &[u8]) -> Result<Vec<&[u8]>> {
+    let read_u32 = |at: usize| {
+        body.get(at..at + 4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+    };
+    let end = read_u32(CHUNKS_END_FIELD)
+        .map(|length| (CHUNKS_END_FIELD + length).min(body.len()))
+        .filter(|&end| end > FIRST_CHUNK)
+        .context("Body.data is too short to hold a dictionary")?;
+
+    let mut chunks = Vec::new();
+    let mut position = FIRST_CHUNK;
+    while position + 4 <= end {
+        let Some(length) = read_u32(position) else {
+            break;
+        };
+        let start = position + 4;
+        if length < 8 || start + length > body.len() {
+            anyhow::bail!("Body.data chunk at offset {} runs past the end", position);
+        }
+        chunks.push(&body[start + 8..start + length]);
+        position = start + length;
+    }
+    Ok(chunks)
+}
+
+/// The `<d:entry ...>...</d:entry>` articles of a decompressed chunk. Their
+/// length prefixes are skipped along with anything else between them.
+fn articles(text: &str) -> Vec<&str> {
+    const END: &str = "</d:entry>";
+    let mut found = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("<d:entry") {
+        let Some(end) = rest[start..].find(END) else {
+            break;
+        };
+        found.push(&rest[start..start + end + END.len()]);
+        rest = &rest[start + end + END.len()..];
+    }
+    found
+}
+
+/// Title and plain-text definition of an article
+fn parse_article(article: &str) -> Option<(String, String)> {
+    let mut title = None;
+    let mut lines = vec![String::new()];
+    // Depth of the headword element being skipped, it repeats the title
+    let mut skipping: Option<usize> = None;
+    let mut depth = 0;
+    for token in xml::tokens(article) {
+        match token {
+            Token::Start {
+                name: "d:entry",
+                attributes,
+                ..
+            } => {
+                title = xml::attribute(attributes, "d:title");
+            }
+            Token::Start {
+                name,
+                attributes,
+                empty,
+            } => {
+                if is_block(name) {
+                    lines.push(String::new());
+                }
+                if empty {
+                    continue;
+                }
+                depth += 1;
+                if skipping.is_none() && xml::attribute(attributes, "d:dhw").is_some() {
+                    skipping = Some(depth);
+                }
+            }
+            Token::End(name) => {
+                if skipping == Some(depth) {
+                    skipping = None;
+                }
+                depth -= usize::from(depth > 0);
+                if is_block(name) {
+                    lines.push(String::new());
+                }
+            }
+            Token::Text(text) if skipping.is_none() => {
+                if let Some(line) = lines.last_mut() {
+                    line.push_str(&text);
+                }
+            }
+            Token::Text(_) => {}
+        }
+    }
+
+    let title = title?.trim().to_string();
+    let definition = lines
+        .iter()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("; ");
+    if title.is_empty() || definition.is_empty() {
+        return None;
+    }
+    Some((title, definition))
+}
+
+fn is_block(name: &str) -> bool {
+    matches!(name, "div" | "p" | "br" | "li" | "ol" | "ul" | "tr")
+}
+
+/// A `<key>name</key><string>value</string>` pair of an XML property list
+fn plist_string(plist: &str, key: &str) -> Option<String> {
+    let after = &plist[plist.find(&format!("<key>{}</key>", key))?..];
+    let value = &after[after.find("<string>")? + "<string>".len()..];
+    let value = xml::decode_entities(&value[..value.find("</string>")?]);
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// Language pair from the indexed and description languages of `DCSDictionaryLanguages`
+fn plist_language(plist: &str) -> Option<String> {
+    let from = plist_string(plist, "DCSDictionaryIndexLanguage")?;
+    let to = plist_string(plist, "DCSDictionaryDescriptionLanguage")?;
+    parser::language_from_file_name(&format!("{}-{}", from, to)).map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::ZlibEncoder;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    const INFO: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0">
+<dict>
+  <key>CFBundleName</key>
+  <string>German - English</string>
+  <key>DCSDictionaryLanguages</key>
+  <array>
+    <dict>
+      <key>DCSDictionaryDescriptionLanguage</key>
+      <string>en</string>
+      <key>DCSDictionaryIndexLanguage</key>
+      <string>de</string>
+    </dict>
+  </array>
+</dict>
+</plist>"#;
+
+    /// Body.data with one chunk per group of articles, each prefixed with its length
+    fn body_data(chunks: &[&[&str]]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for articles in chunks {
+            let mut plain = Vec::new();
+            for article in *articles {
+                plain.extend((article.len() as u32).to_le_bytes());
+                plain.extend(article.as_bytes());
+            }
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(&plain).unwrap();
+            let compressed = encoder.finish().unwrap();
+            data.extend(((compressed.len() + 8) as u32).to_le_bytes());
+            data.extend(((compressed.len() + 4) as u32).to_le_bytes());
+            data.extend((plain.len() as u32).to_le_bytes());
+            data.extend(compressed);
+        }
+        let mut body = vec![0u8; FIRST_CHUNK];
+        body[CHUNKS_END_FIELD..CHUNKS_END_FIELD + 4]
+            .copy_from_slice(&((FIRST_CHUNK - CHUNKS_END_FIELD + data.len()) as u32).to_le_bytes());
+        body.extend(data);
+        body
+    }
+
+    #[test]
+    fn test_parse_apple_dictionary() {
+        let temp_dir = TempDir::new().unwrap();
+        let bundle = temp_dir.path().join("German - English.dictionary");
+        let resources = bundle.join("Contents").join("Resources");
+        std::fs::create_dir_all(&resources).unwrap();
+        std::fs::write(bundle.join("Contents").join("Info.plist"), INFO).unwrap();
+        std::fs::write(
+            resources.join("Body.data"),
+            body_data(&[
+                &[
+                    r#"<d:entry xmlns:d="http://www.apple.com/DTDs/DictionaryService-1.0.rng" id="h1" d:title="Haus"><span class="hg"><span d:dhw="1" class="hw">Haus</span> <span class="pr">| haʊs |</span></span><div class="se1"><span class="posg">noun</span> house</div><div class="se1">home &amp; hearth</div></d:entry>"#,
+                    r#"<d:entry id="h2" d:title="leer"><span d:dhw="1">leer</span></d:entry>"#,
+                ],
+                &[r#"<d:entry id="h3" d:title="Baum"><span d:dhw="1">Baum</span><div>tree</div></d:entry>"#],
+            ]),
+        )
+        .unwrap();
+
+        let mut stats = ParseStats::default();
+        let dictionary = parse_apple_dictionary(&bundle, "en-de", &mut stats).unwrap();
+
+        assert_eq!(dictionary.name.as_deref(), Some("German - English"));
+        assert_eq!(stats.detected_language.as_deref(), Some("de-en"));
+        let entries: Vec<(&str, &str, &str)> = dictionary
+            .entries
+            .iter()
+            .map(|e| (e.word.as_str(), e.definition.as_str(), e.language.as_str()))
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                ("Haus", "| haʊs |; noun house; home & hearth", "de-en"),
+                ("Baum", "tree", "de-en"),
+            ]
+        );
+        assert_eq!(stats.malformed_lines, 1);
+
+        // Body.data on its own, without the Info.plist of a bundle
+        let body = temp_dir.path().join("Body.data");
+        std::fs::copy(resources.join("Body.data"), &body).unwrap();
+        let dictionary =
+            parse_apple_dictionary(&body, "de-en", &mut ParseStats::default()).unwrap();
+        assert_eq!(dictionary.entries.len(), 2);
+        assert_eq!(dictionary.language, None);
+    }
+
+    #[test]
+    fn test_excerpt_keeps_whole_characters() {
+        let article = format!("{}ä{}", "x".repeat(79), "ß".repeat(10));
+        assert_eq!(excerpt(&article), format!("{}ä", "x".repeat(79)));
+        assert_eq!(excerpt("kurz"), "kurz");
+    }
+}
@@ -0,0 +1,200 @@
+use anyhow::Result;
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::models::{DictionaryEntry, LanguageCount, SearchOptions};
+use crate::search::{IndexOptions, SearchEngine, SearchOutput};
+
+/// Store the index is kept in, from `backend` in config.toml
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    /// Tantivy index, supporting every search mode and the server
+    #[default]
+    Tantivy,
+    /// One SQLite database with an FTS5 table of definitions, for small
+    /// deployments (needs the `sqlite` feature)
+    Sqlite,
+}
+
+impl BackendKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BackendKind::Tantivy => "tantivy",
+            BackendKind::Sqlite => "sqlite",
+        }
+    }
+
+    /// Replace the index in `dir` with the entries of `batches`, returning their count
+    pub fn build_from_batches<I>(
+        self,
+        dir: &Path,
+        batches: I,
+        options: &IndexOptions,
+    ) -> Result<usize>
+    where
+        I: IntoIterator<Item = Vec<DictionaryEntry>>,
+    {
+        match self {
+            BackendKind::Tantivy => SearchEngine::build_index_from_batches(dir, batches, options),
+            BackendKind::Sqlite => sqlite_build(dir, batches),
+        }
+    }
+
+    /// Add the entries of `batches` to the index in `dir`, returning their count
+    pub fn add_from_batches<I>(self, dir: &Path, batches: I) -> Result<usize>
+    where
+        I: IntoIterator<Item = Vec<DictionaryEntry>>,
+    {
+        match self {
+            BackendKind::Tantivy => SearchEngine::add_to_index_from_batches(dir, batches),
+            BackendKind::Sqlite => sqlite_add(dir, batches),
+        }
+    }
+
+    /// Whether an index has been built in `dir`
+    pub fn has_index(self, dir: &Path) -> bool {
+        match self {
+            BackendKind::Tantivy => dir.join("meta.json").exists(),
+            BackendKind::Sqlite => dir.join(SQLITE_FILE).exists(),
+        }
+    }
+}
+
+impl std::str::FromStr for BackendKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tantivy" => Ok(BackendKind::Tantivy),
+            "sqlite" => Ok(BackendKind::Sqlite),
+            _ => Err(anyhow::anyhow!(
+                "Invalid backend: {} (expected tantivy or sqlite)",
+                s
+            )),
+        }
+    }
+}
+
+/// Searches over an index, whichever store holds it
+pub trait Backend: Send + Sync {
+    fn kind(&self) -> BackendKind;
+
+    fn search(&self, query: &str, options: &SearchOptions) -> Result<SearchOutput>;
+
+    /// Number of indexed entries
+    fn num_docs(&self) -> u64;
+
+    /// Total entry count and the entry count of each indexed language pair
+    fn get_stats(&self) -> Result<(usize, Vec<LanguageCount>)>;
+}
+
+impl Backend for SearchEngine {
+    fn kind(&self) -> BackendKind {
+        BackendKind::Tantivy
+    }
+
+    fn search(&self, query: &str, options: &SearchOptions) -> Result<SearchOutput> {
+        self.search_with_options(query, options)
+    }
+
+    fn num_docs(&self) -> u64 {
+        SearchEngine::num_docs(self)
+    }
+
+    fn get_stats(&self) -> Result<(usize, Vec<LanguageCount>)> {
+        SearchEngine::get_stats(self)
+    }
+}
+
+/// Database file of the SQLite backend in the index directory
+pub const SQLITE_FILE: &str = "entries.sqlite";
+
+/// Open the SQLite index in `dir`
+#[cfg(feature = "sqlite")]
+pub fn open_sqlite(dir: &Path) -> Result<Box<dyn Backend>> {
+    Ok(Box::new(crate::sqlite::SqliteBackend::open(
+        dir.join(SQLITE_FILE),
+    )?))
+}
+
+#[cfg(feature = "sqlite")]
+fn sqlite_build<I>(dir: &Path, batches: I) -> Result<usize>
+where
+    I: IntoIterator<Item = Vec<DictionaryEntry>>,
+{
+    std::fs::create_dir_all(dir)?;
+    crate::sqlite::SqliteBackend::build_from_batches(dir.join(SQLITE_FILE), batches)
+}
+
+#[cfg(feature = "sqlite")]
+fn sqlite_add<I>(dir: &Path, batches: I) -> Result<usize>
+where
+    I: IntoIterator<Item = Vec<DictionaryEntry>>,
+{
+    crate::sqlite::SqliteBackend::add_from_batches(dir.join(SQLITE_FILE), batches)
+}
+
+/// Open the SQLite index in `dir`
+#[cfg(not(feature = "sqlite"))]
+pub fn open_sqlite(_dir: &Path) -> Result<Box<dyn Backend>> {
+    without_sqlite()
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn sqlite_build<I>(_dir: &Path, _batches: I) -> Result<usize> {
+    without_sqlite()
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn sqlite_add<I>(_dir: &Path, _batches: I) -> Result<usize> {
+    without_sqlite()
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn without_sqlite<T>() -> Result<T> {
+    anyhow::bail!("dictv was built without the SQLite backend (enable the `sqlite` feature)")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{LanguagePair, SearchMode};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_tantivy_backend() {
+        let temp_dir = TempDir::new().unwrap();
+        let kind: BackendKind = "tantivy".parse().unwrap();
+        assert!(!kind.has_index(temp_dir.path()));
+        let entries = vec![DictionaryEntry::new(
+            "Haus".to_string(),
+            "house".to_string(),
+            "de-en".to_string(),
+        )];
+        let built = kind
+            .build_from_batches(temp_dir.path(), vec![entries], &IndexOptions::default())
+            .unwrap();
+        assert_eq!(built, 1);
+        assert!(kind.has_index(temp_dir.path()));
+
+        let backend: Box<dyn Backend> = Box::new(SearchEngine::new(temp_dir.path()).unwrap());
+        let options = SearchOptions {
+            mode: SearchMode::Exact,
+            language: LanguagePair::DE_EN,
+            ..SearchOptions::default()
+        };
+        let results = backend.search("haus", &options).unwrap().results;
+        assert_eq!(results[0].definitions, ["house"]);
+        assert_eq!(
+            backend.get_stats().unwrap(),
+            (
+                1,
+                vec![LanguageCount {
+                    language: LanguagePair::DE_EN,
+                    entries: 1
+                }]
+            )
+        );
+    }
+}
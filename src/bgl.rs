@@ -0,0 +1,425 @@
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use std::io::Read;
+use std::path::Path;
+use tracing::warn;
+
+use crate::models::DictionaryEntry;
+use crate::parser::{self, ParseStats};
+use crate::stardict;
+
+/// Signatures a BGL file starts with, before the offset of its gzip stream
+const SIGNATURES: [[u8; 4]; 2] = [[0x12, 0x34, 0x00, 0x01], [0x12, 0x34, 0x00, 0x02]];
+
+/// Block types holding an entry
+const ENTRY_BLOCKS: [u8; 4] = [1, 7, 10, 11];
+/// Block type of a dictionary property (title, languages, charsets)
+const PROPERTY_BLOCK: u8 = 3;
+
+/// Property codes
+const TITLE: u16 = 0x01;
+const SOURCE_LANGUAGE: u16 = 0x07;
+const TARGET_LANGUAGE: u16 = 0x08;
+
+/// Definitions end at this byte, binary fields (part of speech, ...) follow it
+const DEFINITION_FIELDS: u8 = 0x14;
+
+/// Decompressed bytes searched for the language properties, which come
+/// before the entries
+const PROPERTIES_PREFIX: u64 = 64 * 1024;
+
+/// A parsed Babylon BGL dictionary
+#[derive(Debug, Clone)]
+pub struct BglDictionary {
+    pub name: Option<String>,
+    /// Language pair declared by the source and target language properties, e.g. "de-en"
+    pub language: Option<String>,
+    pub entries: Vec<DictionaryEntry>,
+}
+
+/// Parse a Babylon `.bgl` file: a short header followed by a gzip stream of
+/// typed blocks. Each entry block becomes an entry for its headword and
+/// every alternate form, with HTML and trailing binary fields removed from
+/// the definition. Entries get the requested `language`; callers without
+/// one can take the file's own from `declared_language`.
+pub fn parse_bgl<P: AsRef<Path>>(
+    path: P,
+    language: &str,
+    stats: &mut ParseStats,
+) -> Result<BglDictionary> {
+    let path = path.as_ref();
+    let bytes = std::fs::read(path).context(format!("Failed to read BGL file: {:?}", path))?;
+    parse_bgl_bytes(&bytes, language, stats).context(format!("Invalid BGL file: {:?}", path))
+}
+
+/// The language pair a BGL file declares, e.g. "de-en", read from the
+/// start of its data
+pub fn declared_language<P: AsRef<Path>>(path: P) -> Result<Option<String>> {
+    let path = path.as_ref();
+    let bytes = std::fs::read(path).context(format!("Failed to read BGL file: {:?}", path))?;
+    let data =
+        decompress(&bytes, PROPERTIES_PREFIX).context(format!("Invalid BGL file: {:?}", path))?;
+    let (mut source, mut target) = (None, None);
+    for (kind, block) in (Blocks { data: &data }) {
+        match kind {
+            PROPERTY_BLOCK if block.len() >= 2 => match u16::from_be_bytes([block[0], block[1]]) {
+                SOURCE_LANGUAGE => source = language_code(&block[2..]),
+                TARGET_LANGUAGE => target = language_code(&block[2..]),
+                _ => {}
+            },
+            kind if kind == 4 || ENTRY_BLOCKS.contains(&kind) => break,
+            _ => {}
+        }
+    }
+    Ok(language_pair(source, target))
+}
+
+/// Up to `limit` bytes of the gzip stream after the file header
+fn decompress(bytes: &[u8], limit: u64) -> Result<Vec<u8>> {
+    if bytes.len() < 6 || !SIGNATURES.iter().any(|s| bytes.starts_with(s)) {
+        anyhow::bail!("Not a Babylon BGL file");
+    }
+    let gzip_offset = u16::from_be_bytes([bytes[4], bytes[5]]) as usize;
+    if gzip_offset < 6 || gzip_offset >= bytes.len() {
+        anyhow::bail!("Gzip offset {} is outside the file", gzip_offset);
+    }
+    let mut data = Vec::new();
+    // Some files end without a complete gzip trailer, keep what decompressed
+    if let Err(e) = GzDecoder::new(&bytes[gzip_offset..])
+        .take(limit)
+        .read_to_end(&mut data)
+    {
+        if data.is_empty() {
+            return Err(e).context("Failed to decompress BGL data");
+        }
+        warn!("BGL data ends early: {}", e);
+    }
+    Ok(data)
+}
+
+/// "de-en" for the German and English language properties
+fn language_pair(source: Option<&str>, target: Option<&str>) -> Option<String> {
+    match (source, target) {
+        (Some(from), Some(to)) => {
+            parser::language_from_file_name(&format!("{}-{}", from, to)).map(String::from)
+        }
+        _ => None,
+    }
+}
+
+fn parse_bgl_bytes(bytes: &[u8], language: &str, stats: &mut ParseStats) -> Result<BglDictionary> {
+    let data = decompress(bytes, u64::MAX)?;
+
+    let mut name = None;
+    let (mut source, mut target) = (None, None);
+    let mut entries: Vec<(Vec<String>, String)> = Vec::new();
+    for (kind, block) in (Blocks { data: &data }) {
+        match kind {
+            PROPERTY_BLOCK if block.len() >= 2 => {
+                let value = &block[2..];
+                match u16::from_be_bytes([block[0], block[1]]) {
+                    TITLE => name = Some(decode(value).trim().to_string()),
+                    SOURCE_LANGUAGE => source = language_code(value),
+                    TARGET_LANGUAGE => target = language_code(value),
+                    _ => {}
+                }
+            }
+            kind if ENTRY_BLOCKS.contains(&kind) => match read_entry(kind, block) {
+                Some(entry) => entries.push(entry),
+                None => stats.record_malformed(&decode(&block[..block.len().min(40)])),
+            },
+            // End of the dictionary
+            4 => break,
+            _ => {}
+        }
+    }
+
+    let detected = language_pair(source, target);
+    if let Some(detected) = &detected
+        && detected != language
+    {
+        warn!(
+            "BGL file declares {}, importing it as the requested {}",
+            detected, language
+        );
+    }
+    // The declared pair is only reported when it is the one imported
+    stats.detected_language = detected.clone().filter(|detected| detected == language);
+
+    let mut dictionary = BglDictionary {
+        name,
+        language: detected.clone(),
+        entries: Vec::new(),
+    };
+    for (headwords, definition) in entries {
+        if definition.is_empty() {
+            stats.record_malformed(headwords.first().map(String::as_str).unwrap_or("<entry>"));
+            continue;
+        }
+        for headword in headwords {
+            dictionary.entries.push(DictionaryEntry::new(
+                headword,
+                definition.clone(),
+                language.to_string(),
+            ));
+        }
+    }
+    Ok(dictionary)
+}
+
+/// Iterator over the (type, data) blocks of decompressed BGL data. The low
+/// nibble of a block's first byte is its type; the high nibble is its length
+/// minus 4, or when below 4, the number of length bytes that follow minus 1.
+struct Blocks<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Iterator for Blocks<'a> {
+    type Item = (u8, &'a [u8]);
+
+    fn next(&mut self) -> Option<(u8, &'a [u8])> {
+        let (&first, rest) = self.data.split_first()?;
+        let (kind, length_code) = (first & 0x0F, (first >> 4) as usize);
+        let (length, rest) = if length_code < 4 {
+            let size = length_code + 1;
+            if rest.len() < size {
+                self.data = &[];
+                return None;
+            }
+            (read_uint(&rest[..size]), &rest[size..])
+        } else {
+            (length_code - 4, rest)
+        };
+        if rest.len() < length {
+            self.data = &[];
+            return None;
+        }
+        self.data = &rest[length..];
+        Some((kind, &rest[..length]))
+    }
+}
+
+/// Headword, alternate forms and definition of an entry block
+fn read_entry(kind: u8, block: &[u8]) -> Option<(Vec<String>, String)> {
+    let mut reader = Reader { data: block };
+    let (word, definition, alternates) = if kind == 11 {
+        let word = reader.take_sized(5)?;
+        let mut alternates = Vec::new();
+        for _ in 0..reader.uint(4)? {
+            alternates.push(reader.take_sized(4)?);
+        }
+        (word, reader.take_sized(4)?, alternates)
+    } else {
+        let word = reader.take_sized(1)?;
+        let definition = reader.take_sized(2)?;
+        let mut alternates = Vec::new();
+        while !reader.data.is_empty() {
+            alternates.push(reader.take_sized(1)?);
+        }
+        (word, definition, alternates)
+    };
+
+    let mut headwords: Vec<String> = Vec::new();
+    for form in std::iter::once(word).chain(alternates) {
+        let form = clean_headword(&decode(form));
+        if !form.is_empty() && !headwords.contains(&form) {
+            headwords.push(form);
+        }
+    }
+    if headwords.is_empty() {
+        return None;
+    }
+    Some((headwords, clean_definition(definition)))
+}
+
+/// Cursor over the fields of an entry block
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn uint(&mut self, size: usize) -> Option<usize> {
+        if self.data.len() < size {
+            return None;
+        }
+        let (value, rest) = self.data.split_at(size);
+        self.data = rest;
+        Some(read_uint(value))
+    }
+
+    /// A field preceded by its length in `size` bytes
+    fn take_sized(&mut self, size: usize) -> Option<&'a [u8]> {
+        let length = self.uint(size)?;
+        if self.data.len() < length {
+            return None;
+        }
+        let (value, rest) = self.data.split_at(length);
+        self.data = rest;
+        Some(value)
+    }
+}
+
+fn read_uint(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .fold(0usize, |value, &byte| (value << 8) | byte as usize)
+}
+
+/// Headwords may carry a homonym number ("bank$1$") and markup
+fn clean_headword(word: &str) -> String {
+    let word = match word.trim_end().strip_suffix('$') {
+        Some(rest) => match rest.rfind('$') {
+            Some(start) if rest[start + 1..].bytes().all(|b| b.is_ascii_digit()) => &rest[..start],
+            _ => word,
+        },
+        None => word,
+    };
+    stardict::strip_tags(word)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn clean_definition(definition: &[u8]) -> String {
+    let end = definition
+        .iter()
+        .position(|&b| b == DEFINITION_FIELDS)
+        .unwrap_or(definition.len());
+    stardict::strip_tags(&decode(&definition[..end]))
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Text of a BGL string: UTF-8 in newer files, Windows-1252 in older Western ones
+fn decode(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => bytes.iter().map(|&b| windows_1252(b)).collect(),
+    }
+}
+
+fn windows_1252(byte: u8) -> char {
+    const HIGH: [char; 32] = [
+        '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž',
+        '\u{8f}', '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}',
+        'ž', 'Ÿ',
+    ];
+    match byte {
+        0x80..=0x9F => HIGH[(byte - 0x80) as usize],
+        _ => byte as char,
+    }
+}
+
+/// Two-letter code for a BGL language number
+fn language_code(value: &[u8]) -> Option<&'static str> {
+    match read_uint(value) {
+        0 => Some("en"),
+        1 => Some("fr"),
+        2 => Some("it"),
+        3 => Some("es"),
+        4 => Some("nl"),
+        5 => Some("pt"),
+        6 => Some("de"),
+        7 => Some("ru"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    fn block(kind: u8, data: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        if data.len() < 12 {
+            bytes.push((((data.len() + 4) as u8) << 4) | kind);
+        } else {
+            bytes.push((1 << 4) | kind);
+            bytes.extend((data.len() as u16).to_be_bytes());
+        }
+        bytes.extend(data);
+        bytes
+    }
+
+    fn entry(word: &[u8], definition: &[u8], alternates: &[&[u8]]) -> Vec<u8> {
+        let mut data = vec![word.len() as u8];
+        data.extend(word);
+        data.extend((definition.len() as u16).to_be_bytes());
+        data.extend(definition);
+        for alternate in alternates {
+            data.push(alternate.len() as u8);
+            data.extend(*alternate);
+        }
+        block(1, &data)
+    }
+
+    fn bgl(blocks: &[Vec<u8>]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&blocks.concat()).unwrap();
+        let mut bytes = vec![0x12, 0x34, 0x00, 0x01, 0x00, 0x06];
+        bytes.extend(encoder.finish().unwrap());
+        bytes
+    }
+
+    #[test]
+    fn test_parse_bgl() {
+        let mut title = vec![0x00, 0x01];
+        title.extend(b"Babylon German-English");
+        let bytes = bgl(&[
+            block(3, &title),
+            block(3, &[0x00, 0x07, 0x00, 0x00, 0x00, 0x06]),
+            block(3, &[0x00, 0x08, 0x00, 0x00, 0x00, 0x00]),
+            entry(
+                b"Haus$1$",
+                b"<b>house</b><br>building\x14\x02\x30",
+                &[b"H\xe4user"],
+            ),
+            entry(b"gr\xfc\xdfen", b"to greet", &[]),
+            entry(b"Leer", b"<br>", &[]),
+            block(4, &[]),
+            entry(b"after", b"the end", &[]),
+        ]);
+
+        let mut stats = ParseStats::default();
+        let dictionary = parse_bgl_bytes(&bytes, "de-en", &mut stats).unwrap();
+
+        assert_eq!(dictionary.name.as_deref(), Some("Babylon German-English"));
+        assert_eq!(stats.detected_language.as_deref(), Some("de-en"));
+        let entries: Vec<(&str, &str)> = dictionary
+            .entries
+            .iter()
+            .map(|e| (e.word.as_str(), e.definition.as_str()))
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                ("Haus", "house; building"),
+                ("Häuser", "house; building"),
+                ("grüßen", "to greet"),
+            ]
+        );
+        assert!(dictionary.entries.iter().all(|e| e.language == "de-en"));
+        assert_eq!(stats.malformed_examples, ["Leer"]);
+
+        // A requested language wins over the declared one
+        let mut stats = ParseStats::default();
+        let dictionary = parse_bgl_bytes(&bytes, "en-de", &mut stats).unwrap();
+        assert_eq!(dictionary.language.as_deref(), Some("de-en"));
+        assert_eq!(stats.detected_language, None);
+        assert!(dictionary.entries.iter().all(|e| e.language == "en-de"));
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("babylon.bgl");
+        std::fs::write(&path, &bytes).unwrap();
+        assert_eq!(declared_language(&path).unwrap().as_deref(), Some("de-en"));
+
+        assert!(parse_bgl_bytes(b"not a bgl file", "de-en", &mut stats).is_err());
+    }
+}
@@ -0,0 +1,148 @@
+use anyhow::{Context, Result};
+use ring::digest::{Context as Digest, SHA256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// File in the cache directory mapping each downloaded URL to the hash of its content
+const URL_INDEX: &str = "urls.json";
+
+/// Downloaded archives stored by the SHA-256 of their content, shared by
+/// every data directory so re-imports do not download them again
+#[derive(Debug, Clone)]
+pub struct DownloadCache {
+    dir: PathBuf,
+}
+
+impl DownloadCache {
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    /// The cache in `DICTV_CACHE_DIR`, or else `~/.cache/dictv/downloads`
+    pub fn from_env() -> Option<Self> {
+        let dir = match std::env::var_os("DICTV_CACHE_DIR") {
+            Some(dir) => PathBuf::from(dir),
+            None => dirs::cache_dir()?.join("dictv").join("downloads"),
+        };
+        Some(Self::new(dir))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Cached copy of `url`, if there is one and its content still matches its hash
+    pub fn lookup(&self, url: &str) -> Result<Option<PathBuf>> {
+        let Some(hash) = self.load_index()?.remove(url) else {
+            return Ok(None);
+        };
+        let path = self.dir.join(&hash);
+        if !path.exists() {
+            return Ok(None);
+        }
+        if sha256_file(&path)? != hash {
+            warn!("Removing corrupt cached download {:?}", path);
+            fs::remove_file(&path)?;
+            return Ok(None);
+        }
+        Ok(Some(path))
+    }
+
+    /// Add a downloaded `file` to the cache as the content of `url`, returning its hash
+    pub fn insert(&self, url: &str, file: &Path) -> Result<String> {
+        fs::create_dir_all(&self.dir)
+            .context(format!("Failed to create download cache {:?}", self.dir))?;
+        let hash = sha256_file(file)?;
+        let path = self.dir.join(&hash);
+        if !path.exists() {
+            // Copy under a temporary name, so a cached file is always complete
+            let partial = self.dir.join(format!("{}.partial", hash));
+            link_or_copy(file, &partial)?;
+            fs::rename(&partial, &path)?;
+        }
+
+        let mut index = self.load_index()?;
+        index.insert(url.to_string(), hash.clone());
+        let partial = self.dir.join(format!("{}.partial", URL_INDEX));
+        fs::write(&partial, serde_json::to_string_pretty(&index)?)?;
+        fs::rename(&partial, self.dir.join(URL_INDEX))?;
+        Ok(hash)
+    }
+
+    fn load_index(&self) -> Result<BTreeMap<String, String>> {
+        let path = self.dir.join(URL_INDEX);
+        if !path.exists() {
+            return Ok(BTreeMap::new());
+        }
+        let content = fs::read_to_string(&path)?;
+        serde_json::from_str(&content).context(format!("Invalid download cache index: {:?}", path))
+    }
+}
+
+/// Hard link `from` to `to`, or copy it when they are on different filesystems
+pub fn link_or_copy(from: &Path, to: &Path) -> Result<()> {
+    if to.exists() {
+        fs::remove_file(to)?;
+    }
+    if fs::hard_link(from, to).is_err() {
+        fs::copy(from, to).context(format!("Failed to copy {:?} to {:?}", from, to))?;
+    }
+    Ok(())
+}
+
+/// Lowercase hex SHA-256 of a file's content
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = fs::File::open(path).context(format!("Failed to open {:?}", path))?;
+    let mut digest = Digest::new(&SHA256);
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        digest.update(&buffer[..read]);
+    }
+    Ok(digest
+        .finish()
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_cache_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = DownloadCache::new(temp_dir.path().join("cache"));
+        let url = "https://example.org/dict.tar.xz";
+        assert!(cache.lookup(url).unwrap().is_none());
+
+        let download = temp_dir.path().join("dict.tar.xz");
+        fs::write(&download, "abc").unwrap();
+        let hash = cache.insert(url, &download).unwrap();
+        assert_eq!(
+            hash,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+
+        let cached = cache.lookup(url).unwrap().unwrap();
+        assert_eq!(cached, cache.dir().join(&hash));
+        assert_eq!(fs::read_to_string(&cached).unwrap(), "abc");
+
+        // A corrupted copy is dropped instead of being reused
+        fs::remove_file(&cached).unwrap();
+        fs::write(&cached, "abd").unwrap();
+        assert!(cache.lookup(url).unwrap().is_none());
+        assert!(!cached.exists());
+    }
+}
@@ -0,0 +1,289 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::apikeys::ApiKey;
+use crate::backend::BackendKind;
+use crate::models::{LanguagePair, SearchDefaults};
+use crate::pools::PoolSizes;
+use crate::search::RankingWeights;
+use crate::tts::TtsConfig;
+
+/// Settings read from `config.toml` in the data directory
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// URLs that receive a JSON POST for import, rebuild and update events
+    pub webhooks: Vec<String>,
+    /// FreeDict dictionaries ("deu-eng", "eng-deu") that `serve` imports when missing
+    pub dictionaries: Vec<String>,
+    /// Keys required by `/search`, each optionally scoped to languages and dictionaries
+    pub api_keys: Vec<ApiKey>,
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub default_lang: Option<LanguagePair>,
+    pub log_format: Option<LogFormat>,
+    /// Relevance tuning, applied when the search engine starts
+    pub ranking: RankingWeights,
+    /// Serve HTTPS and require a client certificate for admin routes
+    pub admin_tls: Option<AdminTls>,
+    /// Search parameters the server uses when a request omits them
+    pub search_defaults: SearchDefaults,
+    /// Threads for light and heavy searches
+    pub pools: PoolSizes,
+    /// Store holding the index, `sqlite` needs the `sqlite` feature
+    pub backend: BackendKind,
+    /// Text-to-speech backend for `/audio/{word}`
+    pub tts: Option<TtsConfig>,
+}
+
+/// Certificates for HTTPS with client certificate checks on admin routes.
+/// Relative paths are resolved against the data directory.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AdminTls {
+    /// PEM server certificate chain
+    pub cert: PathBuf,
+    /// PEM server private key
+    pub key: PathBuf,
+    /// PEM certificates admin clients must present one signed by: a CA, or the
+    /// self-signed client certificates themselves
+    pub client_ca: PathBuf,
+}
+
+impl AdminTls {
+    /// Resolve relative paths against `dir`
+    pub fn relative_to(&self, dir: &Path) -> Self {
+        Self {
+            cert: dir.join(&self.cert),
+            key: dir.join(&self.key),
+            client_ca: dir.join(&self.client_ca),
+        }
+    }
+}
+
+/// Log output format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl std::str::FromStr for LogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(anyhow::anyhow!("Invalid log format: {}", s)),
+        }
+    }
+}
+
+/// Settings given explicitly, by command-line flags or environment variables
+#[derive(Debug, Clone, Default)]
+pub struct Overrides {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub data_dir: Option<PathBuf>,
+    pub default_lang: Option<LanguagePair>,
+    pub log_format: Option<LogFormat>,
+}
+
+impl Overrides {
+    /// Read `DICTV_HOST`, `DICTV_PORT`, `DICTV_DATA`, `DICTV_DEFAULT_LANG` and `DICTV_LOG_FORMAT`
+    pub fn from_env() -> Result<Self> {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let var = |name: &str| var(name).filter(|value| !value.is_empty());
+        Ok(Self {
+            host: var("DICTV_HOST"),
+            port: var("DICTV_PORT")
+                .map(|port| port.parse().context("Invalid DICTV_PORT"))
+                .transpose()?,
+            data_dir: var("DICTV_DATA").map(PathBuf::from),
+            default_lang: var("DICTV_DEFAULT_LANG")
+                .map(|lang| lang.parse().context("Invalid DICTV_DEFAULT_LANG"))
+                .transpose()?,
+            log_format: var("DICTV_LOG_FORMAT")
+                .map(|format| format.parse().context("Invalid DICTV_LOG_FORMAT"))
+                .transpose()?,
+        })
+    }
+}
+
+/// Effective settings after applying flags, environment, config file and defaults, in that order
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub host: String,
+    pub port: u16,
+    pub data_dir: PathBuf,
+    pub default_lang: LanguagePair,
+    pub log_format: LogFormat,
+    pub config: Config,
+}
+
+impl Settings {
+    /// Resolve settings; the config file is read from the resolved data directory
+    pub fn resolve(flags: Overrides, env: Overrides) -> Result<Self> {
+        let data_dir = match flags.data_dir.or(env.data_dir) {
+            Some(data_dir) => data_dir,
+            None => dirs::home_dir()
+                .context("Could not find home directory")?
+                .join(".dictv"),
+        };
+        let config = Config::load(data_dir.join("config.toml"))?;
+
+        Ok(Self {
+            host: flags
+                .host
+                .or(env.host)
+                .or(config.host.clone())
+                .unwrap_or_else(|| "127.0.0.1".to_string()),
+            port: flags.port.or(env.port).or(config.port).unwrap_or(3000),
+            default_lang: flags
+                .default_lang
+                .or(env.default_lang)
+                .or(config.default_lang)
+                .unwrap_or(LanguagePair::DE_EN),
+            log_format: flags
+                .log_format
+                .or(env.log_format)
+                .or(config.log_format)
+                .unwrap_or(LogFormat::Text),
+            data_dir,
+            config,
+        })
+    }
+}
+
+impl Config {
+    /// Load the config file, or the defaults if it does not exist
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content =
+            fs::read_to_string(path).context(format!("Failed to read config: {:?}", path))?;
+        let config: Self =
+            toml::from_str(&content).context(format!("Invalid config: {:?}", path))?;
+        if config.search_defaults.max_distance > 2 {
+            anyhow::bail!(
+                "Invalid config: {:?}: search_defaults.max_distance must be 0-2",
+                path
+            );
+        }
+        if config.pools.light == 0 || config.pools.heavy == 0 {
+            anyhow::bail!(
+                "Invalid config: {:?}: pools.light and pools.heavy must be at least 1",
+                path
+            );
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_load_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("config.toml");
+        assert!(Config::load(&path).unwrap().webhooks.is_empty());
+
+        fs::write(&path, "webhooks = [\"http://localhost:9000/hook\"]\n").unwrap();
+        assert_eq!(
+            Config::load(&path).unwrap().webhooks,
+            vec!["http://localhost:9000/hook"]
+        );
+
+        fs::write(&path, "dictionaries = [\"deu-eng\", \"eng-deu\"]\n").unwrap();
+        assert_eq!(
+            Config::load(&path).unwrap().dictionaries,
+            vec!["deu-eng", "eng-deu"]
+        );
+
+        fs::write(
+            &path,
+            "[[api_keys]]\nkey = \"secret\"\nlanguages = [\"de-en\"]\n",
+        )
+        .unwrap();
+        let api_keys = Config::load(&path).unwrap().api_keys;
+        assert_eq!(api_keys[0].languages, vec![LanguagePair::DE_EN]);
+
+        fs::write(
+            &path,
+            "[ranking]\nexact_boost = 2.0\nedit_distance_penalty = [0.0, 1.5]\nsource_priority = [\"ding\"]\n",
+        )
+        .unwrap();
+        let ranking = Config::load(&path).unwrap().ranking;
+        assert_eq!(ranking.exact_boost, 2.0);
+        assert_eq!(ranking.edit_distance_penalty, Some(vec![0.0, 1.5]));
+        assert_eq!(ranking.source_priority, vec!["ding"]);
+        assert_eq!(ranking.frequency_weight, 0.0);
+
+        fs::write(&path, "[search_defaults]\nmode = \"prefix\"\nlimit = 5\n").unwrap();
+        let defaults = Config::load(&path).unwrap().search_defaults;
+        assert_eq!(defaults.mode, crate::models::SearchMode::Prefix);
+        assert_eq!(defaults.limit, 5);
+        assert_eq!(defaults.max_distance, 2);
+
+        fs::write(&path, "[search_defaults]\nmax_distance = 3\n").unwrap();
+        assert!(Config::load(&path).is_err());
+        fs::write(&path, "[search_defaults]\nlang = \"en-de\"\n").unwrap();
+        assert!(Config::load(&path).is_err());
+
+        fs::write(&path, "[pools]\nheavy = 2\n").unwrap();
+        let pools = Config::load(&path).unwrap().pools;
+        assert_eq!(pools.heavy, 2);
+        assert_eq!(pools.heavy_queue, 16);
+        fs::write(&path, "[pools]\nlight = 0\n").unwrap();
+        assert!(Config::load(&path).is_err());
+
+        fs::write(&path, "webhook = \"typo\"\n").unwrap();
+        assert!(Config::load(&path).is_err());
+    }
+
+    #[test]
+    fn test_settings_precedence() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("config.toml"),
+            "port = 4000\nhost = \"0.0.0.0\"\ndefault_lang = \"en-de\"\n",
+        )
+        .unwrap();
+
+        let env = Overrides::from_vars(|name| match name {
+            "DICTV_DATA" => Some(temp_dir.path().display().to_string()),
+            "DICTV_PORT" => Some("5000".to_string()),
+            "DICTV_LOG_FORMAT" => Some("json".to_string()),
+            _ => None,
+        })
+        .unwrap();
+        let flags = Overrides {
+            port: Some(6000),
+            ..Overrides::default()
+        };
+
+        let settings = Settings::resolve(flags, env.clone()).unwrap();
+        assert_eq!(settings.port, 6000);
+        assert_eq!(settings.host, "0.0.0.0");
+        assert_eq!(settings.default_lang, LanguagePair::EN_DE);
+        assert_eq!(settings.log_format, LogFormat::Json);
+
+        let settings = Settings::resolve(Overrides::default(), env).unwrap();
+        assert_eq!(settings.port, 5000);
+
+        let invalid = Overrides::from_vars(|name| (name == "DICTV_PORT").then(|| "x".to_string()));
+        assert!(invalid.is_err());
+    }
+}
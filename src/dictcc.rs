@@ -0,0 +1,207 @@
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use crate::models::{DictionaryEntry, PartOfSpeech};
+use crate::parser::ParseStats;
+use crate::tei;
+
+/// Parse a dict.cc TSV export.
+///
+/// Lines look like `Haus {n}\thouse\tnoun\t[archi.]`: source term, target term,
+/// word class and subject tags. The `# DE-EN ...` header names the column
+/// languages; when it is the reverse of `language` the columns are swapped.
+/// The headword's `{m}`/`{f}`/`{n}` tag and the word class are also kept as
+/// the entry's gender and part of speech.
+pub fn parse_dictcc<P: AsRef<Path>>(
+    path: P,
+    language: &str,
+    stats: &mut ParseStats,
+) -> Result<Vec<DictionaryEntry>> {
+    let file = File::open(path.as_ref())
+        .context(format!("Failed to open dict.cc file: {:?}", path.as_ref()))?;
+    let reader = BufReader::new(file);
+    let mut entries = Vec::new();
+    let mut swap = false;
+
+    for line in reader.lines() {
+        let line = line?;
+
+        if let Some(header) = line.strip_prefix('#') {
+            if let Some(pair) = header_language_pair(header) {
+                swap = is_reversed(&pair, language);
+                stats.detected_language = Some(pair);
+            }
+            continue;
+        }
+
+        if line.trim().is_empty() {
+            continue;
+        }
+        let columns: Vec<&str> = line.split('\t').collect();
+        if columns.len() < 2 {
+            stats.record_malformed(&line);
+            continue;
+        }
+
+        let (source, target) = if swap {
+            (columns[1], columns[0])
+        } else {
+            (columns[0], columns[1])
+        };
+        let word_class = columns.get(2).map(|s| s.trim()).unwrap_or("");
+        let subjects = columns.get(3).map(|s| s.trim()).unwrap_or("");
+
+        let word = strip_annotations(source);
+        if word.is_empty() || target.trim().is_empty() {
+            stats.record_malformed(&line);
+            continue;
+        }
+
+        let mut entry = DictionaryEntry::new(
+            word,
+            format_definition(source, target, word_class),
+            language.to_string(),
+        )
+        .with_domains(parse_subjects(subjects));
+        // Word classes such as "adj past-p" start with the part of speech
+        entry.part_of_speech = word_class
+            .split_whitespace()
+            .next()
+            .and_then(PartOfSpeech::from_tag);
+        entry.gender = bracketed(source, '{', '}').find_map(tei::normalize_gender);
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+/// Extract a language pair like "de-en" from a header such as "DE-EN vocabulary database"
+fn header_language_pair(header: &str) -> Option<String> {
+    header.split_whitespace().find_map(|token| {
+        let (a, b) = token.split_once('-')?;
+        let is_code = |s: &str| s.len() == 2 && s.chars().all(|c| c.is_ascii_alphabetic());
+        (is_code(a) && is_code(b)).then(|| token.to_lowercase())
+    })
+}
+
+/// Whether the file's column order is the reverse of the requested direction
+fn is_reversed(file_pair: &str, language: &str) -> bool {
+    match (file_pair.split_once('-'), language.split_once('-')) {
+        (Some((a, b)), Some((from, to))) => a == to && b == from,
+        _ => false,
+    }
+}
+
+/// Remove `{gender}`, `[label]`, `<abbreviation>` and `(optional)` annotations from a term
+fn strip_annotations(term: &str) -> String {
+    let mut word = String::with_capacity(term.len());
+    let mut depth = 0usize;
+
+    for c in term.chars() {
+        match c {
+            '{' | '[' | '<' | '(' => depth += 1,
+            '}' | ']' | '>' | ')' => depth = depth.saturating_sub(1),
+            _ if depth == 0 => word.push(c),
+            _ => {}
+        }
+    }
+
+    word.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Build a definition such as `<noun, {n}> house`, keeping the source term's gender tags
+fn format_definition(source: &str, target: &str, word_class: &str) -> String {
+    let mut tags: Vec<String> = Vec::new();
+    if !word_class.is_empty() {
+        tags.push(word_class.to_string());
+    }
+    tags.extend(bracketed(source, '{', '}').map(|g| format!("{{{}}}", g)));
+
+    let target = target.split_whitespace().collect::<Vec<_>>().join(" ");
+    if tags.is_empty() {
+        target
+    } else {
+        format!("<{}> {}", tags.join(", "), target)
+    }
+}
+
+/// Subject tags are written as `[archi.] [constr.]`, older exports omit the brackets
+fn parse_subjects(subjects: &str) -> Vec<String> {
+    if subjects.contains('[') {
+        bracketed(subjects, '[', ']').map(str::to_string).collect()
+    } else {
+        subjects.split_whitespace().map(str::to_string).collect()
+    }
+}
+
+/// Contents of each `open ... close` group in a string
+fn bracketed(text: &str, open: char, close: char) -> impl Iterator<Item = &str> {
+    text.split(open)
+        .skip(1)
+        .filter_map(move |part| part.split_once(close).map(|(inner, _)| inner.trim()))
+        .filter(|inner| !inner.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn write_export(content: &str) -> NamedTempFile {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_strip_annotations() {
+        assert_eq!(strip_annotations("Haus {n}"), "Haus");
+        assert_eq!(
+            strip_annotations("(sich) etw. merken [ugs.]"),
+            "etw. merken"
+        );
+        assert_eq!(strip_annotations("Abkürzung <Abk.>"), "Abkürzung");
+    }
+
+    #[test]
+    fn test_parse_dictcc() {
+        let file = write_export(
+            "# DE-EN vocabulary database\tcompiled by dict.cc\n\
+             Haus {n}\thouse\tnoun\t[archi.] [constr.]\n\
+             gehen\tto go\tverb\t\n\
+             malformed line\n",
+        );
+
+        let mut stats = ParseStats::default();
+        let entries = parse_dictcc(file.path(), "de-en", &mut stats).unwrap();
+
+        assert_eq!(stats.detected_language.as_deref(), Some("de-en"));
+        assert_eq!(stats.malformed_lines, 1);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].word, "Haus");
+        assert_eq!(entries[0].definition, "<noun, {n}> house");
+        assert_eq!(entries[0].domains, vec!["archi.", "constr."]);
+        assert_eq!(entries[0].part_of_speech, Some(PartOfSpeech::Noun));
+        assert_eq!(entries[0].gender.as_deref(), Some("n"));
+        assert_eq!(entries[1].definition, "<verb> to go");
+        assert!(entries[1].domains.is_empty());
+        assert_eq!(entries[1].part_of_speech, Some(PartOfSpeech::Verb));
+        assert_eq!(entries[1].gender, None);
+    }
+
+    #[test]
+    fn test_parse_dictcc_reversed_direction() {
+        let file = write_export("# DE-EN vocabulary database\nHaus {n}\thouse\tnoun\n");
+
+        let entries = parse_dictcc(file.path(), "en-de", &mut ParseStats::default()).unwrap();
+
+        assert_eq!(entries[0].word, "house");
+        assert_eq!(entries[0].definition, "<noun> Haus {n}");
+        assert_eq!(entries[0].language, "en-de");
+        // The gender belongs to the German term, not the headword
+        assert_eq!(entries[0].gender, None);
+    }
+}
@@ -0,0 +1,179 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::models::DictionaryEntry;
+
+/// A headword in one language direction
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct DiffHeadword {
+    pub language: String,
+    pub word: String,
+}
+
+/// A headword both dictionaries define, but differently
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChangedEntry {
+    pub language: String,
+    pub word: String,
+    pub definitions_a: Vec<String>,
+    pub definitions_b: Vec<String>,
+}
+
+/// Differences between two imported dictionaries
+#[derive(Debug, Clone, Serialize)]
+pub struct DictionaryDiff {
+    pub source_a: String,
+    pub source_b: String,
+    pub only_in_a: Vec<DiffHeadword>,
+    pub only_in_b: Vec<DiffHeadword>,
+    pub changed: Vec<ChangedEntry>,
+}
+
+/// Compare the entries of dictionaries `source_a` and `source_b`.
+///
+/// Entries from other dictionaries are ignored. Definitions are compared as
+/// sets, so the same definitions in a different order are not a change.
+pub fn diff_entries(
+    entries: impl IntoIterator<Item = DictionaryEntry>,
+    source_a: &str,
+    source_b: &str,
+) -> DictionaryDiff {
+    let mut a: BTreeMap<DiffHeadword, Vec<String>> = BTreeMap::new();
+    let mut b: BTreeMap<DiffHeadword, Vec<String>> = BTreeMap::new();
+    for entry in entries {
+        let side = match entry.source.as_deref() {
+            Some(source) if source == source_a => &mut a,
+            Some(source) if source == source_b => &mut b,
+            _ => continue,
+        };
+        let headword = DiffHeadword {
+            language: entry.language,
+            word: entry.word,
+        };
+        side.entry(headword).or_default().push(entry.definition);
+    }
+    for definitions in a.values_mut().chain(b.values_mut()) {
+        definitions.sort();
+        definitions.dedup();
+    }
+
+    let only_in_b = b
+        .keys()
+        .filter(|headword| !a.contains_key(headword))
+        .cloned()
+        .collect();
+    let mut only_in_a = Vec::new();
+    let mut changed = Vec::new();
+    for (headword, definitions_a) in a {
+        match b.remove(&headword) {
+            None => only_in_a.push(headword),
+            Some(definitions_b) if definitions_b != definitions_a => changed.push(ChangedEntry {
+                language: headword.language,
+                word: headword.word,
+                definitions_a,
+                definitions_b,
+            }),
+            Some(_) => {}
+        }
+    }
+
+    DictionaryDiff {
+        source_a: source_a.to_string(),
+        source_b: source_b.to_string(),
+        only_in_a,
+        only_in_b,
+        changed,
+    }
+}
+
+impl DictionaryDiff {
+    /// Human-readable report
+    pub fn report(&self) -> String {
+        let mut out = String::new();
+        let mut section = |title: String, lines: Vec<String>| {
+            let _ = writeln!(out, "{} ({})", title, lines.len());
+            for line in lines {
+                let _ = writeln!(out, "  {}", line);
+            }
+            out.push('\n');
+        };
+        let headwords = |headwords: &[DiffHeadword]| {
+            headwords
+                .iter()
+                .map(|h| format!("[{}] {}", h.language, h.word))
+                .collect()
+        };
+
+        section(
+            format!("Only in {}", self.source_a),
+            headwords(&self.only_in_a),
+        );
+        section(
+            format!("Only in {}", self.source_b),
+            headwords(&self.only_in_b),
+        );
+        section(
+            "Different definitions".to_string(),
+            self.changed
+                .iter()
+                .map(|c| {
+                    format!(
+                        "[{}] {}\n    - {}\n    + {}",
+                        c.language,
+                        c.word,
+                        c.definitions_a.join("; "),
+                        c.definitions_b.join("; ")
+                    )
+                })
+                .collect(),
+        );
+        out.truncate(out.trim_end().len());
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(word: &str, definition: &str, source: &str) -> DictionaryEntry {
+        DictionaryEntry::new(word.into(), definition.into(), "de-en".into()).with_source(source)
+    }
+
+    #[test]
+    fn test_diff_entries() {
+        let entries = vec![
+            entry("Haus", "house", "freedict"),
+            entry("Haus", "building", "freedict"),
+            entry("Haus", "building", "ding"),
+            entry("Haus", "house", "ding"),
+            entry("Auto", "car", "freedict"),
+            entry("Auto", "automobile", "ding"),
+            entry("Buch", "book", "freedict"),
+            entry("Tisch", "table", "ding"),
+            entry("Stuhl", "chair", "other"),
+        ];
+
+        let diff = diff_entries(entries, "freedict", "ding");
+        let headword = |word: &str| DiffHeadword {
+            language: "de-en".into(),
+            word: word.into(),
+        };
+        assert_eq!(diff.only_in_a, vec![headword("Buch")]);
+        assert_eq!(diff.only_in_b, vec![headword("Tisch")]);
+        assert_eq!(
+            diff.changed,
+            vec![ChangedEntry {
+                language: "de-en".into(),
+                word: "Auto".into(),
+                definitions_a: vec!["car".into()],
+                definitions_b: vec!["automobile".into()],
+            }]
+        );
+
+        let report = diff.report();
+        assert!(report.starts_with("Only in freedict (1)\n  [de-en] Buch"));
+        assert!(report.contains("    - car\n    + automobile"));
+    }
+}
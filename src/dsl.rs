@@ -0,0 +1,352 @@
+use anyhow::{Context, Result};
+use flate2::read::MultiGzDecoder;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use tracing::warn;
+
+use crate::models::DictionaryEntry;
+use crate::parser::ParseStats;
+
+/// A parsed Lingvo DSL dictionary
+#[derive(Debug, Clone)]
+pub struct DslDictionary {
+    /// Dictionary name from the `#NAME` header
+    pub name: Option<String>,
+    /// Language pair from `#INDEX_LANGUAGE`/`#CONTENTS_LANGUAGE`, e.g. "de-en"
+    pub language: Option<String>,
+    pub entries: Vec<DictionaryEntry>,
+}
+
+/// Parse a Lingvo `.dsl` or dictzipped `.dsl.dz` file.
+///
+/// Entries are tagged with the header's language pair when it names known
+/// languages, otherwise with `language`.
+pub fn parse_dsl<P: AsRef<Path>>(
+    path: P,
+    language: &str,
+    stats: &mut ParseStats,
+) -> Result<DslDictionary> {
+    let path = path.as_ref();
+    let mut file = File::open(path).context(format!("Failed to open DSL file: {:?}", path))?;
+
+    let mut bytes = Vec::new();
+    if path.extension().and_then(|s| s.to_str()) == Some("dz") {
+        MultiGzDecoder::new(file).read_to_end(&mut bytes)?;
+    } else {
+        file.read_to_end(&mut bytes)?;
+    }
+
+    Ok(parse_dsl_text(&decode_text(&bytes)?, language, stats))
+}
+
+/// DSL files are usually UTF-16LE with a BOM, sometimes UTF-8. Without a BOM,
+/// UTF-16 shows as a zero byte next to the first (ASCII `#` header) character.
+fn decode_text(bytes: &[u8]) -> Result<String> {
+    match bytes {
+        [0xFF, 0xFE, rest @ ..] => decode_utf16(rest, u16::from_le_bytes),
+        [0xFE, 0xFF, rest @ ..] => decode_utf16(rest, u16::from_be_bytes),
+        [0xEF, 0xBB, 0xBF, rest @ ..] => Ok(String::from_utf8_lossy(rest).into_owned()),
+        [first, 0, ..] if *first != 0 => decode_utf16(bytes, u16::from_le_bytes),
+        [0, second, ..] if *second != 0 => decode_utf16(bytes, u16::from_be_bytes),
+        _ => Ok(String::from_utf8_lossy(bytes).into_owned()),
+    }
+}
+
+fn decode_utf16(bytes: &[u8], to_u16: fn([u8; 2]) -> u16) -> Result<String> {
+    let units = bytes.chunks_exact(2).map(|pair| to_u16([pair[0], pair[1]]));
+    char::decode_utf16(units)
+        .collect::<Result<String, _>>()
+        .context("Invalid UTF-16 in DSL file")
+}
+
+fn parse_dsl_text(text: &str, language: &str, stats: &mut ParseStats) -> DslDictionary {
+    let mut name = None;
+    let mut index_language = None;
+    let mut contents_language = None;
+
+    let mut cards: Vec<(Vec<String>, Vec<String>)> = Vec::new();
+    let mut in_body = false;
+
+    for line in text.lines() {
+        if let Some(header) = line.strip_prefix('#') {
+            let (key, value) = header
+                .split_once(char::is_whitespace)
+                .unwrap_or((header, ""));
+            let value = value.trim().trim_matches('"').to_string();
+            match key {
+                "NAME" => name = Some(value),
+                "INDEX_LANGUAGE" => index_language = Some(value),
+                "CONTENTS_LANGUAGE" => contents_language = Some(value),
+                _ => {}
+            }
+            continue;
+        }
+
+        let line = strip_comments(line);
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        if line.starts_with([' ', '\t']) {
+            // Card body
+            match cards.last_mut() {
+                Some((_, body)) => {
+                    body.push(line.trim().to_string());
+                    in_body = true;
+                }
+                // Body text before any headword
+                None => stats.record_malformed(&line),
+            }
+        } else {
+            // Consecutive headword lines share the card that follows them
+            if in_body || cards.is_empty() {
+                cards.push((Vec::new(), Vec::new()));
+                in_body = false;
+            }
+            if let Some((headwords, _)) = cards.last_mut() {
+                for variant in headword_variants(&line) {
+                    if !headwords.contains(&variant) {
+                        headwords.push(variant);
+                    }
+                }
+            }
+        }
+    }
+
+    let header_language = match (&index_language, &contents_language) {
+        (Some(from), Some(to)) => match (language_code(from), language_code(to)) {
+            (Some(from), Some(to)) => Some(format!("{}-{}", from, to)),
+            _ => None,
+        },
+        _ => None,
+    };
+    if let Some(detected) = &header_language
+        && detected != language
+    {
+        warn!(
+            "DSL header declares {}, ignoring requested {}",
+            detected, language
+        );
+    }
+    let entry_language = header_language.as_deref().unwrap_or(language);
+    stats.detected_language = header_language.clone();
+
+    let mut entries = Vec::new();
+    for (headwords, body) in cards {
+        let definition = body
+            .iter()
+            .map(|line| clean_markup(line))
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("; ");
+        if definition.is_empty() {
+            continue;
+        }
+        for headword in headwords.into_iter().filter(|h| !h.is_empty()) {
+            entries.push(DictionaryEntry::new(
+                headword,
+                definition.clone(),
+                entry_language.to_string(),
+            ));
+        }
+    }
+
+    DslDictionary {
+        name,
+        language: header_language,
+        entries,
+    }
+}
+
+/// Map a DSL language name to a two-letter code
+fn language_code(name: &str) -> Option<&'static str> {
+    match name.to_lowercase().as_str() {
+        "german" | "deutsch" => Some("de"),
+        "english" => Some("en"),
+        "french" => Some("fr"),
+        "spanish" => Some("es"),
+        "italian" => Some("it"),
+        "russian" => Some("ru"),
+        "dutch" => Some("nl"),
+        "polish" => Some("pl"),
+        "portuguese" => Some("pt"),
+        _ => None,
+    }
+}
+
+/// Remove `{{comment}}` blocks
+fn strip_comments(line: &str) -> String {
+    let mut result = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = rest.find("{{") {
+        result.push_str(&rest[..start]);
+        match rest[start..].find("}}") {
+            Some(end) => rest = &rest[start + end + 2..],
+            None => {
+                rest = "";
+                break;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Headwords may contain `{unsorted}` parts, which are left out, `(optional)`
+/// parts and escaped characters. A headword with optional parts is indexed
+/// both with and without them: "Haus(e)" gives "Hause" and "Haus".
+fn headword_variants(line: &str) -> Vec<String> {
+    let mut full = String::with_capacity(line.len());
+    let mut short = String::with_capacity(line.len());
+    let mut chars = line.trim().chars();
+    let mut in_braces = false;
+    let mut optional_depth = 0usize;
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '\\' => match chars.next() {
+                Some(escaped) => escaped,
+                None => break,
+            },
+            '{' => {
+                in_braces = true;
+                continue;
+            }
+            '}' => {
+                in_braces = false;
+                continue;
+            }
+            _ if in_braces => continue,
+            '(' => {
+                optional_depth += 1;
+                continue;
+            }
+            ')' => {
+                optional_depth = optional_depth.saturating_sub(1);
+                continue;
+            }
+            c => c,
+        };
+        full.push(c);
+        if optional_depth == 0 {
+            short.push(c);
+        }
+    }
+
+    let mut variants = Vec::new();
+    for variant in [full, short] {
+        let variant = variant.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !variant.is_empty() && !variants.contains(&variant) {
+            variants.push(variant);
+        }
+    }
+    variants
+}
+
+/// Strip card markup, keeping text and turning `[p]n[/p]` labels into `<n>`
+fn clean_markup(line: &str) -> String {
+    let mut text = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    let mut skip_depth = 0usize;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if skip_depth == 0 {
+                    text.extend(chars.next());
+                } else {
+                    chars.next();
+                }
+            }
+            '[' => {
+                let mut tag = String::new();
+                for t in chars.by_ref() {
+                    if t == ']' {
+                        break;
+                    }
+                    tag.push(t);
+                }
+                let name = tag.split_whitespace().next().unwrap_or("");
+                match name {
+                    // Media references carry file names, not text
+                    "s" => skip_depth += 1,
+                    "/s" => skip_depth = skip_depth.saturating_sub(1),
+                    "p" if skip_depth == 0 => text.push('<'),
+                    "/p" if skip_depth == 0 => text.push('>'),
+                    _ => {}
+                }
+            }
+            _ if skip_depth == 0 => text.push(c),
+            _ => {}
+        }
+    }
+
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "#NAME \"Test Dictionary\"\n\
+#INDEX_LANGUAGE \"German\"\n\
+#CONTENTS_LANGUAGE \"English\"\n\
+\n\
+Haus{-es}\n\
+Häuschen\n\
+\t[m1][p]n[/p] [trn]house[/trn][/m]\n\
+\t[m1][ex]ein \\[neues\\] Haus[/ex] {{comment}}[s]haus.wav[/s][/m]\n\
+gehen\n\
+\t[m1][p]v[/p] to go[/m]\n";
+
+    #[test]
+    fn test_parse_dsl_text() {
+        let dictionary = parse_dsl_text(SAMPLE, "en-de", &mut ParseStats::default());
+
+        assert_eq!(dictionary.name.as_deref(), Some("Test Dictionary"));
+        assert_eq!(dictionary.language.as_deref(), Some("de-en"));
+        assert_eq!(dictionary.entries.len(), 3);
+
+        let words: Vec<&str> = dictionary.entries.iter().map(|e| e.word.as_str()).collect();
+        assert_eq!(words, ["Haus", "Häuschen", "gehen"]);
+        assert_eq!(
+            dictionary.entries[0].definition,
+            "<n> house; ein [neues] Haus"
+        );
+        assert_eq!(
+            dictionary.entries[1].definition,
+            dictionary.entries[0].definition
+        );
+        assert_eq!(dictionary.entries[2].definition, "<v> to go");
+        assert!(dictionary.entries.iter().all(|e| e.language == "de-en"));
+    }
+
+    #[test]
+    fn test_headword_variants() {
+        assert_eq!(headword_variants("Haus(e)"), ["Hause", "Haus"]);
+        assert_eq!(
+            headword_variants("lavender (water){ [sg]}"),
+            ["lavender water", "lavender"]
+        );
+        assert_eq!(headword_variants("AT\\(T\\)"), ["AT(T)"]);
+        assert_eq!(headword_variants("{·}"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_decode_utf16_with_bom() {
+        let mut bytes = vec![0xFF, 0xFE];
+        for unit in "Haus".encode_utf16() {
+            bytes.extend(unit.to_le_bytes());
+        }
+        assert_eq!(decode_text(&bytes).unwrap(), "Haus");
+    }
+
+    #[test]
+    fn test_decode_utf16_without_bom() {
+        let little: Vec<u8> = "#NAME".encode_utf16().flat_map(u16::to_le_bytes).collect();
+        assert_eq!(decode_text(&little).unwrap(), "#NAME");
+        let big: Vec<u8> = "#NAME".encode_utf16().flat_map(u16::to_be_bytes).collect();
+        assert_eq!(decode_text(&big).unwrap(), "#NAME");
+        assert_eq!(decode_text(b"#NAME").unwrap(), "#NAME");
+    }
+}
@@ -0,0 +1,381 @@
+use anyhow::Result;
+use flate2::{Compress, Compression, Crc, FlushCompress, Status};
+use serde::Serialize;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::models::{DictionaryEntry, PartOfSpeech};
+
+/// Output format for exported entries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Tsv,
+    Csv,
+    Json,
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tsv" => Ok(ExportFormat::Tsv),
+            "csv" => Ok(ExportFormat::Csv),
+            "json" => Ok(ExportFormat::Json),
+            _ => Err(anyhow::anyhow!("Invalid export format: {}", s)),
+        }
+    }
+}
+
+/// A single exported row
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportRecord {
+    pub word: String,
+    pub definition: String,
+    pub language: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub part_of_speech: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_rank: Option<u32>,
+}
+
+impl ExportRecord {
+    /// The row for an entry, with the part of speech read from the
+    /// definition when the source did not mark it up
+    pub fn from_entry(entry: DictionaryEntry, frequency_rank: Option<u32>) -> Self {
+        ExportRecord {
+            part_of_speech: entry
+                .part_of_speech
+                .or_else(|| PartOfSpeech::detect(&entry.definition))
+                .map(|pos| pos.as_str().to_string()),
+            frequency_rank,
+            word: entry.word,
+            definition: entry.definition,
+            language: entry.language,
+        }
+    }
+}
+
+const HEADER: [&str; 5] = [
+    "word",
+    "definition",
+    "language",
+    "part_of_speech",
+    "frequency_rank",
+];
+
+/// Write records in the given format
+pub fn write_records<W: Write>(
+    writer: &mut W,
+    format: ExportFormat,
+    records: &[ExportRecord],
+) -> Result<()> {
+    match format {
+        ExportFormat::Json => {
+            serde_json::to_writer_pretty(&mut *writer, records)?;
+            writeln!(writer)?;
+        }
+        ExportFormat::Tsv | ExportFormat::Csv => {
+            let separator = if format == ExportFormat::Tsv {
+                "\t"
+            } else {
+                ","
+            };
+            let escape = |field: &str| match format {
+                ExportFormat::Tsv => escape_tsv(field),
+                _ => escape_csv(field),
+            };
+
+            writeln!(writer, "{}", HEADER.join(separator))?;
+            for record in records {
+                let rank = record
+                    .frequency_rank
+                    .map(|rank| rank.to_string())
+                    .unwrap_or_default();
+                let fields = [
+                    escape(&record.word),
+                    escape(&record.definition),
+                    escape(&record.language),
+                    escape(record.part_of_speech.as_deref().unwrap_or("")),
+                    rank,
+                ];
+                writeln!(writer, "{}", fields.join(separator))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Uncompressed bytes per dictzip chunk, the size dictzip itself uses
+const DICTZIP_CHUNK_LEN: usize = 58315;
+
+/// Write entries as a dictd dictionary, `<name>.index` and a dictzip
+/// compressed `<name>.dict.dz` in `dir`, and return both paths.
+///
+/// Every entry becomes an article of its own, so a headword with several
+/// entries is listed once per entry, and importing the files gives back the
+/// same entries. The index is sorted the way dictd searches it: ignoring
+/// case and characters other than letters, digits and spaces. The
+/// `00-database-short` header holds `short_name`, and `00-database-utf8`
+/// tells dictd the text is UTF-8.
+pub fn write_dictd(
+    dir: &Path,
+    name: &str,
+    short_name: &str,
+    entries: &[DictionaryEntry],
+) -> Result<(PathBuf, PathBuf)> {
+    let mut articles: Vec<(String, String)> = vec![
+        ("00-database-short".to_string(), format!("{}\n", short_name)),
+        ("00-database-utf8".to_string(), "\n".to_string()),
+    ];
+    articles.extend(entries.iter().map(|entry| {
+        (
+            entry.word.replace(['\t', '\n', '\r'], " "),
+            format!("{}\n", entry.definition),
+        )
+    }));
+    articles.sort_by_cached_key(|(word, _)| {
+        let key: String = word
+            .chars()
+            .filter(|c| c.is_alphanumeric() || *c == ' ')
+            .flat_map(char::to_lowercase)
+            .collect();
+        (key, word.clone())
+    });
+
+    let mut data = Vec::new();
+    let mut index = String::new();
+    for (word, article) in &articles {
+        index.push_str(&format!(
+            "{}\t{}\t{}\n",
+            word,
+            encode_base64_offset(data.len() as u64),
+            encode_base64_offset(article.len() as u64)
+        ));
+        data.extend_from_slice(article.as_bytes());
+    }
+
+    fs::create_dir_all(dir)?;
+    let index_path = dir.join(format!("{}.index", name));
+    let dict_path = dir.join(format!("{}.dict.dz", name));
+    fs::write(&index_path, index)?;
+    fs::write(&dict_path, dictzip(&data)?)?;
+    Ok((dict_path, index_path))
+}
+
+/// Offset or length in the base64 digits of dictd indexes ("A" is 0)
+fn encode_base64_offset(mut value: u64) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut digits = vec![ALPHABET[(value % 64) as usize]];
+    value /= 64;
+    while value > 0 {
+        digits.push(ALPHABET[(value % 64) as usize]);
+        value /= 64;
+    }
+    digits.reverse();
+    String::from_utf8(digits).unwrap_or_default()
+}
+
+/// Gzip `data` in the dictzip layout: deflated in independently flushed
+/// chunks whose compressed sizes are listed in an "RA" extra field, so dict
+/// servers can read an article without inflating everything before it
+fn dictzip(data: &[u8]) -> Result<Vec<u8>> {
+    let chunks: Vec<&[u8]> = if data.is_empty() {
+        vec![data]
+    } else {
+        data.chunks(DICTZIP_CHUNK_LEN).collect()
+    };
+    // The extra field, at most 65535 bytes, lists two bytes per chunk
+    if chunks.len() > (u16::MAX as usize - 10) / 2 {
+        anyhow::bail!("Too much text for a dictzip file ({} bytes)", data.len());
+    }
+
+    let mut compress = Compress::new(Compression::best(), false);
+    let mut deflated = Vec::new();
+    let mut sizes = Vec::with_capacity(chunks.len());
+    for (i, chunk) in chunks.iter().enumerate() {
+        let flush = if i + 1 == chunks.len() {
+            FlushCompress::Finish
+        } else {
+            FlushCompress::Full
+        };
+        let start = deflated.len();
+        let mut consumed = 0;
+        loop {
+            deflated.reserve(chunk.len() - consumed + 1024);
+            let before = compress.total_in();
+            let status = compress.compress_vec(&chunk[consumed..], &mut deflated, flush)?;
+            consumed += (compress.total_in() - before) as usize;
+            let flushed = match flush {
+                FlushCompress::Finish => status == Status::StreamEnd,
+                _ => deflated.len() < deflated.capacity(),
+            };
+            if consumed == chunk.len() && flushed {
+                break;
+            }
+        }
+        let size = u16::try_from(deflated.len() - start)
+            .map_err(|_| anyhow::anyhow!("A dictzip chunk does not compress"))?;
+        sizes.push(size);
+    }
+
+    let mut extra = Vec::with_capacity(10 + sizes.len() * 2);
+    extra.extend_from_slice(b"RA");
+    extra.extend_from_slice(&((6 + sizes.len() * 2) as u16).to_le_bytes());
+    extra.extend_from_slice(&1u16.to_le_bytes());
+    extra.extend_from_slice(&(DICTZIP_CHUNK_LEN as u16).to_le_bytes());
+    extra.extend_from_slice(&(sizes.len() as u16).to_le_bytes());
+    for size in &sizes {
+        extra.extend_from_slice(&size.to_le_bytes());
+    }
+
+    let mut crc = Crc::new();
+    crc.update(data);
+    // Magic, deflate, FEXTRA, no modification time, best compression, Unix
+    let mut gzip = vec![0x1f, 0x8b, 8, 4, 0, 0, 0, 0, 2, 3];
+    gzip.extend_from_slice(&(extra.len() as u16).to_le_bytes());
+    gzip.extend_from_slice(&extra);
+    gzip.extend_from_slice(&deflated);
+    gzip.extend_from_slice(&crc.sum().to_le_bytes());
+    gzip.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    Ok(gzip)
+}
+
+/// TSV has no quoting, so tabs and newlines become spaces
+fn escape_tsv(field: &str) -> String {
+    field.replace(['\t', '\n', '\r'], " ")
+}
+
+/// Quote a CSV field if it contains a separator, quote or newline
+pub(crate) fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(word: &str, definition: &str) -> ExportRecord {
+        ExportRecord {
+            word: word.to_string(),
+            definition: definition.to_string(),
+            language: "de-en".to_string(),
+            part_of_speech: None,
+            frequency_rank: Some(7),
+        }
+    }
+
+    #[test]
+    fn test_write_csv_quotes_fields() {
+        let mut out = Vec::new();
+        write_records(
+            &mut out,
+            ExportFormat::Csv,
+            &[record("Haus", "house, \"home\"")],
+        )
+        .unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(
+            text.lines().nth(1).unwrap(),
+            "Haus,\"house, \"\"home\"\"\",de-en,,7"
+        );
+    }
+
+    #[test]
+    fn test_record_from_entry_detects_part_of_speech() {
+        let entry = DictionaryEntry::new(
+            "Haus".to_string(),
+            "<n> house".to_string(),
+            "de-en".to_string(),
+        );
+        let record = ExportRecord::from_entry(entry, Some(3));
+        assert_eq!(record.part_of_speech.as_deref(), Some("noun"));
+        assert_eq!(record.frequency_rank, Some(3));
+
+        let mut out = Vec::new();
+        write_records(&mut out, ExportFormat::Json, &[record]).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(json[0]["word"], "Haus");
+        assert_eq!(json[0]["definition"], "<n> house");
+    }
+
+    #[test]
+    fn test_write_dictd_round_trips() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        // Enough text for several dictzip chunks
+        let mut entries: Vec<DictionaryEntry> = (0..4000)
+            .map(|i| {
+                DictionaryEntry::new(
+                    format!("Wort{:04}", i),
+                    format!("word number {} of the exported dictionary", i),
+                    "de-en".to_string(),
+                )
+            })
+            .collect();
+        entries.push(DictionaryEntry::new(
+            "Äpfel".to_string(),
+            "apples".to_string(),
+            "de-en".to_string(),
+        ));
+        entries.push(DictionaryEntry::new(
+            "apfel".to_string(),
+            "apple".to_string(),
+            "de-en".to_string(),
+        ));
+        entries.push(DictionaryEntry::new(
+            "Apfel".to_string(),
+            "apple tree".to_string(),
+            "de-en".to_string(),
+        ));
+
+        let (dict_path, index_path) =
+            write_dictd(temp_dir.path(), "dictv-de-en", "dictv de-en", &entries).unwrap();
+
+        let dz = fs::read(&dict_path).unwrap();
+        assert_eq!(&dz[12..14], b"RA");
+        assert!(u16::from_le_bytes([dz[20], dz[21]]) > 2);
+
+        let index = fs::read_to_string(&index_path).unwrap();
+        let words: Vec<&str> = index
+            .lines()
+            .map(|line| line.split('\t').next().unwrap())
+            .collect();
+        assert_eq!(
+            words[..4],
+            ["00-database-short", "00-database-utf8", "Apfel", "apfel"]
+        );
+        // dictd compares lowercased code points, so umlauts come after z
+        assert_eq!(words.last(), Some(&"Äpfel"));
+
+        let parsed = crate::parser::parse_dict(&dict_path, &index_path, "de-en").unwrap();
+        assert_eq!(parsed.len(), entries.len() + 2);
+        assert_eq!(parsed[0].definition, "dictv de-en");
+        let mut round_trip: Vec<(String, String)> = parsed[2..]
+            .iter()
+            .map(|e| (e.word.clone(), e.definition.clone()))
+            .collect();
+        let mut expected: Vec<(String, String)> = entries
+            .iter()
+            .map(|e| (e.word.clone(), e.definition.clone()))
+            .collect();
+        round_trip.sort();
+        expected.sort();
+        assert_eq!(round_trip, expected);
+    }
+
+    #[test]
+    fn test_write_tsv_replaces_tabs() {
+        let mut out = Vec::new();
+        write_records(&mut out, ExportFormat::Tsv, &[record("Haus", "a\tb")]).unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(text.lines().nth(1).unwrap(), "Haus\ta b\tde-en\t\t7");
+    }
+}
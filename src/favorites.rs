@@ -0,0 +1,355 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::export::escape_csv;
+use crate::models::{LanguagePair, SearchMode, SearchOptions, SearchResult};
+use crate::search::SearchEngine;
+use crate::wotd;
+
+const MS_PER_DAY: u64 = 24 * 60 * 60 * 1000;
+
+/// A saved word
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Favorite {
+    pub word: String,
+    pub language: String,
+    /// German article derived from the gender tag of a definition ("der", "die", "das")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub article: Option<String>,
+    pub definitions: Vec<String>,
+    /// Milliseconds since the Unix epoch
+    pub added_ms: u64,
+    /// Imported from a word list but missing from the dictionaries
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub not_found: bool,
+}
+
+impl Favorite {
+    /// Save a search result, stamped with the current time
+    pub fn from_result(result: SearchResult) -> Self {
+        Self {
+            article: article(&result.definitions),
+            word: result.word,
+            language: result.language,
+            definitions: result.definitions,
+            added_ms: now_ms(),
+            not_found: false,
+        }
+    }
+
+    /// Keep a word that has no dictionary entry, flagged for review
+    pub fn not_found(word: &str, language: LanguagePair) -> Self {
+        Self {
+            word: word.to_lowercase(),
+            language: language.as_str().to_string(),
+            article: None,
+            definitions: Vec::new(),
+            added_ms: now_ms(),
+            not_found: true,
+        }
+    }
+
+    /// Definitions as one sheet cell
+    fn definitions_cell(&self) -> String {
+        if self.not_found {
+            "(not found)".to_string()
+        } else {
+            self.definitions.join("; ")
+        }
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Outcome of importing a word list
+#[derive(Debug, Default)]
+pub struct WordListImport {
+    /// Listed words and the headwords saved for them
+    pub saved: Vec<(String, String)>,
+    /// Listed words without any match, saved flagged as not found
+    pub not_found: Vec<String>,
+}
+
+/// Look up every word of a word list (one per line, `#` comments, anything after
+/// a tab ignored) and save the best match: the exact headword, else the closest
+/// fuzzy match
+pub fn import_word_list(
+    engine: &SearchEngine,
+    favorites: &mut Favorites,
+    list: &str,
+    language: LanguagePair,
+) -> Result<WordListImport> {
+    let mut import = WordListImport::default();
+    let lookup = |word: &str, mode| {
+        let options = SearchOptions {
+            mode,
+            language,
+            limit: 1,
+            // The list belongs to one direction, fall back to fuzzy matches instead
+            flip_direction: false,
+            ..SearchOptions::default()
+        };
+        engine
+            .search_with_options(word, &options)
+            .map(|mut output| output.results.pop())
+    };
+
+    for line in list.lines() {
+        let word = line.split('\t').next().unwrap_or_default().trim();
+        if word.is_empty() || word.starts_with('#') {
+            continue;
+        }
+        let best = match lookup(word, SearchMode::Exact)? {
+            Some(result) => Some(result),
+            None => lookup(word, SearchMode::Fuzzy)?,
+        };
+        match best {
+            Some(result) => {
+                import.saved.push((word.to_string(), result.word.clone()));
+                favorites.add(Favorite::from_result(result));
+            }
+            None => {
+                import.not_found.push(word.to_string());
+                favorites.add(Favorite::not_found(word, language));
+            }
+        }
+    }
+
+    Ok(import)
+}
+
+/// Saved words, stored as JSON in the data directory
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Favorites {
+    #[serde(default)]
+    pub favorites: Vec<Favorite>,
+}
+
+impl Favorites {
+    /// Load favorites, or none if the file does not exist yet
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content =
+            fs::read_to_string(path).context(format!("Failed to read favorites: {:?}", path))?;
+        serde_json::from_str(&content).context(format!("Invalid favorites: {:?}", path))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        fs::write(path.as_ref(), serde_json::to_string_pretty(self)?)
+            .context(format!("Failed to write favorites: {:?}", path.as_ref()))
+    }
+
+    /// Add a favorite, replacing an earlier one for the same word and language
+    pub fn add(&mut self, favorite: Favorite) {
+        self.remove(&favorite.word, &favorite.language);
+        self.favorites.push(favorite);
+    }
+
+    /// Remove a word, returning whether it was saved
+    pub fn remove(&mut self, word: &str, language: &str) -> bool {
+        let before = self.favorites.len();
+        self.favorites
+            .retain(|f| !(f.word.eq_ignore_ascii_case(word) && f.language == language));
+        self.favorites.len() != before
+    }
+}
+
+/// Article for the first gender tag in the definitions: dict.cc's `{m}`, `{f}`, `{n}`
+/// or FreeDict's `<n, masc>`, `<n, fem>`, `<n, neut>`
+pub fn article(definitions: &[String]) -> Option<String> {
+    definitions.iter().find_map(|definition| {
+        let tagged = |dictcc: &str, freedict: &str| {
+            definition.contains(dictcc)
+                || definition
+                    .split('<')
+                    .skip(1)
+                    .filter_map(|rest| rest.split_once('>').map(|(tag, _)| tag))
+                    .any(|tag| tag.split([',', ' ']).any(|part| part == freedict))
+        };
+        [
+            ("{m}", "masc", "der"),
+            ("{f}", "fem", "die"),
+            ("{n}", "neut", "das"),
+        ]
+        .iter()
+        .find(|(dictcc, freedict, _)| tagged(dictcc, freedict))
+        .map(|(_, _, article)| article.to_string())
+    })
+}
+
+/// Study sheet format for exported favorites
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SheetFormat {
+    Csv,
+    Markdown,
+}
+
+impl std::str::FromStr for SheetFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(SheetFormat::Csv),
+            "md" | "markdown" => Ok(SheetFormat::Markdown),
+            _ => Err(anyhow::anyhow!("Invalid sheet format: {}", s)),
+        }
+    }
+}
+
+/// Write favorites as a study sheet: word, article, definitions and date added
+pub fn write_sheet<W: Write>(
+    writer: &mut W,
+    format: SheetFormat,
+    favorites: &[Favorite],
+) -> Result<()> {
+    match format {
+        SheetFormat::Csv => {
+            writeln!(writer, "word,article,definitions,language,added")?;
+            for favorite in favorites {
+                let fields = [
+                    escape_csv(&favorite.word),
+                    escape_csv(favorite.article.as_deref().unwrap_or("")),
+                    escape_csv(&favorite.definitions_cell()),
+                    escape_csv(&favorite.language),
+                    wotd::date(favorite.added_ms / MS_PER_DAY),
+                ];
+                writeln!(writer, "{}", fields.join(","))?;
+            }
+        }
+        SheetFormat::Markdown => {
+            writeln!(writer, "| Word | Article | Definitions | Added |")?;
+            writeln!(writer, "|------|---------|-------------|-------|")?;
+            for favorite in favorites {
+                writeln!(
+                    writer,
+                    "| {} | {} | {} | {} |",
+                    escape_markdown(&favorite.word),
+                    favorite.article.as_deref().unwrap_or(""),
+                    escape_markdown(&favorite.definitions_cell()),
+                    wotd::date(favorite.added_ms / MS_PER_DAY)
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Keep a cell on one line and its pipes out of the table syntax
+fn escape_markdown(cell: &str) -> String {
+    cell.replace('|', "\\|").replace(['\n', '\r'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn favorite(word: &str, definitions: &[&str]) -> Favorite {
+        let definitions: Vec<String> = definitions.iter().map(|d| d.to_string()).collect();
+        Favorite {
+            word: word.to_string(),
+            language: "de-en".to_string(),
+            article: article(&definitions),
+            definitions,
+            added_ms: 0,
+            not_found: false,
+        }
+    }
+
+    #[test]
+    fn test_favorites_round_trip_replaces_same_word() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("favorites.json");
+
+        let mut favorites = Favorites::load(&path).unwrap();
+        favorites.add(favorite("haus", &["house"]));
+        favorites.add(favorite("haus", &["<noun, {n}> house", "home"]));
+        favorites.add(favorite("auto", &["car"]));
+        assert!(favorites.remove("Auto", "de-en"));
+        assert!(!favorites.remove("auto", "de-en"));
+        favorites.save(&path).unwrap();
+
+        let loaded = Favorites::load(&path).unwrap();
+        assert_eq!(loaded.favorites.len(), 1);
+        assert_eq!(loaded.favorites[0].article.as_deref(), Some("das"));
+    }
+
+    #[test]
+    fn test_article_from_gender_tags() {
+        let article_of = |definition: &str| article(&[definition.to_string()]);
+        assert_eq!(article_of("<n, fem> cat").as_deref(), Some("die"));
+        assert_eq!(article_of("<noun, {m}> dog").as_deref(), Some("der"));
+        assert_eq!(article_of("<vt> to go"), None);
+    }
+
+    #[test]
+    fn test_import_word_list_saves_best_matches() {
+        use crate::models::DictionaryEntry;
+
+        let temp_dir = TempDir::new().unwrap();
+        let entries = vec![
+            DictionaryEntry::new("Haus".into(), "<n, neut> house".into(), "de-en".into()),
+            DictionaryEntry::new("Katze".into(), "<n, fem> cat".into(), "de-en".into()),
+        ];
+        SearchEngine::build_index(temp_dir.path(), entries).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let mut favorites = Favorites::default();
+        let import = import_word_list(
+            &engine,
+            &mut favorites,
+            "# my words\nHaus\thouse\nKatz\n\nxyzzyq\n",
+            LanguagePair::DE_EN,
+        )
+        .unwrap();
+
+        assert_eq!(
+            import.saved,
+            vec![
+                ("Haus".to_string(), "haus".to_string()),
+                ("Katz".to_string(), "katze".to_string())
+            ]
+        );
+        assert_eq!(import.not_found, vec!["xyzzyq"]);
+        assert_eq!(favorites.favorites.len(), 3);
+        assert_eq!(favorites.favorites[1].article.as_deref(), Some("die"));
+        assert!(favorites.favorites[2].not_found);
+    }
+
+    #[test]
+    fn test_write_sheet() {
+        let favorites = [
+            favorite("haus", &["<noun, {n}> house", "home"]),
+            favorite("ja", &["yes | yeah"]),
+        ];
+
+        let mut csv = Vec::new();
+        write_sheet(&mut csv, SheetFormat::Csv, &favorites).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(
+            csv.lines().nth(1).unwrap(),
+            "haus,das,\"<noun, {n}> house; home\",de-en,1970-01-01"
+        );
+
+        let mut markdown = Vec::new();
+        write_sheet(&mut markdown, SheetFormat::Markdown, &favorites).unwrap();
+        let markdown = String::from_utf8(markdown).unwrap();
+        assert_eq!(
+            markdown.lines().nth(3).unwrap(),
+            "| ja |  | yes \\| yeah | 1970-01-01 |"
+        );
+    }
+}
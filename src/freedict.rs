@@ -0,0 +1,143 @@
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::models::LanguagePair;
+
+/// FreeDict's machine-readable list of dictionaries and releases
+pub const DATABASE_URL: &str = "https://freedict.org/freedict-database.json";
+
+/// A dictionary listed in the FreeDict database
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct FreedictDictionary {
+    /// Three-letter codes of the headword and definition languages, "deu-eng"
+    pub name: String,
+    /// Headword count, a string in the database
+    #[serde(default)]
+    headwords: Value,
+    #[serde(default)]
+    pub releases: Vec<FreedictRelease>,
+}
+
+/// A downloadable release of a FreeDict dictionary for one platform
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct FreedictRelease {
+    /// "dictd", "src" (TEI), "slob", ...
+    pub platform: String,
+    pub version: String,
+    #[serde(default, rename = "URL")]
+    pub url: String,
+}
+
+impl FreedictDictionary {
+    pub fn headwords(&self) -> Option<u64> {
+        match &self.headwords {
+            Value::Number(number) => number.as_u64(),
+            Value::String(text) => text.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// The release for `platform`, e.g. "dictd"
+    pub fn release(&self, platform: &str) -> Option<&FreedictRelease> {
+        self.releases
+            .iter()
+            .find(|release| release.platform == platform)
+    }
+
+    /// Language pair of the name: de-en for "deu-eng"
+    pub fn language(&self) -> Option<LanguagePair> {
+        self.name.parse().ok()
+    }
+}
+
+/// Dictionaries of the FreeDict database, by name. Entries without a name,
+/// like the trailing software list, are skipped.
+pub fn parse_database(database_json: &str) -> Result<Vec<FreedictDictionary>> {
+    let database: Vec<Value> = serde_json::from_str(database_json)?;
+    let mut dictionaries: Vec<FreedictDictionary> = database
+        .into_iter()
+        .filter(|dictionary| dictionary.get("name").is_some())
+        .filter_map(|dictionary| serde_json::from_value(dictionary).ok())
+        .collect();
+    dictionaries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(dictionaries)
+}
+
+/// Download and parse the FreeDict database
+pub fn fetch_database() -> Result<Vec<FreedictDictionary>> {
+    let database = reqwest::blocking::get(DATABASE_URL)?
+        .error_for_status()?
+        .text()?;
+    parse_database(&database)
+}
+
+/// The dictionary called `name`, in the short ("deu-eng") or full
+/// ("freedict-deu-eng") form
+pub fn find<'a>(
+    dictionaries: &'a [FreedictDictionary],
+    name: &str,
+) -> Result<&'a FreedictDictionary> {
+    let short = name.strip_prefix("freedict-").unwrap_or(name);
+    if let Some(dictionary) = dictionaries.iter().find(|d| d.name == short) {
+        return Ok(dictionary);
+    }
+    let suggestion = dictionaries
+        .iter()
+        .map(|dictionary| {
+            (
+                strsim::levenshtein(short, &dictionary.name),
+                &dictionary.name,
+            )
+        })
+        .filter(|(distance, _)| *distance <= 2)
+        .min();
+    match suggestion {
+        Some((_, candidate)) => {
+            anyhow::bail!("Unknown dictionary: {} (did you mean {}?)", name, candidate)
+        }
+        None => anyhow::bail!(
+            "Unknown dictionary: {} (run `dictv import --list` for the available ones)",
+            name
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_database_and_find() {
+        let database = r#"[
+            {"name": "fra-deu", "headwords": "8174", "releases": [
+                {"platform": "dictd", "version": "0.4.1",
+                 "URL": "https://download.freedict.org/dictionaries/fra-deu/0.4.1/freedict-fra-deu-0.4.1.dictd.tar.xz"}
+            ]},
+            {"name": "deu-eng", "headwords": 81628, "releases": [
+                {"platform": "src", "version": "1.9-fd1", "URL": "deu-eng.src.tar.xz"},
+                {"platform": "dictd", "version": "1.9-fd1", "URL": "deu-eng.dictd.tar.xz"}
+            ]},
+            {"software": {}}
+        ]"#;
+        let dictionaries = parse_database(database).unwrap();
+        let names: Vec<&str> = dictionaries.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, ["deu-eng", "fra-deu"]);
+
+        let dictionary = find(&dictionaries, "freedict-fra-deu").unwrap();
+        assert_eq!(dictionary.headwords(), Some(8174));
+        assert_eq!(dictionary.language().unwrap().as_str(), "fr-de");
+        assert_eq!(dictionary.release("dictd").unwrap().version, "0.4.1");
+        assert!(dictionary.release("src").is_none());
+
+        let deu_eng = find(&dictionaries, "deu-eng").unwrap();
+        assert_eq!(deu_eng.headwords(), Some(81628));
+        assert_eq!(deu_eng.language(), Some(LanguagePair::DE_EN));
+
+        assert_eq!(
+            find(&dictionaries, "fra-deo").unwrap_err().to_string(),
+            "Unknown dictionary: fra-deo (did you mean fra-deu?)"
+        );
+        assert!(find(&dictionaries, "spa-eng").is_err());
+    }
+}
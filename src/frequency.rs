@@ -0,0 +1,108 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Word frequency ranks per language direction (rank 1 = most frequent)
+#[derive(Debug, Clone, Default)]
+pub struct FrequencyList {
+    ranks: HashMap<String, HashMap<String, u32>>,
+}
+
+impl FrequencyList {
+    /// Load all frequency lists from a directory containing `<language>.txt` files
+    pub fn load_dir<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let mut list = Self::default();
+
+        if !dir.as_ref().is_dir() {
+            return Ok(list);
+        }
+
+        for entry in fs::read_dir(dir.as_ref())? {
+            let path = entry?.path();
+            if path.extension().and_then(|s| s.to_str()) != Some("txt") {
+                continue;
+            }
+            if let Some(language) = path.file_stem().and_then(|s| s.to_str()) {
+                let ranks = parse_frequency_file(&path)?;
+                list.ranks.insert(language.to_string(), ranks);
+            }
+        }
+
+        Ok(list)
+    }
+
+    /// Frequency rank of a word, if known
+    pub fn rank(&self, language: &str, word: &str) -> Option<u32> {
+        self.ranks
+            .get(language)
+            .and_then(|ranks| ranks.get(&word.to_lowercase()))
+            .copied()
+    }
+
+    /// Whether any frequency data is loaded
+    pub fn is_empty(&self) -> bool {
+        self.ranks.is_empty()
+    }
+
+    /// Number of ranked words over all languages
+    pub fn word_count(&self) -> usize {
+        self.ranks.values().map(HashMap::len).sum()
+    }
+}
+
+/// Parse a frequency file ordered from most to least frequent.
+///
+/// Each line starts with a word, optionally followed by whitespace and a count
+/// (e.g. `der 12345`). Blank lines and lines starting with `#` are skipped.
+pub fn parse_frequency_file<P: AsRef<Path>>(path: P) -> Result<HashMap<String, u32>> {
+    let file = File::open(path.as_ref()).context(format!(
+        "Failed to open frequency file: {:?}",
+        path.as_ref()
+    ))?;
+    let reader = BufReader::new(file);
+    let mut ranks = HashMap::new();
+    let mut rank = 0u32;
+
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(word) = line.split_whitespace().next() {
+            rank += 1;
+            // Keep the best rank if a word appears twice
+            ranks.entry(word.to_lowercase()).or_insert(rank);
+        }
+    }
+
+    Ok(ranks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_load_frequency_dir() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("de-en.txt"),
+            "# word count\nder 1000\nHaus 500\n\nhaus 10\nAuto 5\n",
+        )
+        .unwrap();
+
+        let list = FrequencyList::load_dir(temp_dir.path()).unwrap();
+
+        assert_eq!(list.rank("de-en", "der"), Some(1));
+        assert_eq!(list.rank("de-en", "Haus"), Some(2));
+        assert_eq!(list.rank("de-en", "auto"), Some(4));
+        assert_eq!(list.rank("de-en", "Buch"), None);
+        assert_eq!(list.rank("en-de", "der"), None);
+    }
+}
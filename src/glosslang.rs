@@ -0,0 +1,100 @@
+/// Common English words that rarely appear in German text ("in", "an" and "am"
+/// are words in both languages)
+const ENGLISH_WORDS: &[&str] = &[
+    "the",
+    "a",
+    "to",
+    "of",
+    "and",
+    "or",
+    "is",
+    "be",
+    "for",
+    "with",
+    "on",
+    "at",
+    "by",
+    "from",
+    "sth",
+    "sb",
+    "someone",
+    "something",
+    "one's",
+    "not",
+    "it",
+    "as",
+    "up",
+    "out",
+    "into",
+    "over",
+];
+
+/// Common German words that rarely appear in English text
+const GERMAN_WORDS: &[&str] = &[
+    "der", "die", "das", "den", "dem", "des", "ein", "eine", "einen", "einem", "einer", "und",
+    "oder", "ist", "sein", "zu", "mit", "von", "für", "auf", "nicht", "sich", "etw", "jdn", "jdm",
+    "jds", "jemand", "etwas", "im", "zum", "zur", "vom", "bei", "aus",
+];
+
+/// Languages `detect` can tell apart
+pub const LANGUAGES: &[&str] = &["en", "de"];
+
+/// Language of a definition's gloss, "en" or "de", by counting function words
+/// and German-only letters. Grammar tags (`<n, fem>`), labels (`{m}`, `[coll.]`)
+/// and parenthesized notes are ignored. `None` when the text gives no clear signal.
+pub fn detect(definition: &str) -> Option<&'static str> {
+    let mut english = 0;
+    let mut german = 0;
+
+    for word in gloss_words(definition) {
+        if word.chars().any(|c| matches!(c, 'ä' | 'ö' | 'ü' | 'ß')) {
+            german += 1;
+        }
+        if ENGLISH_WORDS.contains(&word.as_str()) {
+            english += 1;
+        }
+        if GERMAN_WORDS.contains(&word.as_str()) {
+            german += 1;
+        }
+    }
+
+    match english.cmp(&german) {
+        std::cmp::Ordering::Greater => Some("en"),
+        std::cmp::Ordering::Less => Some("de"),
+        std::cmp::Ordering::Equal => None,
+    }
+}
+
+/// Lowercased words of `definition` outside of brackets
+fn gloss_words(definition: &str) -> Vec<String> {
+    let mut text = String::with_capacity(definition.len());
+    let mut depth = 0usize;
+    for c in definition.chars() {
+        match c {
+            '<' | '{' | '[' | '(' => depth += 1,
+            '>' | '}' | ']' | ')' => depth = depth.saturating_sub(1),
+            _ if depth == 0 => text.push(c),
+            _ => {}
+        }
+    }
+
+    text.split(|c: char| !(c.is_alphabetic() || c == '\''))
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect("<vt> to give sth. to sb."), Some("en"));
+        assert_eq!(detect("the house"), Some("en"));
+        assert_eq!(detect("<n, fem> die Größe des Hauses"), Some("de"));
+        assert_eq!(detect("jdm. etw. geben"), Some("de"));
+        assert_eq!(detect("<n, neut> house"), None);
+        assert_eq!(detect("[the] Haus"), None);
+    }
+}
@@ -0,0 +1,384 @@
+use fst::{IntoStreamer, Map, MapBuilder, Streamer};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use tantivy::query::Bm25Weight;
+use tantivy::schema::{Field, IndexRecordOption};
+use tantivy::{
+    DocAddress, DocId, DocSet, Score, Searcher, SearcherGeneration, SegmentId, SegmentReader,
+    TERMINATED, TantivyError, Term, Warmer,
+};
+
+/// Lookup served by `HeadwordLookup` instead of a Tantivy query
+#[derive(Debug, Clone, Copy)]
+pub enum HeadwordQuery<'a> {
+    /// Headwords containing the folded `word`, or one of `spellings` among
+    /// their spelling variants
+    Exact {
+        word: &'a str,
+        spellings: &'a [String],
+    },
+    /// Headwords with a word starting with the folded prefix
+    Prefix(&'a str),
+}
+
+/// In-memory FSTs of the headword terms of every segment, mapping each term
+/// to the documents containing it.
+///
+/// Built as a warmer, so a segment's maps exist before any searcher over it
+/// is handed out, and dropped once no live searcher uses the segment. Exact
+/// and prefix lookups are answered from them without running a query,
+/// with the same hits and scores the equivalent Tantivy query would give
+/// when restricted to one language.
+pub struct HeadwordLookup {
+    word_field: Field,
+    /// Absent in indexes built before spelling variants were indexed
+    variants_field: Option<Field>,
+    language_field: Field,
+    segments: RwLock<HashMap<SegmentId, Arc<SegmentHeadwords>>>,
+}
+
+struct SegmentHeadwords {
+    words: TermMap,
+    variants: Option<TermMap>,
+    /// Language pair -> its documents, in doc id order
+    languages: TermMap,
+}
+
+/// Terms of one field mapped to their run of `docs`
+struct TermMap {
+    /// Term -> start << 32 | count
+    terms: Map<Vec<u8>>,
+    docs: Vec<DocId>,
+}
+
+impl TermMap {
+    fn build(segment: &SegmentReader, field: Field) -> tantivy::Result<Self> {
+        let inverted_index = segment.inverted_index(field)?;
+        let mut builder = MapBuilder::memory();
+        let mut docs = Vec::new();
+        let mut terms = inverted_index.terms().stream()?;
+        while terms.advance() {
+            let start = docs.len();
+            let mut postings = inverted_index
+                .read_postings_from_terminfo(terms.value(), IndexRecordOption::Basic)?;
+            while postings.doc() != TERMINATED {
+                docs.push(postings.doc());
+                postings.advance();
+            }
+            builder
+                .insert(
+                    terms.key(),
+                    ((start as u64) << 32) | (docs.len() - start) as u64,
+                )
+                .map_err(fst_error)?;
+        }
+        let terms = Map::new(builder.into_inner().map_err(fst_error)?).map_err(fst_error)?;
+        Ok(Self { terms, docs })
+    }
+
+    fn docs(&self, value: u64) -> &[DocId] {
+        let start = (value >> 32) as usize;
+        &self.docs[start..start + (value & u32::MAX as u64) as usize]
+    }
+
+    fn get(&self, term: &str) -> &[DocId] {
+        self.terms.get(term).map_or(&[], |value| self.docs(value))
+    }
+
+    fn size_bytes(&self) -> usize {
+        self.terms.as_fst().as_bytes().len() + self.docs.len() * size_of::<DocId>()
+    }
+}
+
+fn fst_error(e: fst::Error) -> TantivyError {
+    TantivyError::InternalError(format!("Failed to build headword FST: {}", e))
+}
+
+impl HeadwordLookup {
+    pub fn new(word_field: Field, variants_field: Option<Field>, language_field: Field) -> Self {
+        Self {
+            word_field,
+            variants_field,
+            language_field,
+            segments: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// The best `limit` hits of the language pair `language` in the order
+    /// `TopDocs` would collect them, or None when a segment of `searcher`
+    /// has no maps
+    pub fn search(
+        &self,
+        searcher: &Searcher,
+        query: HeadwordQuery<'_>,
+        language: &str,
+        limit: usize,
+    ) -> tantivy::Result<Option<Vec<(Score, DocAddress)>>> {
+        let segments = {
+            let Ok(maps) = self.segments.read() else {
+                return Ok(None);
+            };
+            let found: Option<Vec<_>> = searcher
+                .segment_readers()
+                .iter()
+                .map(|segment| maps.get(&segment.segment_id()).cloned())
+                .collect();
+            match found {
+                Some(found) => found,
+                None => return Ok(None),
+            }
+        };
+
+        let mut hits: Vec<(Score, DocAddress)> = Vec::new();
+        match query {
+            HeadwordQuery::Exact { word, spellings } => {
+                // One scored clause per term, summed like a boolean query's should clauses
+                let mut clauses = vec![(self.word_field, word)];
+                if let Some(variants_field) = self.variants_field {
+                    clauses.extend(spellings.iter().map(|s| (variants_field, s.as_str())));
+                }
+                let weights = clauses
+                    .iter()
+                    .map(|&(field, text)| {
+                        Bm25Weight::for_terms(searcher, &[Term::from_field_text(field, text)])
+                    })
+                    .collect::<tantivy::Result<Vec<_>>>()?;
+                for (ord, (reader, maps)) in
+                    searcher.segment_readers().iter().zip(&segments).enumerate()
+                {
+                    let in_language = maps.languages.get(language);
+                    let mut scores: HashMap<DocId, Score> = HashMap::new();
+                    for (&(field, text), weight) in clauses.iter().zip(&weights) {
+                        let map = if field == self.word_field {
+                            Some(&maps.words)
+                        } else {
+                            maps.variants.as_ref()
+                        };
+                        let Some(map) = map else {
+                            continue;
+                        };
+                        let fieldnorms = reader.get_fieldnorms_reader(field)?;
+                        for &doc in map.get(text) {
+                            if in_language.binary_search(&doc).is_err() {
+                                continue;
+                            }
+                            *scores.entry(doc).or_default() +=
+                                weight.score(fieldnorms.fieldnorm_id(doc), 1);
+                        }
+                    }
+                    hits.extend(
+                        scores
+                            .into_iter()
+                            .filter(|&(doc, _)| !reader.is_deleted(doc))
+                            .map(|(doc, score)| (score, DocAddress::new(ord as u32, doc))),
+                    );
+                }
+            }
+            HeadwordQuery::Prefix(prefix) => {
+                // Same range as the prefix query, which scores every match 1
+                let upper = format!("{}\u{10FFFF}", prefix);
+                for (ord, (reader, maps)) in
+                    searcher.segment_readers().iter().zip(&segments).enumerate()
+                {
+                    let in_language = maps.languages.get(language);
+                    let mut terms = maps.words.terms.range().ge(prefix).le(&upper).into_stream();
+                    while let Some((_, value)) = terms.next() {
+                        hits.extend(
+                            maps.words
+                                .docs(value)
+                                .iter()
+                                .filter(|&&doc| {
+                                    !reader.is_deleted(doc)
+                                        && in_language.binary_search(&doc).is_ok()
+                                })
+                                .map(|&doc| (1.0, DocAddress::new(ord as u32, doc))),
+                        );
+                    }
+                }
+            }
+        }
+
+        // Best score first, ties in document order
+        hits.sort_by(|(score_a, doc_a), (score_b, doc_b)| {
+            score_b
+                .partial_cmp(score_a)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| doc_a.cmp(doc_b))
+        });
+        hits.dedup_by_key(|(_, doc)| *doc);
+        hits.truncate(limit);
+        Ok(Some(hits))
+    }
+
+    /// Memory held by the maps of all segments
+    pub fn size_bytes(&self) -> usize {
+        self.segments
+            .read()
+            .map(|maps| {
+                maps.values()
+                    .map(|maps| {
+                        maps.words.size_bytes()
+                            + maps.variants.as_ref().map_or(0, TermMap::size_bytes)
+                            + maps.languages.size_bytes()
+                    })
+                    .sum()
+            })
+            .unwrap_or(0)
+    }
+}
+
+impl Warmer for HeadwordLookup {
+    fn warm(&self, searcher: &Searcher) -> tantivy::Result<()> {
+        for segment in searcher.segment_readers() {
+            let known = self
+                .segments
+                .read()
+                .is_ok_and(|maps| maps.contains_key(&segment.segment_id()));
+            if known {
+                continue;
+            }
+            let maps = SegmentHeadwords {
+                words: TermMap::build(segment, self.word_field)?,
+                variants: self
+                    .variants_field
+                    .map(|field| TermMap::build(segment, field))
+                    .transpose()?,
+                languages: TermMap::build(segment, self.language_field)?,
+            };
+            if let Ok(mut segments) = self.segments.write() {
+                segments.insert(segment.segment_id(), Arc::new(maps));
+            }
+        }
+        Ok(())
+    }
+
+    fn garbage_collect(&self, live_generations: &[&SearcherGeneration]) {
+        if let Ok(mut segments) = self.segments.write() {
+            segments.retain(|segment_id, _| {
+                live_generations
+                    .iter()
+                    .any(|generation| generation.segments().contains_key(segment_id))
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::DictionaryEntry;
+    use crate::search::SearchEngine;
+    use std::ops::Bound;
+    use std::sync::Weak;
+    use tantivy::collector::TopDocs;
+    use tantivy::query::{BooleanQuery, Occur, Query, RangeQuery, TermQuery};
+    use tantivy::{Index, ReloadPolicy};
+    use tempfile::TempDir;
+
+    fn entry(word: &str, definition: &str) -> DictionaryEntry {
+        DictionaryEntry::new(
+            word.to_string(),
+            definition.to_string(),
+            "de-en".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_lookup_matches_queries() {
+        let temp_dir = TempDir::new().unwrap();
+        SearchEngine::build_index(
+            temp_dir.path(),
+            vec![
+                entry("Haus", "house"),
+                entry("das Haus", "the house"),
+                entry("Haus", "home"),
+                entry("Hausarzt", "family doctor"),
+                entry("Straße", "street"),
+                entry("Baum", "tree"),
+            ],
+        )
+        .unwrap();
+
+        let index = Index::open_in_dir(temp_dir.path()).unwrap();
+        let schema = index.schema();
+        let word = schema.get_field("word").unwrap();
+        let variants = schema.get_field("word_variants").unwrap();
+        let language = schema.get_field("language").unwrap();
+        let lookup = Arc::new(HeadwordLookup::new(word, Some(variants), language));
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .warmers(vec![Arc::downgrade(&lookup) as Weak<dyn Warmer>])
+            .try_into()
+            .unwrap();
+        let searcher = reader.searcher();
+        let term_query = |field: Field, text: &str| -> Box<dyn Query> {
+            Box::new(TermQuery::new(
+                Term::from_field_text(field, text),
+                IndexRecordOption::Basic,
+            ))
+        };
+
+        for (query, spellings) in [("haus", vec!["haus"]), ("strasse", vec!["strasse"])] {
+            let spellings: Vec<String> = spellings.into_iter().map(str::to_string).collect();
+            let mut clauses = vec![(Occur::Should, term_query(word, query))];
+            clauses.extend(
+                spellings
+                    .iter()
+                    .map(|s| (Occur::Should, term_query(variants, s))),
+            );
+            let expected = searcher
+                .search(&BooleanQuery::new(clauses), &TopDocs::with_limit(10))
+                .unwrap();
+            let exact = HeadwordQuery::Exact {
+                word: query,
+                spellings: &spellings,
+            };
+            let found = lookup
+                .search(&searcher, exact, "de-en", 10)
+                .unwrap()
+                .unwrap();
+            assert!(!found.is_empty());
+            assert_eq!(found, expected);
+        }
+
+        let expected = searcher
+            .search(
+                &RangeQuery::new_str_bounds(
+                    "word".to_string(),
+                    Bound::Included("ha"),
+                    Bound::Included("ha\u{10FFFF}"),
+                ),
+                &TopDocs::with_limit(10),
+            )
+            .unwrap();
+        let found = lookup
+            .search(&searcher, HeadwordQuery::Prefix("ha"), "de-en", 10)
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.len(), 4);
+        assert_eq!(found, expected);
+        assert!(
+            lookup
+                .search(&searcher, HeadwordQuery::Prefix("ha"), "en-de", 10)
+                .unwrap()
+                .unwrap()
+                .is_empty()
+        );
+
+        // A commit adds a segment, which is mapped before the reload swaps it in
+        SearchEngine::add_to_index_from_batches(
+            temp_dir.path(),
+            vec![vec![entry("Haus", "building")]],
+        )
+        .unwrap();
+        reader.reload().unwrap();
+        let searcher = reader.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+        let found = lookup
+            .search(&searcher, HeadwordQuery::Prefix("haus"), "de-en", 10)
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.len(), 5);
+    }
+}
@@ -0,0 +1,120 @@
+use axum::http::HeaderMap;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::models::Lookup;
+
+/// Lookups kept per session, older ones are dropped
+const MAX_LOOKUPS_PER_SESSION: usize = 100;
+
+/// Sessions kept before the least recently active one is dropped
+const MAX_SESSIONS: usize = 10_000;
+
+/// Cookie that carries the session ID when a request has no `session` parameter
+pub const SESSION_COOKIE: &str = "dictv_session";
+
+/// Session ID from the `session` parameter, or else the session cookie
+pub fn session_id(param: Option<&str>, headers: &HeaderMap) -> Option<String> {
+    let from_cookie = || {
+        headers
+            .get_all("cookie")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|cookies| cookies.split(';'))
+            .find_map(|cookie| {
+                let (name, value) = cookie.trim().split_once('=')?;
+                (name == SESSION_COOKIE).then(|| value.to_string())
+            })
+    };
+    param
+        .map(str::to_string)
+        .or_else(from_cookie)
+        .filter(|id| !id.is_empty())
+}
+
+/// Recent lookups of each client session, kept in memory
+#[derive(Debug, Default)]
+pub struct SessionHistory {
+    sessions: Mutex<HashMap<String, VecDeque<Lookup>>>,
+}
+
+impl SessionHistory {
+    /// Number of sessions with lookups in memory
+    pub fn tracked(&self) -> usize {
+        self.sessions.lock().map_or(0, |sessions| sessions.len())
+    }
+
+    /// Record a search made in `session`
+    pub fn record(&self, session: &str, query: &str, language: &str, total_results: usize) {
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+
+        let Ok(mut sessions) = self.sessions.lock() else {
+            return;
+        };
+        if !sessions.contains_key(session)
+            && sessions.len() >= MAX_SESSIONS
+            && let Some(idle) = sessions
+                .iter()
+                .min_by_key(|(_, lookups)| lookups.back().map(|l| l.timestamp_ms))
+                .map(|(id, _)| id.clone())
+        {
+            sessions.remove(&idle);
+        }
+        let lookups = sessions.entry(session.to_string()).or_default();
+        if lookups.len() >= MAX_LOOKUPS_PER_SESSION {
+            lookups.pop_front();
+        }
+        lookups.push_back(Lookup {
+            query: query.to_string(),
+            language: language.to_string(),
+            total_results,
+            timestamp_ms,
+        });
+    }
+
+    /// Most recent lookups of `session` first
+    pub fn recent(&self, session: &str, limit: usize) -> Vec<Lookup> {
+        let Ok(sessions) = self.sessions.lock() else {
+            return Vec::new();
+        };
+        sessions
+            .get(session)
+            .map(|lookups| lookups.iter().rev().take(limit).cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sessions_are_kept_apart() {
+        let history = SessionHistory::default();
+        history.record("a", "Haus", "de-en", 1);
+        history.record("b", "Auto", "de-en", 1);
+        history.record("a", "Buch", "de-en", 0);
+
+        let recent: Vec<String> = history
+            .recent("a", 10)
+            .into_iter()
+            .map(|l| l.query)
+            .collect();
+        assert_eq!(recent, ["Buch", "Haus"]);
+        assert_eq!(history.recent("a", 1).len(), 1);
+        assert!(history.recent("c", 10).is_empty());
+    }
+
+    #[test]
+    fn test_session_id_from_param_or_cookie() {
+        let mut headers = HeaderMap::new();
+        headers.insert("cookie", "theme=dark; dictv_session=abc".parse().unwrap());
+        assert_eq!(session_id(None, &headers).as_deref(), Some("abc"));
+        assert_eq!(session_id(Some("xyz"), &headers).as_deref(), Some("xyz"));
+        assert_eq!(session_id(None, &HeaderMap::new()), None);
+    }
+}
@@ -0,0 +1,163 @@
+use crate::models::{LanguagePair, SearchResult};
+
+/// Render a search result as a semantic HTML fragment for web frontends:
+///
+/// ```html
+/// <article class="entry" lang="de">
+///   <header><dfn>Haus</dfn> <span class="pos">noun</span> <abbr class="gender" title="neuter">n</abbr></header>
+///   <ol class="senses" lang="en"><li class="sense">house <span class="label">[archi.]</span></li></ol>
+/// </article>
+/// ```
+///
+/// Each definition is one sense. Inside a definition, `<...>` grammar notes
+/// and `[...]` labels become spans, `{word}` cross-references become links to
+/// `/search`, and quoted text („...“, "...", «...») becomes an example.
+pub fn render_result(result: &SearchResult) -> String {
+    let language = result.language.parse::<LanguagePair>().ok();
+    let lang_attribute =
+        |code: Option<&str>| code.map_or_else(String::new, |code| format!(" lang=\"{}\"", code));
+
+    let mut header = format!("<dfn>{}</dfn>", escape_html(&result.word));
+    for part_of_speech in &result.parts_of_speech {
+        header.push_str(&format!(
+            " <span class=\"pos\">{}</span>",
+            part_of_speech.as_str()
+        ));
+    }
+    for gender in &result.genders {
+        let title = match gender.as_str() {
+            "m" => "masculine",
+            "f" => "feminine",
+            _ => "neuter",
+        };
+        header.push_str(&format!(
+            " <abbr class=\"gender\" title=\"{}\">{}</abbr>",
+            title,
+            escape_html(gender)
+        ));
+    }
+    for domain in &result.domains {
+        header.push_str(&format!(
+            " <span class=\"domain\">{}</span>",
+            escape_html(domain)
+        ));
+    }
+
+    let senses: String = result
+        .definitions
+        .iter()
+        .map(|definition| {
+            format!(
+                "<li class=\"sense\">{}</li>",
+                render_definition(definition, &result.language)
+            )
+        })
+        .collect();
+
+    format!(
+        "<article class=\"entry\"{}><header>{}</header><ol class=\"senses\"{}>{}</ol></article>",
+        lang_attribute(language.as_ref().map(|language| language.source())),
+        header,
+        lang_attribute(language.as_ref().map(|language| language.target())),
+        senses
+    )
+}
+
+/// Markup for the notes, labels, cross-references and examples of one definition
+fn render_definition(definition: &str, language: &str) -> String {
+    let mut html = String::new();
+    let mut rest = definition;
+    while let Some(start) = rest.find(['<', '[', '{', '„', '"', '«']) {
+        let open = rest[start..].chars().next().unwrap_or_default();
+        let close = match open {
+            '<' => '>',
+            '[' => ']',
+            '{' => '}',
+            '„' => '“',
+            '"' => '"',
+            _ => '»',
+        };
+        let inner_start = start + open.len_utf8();
+        let Some(length) = rest[inner_start..].find(close) else {
+            html.push_str(&escape_html(&rest[..inner_start]));
+            rest = &rest[inner_start..];
+            continue;
+        };
+        let inner = &rest[inner_start..inner_start + length];
+        html.push_str(&escape_html(&rest[..start]));
+        match open {
+            '<' => html.push_str(&format!(
+                "<span class=\"grammar\">&lt;{}&gt;</span>",
+                escape_html(inner)
+            )),
+            '[' => html.push_str(&format!(
+                "<span class=\"label\">[{}]</span>",
+                escape_html(inner)
+            )),
+            '{' => {
+                let query = serde_urlencoded::to_string([("q", inner), ("lang", language)])
+                    .unwrap_or_default();
+                html.push_str(&format!(
+                    "<a class=\"xref\" href=\"/search?{}\">{}</a>",
+                    escape_html(&query),
+                    escape_html(inner)
+                ));
+            }
+            _ => html.push_str(&format!("<q class=\"example\">{}</q>", escape_html(inner))),
+        }
+        rest = &rest[inner_start + length + close.len_utf8()..];
+    }
+    html.push_str(&escape_html(rest));
+    html
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::PartOfSpeech;
+
+    #[test]
+    fn test_render_result() {
+        let result = SearchResult {
+            word: "Haus".to_string(),
+            definitions: vec![
+                "<n, neut> house [archi.]".to_string(),
+                "home, see {Heim}; „Er ist zu Hause.“ - he is at home".to_string(),
+                "R&D <unclosed".to_string(),
+            ],
+            language: "de-en".to_string(),
+            edit_distance: Some(0),
+            score: None,
+            frequency_rank: None,
+            domains: vec!["archi.".to_string()],
+            registers: Vec::new(),
+            parts_of_speech: vec![PartOfSpeech::Noun],
+            genders: vec!["n".to_string()],
+            audio_urls: Vec::new(),
+            sources: Vec::new(),
+            html: None,
+        };
+
+        assert_eq!(
+            render_result(&result),
+            "<article class=\"entry\" lang=\"de\"><header><dfn>Haus</dfn> \
+             <span class=\"pos\">noun</span> \
+             <abbr class=\"gender\" title=\"neuter\">n</abbr> \
+             <span class=\"domain\">archi.</span></header>\
+             <ol class=\"senses\" lang=\"en\">\
+             <li class=\"sense\"><span class=\"grammar\">&lt;n, neut&gt;</span> house \
+             <span class=\"label\">[archi.]</span></li>\
+             <li class=\"sense\">home, see \
+             <a class=\"xref\" href=\"/search?q=Heim&amp;lang=de-en\">Heim</a>; \
+             <q class=\"example\">Er ist zu Hause.</q> - he is at home</li>\
+             <li class=\"sense\">R&amp;D &lt;unclosed</li></ol></article>"
+        );
+    }
+}
@@ -1,16 +1,114 @@
 use anyhow::{Context, Result};
 use std::fs;
 use std::path::{Path, PathBuf};
-use tracing::info;
+use std::sync::mpsc;
+use std::thread;
+use tracing::{info, warn};
 
-use crate::models::DictionaryEntry;
-use crate::parser;
-use crate::search::SearchEngine;
+use crate::abbreviations::Abbreviations;
+use crate::backend::{self, Backend, BackendKind};
+use crate::cache::{self, DownloadCache};
+use crate::config::Config;
+use crate::freedict;
+use crate::frequency::FrequencyList;
+use crate::license;
+use crate::manifest::{self, Manifest, ManifestSource};
+use crate::models::{DictionaryEntry, ImportFormat, LanguageCount, LanguagePair};
+use crate::parser::{self, ParseStats};
+use crate::progress::Progress;
+use crate::search::{IndexOptions, SearchEngine};
+use crate::wiktionary;
+use crate::wordlist::{self, Columns};
+
+/// Parsed batches buffered between the parser thread and the index writer
+const IMPORT_QUEUE_BATCHES: usize = 8;
+
+/// Directory under `data/` where downloads are extracted before they are
+/// moved into place, skipped when looking for dictionary files
+const STAGING_DIR: &str = ".partial";
+
+/// Estimated size of an extracted FreeDict archive, per archive byte
+const EXTRACTED_BYTES_PER_ARCHIVE_BYTE: u64 = 2;
+
+/// Estimated index size per byte of (compressed) dictionary source;
+/// edge n-grams double it
+const INDEX_BYTES_PER_SOURCE_BYTE: u64 = 4;
+
+/// Free space required on top of the estimate, for index merges and the manifest
+const SPACE_HEADROOM_BYTES: u64 = 50_000_000;
+
+/// Receives parsed entries batch by batch
+type BatchSink<'a> = dyn FnMut(Vec<DictionaryEntry>) -> Result<()> + 'a;
+
+/// What an import would add, gathered by a dry run
+#[derive(Debug, Default)]
+pub struct ImportPreview {
+    /// Number of entries that would be indexed
+    pub entries: usize,
+    /// The first parsed entries
+    pub samples: Vec<DictionaryEntry>,
+    pub stats: ParseStats,
+}
+
+/// Outcome of a rebuild, per dictionary
+#[derive(Debug, Default)]
+pub struct RebuildReport {
+    /// Total number of entries in the new index
+    pub indexed: usize,
+    /// Dictionaries that were indexed, with their entry counts
+    pub succeeded: Vec<(PathBuf, usize)>,
+    /// Dictionaries that failed to parse, with the error
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+impl RebuildReport {
+    /// Whether there were dictionaries to rebuild and none of them succeeded
+    pub fn all_failed(&self) -> bool {
+        self.succeeded.is_empty() && !self.failed.is_empty()
+    }
+}
+
+/// A dictd dictionary found by `find_dictd_pairs`
+#[derive(Debug, Clone, PartialEq)]
+pub struct DictdPair {
+    pub dict_path: PathBuf,
+    pub index_path: PathBuf,
+    /// Language pair recognized in the file name
+    pub language: Option<LanguagePair>,
+}
+
+/// A dictionary file in the data directory that is not in the manifest yet
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewSource {
+    pub path: PathBuf,
+    /// The `.index` file of a dictd dictionary
+    pub index_path: Option<PathBuf>,
+    pub format: ImportFormat,
+    /// Language pair recognized in the file name
+    pub language: Option<LanguagePair>,
+}
+
+/// Files removed by `clean`
+#[derive(Debug, Default)]
+pub struct CleanReport {
+    /// Archives and extracted directories that were removed
+    pub removed: Vec<PathBuf>,
+    /// Bytes freed
+    pub reclaimed_bytes: u64,
+}
 
 /// Index manager for dictionaries
 pub struct IndexManager {
+    base_dir: PathBuf,
     data_dir: PathBuf,
     index_dir: PathBuf,
+    index_options: IndexOptions,
+    backend: BackendKind,
+    progress: Progress,
+    download_cache: Option<DownloadCache>,
+    columns: Option<Columns>,
+    /// License recorded for imported dictionaries instead of the detected one
+    license: Option<String>,
 }
 
 impl IndexManager {
@@ -22,14 +120,59 @@ impl IndexManager {
 
         fs::create_dir_all(&data_dir)?;
         fs::create_dir_all(&index_dir)?;
+        let backend = Config::load(base_path.join("config.toml"))?.backend;
 
         Ok(Self {
+            base_dir: base_path.to_path_buf(),
             data_dir,
             index_dir,
+            index_options: IndexOptions::default(),
+            backend,
+            progress: Progress::default(),
+            download_cache: DownloadCache::from_env(),
+            columns: None,
+            license: None,
         })
     }
 
+    /// Set the options used when (re)building the index
+    pub fn with_index_options(mut self, index_options: IndexOptions) -> Self {
+        self.index_options = index_options;
+        self
+    }
+
+    /// Set the store the index is kept in, instead of `backend` from config.toml
+    pub fn with_backend(mut self, backend: BackendKind) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Set which columns hold the word and definition of imported CSV/TSV word lists
+    pub fn with_columns(mut self, columns: Option<Columns>) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    /// Record imported dictionaries under this license instead of detecting it
+    pub fn with_license(mut self, license: Option<String>) -> Self {
+        self.license = license;
+        self
+    }
+
+    /// Report import and rebuild progress to `progress`
+    pub fn with_progress(mut self, progress: Progress) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Reuse downloads from `cache`, or always download with `None`
+    pub fn with_download_cache(mut self, cache: Option<DownloadCache>) -> Self {
+        self.download_cache = cache;
+        self
+    }
+
     /// Get the default index manager using system directories
+    #[allow(clippy::should_implement_trait)]
     pub fn default() -> Result<Self> {
         let home = dirs::home_dir().context("Could not find home directory")?;
         let base_dir = home.join(".dictv");
@@ -49,59 +192,574 @@ impl IndexManager {
             index_path.as_ref()
         );
 
-        let entries = parser::parse_dict(dict_path, index_path, language)?;
-        info!("Parsed {} entries", entries.len());
-
-        self.add_entries_to_index(entries)?;
+        let dict_path = dict_path.as_ref().to_path_buf();
+        let index_path = index_path.as_ref().to_path_buf();
+        let name = manifest::source_name(&dict_path);
+        self.ensure_index_space(&[&dict_path], 0, "import")?;
+        self.progress
+            .report("parse", 0, format!("Parsing {}", dict_path.display()));
+        let (parsed, _) = self.build_index_streaming(|sink| {
+            parser::parse_dict_batches(
+                &dict_path,
+                &index_path,
+                language,
+                &mut ParseStats::default(),
+                |batch| sink(tag_source(batch, &name)),
+            )
+        })?;
+        info!("Parsed {} entries", parsed);
 
+        self.record_source(&dict_path, Some(&index_path), ImportFormat::Dictd, language)?;
+        self.progress
+            .report("done", 100, format!("Indexed {} entries", parsed));
         Ok(())
     }
 
-    /// Download and import FreeDict dictionary
-    pub fn import_freedict(&self, dict_name: &str) -> Result<()> {
-        let (url, language, base_name) = match dict_name {
-            "freedict-eng-deu" => (
-                "https://download.freedict.org/dictionaries/eng-deu/1.9-fd1/freedict-eng-deu-1.9-fd1.dictd.tar.xz",
-                "en-de",
-                "eng-deu",
-            ),
-            "freedict-deu-eng" => (
-                "https://download.freedict.org/dictionaries/deu-eng/1.9-fd1/freedict-deu-eng-1.9-fd1.dictd.tar.xz",
-                "de-en",
-                "deu-eng",
-            ),
-            _ => anyhow::bail!("Unknown dictionary: {}", dict_name),
+    /// Import several dictd dictionaries in one rebuild, like `provision` does
+    /// for downloads. Dictionaries that fail to parse are not recorded.
+    pub fn import_dictd_pairs(&self, pairs: &[DictdPair]) -> Result<RebuildReport> {
+        for pair in pairs {
+            let language = pair
+                .language
+                .context(format!("No language pair for {}", pair.dict_path.display()))?;
+            self.record_source(
+                &pair.dict_path,
+                Some(&pair.index_path),
+                ImportFormat::Dictd,
+                language.as_str(),
+            )?;
+        }
+
+        let report = self.rebuild()?;
+        if !report.failed.is_empty() {
+            let mut manifest = self.manifest()?;
+            manifest
+                .sources
+                .retain(|source| !report.failed.iter().any(|(path, _)| *path == source.path));
+            manifest.save(self.manifest_path())?;
+        }
+        Ok(report)
+    }
+
+    /// Dictionaries dropped into the data directory without being imported:
+    /// dictd pairs anywhere below it, and dict.cc exports (`.tsv`, `.txt`)
+    /// directly in it whose name gives the language pair. Files of imports
+    /// in progress are not included.
+    pub fn new_sources(&self) -> Result<Vec<NewSource>> {
+        let manifest = self.manifest()?;
+        let known = |path: &Path| {
+            fs::canonicalize(path).is_ok_and(|path| manifest.contains(&path))
+                || manifest
+                    .pending
+                    .iter()
+                    .flat_map(|import| &import.artifacts)
+                    .any(|artifact| path.starts_with(artifact))
+                || path.starts_with(self.data_dir.join(STAGING_DIR))
         };
 
-        info!("Downloading {} from FreeDict", dict_name);
+        let mut sources: Vec<NewSource> = find_dictd_pairs(&self.data_dir)?
+            .into_iter()
+            .filter(|pair| !known(&pair.dict_path))
+            .map(|pair| NewSource {
+                path: pair.dict_path,
+                index_path: Some(pair.index_path),
+                format: ImportFormat::Dictd,
+                language: pair.language,
+            })
+            .collect();
+        for entry in fs::read_dir(&self.data_dir)? {
+            let path = entry?.path();
+            let is_export = matches!(
+                path.extension().and_then(|s| s.to_str()),
+                Some("tsv" | "txt")
+            );
+            let language = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(parser::language_from_file_name);
+            if path.is_file() && is_export && language.is_some() && !known(&path) {
+                sources.push(NewSource {
+                    path,
+                    index_path: None,
+                    format: ImportFormat::DictCc,
+                    language,
+                });
+            }
+        }
+        Ok(sources)
+    }
+
+    /// Add a dropped dictionary to the existing index and record it, without
+    /// rebuilding. Returns the number of entries added.
+    pub fn import_new_source(&self, source: &NewSource) -> Result<usize> {
+        let language = source.language.context(format!(
+            "Cannot tell the language pair of {} from its name",
+            source.path.display()
+        ))?;
+        let language = language.as_str();
+        let name = manifest::source_name(&source.path);
+        self.ensure_index_space(&[&source.path], 0, "import")?;
+        // Parse the whole file first, so a broken one adds nothing to the index
+        let entries = match &source.index_path {
+            Some(index_path) => parser::parse_dict(&source.path, index_path, language)?,
+            None => parser::parse_file(
+                &source.path,
+                source.format,
+                language,
+                &mut ParseStats::default(),
+            )?,
+        };
+        let added = self
+            .backend
+            .add_from_batches(&self.index_dir, std::iter::once(tag_source(entries, &name)))?;
+        self.record_source(
+            &source.path,
+            source.index_path.as_deref(),
+            source.format,
+            language,
+        )?;
+        Ok(added)
+    }
 
-        // Download tar.xz archive
-        let tar_path = self.data_dir.join(format!("{}.tar.xz", dict_name));
-        download_file(url, &tar_path)?;
+    /// Build the index from batches emitted by `produce` on a parser thread.
+    ///
+    /// Batches pass through a bounded channel, so at most a few batches are
+    /// held in memory no matter how many entries `produce` emits. Returns the
+    /// number of indexed entries along with `produce`'s result.
+    fn build_index_streaming<F, T>(&self, produce: F) -> Result<(usize, T)>
+    where
+        F: FnOnce(&mut BatchSink<'_>) -> Result<T> + Send,
+        T: Send,
+    {
+        let (sender, receiver) = mpsc::sync_channel(IMPORT_QUEUE_BATCHES);
 
-        info!("Extracting archive...");
+        thread::scope(|scope| {
+            let parser = scope.spawn(move || {
+                let mut sink = |batch: Vec<DictionaryEntry>| {
+                    sender
+                        .send(batch)
+                        .map_err(|_| anyhow::anyhow!("Index writer stopped"))
+                };
+                produce(&mut sink)
+            });
 
-        // Extract the tar.xz archive
-        extract_tar_xz(&tar_path, &self.data_dir)?;
+            let indexed =
+                self.backend
+                    .build_from_batches(&self.index_dir, receiver, &self.index_options);
+            let parsed = parser
+                .join()
+                .map_err(|_| anyhow::anyhow!("Parser thread panicked"))?;
+            // Report the parser's error first, a failed parse stops the writer early
+            let produced = parsed?;
+            Ok((indexed?, produced))
+        })
+    }
 
-        // Find the extracted .dict.dz and .index files by searching recursively
-        let (dict_path, index_path) = find_dict_files(&self.data_dir, base_name)?;
+    /// Import a single-file dictionary (e.g. a dict.cc export) from a local path
+    pub fn import_file<P: AsRef<Path>>(
+        &self,
+        path: P,
+        format: ImportFormat,
+        language: &str,
+    ) -> Result<()> {
+        info!(
+            "Importing {} dictionary from {:?}",
+            format.as_str(),
+            path.as_ref()
+        );
+
+        self.ensure_index_space(&[path.as_ref()], 0, "import")?;
+        self.progress
+            .report("parse", 0, format!("Parsing {}", path.as_ref().display()));
+        let entries = parse_file(
+            &path,
+            format,
+            self.columns.as_ref(),
+            language,
+            &mut ParseStats::default(),
+        )?;
+        let entries = tag_source(entries, &manifest::source_name(path.as_ref()));
+        let count = entries.len();
+        info!("Parsed {} entries", count);
+
+        self.add_entries_to_index(entries)?;
+        if format == ImportFormat::Yomichan {
+            self.import_yomichan_frequencies(path.as_ref(), language)?;
+        }
+
+        self.record_source(path.as_ref(), None, format, language)?;
+        self.progress
+            .report("done", 100, format!("Indexed {} entries", count));
+        Ok(())
+    }
+
+    /// Parse local dictd files without touching the index
+    pub fn preview_local<P: AsRef<Path>>(
+        &self,
+        dict_path: P,
+        index_path: P,
+        language: &str,
+        sample_size: usize,
+    ) -> Result<ImportPreview> {
+        let mut preview = ImportPreview::default();
+        preview.entries = parser::parse_dict_batches(
+            dict_path,
+            index_path,
+            language,
+            &mut preview.stats,
+            |batch| {
+                let remaining = sample_size.saturating_sub(preview.samples.len());
+                preview.samples.extend(batch.into_iter().take(remaining));
+                Ok(())
+            },
+        )?;
+        Ok(preview)
+    }
+
+    /// Parse a single-file dictionary without touching the index
+    pub fn preview_file<P: AsRef<Path>>(
+        &self,
+        path: P,
+        format: ImportFormat,
+        language: &str,
+        sample_size: usize,
+    ) -> Result<ImportPreview> {
+        let mut stats = ParseStats::default();
+        let entries = parse_file(path, format, self.columns.as_ref(), language, &mut stats)?;
+        Ok(ImportPreview {
+            entries: entries.len(),
+            samples: entries.into_iter().take(sample_size).collect(),
+            stats,
+        })
+    }
+
+    /// Download and import a FreeDict dictionary, returning its language pair
+    pub fn import_freedict(&self, dict_name: &str) -> Result<String> {
+        let (dict_path, index_path, language) = self.download_freedict(dict_name)?;
 
         info!("Downloaded successfully, parsing...");
 
         // Parse and import
-        self.import_local(&dict_path, &index_path, language)?;
+        let imported = self.import_local(&dict_path, &index_path, &language);
+        self.finish_import(&freedict_name(dict_name), imported.is_ok())?;
+        imported?;
+        self.clean_after_import();
+        Ok(language)
+    }
+
+    /// Download and import the TEI source of a FreeDict dictionary, keeping
+    /// the senses, parts of speech and genders the dictd conversion flattens.
+    /// Returns its language pair.
+    pub fn import_freedict_tei(&self, dict_name: &str) -> Result<String> {
+        let (tei_path, language) = self.download_freedict_tei(dict_name)?;
+
+        info!("Downloaded successfully, parsing...");
+
+        let imported = self.import_file(&tei_path, ImportFormat::Tei, &language);
+        self.finish_import(&freedict_name(dict_name), imported.is_ok())?;
+        imported?;
+        self.clean_after_import();
+        Ok(language)
+    }
+
+    /// Download and extract a FreeDict dictionary into the data directory,
+    /// returning its `.dict.dz` and `.index` files and language pair
+    fn download_freedict(&self, dict_name: &str) -> Result<(PathBuf, PathBuf, String)> {
+        let (language, base_name) =
+            self.download_freedict_release(dict_name, "dictd", |dir, base_name| {
+                find_dict_files(dir, base_name).map(drop)
+            })?;
+
+        // Find the extracted .dict.dz and .index files by searching recursively
+        let (dict_path, index_path) = find_dict_files(&self.data_dir, &base_name)?;
+        Ok((dict_path, index_path, language))
+    }
+
+    /// Download and extract the TEI source of a FreeDict dictionary into the
+    /// data directory, returning its `.tei` file and language pair
+    fn download_freedict_tei(&self, dict_name: &str) -> Result<(PathBuf, String)> {
+        let (language, base_name) =
+            self.download_freedict_release(dict_name, "src", |dir, base_name| {
+                find_tei_file(dir, base_name).map(drop)
+            })?;
+        Ok((find_tei_file(&self.data_dir, &base_name)?, language))
+    }
+
+    /// Download the `variant` archive ("dictd" or "src") of a FreeDict release
+    /// listed in the FreeDict database and extract it into the data directory,
+    /// after `locate` found the expected files in it. Returns the language
+    /// pair and the file base name.
+    fn download_freedict_release(
+        &self,
+        dict_name: &str,
+        variant: &str,
+        locate: impl Fn(&Path, &str) -> Result<()>,
+    ) -> Result<(String, String)> {
+        let database =
+            freedict::fetch_database().context("Failed to fetch the FreeDict database")?;
+        let dictionary = freedict::find(&database, dict_name)?;
+        let release = dictionary.release(variant).context(format!(
+            "FreeDict has no {} release of {}",
+            variant, dictionary.name
+        ))?;
+        let language = dictionary
+            .language()
+            .context(format!("Unknown language pair: {}", dictionary.name))?
+            .to_string();
+        let (url, base_name) = (release.url.as_str(), dictionary.name.as_str());
+
+        info!("Downloading {} from FreeDict", dict_name);
+        self.progress
+            .report("download", 0, format!("Downloading {}", dict_name));
+
+        // Download and extract into a staging directory, so an interrupted
+        // download never leaves partial files where rebuild looks for them
+        let name = freedict_name(dict_name);
+        let staging = self.data_dir.join(STAGING_DIR).join(&name);
+        self.begin_import(&name, vec![staging.clone()])?;
+        let staged = (|| {
+            if staging.exists() {
+                fs::remove_dir_all(&staging)?;
+            }
+            fs::create_dir_all(&staging)?;
+
+            // Download tar.xz archive
+            let tar_path = staging.join(format!("freedict-{}.{}.tar.xz", base_name, variant));
+            self.fetch(url, &tar_path, |archive| {
+                let extracted = archive * EXTRACTED_BYTES_PER_ARCHIVE_BYTE;
+                ensure_free_space(
+                    &self.data_dir,
+                    archive + extracted + self.estimated_index_size(archive),
+                    &format!("download and import {}", dict_name),
+                )
+            })?;
+
+            info!("Extracting archive...");
+            self.progress
+                .report("extract", 0, format!("Extracting {}", tar_path.display()));
+
+            // Extract the tar.xz archive
+            extract_tar_xz(&tar_path, &staging)?;
 
-        // Clean up tar archive
-        let _ = fs::remove_file(&tar_path);
+            // Clean up tar archive
+            let _ = fs::remove_file(&tar_path);
 
+            // Check the extracted dictionary files are there before moving them
+            locate(&staging, base_name)
+        })();
+        let moved = staged.and_then(|_| self.unstage(&name, &staging));
+        if let Err(e) = moved {
+            self.finish_import(&name, false)?;
+            return Err(e);
+        }
+        Ok((language, base_name.to_string()))
+    }
+
+    /// Download `url` to `dest`, or copy it from the download cache when it was
+    /// downloaded before. `check_size` is called with the size first.
+    fn fetch(
+        &self,
+        url: &str,
+        dest: &Path,
+        check_size: impl FnOnce(u64) -> Result<()>,
+    ) -> Result<()> {
+        if let Some(cache) = &self.download_cache {
+            match cache.lookup(url) {
+                Ok(Some(cached)) => {
+                    info!("Using cached download {:?}", cached);
+                    check_size(fs::metadata(&cached)?.len())?;
+                    return cache::link_or_copy(&cached, dest);
+                }
+                Ok(None) => {}
+                Err(e) => warn!("Failed to read the download cache: {:#}", e),
+            }
+        }
+
+        download_file(url, dest, check_size)?;
+        if let Some(cache) = &self.download_cache
+            && let Err(e) = cache.insert(url, dest)
+        {
+            warn!("Failed to cache the download of {}: {:#}", url, e);
+        }
         Ok(())
     }
 
+    /// Index size expected for `source_bytes` of dictionary files
+    fn estimated_index_size(&self, source_bytes: u64) -> u64 {
+        let factor = if self.index_options.edge_ngrams { 2 } else { 1 };
+        source_bytes * INDEX_BYTES_PER_SOURCE_BYTE * factor
+    }
+
+    /// Fail early if the index of `sources` will not fit, counting `freed`
+    /// bytes of the current index as free when it is about to be replaced
+    fn ensure_index_space(&self, sources: &[&Path], freed: u64, task: &str) -> Result<()> {
+        let source_bytes = sources
+            .iter()
+            .filter_map(|path| fs::metadata(path).ok())
+            .map(|metadata| metadata.len())
+            .sum();
+        let required = self
+            .estimated_index_size(source_bytes)
+            .saturating_sub(freed);
+        ensure_free_space(&self.index_dir, required, task)
+    }
+
+    /// Record an import as in progress with the files it creates
+    fn begin_import(&self, name: &str, artifacts: Vec<PathBuf>) -> Result<()> {
+        let mut manifest = self.manifest()?;
+        manifest.begin_import(name, artifacts);
+        manifest.save(self.manifest_path())
+    }
+
+    /// Move extracted files from `staging` into the data directory, merging
+    /// into directories that already exist and replacing files of the same name
+    fn unstage(&self, name: &str, staging: &Path) -> Result<()> {
+        let mut created = Vec::new();
+        merge_dir(staging, &self.data_dir, &mut created)?;
+        fs::remove_dir_all(staging)?;
+        // Until the import finishes, the files it added are its partial artifacts
+        self.begin_import(name, created)
+    }
+
+    /// Stop tracking an import, deleting its files unless it succeeded
+    fn finish_import(&self, name: &str, succeeded: bool) -> Result<()> {
+        let mut manifest = self.manifest()?;
+        if let Some(pending) = manifest.end_import(name)
+            && !succeeded
+        {
+            remove_artifacts(&pending.artifacts);
+        }
+        manifest.save(self.manifest_path())
+    }
+
+    /// Delete the files of imports that were interrupted, so they are never indexed.
+    /// Returns the names of those imports.
+    pub fn clean_interrupted_imports(&self) -> Result<Vec<String>> {
+        let mut manifest = self.manifest()?;
+        if manifest.pending.is_empty() {
+            return Ok(Vec::new());
+        }
+        let pending = std::mem::take(&mut manifest.pending);
+        for import in &pending {
+            warn!("Removing files of interrupted import {}", import.name);
+            remove_artifacts(&import.artifacts);
+        }
+        manifest.save(self.manifest_path())?;
+        Ok(pending.into_iter().map(|import| import.name).collect())
+    }
+
+    /// Remove archives and extracted directories under `data/` that no imported
+    /// dictionary refers to. Directories without dictionary files (logs,
+    /// frequency lists) and files of imports in progress are left alone.
+    pub fn clean(&self) -> Result<CleanReport> {
+        let manifest = self.manifest()?;
+        let in_progress: Vec<&PathBuf> = manifest
+            .pending
+            .iter()
+            .flat_map(|import| &import.artifacts)
+            .collect();
+        let mut report = CleanReport::default();
+
+        for entry in fs::read_dir(&self.data_dir)? {
+            let path = entry?.path();
+            let file_name = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or_default();
+            if file_name == STAGING_DIR || in_progress.iter().any(|p| p.starts_with(&path)) {
+                continue;
+            }
+
+            let referenced =
+                |file: &Path| fs::canonicalize(file).is_ok_and(|file| manifest.contains(&file));
+            let (stale, size) = if path.is_dir() {
+                let mut files = Vec::new();
+                collect_dictionary_files(&path, &mut files)?;
+                let referenced = files.iter().any(|file| referenced(file));
+                (!files.is_empty() && !referenced, get_dir_size(&path)?)
+            } else {
+                // Archives can be dictionaries themselves, e.g. Yomichan term banks
+                (
+                    is_archive(file_name) && !referenced(&path),
+                    fs::metadata(&path)?.len(),
+                )
+            };
+            if !stale {
+                continue;
+            }
+
+            info!("Removing {:?}", path);
+            if path.is_dir() {
+                fs::remove_dir_all(&path)?;
+            } else {
+                fs::remove_file(&path)?;
+            }
+            report.reclaimed_bytes += size;
+            report.removed.push(path);
+        }
+        Ok(report)
+    }
+
+    /// Clean up after a successful import, which should not fail because of it
+    fn clean_after_import(&self) {
+        match self.clean() {
+            Ok(report) if !report.removed.is_empty() => info!(
+                "Removed {} stale archives and directories, reclaimed {} bytes",
+                report.removed.len(),
+                report.reclaimed_bytes
+            ),
+            Ok(_) => {}
+            Err(e) => warn!("Failed to clean up the data directory: {:#}", e),
+        }
+    }
+
+    /// FreeDict dictionaries from `names` that have not been imported yet
+    pub fn missing_freedict(&self, names: &[String]) -> Result<Vec<String>> {
+        let manifest = self.manifest()?;
+        Ok(names
+            .iter()
+            .map(|name| freedict_name(name))
+            .filter(|name| {
+                !manifest
+                    .sources
+                    .iter()
+                    .any(|source| source.name.starts_with(&format!("{}-", name)))
+            })
+            .collect())
+    }
+
+    /// Download every missing FreeDict dictionary from `names` and rebuild the
+    /// index once. Returns the dictionaries that were added.
+    pub fn provision(&self, names: &[String]) -> Result<Vec<String>> {
+        let missing = self.missing_freedict(names)?;
+        if missing.is_empty() {
+            return Ok(missing);
+        }
+
+        for name in &missing {
+            let (dict_path, index_path, language) = self.download_freedict(name)?;
+            let recorded = self.record_source(
+                &dict_path,
+                Some(&index_path),
+                ImportFormat::Dictd,
+                &language,
+            );
+            self.finish_import(name, recorded.is_ok())?;
+            recorded?;
+        }
+
+        let report = self.rebuild()?;
+        if report.all_failed() {
+            anyhow::bail!("No dictionary could be indexed");
+        }
+        self.clean_after_import();
+        Ok(missing)
+    }
+
     /// Add entries to the index
     fn add_entries_to_index(&self, entries: Vec<DictionaryEntry>) -> Result<()> {
         // Check if index exists
-        let index_exists = self.index_dir.join("meta.json").exists();
+        let index_exists = self.has_index();
 
         if index_exists {
             // Load existing index and merge
@@ -110,100 +768,540 @@ impl IndexManager {
             // In production, you might want to merge incrementally
         }
 
-        SearchEngine::build_index(&self.index_dir, entries)?;
+        self.backend.build_from_batches(
+            &self.index_dir,
+            std::iter::once(entries),
+            &self.index_options,
+        )?;
 
         Ok(())
     }
 
-    /// Rebuild the index from all dictionary files
-    pub fn rebuild(&self) -> Result<()> {
+    /// Remember an imported file's format and language for `rebuild`
+    fn record_source(
+        &self,
+        path: &Path,
+        index_path: Option<&Path>,
+        format: ImportFormat,
+        language: &str,
+    ) -> Result<()> {
+        let mut manifest = self.manifest()?;
+        let mut source = ManifestSource::new(fs::canonicalize(path)?, format, language);
+        if let Some(index_path) = index_path {
+            source = source.with_index_path(fs::canonicalize(index_path)?);
+        }
+        if matches!(format, ImportFormat::Csv | ImportFormat::Tsv) {
+            source = source.with_columns(self.columns.clone());
+        }
+        let license = self
+            .license
+            .clone()
+            .or_else(|| license::detect_source(path, index_path, format));
+        manifest.record(source.with_license(license));
+        manifest.save(self.manifest_path())
+    }
+
+    /// Load the manifest of imported dictionaries
+    pub fn manifest(&self) -> Result<Manifest> {
+        Manifest::load(self.manifest_path())
+    }
+
+    /// Include or exclude a dictionary from search without touching its data
+    pub fn set_source_enabled(&self, name: &str, enabled: bool) -> Result<()> {
+        let mut manifest = self.manifest()?;
+        manifest.set_enabled(name, enabled)?;
+        manifest.save(self.manifest_path())
+    }
+
+    /// Set the license a dictionary is distributed under
+    pub fn set_source_license(&self, name: &str, license: &str) -> Result<()> {
+        let mut manifest = self.manifest()?;
+        manifest.set_license(name, license)?;
+        manifest.save(self.manifest_path())
+    }
+
+    /// Search engine over the index with frequencies, disabled dictionaries and licenses applied
+    pub fn search_engine(&self) -> Result<SearchEngine> {
+        if self.backend != BackendKind::Tantivy {
+            anyhow::bail!(
+                "This command needs the tantivy backend, the index is kept in {}",
+                self.backend.as_str()
+            );
+        }
+        let manifest = self.manifest()?;
+        Ok(SearchEngine::new(&self.index_dir)?
+            .with_frequencies(self.frequencies()?)
+            .with_disabled_sources(manifest.disabled())
+            .with_licenses(manifest.licenses()))
+    }
+
+    /// Searches over the index in whichever store `backend` selects
+    pub fn search_backend(&self) -> Result<Box<dyn Backend>> {
+        match self.backend {
+            BackendKind::Tantivy => Ok(Box::new(self.search_engine()?)),
+            BackendKind::Sqlite => backend::open_sqlite(&self.index_dir),
+        }
+    }
+
+    fn manifest_path(&self) -> PathBuf {
+        self.data_dir.join("manifest.json")
+    }
+
+    /// Rebuild the index from all dictionaries recorded in the manifest.
+    ///
+    /// Fails without touching the index if the data directory holds
+    /// dictionary files that were never imported. A dictionary that fails to
+    /// parse is skipped and listed in the report.
+    pub fn rebuild(&self) -> Result<RebuildReport> {
         info!("Rebuilding index from all dictionary files");
 
+        self.clean_interrupted_imports()?;
+        let manifest = self.manifest()?;
+        let mut unrecorded = Vec::new();
+        find_unrecorded_dictionaries(&self.data_dir, &manifest, &mut unrecorded)?;
+        if !unrecorded.is_empty() {
+            anyhow::bail!(
+                "Unknown dictionary files in {:?} (import them with `dictv import` so their language and format are recorded): {:?}",
+                self.data_dir,
+                unrecorded
+            );
+        }
+        let sources: Vec<&Path> = manifest.sources.iter().map(|s| s.path.as_path()).collect();
+        self.ensure_index_space(
+            &sources,
+            get_dir_size(&self.index_dir)?,
+            "rebuild the index",
+        )?;
+
         // Remove existing index
         if self.index_dir.exists() {
             fs::remove_dir_all(&self.index_dir)?;
             fs::create_dir_all(&self.index_dir)?;
         }
 
-        // Find all dictionary files (recursively)
-        let mut all_entries = Vec::new();
-        find_dict_files_recursively(&self.data_dir, &mut all_entries)?;
+        // Stream all recorded dictionaries into the new index
+        let (total, mut report) = self
+            .build_index_streaming(|sink| parse_sources(&manifest.sources, sink, &self.progress))?;
+        report.indexed = total;
 
-        info!("Rebuilding index with {} total entries", all_entries.len());
-        SearchEngine::build_index(&self.index_dir, all_entries)?;
+        info!(
+            "Rebuilt index with {} total entries from {} of {} dictionaries",
+            total,
+            report.succeeded.len(),
+            manifest.sources.len()
+        );
+        self.progress
+            .report("done", 100, format!("Indexed {} entries", total));
 
-        Ok(())
+        Ok(report)
     }
 
-    /// Get index statistics
-    pub fn stats(&self) -> Result<(usize, usize, usize, u64)> {
-        let engine = SearchEngine::new(&self.index_dir)?;
-        let (total, en_de, de_en) = engine.get_stats()?;
+    /// Total entry count, the entry count of each language pair and the index size
+    pub fn stats(&self) -> Result<(usize, Vec<LanguageCount>, u64)> {
+        let (total, languages) = self.search_backend()?.get_stats()?;
 
         let index_size = get_dir_size(&self.index_dir)?;
 
-        Ok((total, en_de, de_en, index_size))
+        Ok((total, languages, index_size))
+    }
+
+    /// Import a word frequency list (most frequent first) for a language direction
+    pub fn import_frequency<P: AsRef<Path>>(&self, path: P, language: &str) -> Result<usize> {
+        let ranks = crate::frequency::parse_frequency_file(path.as_ref())?;
+
+        let frequency_dir = self.frequency_dir();
+        fs::create_dir_all(&frequency_dir)?;
+        fs::copy(
+            path.as_ref(),
+            frequency_dir.join(format!("{}.txt", language)),
+        )?;
+
+        info!("Imported {} frequency ranks for {}", ranks.len(), language);
+        Ok(ranks.len())
+    }
+
+    /// Use the frequency data of a Yomichan dictionary as the frequency list
+    /// of its language direction, unless one was already imported
+    fn import_yomichan_frequencies(&self, path: &Path, language: &str) -> Result<()> {
+        let terms = crate::yomichan::parse_frequencies(path)?;
+        let list_path = self.frequency_dir().join(format!("{}.txt", language));
+        if terms.is_empty() {
+            return Ok(());
+        }
+        if list_path.exists() {
+            info!(
+                "Keeping the imported frequency list for {}, ignoring {} ranks in {:?}",
+                language,
+                terms.len(),
+                path
+            );
+            return Ok(());
+        }
+
+        fs::create_dir_all(self.frequency_dir())?;
+        let mut list = terms.join("\n");
+        list.push('\n');
+        fs::write(&list_path, list)?;
+        info!("Imported {} frequency ranks for {}", terms.len(), language);
+        Ok(())
+    }
+
+    /// Load all imported frequency lists
+    pub fn frequencies(&self) -> Result<FrequencyList> {
+        FrequencyList::load_dir(self.frequency_dir())
+    }
+
+    /// Load `config.toml` from the base directory
+    pub fn config(&self) -> Result<Config> {
+        Config::load(self.base_dir.join("config.toml"))
+    }
+
+    /// Load the abbreviation glossary, extended by `data/abbreviations.tsv` if present
+    pub fn abbreviations(&self) -> Result<Abbreviations> {
+        Abbreviations::default().with_file(self.data_dir.join("abbreviations.tsv"))
+    }
+
+    /// Saved words, see `favorites`
+    pub fn favorites_path(&self) -> PathBuf {
+        self.data_dir.join("favorites.json")
+    }
+
+    /// Pronunciations: TTS audio by language, and downloaded recordings in `wiktionary/`
+    pub fn audio_dir(&self) -> PathBuf {
+        self.base_dir.join("audio")
+    }
+
+    /// Current query log file, see `querylog`
+    pub fn query_log_path(&self) -> PathBuf {
+        self.data_dir.join("logs").join("queries.jsonl")
+    }
+
+    /// Directory holding imported frequency lists
+    fn frequency_dir(&self) -> PathBuf {
+        self.data_dir.join("frequency")
     }
 
     /// Get the index directory path
     pub fn index_dir(&self) -> &Path {
         &self.index_dir
     }
+
+    /// Whether an index has been built in the index directory
+    pub fn has_index(&self) -> bool {
+        self.backend.has_index(&self.index_dir)
+    }
 }
 
-/// Recursively find and parse dictionary files
-fn find_dict_files_recursively<P: AsRef<Path>>(
-    dir: P,
-    all_entries: &mut Vec<DictionaryEntry>,
+/// Parse each recorded source with its recorded format and language.
+///
+/// A source that fails to parse is recorded in the report and skipped; only a
+/// stopped index writer aborts the whole run.
+fn parse_sources(
+    sources: &[ManifestSource],
+    sink: &mut BatchSink<'_>,
+    progress: &Progress,
+) -> Result<RebuildReport> {
+    let mut report = RebuildReport::default();
+
+    for (position, source) in sources.iter().enumerate() {
+        progress.report(
+            "parse",
+            position * 100 / sources.len(),
+            format!("Parsing {}", source.name),
+        );
+        info!(
+            "[{}/{}] Processing {:?} ({}, {})",
+            position + 1,
+            sources.len(),
+            source.path,
+            source.format.as_str(),
+            source.language
+        );
+
+        let mut writer_stopped = false;
+        let mut forward = |batch: Vec<DictionaryEntry>| {
+            let sent = sink(batch);
+            writer_stopped |= sent.is_err();
+            sent
+        };
+        let parsed = parse_source(source, &mut forward);
+        if writer_stopped {
+            return parsed.map(|_| report);
+        }
+
+        match parsed {
+            Ok(entries) => {
+                info!(
+                    "[{}/{}] Parsed {} entries",
+                    position + 1,
+                    sources.len(),
+                    entries
+                );
+                report.succeeded.push((source.path.clone(), entries));
+            }
+            Err(e) => {
+                warn!(
+                    "[{}/{}] Skipping {:?}: {:#}",
+                    position + 1,
+                    sources.len(),
+                    source.path,
+                    e
+                );
+                progress.report(
+                    "parse",
+                    (position + 1) * 100 / sources.len(),
+                    format!("Skipping {}: {:#}", source.name, e),
+                );
+                report
+                    .failed
+                    .push((source.path.clone(), format!("{:#}", e)));
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Parse one recorded source into `sink`, returning its entry count
+fn parse_source(source: &ManifestSource, sink: &mut BatchSink<'_>) -> Result<usize> {
+    match (source.format, &source.index_path) {
+        (ImportFormat::Dictd, Some(index_path)) => parser::parse_dict_batches(
+            &source.path,
+            index_path,
+            &source.language,
+            &mut ParseStats::default(),
+            |batch| sink(tag_source(batch, &source.name)),
+        ),
+        (ImportFormat::Dictd, None) => {
+            anyhow::bail!("No index file recorded for {:?}", source.path)
+        }
+        // Dumps are too large to parse into memory before indexing
+        (ImportFormat::Wiktionary, _) => wiktionary::parse_wiktionary_batches(
+            &source.path,
+            &mut ParseStats::default(),
+            |batch| sink(tag_source(batch, &source.name)),
+        ),
+        (format, _) => {
+            let entries = parse_file(
+                &source.path,
+                format,
+                source.columns.as_ref(),
+                &source.language,
+                &mut ParseStats::default(),
+            )?;
+            let count = entries.len();
+            sink(tag_source(entries, &source.name))?;
+            Ok(count)
+        }
+    }
+}
+
+/// `parser::parse_file` with the column mapping of word lists applied
+fn parse_file<P: AsRef<Path>>(
+    path: P,
+    format: ImportFormat,
+    columns: Option<&Columns>,
+    language: &str,
+    stats: &mut ParseStats,
+) -> Result<Vec<DictionaryEntry>> {
+    match (format, columns) {
+        (ImportFormat::Csv | ImportFormat::Tsv, Some(columns)) => {
+            wordlist::parse_word_list(path, format, columns, language, stats)
+        }
+        _ => parser::parse_file(path, format, language, stats),
+    }
+}
+
+/// Mark entries as coming from the named dictionary
+fn tag_source(entries: Vec<DictionaryEntry>, name: &str) -> Vec<DictionaryEntry> {
+    entries
+        .into_iter()
+        .map(|entry| entry.with_source(name))
+        .collect()
+}
+
+/// Recursively collect dictionary files under `dir` that the manifest does not know
+fn find_unrecorded_dictionaries(
+    dir: &Path,
+    manifest: &Manifest,
+    unrecorded: &mut Vec<PathBuf>,
 ) -> Result<()> {
     for entry in fs::read_dir(dir)? {
-        let entry = entry?;
-        let path = entry.path();
+        let path = entry?.path();
 
         if path.is_dir() {
-            // Recurse into subdirectories
-            find_dict_files_recursively(&path, all_entries)?;
-        } else if path.extension().and_then(|s| s.to_str()) == Some("dz") {
-            let dict_name = path.file_stem().unwrap().to_str().unwrap();
-            // Handle .dict.dz files - remove the .dict extension to find matching .index file
-            let base_name = if dict_name.ends_with(".dict") {
-                &dict_name[..dict_name.len() - 5]
-            } else {
-                dict_name
-            };
+            if path.file_name().is_some_and(|name| name == STAGING_DIR) {
+                continue;
+            }
+            find_unrecorded_dictionaries(&path, manifest, unrecorded)?;
+        } else if path.extension().and_then(|s| s.to_str()) == Some("dz")
+            && !manifest.contains(&fs::canonicalize(&path)?)
+        {
+            unrecorded.push(path);
+        }
+    }
+    Ok(())
+}
 
-            // Look for index file in the same directory as the dict file
-            let parent_dir = path.parent().unwrap();
-            let index_path = parent_dir.join(format!("{}.index", base_name));
-
-            if index_path.exists() {
-                // Determine language from filename
-                let language = if base_name.contains("eng-deu") {
-                    "en-de"
-                } else if base_name.contains("deu-eng") {
-                    "de-en"
-                } else {
-                    "unknown"
-                };
+/// Recursively collect the dictionary and index files under `dir`
+fn collect_dictionary_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_dictionary_files(&path, files)?;
+        } else if matches!(
+            path.extension().and_then(|s| s.to_str()),
+            Some("dz" | "dict" | "index" | "tei")
+        ) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
 
-                info!("Processing {} ({})", base_name, language);
-                let entries = parser::parse_dict(&path, &index_path, language)?;
-                all_entries.extend(entries);
-            }
+/// Whether a file name is a downloaded dictionary archive
+fn is_archive(file_name: &str) -> bool {
+    [".tar.xz", ".tar.gz", ".tar.bz2", ".zip"]
+        .iter()
+        .any(|extension| file_name.ends_with(extension))
+}
+
+/// Move the contents of `from` into `to`, collecting the paths that did not exist before
+fn merge_dir(from: &Path, to: &Path, created: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(from)? {
+        let path = entry?.path();
+        let target = to.join(path.file_name().unwrap_or_default());
+        if !target.exists() {
+            fs::rename(&path, &target)?;
+            created.push(target);
+        } else if path.is_dir() && target.is_dir() {
+            merge_dir(&path, &target, created)?;
+        } else {
+            fs::rename(&path, &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Delete files and directories left by an unfinished import
+fn remove_artifacts(artifacts: &[PathBuf]) {
+    for path in artifacts {
+        let removed = if path.is_dir() {
+            fs::remove_dir_all(path)
+        } else {
+            fs::remove_file(path)
+        };
+        if let Err(e) = removed
+            && e.kind() != std::io::ErrorKind::NotFound
+        {
+            warn!("Failed to remove {:?}: {}", path, e);
         }
     }
+}
+
+/// Fail early when the filesystem holding `dir` has less than `required`
+/// bytes (plus headroom) free, instead of running out of space halfway
+fn ensure_free_space(dir: &Path, required: u64, task: &str) -> Result<()> {
+    let available = match fs4::available_space(dir) {
+        Ok(available) => available,
+        Err(e) => {
+            warn!("Could not check free disk space in {:?}: {}", dir, e);
+            return Ok(());
+        }
+    };
+    let needed = required.saturating_add(SPACE_HEADROOM_BYTES);
+    if available < needed {
+        anyhow::bail!(
+            "Not enough disk space to {}: about {} MB needed in {:?}, only {} MB available",
+            task,
+            needed / 1_000_000,
+            dir,
+            available / 1_000_000
+        );
+    }
     Ok(())
 }
 
-/// Download a file from a URL
-fn download_file<P: AsRef<Path>>(url: &str, dest: P) -> Result<()> {
+/// Download a file from a URL. `check_size` is called with the download size,
+/// when the server reports it, before anything is written.
+fn download_file<P: AsRef<Path>>(
+    url: &str,
+    dest: P,
+    check_size: impl FnOnce(u64) -> Result<()>,
+) -> Result<()> {
     let response = reqwest::blocking::get(url)?;
+    if let Some(length) = response.content_length() {
+        check_size(length)?;
+    }
     let mut file = fs::File::create(dest)?;
     let content = response.bytes()?;
     std::io::copy(&mut content.as_ref(), &mut file)?;
     Ok(())
 }
 
+/// Find `.dict` and `.dict.dz` files under `dir` that have a matching `.index`
+/// file next to them, sorted by path
+pub fn find_dictd_pairs(dir: &Path) -> Result<Vec<DictdPair>> {
+    let mut pairs = Vec::new();
+    for entry in fs::read_dir(dir).context(format!("Failed to read directory {:?}", dir))? {
+        let path = entry?.path();
+        if path.is_dir() {
+            pairs.extend(find_dictd_pairs(&path)?);
+            continue;
+        }
+        let file_name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default();
+        let Some(base_name) = file_name
+            .strip_suffix(".dict.dz")
+            .or_else(|| file_name.strip_suffix(".dict"))
+        else {
+            continue;
+        };
+        let index_path = path.with_file_name(format!("{}.index", base_name));
+        if index_path.exists() {
+            pairs.push(DictdPair {
+                language: parser::language_from_file_name(base_name),
+                dict_path: path,
+                index_path,
+            });
+        }
+    }
+    pairs.sort_by(|a, b| a.dict_path.cmp(&b.dict_path));
+    Ok(pairs)
+}
+
+/// Full FreeDict name, accepting the short form: "deu-eng" -> "freedict-deu-eng"
+pub fn freedict_name(name: &str) -> String {
+    if name.starts_with("freedict-") {
+        name.to_string()
+    } else {
+        format!("freedict-{}", name)
+    }
+}
+
+/// Find the `<base_name>.tei` file of a FreeDict source release under `dir`
+fn find_tei_file(dir: &Path, base_name: &str) -> Result<PathBuf> {
+    fn walk_dir(dir: &Path, file_name: &str) -> Result<Option<PathBuf>> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                if let Some(found) = walk_dir(&path, file_name)? {
+                    return Ok(Some(found));
+                }
+            } else if path.file_name().and_then(|n| n.to_str()) == Some(file_name) {
+                return Ok(Some(path));
+            }
+        }
+        Ok(None)
+    }
+
+    let file_name = format!("{}.tei", base_name);
+    walk_dir(dir, &file_name)?
+        .ok_or_else(|| anyhow::anyhow!("Could not find {} in {:?}", file_name, dir))
+}
+
 /// Extract a tar.xz archive
 fn extract_tar_xz<P: AsRef<Path>>(archive_path: P, dest_dir: P) -> Result<()> {
     use std::process::Command;
@@ -323,4 +1421,462 @@ mod tests {
         assert!(manager.data_dir.exists());
         assert!(manager.index_dir.exists());
     }
+
+    /// Write a small dictd dictionary (.dict.dz + .index) with the given entries
+    pub(crate) fn write_dictd(dir: &Path, base_name: &str, entries: &[(&str, &str)]) {
+        use flate2::Compression;
+        use flate2::write::GzEncoder;
+        use std::io::Write;
+
+        let mut content = String::new();
+        let mut index = String::new();
+        for (word, definition) in entries {
+            index.push_str(&format!(
+                "{}\t{}\t{}\n",
+                word,
+                content.len(),
+                definition.len()
+            ));
+            content.push_str(definition);
+        }
+
+        let dict_file = fs::File::create(dir.join(format!("{}.dict.dz", base_name))).unwrap();
+        let mut encoder = GzEncoder::new(dict_file, Compression::default());
+        encoder.write_all(content.as_bytes()).unwrap();
+        encoder.finish().unwrap();
+        fs::write(dir.join(format!("{}.index", base_name)), index).unwrap();
+    }
+
+    #[test]
+    fn test_import_local_streams_entries_into_index() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = IndexManager::new(temp_dir.path()).unwrap();
+        write_dictd(
+            temp_dir.path(),
+            "deu-eng",
+            &[("Haus", "house"), ("Auto", "car"), ("Buch", "book")],
+        );
+
+        manager
+            .import_local(
+                temp_dir.path().join("deu-eng.dict.dz"),
+                temp_dir.path().join("deu-eng.index"),
+                "de-en",
+            )
+            .unwrap();
+
+        let engine = SearchEngine::new(manager.index_dir()).unwrap();
+        let results = engine
+            .search(
+                "Auto",
+                crate::models::SearchMode::Exact,
+                crate::models::LanguagePair::DE_EN,
+                0,
+                10,
+            )
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].definitions, vec!["car"]);
+        assert_eq!(engine.get_stats().unwrap().0, 3);
+
+        manager.set_source_enabled("deu-eng", false).unwrap();
+        let results = manager
+            .search_engine()
+            .unwrap()
+            .search(
+                "Auto",
+                crate::models::SearchMode::Exact,
+                crate::models::LanguagePair::DE_EN,
+                0,
+                10,
+            )
+            .unwrap();
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn test_preview_local_leaves_index_untouched() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = IndexManager::new(temp_dir.path()).unwrap();
+        write_dictd(
+            temp_dir.path(),
+            "freedict-deu-eng",
+            &[("Haus", "house"), ("Auto", "car"), ("Buch", "book")],
+        );
+        let index_path = temp_dir.path().join("freedict-deu-eng.index");
+        let mut index = fs::read_to_string(&index_path).unwrap();
+        index.push_str("Zug\t999\t5\n");
+        fs::write(&index_path, index).unwrap();
+
+        let preview = manager
+            .preview_local(
+                temp_dir.path().join("freedict-deu-eng.dict.dz"),
+                index_path,
+                "de-en",
+                2,
+            )
+            .unwrap();
+
+        assert_eq!(preview.entries, 3);
+        assert_eq!(preview.samples.len(), 2);
+        assert_eq!(preview.samples[0].word, "Haus");
+        assert_eq!(preview.stats.detected_language.as_deref(), Some("de-en"));
+        assert_eq!(preview.stats.malformed_lines, 1);
+        assert!(!manager.index_dir().join("meta.json").exists());
+    }
+
+    #[test]
+    fn test_rebuild_streams_all_dictionaries() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = IndexManager::new(temp_dir.path()).unwrap();
+        let data_dir = temp_dir.path().join("data");
+        let nested = data_dir.join("renamed");
+        fs::create_dir_all(&nested).unwrap();
+        write_dictd(&data_dir, "german", &[("Haus", "house"), ("Auto", "car")]);
+        write_dictd(&nested, "english", &[("house", "Haus")]);
+        manager
+            .import_local(
+                data_dir.join("german.dict.dz"),
+                data_dir.join("german.index"),
+                "de-en",
+            )
+            .unwrap();
+        manager
+            .record_source(
+                &nested.join("english.dict.dz"),
+                Some(&nested.join("english.index")),
+                ImportFormat::Dictd,
+                "en-de",
+            )
+            .unwrap();
+
+        let report = manager.rebuild().unwrap();
+
+        assert_eq!(report.indexed, 3);
+        assert_eq!(report.succeeded.len(), 2);
+        let engine = SearchEngine::new(manager.index_dir()).unwrap();
+        let results = engine
+            .search(
+                "house",
+                crate::models::SearchMode::Exact,
+                crate::models::LanguagePair::EN_DE,
+                0,
+                10,
+            )
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(manager.stats().unwrap().0, 3);
+    }
+
+    #[test]
+    fn test_word_list_columns_survive_rebuild() {
+        let temp_dir = TempDir::new().unwrap();
+        let columns = Columns {
+            word: "Deutsch".to_string(),
+            definition: "Englisch".to_string(),
+        };
+        let manager = IndexManager::new(temp_dir.path())
+            .unwrap()
+            .with_columns(Some(columns.clone()));
+        let path = temp_dir.path().join("data").join("vokabeln.tsv");
+        fs::write(
+            &path,
+            "Lektion\tEnglisch\tDeutsch\n1\thouse\tHaus\n2\tcar\tAuto\n",
+        )
+        .unwrap();
+
+        manager
+            .import_file(&path, ImportFormat::Tsv, "de-en")
+            .unwrap();
+        assert_eq!(
+            manager.manifest().unwrap().sources[0].columns,
+            Some(columns)
+        );
+
+        let manager = IndexManager::new(temp_dir.path()).unwrap();
+        let report = manager.rebuild().unwrap();
+        assert_eq!(report.indexed, 2);
+        let engine = SearchEngine::new(manager.index_dir()).unwrap();
+        let results = engine
+            .search(
+                "Auto",
+                crate::models::SearchMode::Exact,
+                crate::models::LanguagePair::DE_EN,
+                0,
+                10,
+            )
+            .unwrap();
+        assert_eq!(results[0].definitions, ["car"]);
+    }
+
+    #[test]
+    fn test_missing_freedict_checks_manifest() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = IndexManager::new(temp_dir.path()).unwrap();
+        write_dictd(
+            &manager.data_dir,
+            "freedict-deu-eng-1.9-fd1",
+            &[("Haus", "house")],
+        );
+        manager
+            .record_source(
+                &manager.data_dir.join("freedict-deu-eng-1.9-fd1.dict.dz"),
+                Some(&manager.data_dir.join("freedict-deu-eng-1.9-fd1.index")),
+                ImportFormat::Dictd,
+                "de-en",
+            )
+            .unwrap();
+
+        let wanted = vec!["deu-eng".to_string(), "freedict-eng-deu".to_string()];
+        assert_eq!(
+            manager.missing_freedict(&wanted).unwrap(),
+            vec!["freedict-eng-deu"]
+        );
+    }
+
+    #[test]
+    fn test_rebuild_removes_interrupted_imports() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = IndexManager::new(temp_dir.path()).unwrap();
+        write_dictd(&manager.data_dir, "german", &[("Haus", "house")]);
+        manager
+            .record_source(
+                &manager.data_dir.join("german.dict.dz"),
+                Some(&manager.data_dir.join("german.index")),
+                ImportFormat::Dictd,
+                "de-en",
+            )
+            .unwrap();
+
+        // Extracted, moved into place, but never indexed or recorded
+        let partial = manager.data_dir.join("freedict-eng-deu-1.9-fd1");
+        fs::create_dir_all(&partial).unwrap();
+        write_dictd(&partial, "freedict-eng-deu", &[("house", "Haus")]);
+        manager
+            .begin_import("freedict-eng-deu", vec![partial.clone()])
+            .unwrap();
+
+        let report = manager.rebuild().unwrap();
+
+        assert_eq!(report.indexed, 1);
+        assert!(!partial.exists());
+        assert!(manager.manifest().unwrap().pending.is_empty());
+        assert!(manager.data_dir.join("german.dict.dz").exists());
+    }
+
+    #[test]
+    fn test_unstage_merges_into_existing_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = IndexManager::new(temp_dir.path()).unwrap();
+        let staging = manager.data_dir.join(STAGING_DIR).join("freedict-deu-eng");
+        fs::create_dir_all(staging.join("deu-eng")).unwrap();
+        fs::write(staging.join("deu-eng").join("deu-eng.index"), "new").unwrap();
+        fs::write(staging.join("deu-eng").join("README"), "").unwrap();
+        let existing = manager.data_dir.join("deu-eng");
+        fs::create_dir_all(&existing).unwrap();
+        fs::write(existing.join("deu-eng.index"), "old").unwrap();
+
+        manager.unstage("freedict-deu-eng", &staging).unwrap();
+
+        assert!(!staging.exists());
+        assert_eq!(
+            fs::read_to_string(existing.join("deu-eng.index")).unwrap(),
+            "new"
+        );
+        let pending = manager.manifest().unwrap().pending;
+        assert_eq!(pending[0].artifacts, vec![existing.join("README")]);
+
+        // A failed import removes only the files it added
+        manager.finish_import("freedict-deu-eng", false).unwrap();
+        assert!(!existing.join("README").exists());
+        assert!(existing.join("deu-eng.index").exists());
+    }
+
+    #[test]
+    fn test_clean_removes_unreferenced_archives_and_trees() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = IndexManager::new(temp_dir.path()).unwrap();
+        let current = manager.data_dir.join("deu-eng-1.9");
+        let old = manager.data_dir.join("deu-eng-1.8");
+        for dir in [&current, &old] {
+            fs::create_dir_all(dir).unwrap();
+            write_dictd(dir, "deu-eng", &[("Haus", "house")]);
+        }
+        manager
+            .record_source(
+                &current.join("deu-eng.dict.dz"),
+                Some(&current.join("deu-eng.index")),
+                ImportFormat::Dictd,
+                "de-en",
+            )
+            .unwrap();
+        fs::write(manager.data_dir.join("freedict-deu-eng.tar.xz"), "xz").unwrap();
+        let yomichan = manager.data_dir.join("jmdict.zip");
+        fs::write(&yomichan, "zip").unwrap();
+        manager
+            .record_source(&yomichan, None, ImportFormat::Yomichan, "ja-en")
+            .unwrap();
+        fs::create_dir_all(manager.data_dir.join("logs")).unwrap();
+        let importing = manager.data_dir.join("eng-deu");
+        fs::create_dir_all(&importing).unwrap();
+        write_dictd(&importing, "eng-deu", &[("house", "Haus")]);
+        manager
+            .begin_import("freedict-eng-deu", vec![importing.clone()])
+            .unwrap();
+
+        let report = manager.clean().unwrap();
+
+        let mut removed = report.removed.clone();
+        removed.sort();
+        assert_eq!(
+            removed,
+            vec![
+                old.clone(),
+                manager.data_dir.join("freedict-deu-eng.tar.xz")
+            ]
+        );
+        assert!(report.reclaimed_bytes > 2);
+        assert!(current.exists());
+        assert!(yomichan.exists());
+        assert!(importing.exists());
+        assert!(manager.data_dir.join("logs").exists());
+        assert!(manager.data_dir.join("manifest.json").exists());
+    }
+
+    #[test]
+    fn test_ensure_free_space() {
+        let temp_dir = TempDir::new().unwrap();
+        assert!(ensure_free_space(temp_dir.path(), 0, "import").is_ok());
+
+        let error = ensure_free_space(temp_dir.path(), u64::MAX / 2, "import")
+            .unwrap_err()
+            .to_string();
+        assert!(error.starts_with("Not enough disk space to import"));
+    }
+
+    #[test]
+    fn test_find_dictd_pairs() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        write_dictd(dir, "freedict-deu-eng-1.9", &[("Haus", "house")]);
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        write_dictd(&dir.join("nested"), "mydict", &[("house", "Haus")]);
+        // No .index next to it
+        fs::write(dir.join("orphan.dict.dz"), "").unwrap();
+
+        let pairs = find_dictd_pairs(dir).unwrap();
+
+        assert_eq!(pairs.len(), 2);
+        assert_eq!(pairs[0].dict_path, dir.join("freedict-deu-eng-1.9.dict.dz"));
+        assert_eq!(pairs[0].index_path, dir.join("freedict-deu-eng-1.9.index"));
+        assert_eq!(pairs[0].language, Some(LanguagePair::DE_EN));
+        assert_eq!(pairs[1].language, None);
+    }
+
+    #[test]
+    fn test_import_dictd_pairs_skips_broken_dictionaries() {
+        let temp_dir = TempDir::new().unwrap();
+        let source_dir = temp_dir.path().join("dictionaries");
+        fs::create_dir_all(&source_dir).unwrap();
+        write_dictd(
+            &source_dir,
+            "deu-eng",
+            &[("Haus", "house"), ("Auto", "car")],
+        );
+        write_dictd(&source_dir, "eng-deu", &[("house", "Haus")]);
+        fs::write(source_dir.join("broken-deu-eng.dict.dz"), "not gzip").unwrap();
+        fs::write(source_dir.join("broken-deu-eng.index"), "").unwrap();
+        let manager = IndexManager::new(temp_dir.path().join("home")).unwrap();
+
+        let pairs = find_dictd_pairs(&source_dir).unwrap();
+        let report = manager.import_dictd_pairs(&pairs).unwrap();
+
+        assert_eq!(report.indexed, 3);
+        assert_eq!(report.succeeded.len(), 2);
+        assert_eq!(report.failed.len(), 1);
+        let recorded: Vec<String> = manager
+            .manifest()
+            .unwrap()
+            .sources
+            .into_iter()
+            .map(|source| source.name)
+            .collect();
+        assert_eq!(recorded, vec!["deu-eng", "eng-deu"]);
+    }
+
+    #[test]
+    fn test_rebuild_rejects_unrecorded_dictionaries() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = IndexManager::new(temp_dir.path()).unwrap();
+        write_dictd(
+            &temp_dir.path().join("data"),
+            "eng-deu",
+            &[("house", "Haus")],
+        );
+        SearchEngine::build_index(
+            manager.index_dir(),
+            vec![DictionaryEntry::new(
+                "Haus".to_string(),
+                "house".to_string(),
+                "de-en".to_string(),
+            )],
+        )
+        .unwrap();
+
+        let error = manager.rebuild().unwrap_err();
+
+        assert!(error.to_string().contains("eng-deu.dict.dz"));
+        // The existing index is left alone
+        assert_eq!(manager.stats().unwrap().0, 1);
+    }
+
+    #[test]
+    fn test_rebuild_skips_failed_dictionaries() {
+        let temp_dir = TempDir::new().unwrap();
+        let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let collected = events.clone();
+        let manager =
+            IndexManager::new(temp_dir.path())
+                .unwrap()
+                .with_progress(Progress::with_sink(move |event| {
+                    collected
+                        .lock()
+                        .unwrap()
+                        .push((event.stage.clone(), event.percent));
+                }));
+        let data_dir = temp_dir.path().join("data");
+        write_dictd(&data_dir, "good", &[("Haus", "house")]);
+        write_dictd(&data_dir, "broken", &[("Auto", "car")]);
+        for (name, language) in [("good", "de-en"), ("broken", "de-en")] {
+            manager
+                .record_source(
+                    &data_dir.join(format!("{}.dict.dz", name)),
+                    Some(&data_dir.join(format!("{}.index", name))),
+                    ImportFormat::Dictd,
+                    language,
+                )
+                .unwrap();
+        }
+        fs::write(data_dir.join("broken.dict.dz"), "not gzip").unwrap();
+
+        let report = manager.rebuild().unwrap();
+
+        assert_eq!(report.indexed, 1);
+        assert_eq!(
+            report.succeeded,
+            vec![(fs::canonicalize(data_dir.join("good.dict.dz")).unwrap(), 1)]
+        );
+        assert_eq!(report.failed.len(), 1);
+        assert!(report.failed[0].0.ends_with("broken.dict.dz"));
+        assert!(!report.all_failed());
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                ("parse".to_string(), 0),
+                ("parse".to_string(), 50),
+                ("parse".to_string(), 100),
+                ("done".to_string(), 100)
+            ]
+        );
+    }
 }
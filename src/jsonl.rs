@@ -0,0 +1,121 @@
+use anyhow::{Context, Result};
+use flate2::read::MultiGzDecoder;
+use serde::Deserialize;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+use crate::models::{DictionaryEntry, LanguagePair, PartOfSpeech};
+use crate::parser::ParseStats;
+use crate::tei;
+
+/// One line of a JSON Lines dictionary. Fields other than these are ignored.
+#[derive(Debug, Deserialize)]
+struct JsonlEntry {
+    word: String,
+    definition: String,
+    /// Language pair of this entry, the import's language when missing
+    #[serde(default)]
+    lang: Option<LanguagePair>,
+    #[serde(default)]
+    domains: Vec<String>,
+    /// Tag such as "noun" or "vt"
+    #[serde(default, alias = "pos")]
+    part_of_speech: Option<String>,
+    /// "m", "f", "n" or a longer form such as "fem"
+    #[serde(default)]
+    gender: Option<String>,
+}
+
+/// Parse a JSON Lines dictionary (`.jsonl`, or gzipped as `.jsonl.gz`) with
+/// one `{"word": ..., "definition": ..., "lang": ...}` object per line.
+/// Lines that are not such an object, or whose word or definition is empty,
+/// are counted as malformed and skipped.
+pub fn parse_jsonl<P: AsRef<Path>>(
+    path: P,
+    language: &str,
+    stats: &mut ParseStats,
+) -> Result<Vec<DictionaryEntry>> {
+    let path = path.as_ref();
+    let file = File::open(path).context(format!("Failed to open JSON Lines file: {:?}", path))?;
+    let reader: Box<dyn Read> = match path.extension().and_then(|s| s.to_str()) {
+        Some("gz") => Box::new(MultiGzDecoder::new(file)),
+        _ => Box::new(file),
+    };
+
+    let mut entries = Vec::new();
+    for line in BufReader::new(reader).lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let Ok(parsed) = serde_json::from_str::<JsonlEntry>(line) else {
+            stats.record_malformed(line);
+            continue;
+        };
+        let (word, definition) = (parsed.word.trim(), parsed.definition.trim());
+        if word.is_empty() || definition.is_empty() {
+            stats.record_malformed(line);
+            continue;
+        }
+
+        let entry_language = parsed.lang.as_ref().map_or(language, |lang| lang.as_str());
+        let mut entry = DictionaryEntry::new(
+            word.to_string(),
+            definition.to_string(),
+            entry_language.to_string(),
+        )
+        .with_domains(parsed.domains);
+        entry.part_of_speech = parsed
+            .part_of_speech
+            .as_deref()
+            .and_then(PartOfSpeech::from_tag);
+        entry.gender = parsed.gender.as_deref().and_then(tei::normalize_gender);
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_jsonl() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("generated.jsonl");
+        let lines = [
+            r#"{"word": "Haus", "definition": "house", "lang": "de-en", "pos": "n", "gender": "neut", "domains": ["archi."], "id": 17}"#,
+            r#"{"word": "house", "definition": "Haus", "lang": "en-de"}"#,
+            r#"{"word": "Auto", "definition": "car"}"#,
+            "",
+            r#"{"word": "Leer", "definition": ""}"#,
+            r#"{"word": "Tür", "definition": "door", "lang": "french"}"#,
+            "not json",
+        ];
+        std::fs::write(&path, lines.join("\n")).unwrap();
+
+        let mut stats = ParseStats::default();
+        let entries = parse_jsonl(&path, "de-en", &mut stats).unwrap();
+
+        let words: Vec<(&str, &str, &str)> = entries
+            .iter()
+            .map(|e| (e.word.as_str(), e.definition.as_str(), e.language.as_str()))
+            .collect();
+        assert_eq!(
+            words,
+            [
+                ("Haus", "house", "de-en"),
+                ("house", "Haus", "en-de"),
+                ("Auto", "car", "de-en"),
+            ]
+        );
+        assert_eq!(entries[0].part_of_speech, Some(PartOfSpeech::Noun));
+        assert_eq!(entries[0].gender.as_deref(), Some("n"));
+        assert_eq!(entries[0].domains, ["archi."]);
+        assert_eq!(stats.malformed_lines, 3);
+        assert_eq!(stats.malformed_examples[2], "not json");
+    }
+}
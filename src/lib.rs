@@ -1,5 +1,58 @@
+pub mod abbreviations;
+pub mod analytics;
+pub mod apikeys;
+pub mod appledict;
+pub mod backend;
+pub mod bgl;
+pub mod cache;
+pub mod config;
+pub mod dictcc;
+pub mod diff;
+pub mod dsl;
+pub mod export;
+pub mod favorites;
+pub mod freedict;
+pub mod frequency;
+pub mod glosslang;
+pub mod headwords;
+pub mod history;
+pub mod html;
 pub mod index;
+pub mod jsonl;
+pub mod license;
+pub mod manifest;
+pub mod markdown;
+pub mod memory;
 pub mod models;
+pub mod normalize;
+pub mod notify;
+pub mod onboarding;
 pub mod parser;
+pub mod pools;
+pub mod progress;
+pub mod querylog;
+pub mod quiz;
+pub mod reload;
+pub mod rhyme;
 pub mod search;
 pub mod server;
+pub mod slob;
+pub mod snapshot;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod stardict;
+pub mod stem;
+pub mod studylist;
+pub mod tei;
+pub mod tts;
+pub mod updates;
+pub mod usage;
+pub mod watch;
+pub mod webhook;
+pub mod wiktionary;
+pub mod wordlist;
+pub mod wotd;
+pub mod xdxf;
+pub mod xml;
+pub mod yomichan;
+pub mod zim;
@@ -0,0 +1,143 @@
+use flate2::read::GzDecoder;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::models::ImportFormat;
+use crate::parser;
+
+/// Phrases naming a license, checked in order so the more specific names win
+/// ("CC-BY-SA" over "CC-BY", "LGPL" over "GPL")
+const LICENSE_PHRASES: &[(&str, &str)] = &[
+    ("cc-by-sa", "CC-BY-SA"),
+    ("cc by-sa", "CC-BY-SA"),
+    ("attribution-sharealike", "CC-BY-SA"),
+    ("attribution-share alike", "CC-BY-SA"),
+    ("attribution sharealike", "CC-BY-SA"),
+    ("cc-by", "CC-BY"),
+    ("creative commons attribution", "CC-BY"),
+    ("gnu free documentation license", "GFDL"),
+    ("gfdl", "GFDL"),
+    ("gnu lesser general public license", "LGPL"),
+    ("lgpl", "LGPL"),
+    ("gnu general public license", "GPL"),
+    ("gpl", "GPL"),
+    ("public domain", "public-domain"),
+    ("personal use", "personal-use"),
+    ("private use", "personal-use"),
+    ("non-commercial", "personal-use"),
+];
+
+/// License named in a dictionary's description, e.g. its "00-database-info" article
+pub fn detect(text: &str) -> Option<&'static str> {
+    let text = text.to_lowercase();
+    LICENSE_PHRASES
+        .iter()
+        .find(|(phrase, _)| text.contains(phrase))
+        .map(|(_, license)| *license)
+}
+
+/// License every dictionary of a format is published under, if there is one:
+/// Wiktionary text is CC-BY-SA, dict.cc exports are for personal use only
+pub fn format_default(format: ImportFormat) -> Option<&'static str> {
+    match format {
+        ImportFormat::Wiktionary => Some("CC-BY-SA"),
+        ImportFormat::DictCc => Some("personal-use"),
+        _ => None,
+    }
+}
+
+/// License of an imported file: named in the "00-database-info" article of a
+/// dictd dictionary, otherwise the default of its format
+pub fn detect_source(
+    path: &Path,
+    index_path: Option<&Path>,
+    format: ImportFormat,
+) -> Option<String> {
+    let described = match (format, index_path) {
+        (ImportFormat::Dictd, Some(index_path)) => {
+            dictd_info(path, index_path).as_deref().and_then(detect)
+        }
+        _ => None,
+    };
+    described.or(format_default(format)).map(str::to_string)
+}
+
+/// The "00-database-info" article of a dictd dictionary, reading the
+/// dictionary only up to its end
+fn dictd_info(dict_path: &Path, index_path: &Path) -> Option<String> {
+    let (offset, length) = parser::parse_index(index_path)
+        .ok()?
+        .into_iter()
+        .find(|entry| matches!(entry.word.as_str(), "00-database-info" | "00databaseinfo"))
+        .map(|entry| (entry.offset, entry.length))?;
+
+    let file = File::open(dict_path).ok()?;
+    let mut content = Vec::new();
+    let end = offset + length;
+    let read = if dict_path.extension().is_some_and(|ext| ext == "dz") {
+        GzDecoder::new(file).take(end).read_to_end(&mut content)
+    } else {
+        file.take(end).read_to_end(&mut content)
+    };
+    read.ok()?;
+    let article = content.get(offset as usize..end as usize)?;
+    Some(String::from_utf8_lossy(article).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::fs;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_detect_prefers_specific_licenses() {
+        assert_eq!(
+            detect("Licensed under the GNU General Public License v2"),
+            Some("GPL")
+        );
+        assert_eq!(
+            detect("Creative Commons Attribution-ShareAlike 3.0"),
+            Some("CC-BY-SA")
+        );
+        assert_eq!(detect("available under CC-BY 4.0"), Some("CC-BY"));
+        assert_eq!(detect("GNU LGPL"), Some("LGPL"));
+        assert_eq!(detect("For personal use only"), Some("personal-use"));
+        assert_eq!(detect("German-English dictionary"), None);
+    }
+
+    #[test]
+    fn test_detect_source_reads_dictd_info() {
+        let temp_dir = TempDir::new().unwrap();
+        let info = "00-database-info\nThis dictionary is licensed under the GNU GPL.\n";
+        let body = format!("{}house\n", info);
+        let dict_path = temp_dir.path().join("deu-eng.dict.dz");
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(body.as_bytes()).unwrap();
+        fs::write(&dict_path, encoder.finish().unwrap()).unwrap();
+        let index_path = temp_dir.path().join("deu-eng.index");
+        fs::write(
+            &index_path,
+            format!(
+                "00-database-info\t0\t{}\nHaus\t{}\t6\n",
+                info.len(),
+                info.len()
+            ),
+        )
+        .unwrap();
+
+        assert_eq!(
+            detect_source(&dict_path, Some(&index_path), ImportFormat::Dictd).as_deref(),
+            Some("GPL")
+        );
+        assert_eq!(
+            detect_source(&dict_path, None, ImportFormat::DictCc).as_deref(),
+            Some("personal-use")
+        );
+        assert_eq!(detect_source(&dict_path, None, ImportFormat::Dsl), None);
+    }
+}
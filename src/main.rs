@@ -1,62 +1,278 @@
-mod index;
-mod models;
-mod parser;
-mod search;
-mod server;
+use anyhow::{Context, Result};
+use clap::{CommandFactory, Parser, Subcommand};
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
+use tracing::{info, warn};
 
-use anyhow::Result;
-use clap::{Parser, Subcommand};
-use tracing::info;
-use tracing_subscriber;
-
-use crate::index::IndexManager;
-use crate::models::{Language, SearchMode};
-use crate::search::SearchEngine;
+use dictv::apikeys::ApiKeys;
+use dictv::backend::{Backend, BackendKind};
+use dictv::bgl;
+use dictv::config::{LogFormat, Overrides, Settings};
+use dictv::diff;
+use dictv::export::{self, ExportFormat};
+use dictv::favorites::{self, Favorite, Favorites};
+use dictv::freedict::{self, FreedictDictionary};
+use dictv::index::{ImportPreview, IndexManager, find_dictd_pairs};
+use dictv::manifest;
+use dictv::markdown;
+use dictv::memory;
+use dictv::models::{
+    Cancellation, Grouping, ImportFormat, LanguagePair, PartOfSpeech, PrimeRequest, PrimeResponse,
+    Register, SearchMode, SearchOptions,
+};
+use dictv::notify::NotifyOptions;
+use dictv::onboarding;
+use dictv::progress::{Progress, ProgressFormat};
+use dictv::querylog::{self, QueryLog, QueryLogConfig};
+use dictv::search::{IndexOptions, PurgeFilter, SearchEngine, SearchOutput};
+use dictv::server;
+use dictv::studylist::{self, StudyListCriteria};
+use dictv::tts::Tts;
+use dictv::updates;
+use dictv::watch::{self, DataWatcher};
+use dictv::webhook::{WebhookEvent, Webhooks};
+use dictv::wiktionary;
+use dictv::wordlist::Columns;
+use dictv::wotd;
 
 #[derive(Parser)]
 #[command(name = "dictv")]
 #[command(about = "German-English Dictionary Server", long_about = None)]
 struct Cli {
+    /// Data directory (default: ~/.dictv, or DICTV_DATA)
+    #[arg(long, global = true)]
+    data_dir: Option<PathBuf>,
+
+    /// Log format: text or json (or DICTV_LOG_FORMAT)
+    #[arg(long, global = true)]
+    log_format: Option<String>,
+
+    /// Import and rebuild progress: text, or json for one event per line on stderr
+    #[arg(long, global = true, default_value = "text")]
+    progress: String,
+
     #[command(subcommand)]
-    command: Commands,
+    command: Option<Commands>,
 }
 
 #[derive(Subcommand)]
 enum Commands {
+    /// Interactive first-run setup: choose dictionaries, download and import them
+    Init,
+
     /// Import dictionary from FreeDict or local files
     Import {
-        /// Download from FreeDict (freedict-eng-deu or freedict-deu-eng)
+        /// Download from FreeDict (e.g. deu-eng, freedict-fra-deu; see --list)
         #[arg(long)]
         download: Option<String>,
 
-        /// Local dictionary file path (.dict.dz)
-        #[arg(long, requires = "index")]
+        /// List the dictionaries available from FreeDict
+        #[arg(long, conflicts_with_all = ["download", "local", "dir"])]
+        list: bool,
+
+        /// Download the TEI source instead of the dictd files, keeping senses,
+        /// parts of speech and genders as separate fields
+        #[arg(long, requires = "download")]
+        tei: bool,
+
+        /// Local dictionary file path (.dict.dz, or the export file for other formats)
+        #[arg(long)]
         local: Option<String>,
 
-        /// Local index file path (.index)
+        /// Local index file path (.index, dictd only)
         #[arg(long, requires = "local")]
         index: Option<String>,
 
-        /// Language direction (en-de or de-en)
-        #[arg(long, default_value = "de-en")]
-        lang: String,
+        /// Import every dictd dictionary (.dict or .dict.dz with a matching .index) in a directory
+        #[arg(long, conflicts_with_all = ["download", "local"])]
+        dir: Option<PathBuf>,
+
+        /// Format of the local dictionary (dictd, dictcc, dsl, stardict, xdxf, tei, csv, tsv, jsonl, bgl, wiktionary, appledict, slob, yomichan, zim)
+        #[arg(long, default_value = "dictd")]
+        format: String,
+
+        /// Column of a csv/tsv word list holding the word: number from 1 or header name
+        #[arg(long, default_value = "1")]
+        word_column: String,
+
+        /// Column of a csv/tsv word list holding the definition: number from 1 or header name
+        #[arg(long, default_value = "2")]
+        definition_column: String,
+
+        /// Language direction (e.g. de-en, en-de, fr-de)
+        #[arg(long)]
+        lang: Option<String>,
+
+        /// Index edge n-grams for fast prefix search (larger index)
+        #[arg(long)]
+        edge_ngrams: bool,
+
+        /// Parse the local source and report what would be imported, without indexing
+        #[arg(long, requires = "local")]
+        dry_run: bool,
+
+        /// Number of sample entries to show with --dry-run
+        #[arg(long, default_value = "10")]
+        preview: usize,
+
+        /// Download the recordings of imported words (Wiktionary) for offline playback
+        #[arg(long, requires = "local", conflicts_with = "dry_run")]
+        download_audio: bool,
+
+        /// License the dictionary is distributed under (e.g. GPL, CC-BY-SA, personal-use),
+        /// instead of the one detected from its metadata
+        #[arg(long)]
+        license: Option<String>,
+    },
+
+    /// Import a word frequency list (one word per line, most frequent first)
+    ImportFrequency {
+        /// Frequency list file path
+        file: String,
+
+        /// Language direction the ranks apply to (e.g. de-en, en-de, fr-de)
+        #[arg(long)]
+        lang: Option<String>,
     },
 
     /// Rebuild the search index from all dictionary files
-    Rebuild,
+    Rebuild {
+        /// Index edge n-grams for fast prefix search (larger index)
+        #[arg(long)]
+        edge_ngrams: bool,
+    },
+
+    /// Remove downloaded archives and extracted dictionaries no longer in the manifest
+    Clean,
+
+    /// Delete matching entries from the index without a rebuild (a rebuild restores them)
+    Purge {
+        /// Only entries imported from this dictionary
+        #[arg(long)]
+        source: Option<String>,
+
+        /// Only entries of this language direction (e.g. de-en, en-de, fr-de)
+        #[arg(long)]
+        lang: Option<String>,
+
+        /// Only entries whose headword starts with this text
+        #[arg(long)]
+        word_prefix: Option<String>,
+
+        /// Count the matching entries without deleting them
+        #[arg(long)]
+        dry_run: bool,
+    },
 
     /// Show index statistics
     Stats,
 
+    /// Check FreeDict for newer releases of imported dictionaries
+    CheckUpdates,
+
+    /// Exclude an imported dictionary from search without deleting it
+    Disable {
+        /// Dictionary name (the file name without extensions, e.g. freedict-deu-eng-1.9-fd1)
+        source: String,
+    },
+
+    /// Include a previously disabled dictionary in search again
+    Enable {
+        /// Dictionary name (the file name without extensions, e.g. freedict-deu-eng-1.9-fd1)
+        source: String,
+    },
+
+    /// Record the license an imported dictionary is distributed under
+    License {
+        /// Dictionary name (the file name without extensions, e.g. freedict-deu-eng-1.9-fd1)
+        source: String,
+
+        /// License, e.g. GPL, CC-BY-SA or personal-use
+        license: String,
+    },
+
+    /// Compare two imported dictionaries: headwords only one has and differing definitions
+    Diff {
+        /// First dictionary name
+        source_a: String,
+
+        /// Second dictionary name
+        source_b: String,
+
+        /// Print the differences as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Manage saved words
+    Fav {
+        #[command(subcommand)]
+        action: FavAction,
+    },
+
     /// Start the HTTP server
     Serve {
         /// Run as daemon in background
         #[arg(long)]
         daemon: bool,
 
-        /// Port to listen on
-        #[arg(long, default_value = "3000")]
-        port: u16,
+        /// Port to listen on (default: 3000, or DICTV_PORT)
+        #[arg(long)]
+        port: Option<u16>,
+
+        /// Address to bind (default: 127.0.0.1, or DICTV_HOST)
+        #[arg(long)]
+        host: Option<String>,
+
+        /// Log every search (parameters, result count, latency) to data/logs/queries.jsonl
+        #[arg(long)]
+        query_log: bool,
+
+        /// Also record client IP addresses in the query log
+        #[arg(long, requires = "query_log")]
+        query_log_ip: bool,
+
+        /// Start even if the index is empty (/health reports not_ready)
+        #[arg(long)]
+        allow_empty: bool,
+
+        /// Import dictionaries dropped into data/ while the server runs
+        #[arg(long)]
+        watch_data: bool,
+    },
+
+    /// Summarize the query log: zero-result and slowest queries
+    AnalyzeLog {
+        /// Query log file (defaults to data/logs/queries.jsonl)
+        #[arg(long)]
+        file: Option<String>,
+
+        /// Number of entries to show per list
+        #[arg(long, default_value = "10")]
+        top: usize,
+    },
+
+    /// Prime a running server's caches by sending it a list of queries
+    Warmup {
+        /// File with one query per line (blank lines and # comments are skipped)
+        #[arg(long)]
+        queries: String,
+
+        /// Server URL (default: http://HOST:PORT from the settings)
+        #[arg(long)]
+        url: Option<String>,
+
+        /// Admin API key, when the server requires keys
+        #[arg(long)]
+        api_key: Option<String>,
+
+        /// Search mode the queries run in (default: the server's)
+        #[arg(long)]
+        mode: Option<String>,
+
+        /// Language direction (default: the server's)
+        #[arg(long)]
+        lang: Option<String>,
     },
 
     /// Query the dictionary directly
@@ -64,13 +280,13 @@ enum Commands {
         /// Search query
         query: String,
 
-        /// Search mode (exact, fuzzy, prefix)
+        /// Search mode (exact, fuzzy, prefix, definition, pattern, anagram, rhyme)
         #[arg(long, default_value = "fuzzy")]
         mode: String,
 
-        /// Language direction (en-de or de-en)
-        #[arg(long, default_value = "de-en")]
-        lang: String,
+        /// Language direction (e.g. de-en, en-de, fr-de)
+        #[arg(long)]
+        lang: Option<String>,
 
         /// Maximum edit distance for fuzzy search
         #[arg(long, default_value = "2")]
@@ -79,92 +295,918 @@ enum Commands {
         /// Maximum number of results
         #[arg(long, default_value = "10")]
         limit: usize,
+
+        /// Match diacritics exactly (keep "Maße" and "Masse" distinct)
+        #[arg(long)]
+        strict_diacritics: bool,
+
+        /// Use max-distance as given, even for short queries
+        #[arg(long)]
+        no_auto_distance: bool,
+
+        /// Result grouping (word, none)
+        #[arg(long, default_value = "word")]
+        group: String,
+
+        /// Only show words ranked within the top N most frequent
+        #[arg(long)]
+        min_frequency_rank: Option<u32>,
+
+        /// Only show definitions with this usage register (colloquial, formal, vulgar, offensive)
+        #[arg(long)]
+        register: Option<String>,
+
+        /// Hide definitions labelled vulgar or offensive
+        #[arg(long)]
+        safe: bool,
+
+        /// Only search these dictionaries (comma-separated names)
+        #[arg(long, value_delimiter = ',')]
+        sources: Vec<String>,
+
+        /// Hide definitions that are not written in the target language
+        #[arg(long)]
+        target_only: bool,
+
+        /// Do not retry without hyphens when the query finds nothing
+        #[arg(long)]
+        no_dehyphenate: bool,
+
+        /// Only show definitions containing this text, or matching /regex/
+        #[arg(long)]
+        filter: Option<String>,
+
+        /// Only show headwords listed in this file (one word per line)
+        #[arg(long)]
+        within: Option<PathBuf>,
+
+        /// Only show headwords of at least this many characters
+        #[arg(long)]
+        min_len: Option<u64>,
+
+        /// Only show headwords of at most this many characters
+        #[arg(long)]
+        max_len: Option<u64>,
+
+        /// Show only the best of several inflected forms of a word
+        #[arg(long)]
+        diversify: bool,
+
+        /// Do not search the reverse direction when nothing is found
+        #[arg(long)]
+        strict_lang: bool,
+
+        /// Result order: distance, score, frequency or hybrid
+        #[arg(long, default_value = "hybrid")]
+        rank: String,
+
+        /// Print how long each search phase took
+        #[arg(long)]
+        debug: bool,
+
+        /// Output format: text, or md for a Markdown block per result
+        #[arg(long, default_value = "text")]
+        format: String,
+
+        /// Show the top result as a desktop notification
+        #[arg(long)]
+        notify: bool,
+
+        /// Notification duration in milliseconds
+        #[arg(long, default_value = "5000")]
+        notify_duration: u32,
+
+        /// Notification urgency (low, normal, critical)
+        #[arg(long, default_value = "normal")]
+        notify_urgency: String,
+    },
+
+    /// Export indexed entries as dictd files, for classic dict servers
+    Export {
+        /// Output format (dictd, json, csv, tsv)
+        #[arg(long, default_value = "dictd")]
+        format: String,
+
+        /// Only export this language direction (default: every one; dictd writes each to its own files)
+        #[arg(long)]
+        lang: Option<String>,
+
+        /// Only export headwords starting with this prefix (any case)
+        #[arg(long)]
+        prefix: Option<String>,
+
+        /// Directory to write dictd files to, or the file for json/csv/tsv (default: stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Write or update a Markdown note for a word, for Obsidian or other notes apps
+    Note {
+        /// Word to look up
+        word: String,
+
+        /// Directory to write the note to (<word>.md)
+        #[arg(long)]
+        out: PathBuf,
+
+        /// Language direction (e.g. de-en, en-de, fr-de)
+        #[arg(long)]
+        lang: Option<String>,
+    },
+
+    /// Sample a study list of entries matching criteria
+    Studylist {
+        /// Language direction (e.g. de-en, en-de, fr-de)
+        #[arg(long)]
+        lang: Option<String>,
+
+        /// Part of speech (noun, verb, adjective, adverb, ...)
+        #[arg(long)]
+        pos: Option<String>,
+
+        /// Only include words ranked within the top N most frequent
+        #[arg(long)]
+        frequency_top: Option<u32>,
+
+        /// Number of entries to sample
+        #[arg(long, default_value = "50")]
+        count: usize,
+
+        /// Output format (tsv, csv, json)
+        #[arg(long, default_value = "tsv")]
+        format: String,
+
+        /// Sampling seed for a reproducible list
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+}
+
+#[derive(Subcommand)]
+enum FavAction {
+    /// Look up a word and save the best match
+    Add {
+        word: String,
+
+        /// Language direction (e.g. de-en, en-de, fr-de)
+        #[arg(long)]
+        lang: Option<String>,
+    },
+
+    /// Remove a saved word
+    Remove {
+        word: String,
+
+        /// Language direction (e.g. de-en, en-de, fr-de)
+        #[arg(long)]
+        lang: Option<String>,
+    },
+
+    /// Look up each word of a list (one per line) and save the best matches
+    Import {
+        file: PathBuf,
+
+        /// Language direction (e.g. de-en, en-de, fr-de)
+        #[arg(long)]
+        lang: Option<String>,
+    },
+
+    /// List saved words
+    List,
+
+    /// Print saved words as a study sheet
+    Export {
+        /// Sheet format (md, csv)
+        #[arg(long, default_value = "md")]
+        format: String,
     },
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let (host, port) = match &cli.command {
+        Some(Commands::Serve { host, port, .. }) => (host.clone(), *port),
+        _ => (None, None),
+    };
+    let flags = Overrides {
+        host,
+        port,
+        data_dir: cli.data_dir.clone(),
+        log_format: cli.log_format.as_deref().map(str::parse).transpose()?,
+        ..Overrides::default()
+    };
+    let settings = Settings::resolve(flags, Overrides::from_env()?)?;
+    let default_lang = settings.default_lang.as_str().to_string();
+    let progress_format: ProgressFormat = cli.progress.parse()?;
+    // JSON progress replaces the human-readable output
+    let human = progress_format == ProgressFormat::Text;
+
     // Initialize logging
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
-        )
-        .init();
+    let logger = tracing_subscriber::fmt().with_env_filter(
+        tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+            tracing_subscriber::EnvFilter::new(if human { "info" } else { "warn" })
+        }),
+    );
+    match settings.log_format {
+        LogFormat::Text => logger.init(),
+        LogFormat::Json => logger.json().init(),
+    }
 
-    let cli = Cli::parse();
+    // Without a subcommand, run setup on first use and show help otherwise
+    let command = match cli.command {
+        Some(command) => command,
+        None if !settings.data_dir.exists() => Commands::Init,
+        None => {
+            Cli::command().print_help()?;
+            return Ok(());
+        }
+    };
+
+    match command {
+        Commands::Init => {
+            let choices = onboarding::prompt_choices(
+                &mut std::io::stdin().lock(),
+                &mut std::io::stdout(),
+                &settings.data_dir,
+            )?;
+            if onboarding::write_config(&choices.data_dir, &choices.dictionaries)? {
+                println!(
+                    "\n✓ Wrote {}",
+                    choices.data_dir.join("config.toml").display()
+                );
+            }
+
+            let dictionaries = choices.dictionaries.clone();
+            let data_dir = choices.data_dir.clone();
+            let added = tokio::task::spawn_blocking(move || {
+                IndexManager::new(data_dir)?.provision(&dictionaries)
+            })
+            .await??;
+            for name in added {
+                println!("✓ Imported {}", name);
+            }
+
+            if choices.install_service {
+                match onboarding::install_systemd_unit(&choices.data_dir) {
+                    Ok(path) => {
+                        println!("✓ Installed {}", path.display());
+                        println!("  Enable it with: systemctl --user enable --now dictv");
+                    }
+                    Err(e) => eprintln!("⚠ {:#}", e),
+                }
+            }
+
+            if choices.data_dir != settings.data_dir {
+                println!(
+                    "Set DICTV_DATA={} or pass --data-dir to use this directory",
+                    choices.data_dir.display()
+                );
+            }
+            println!("\nStart the server with: dictv serve");
+        }
 
-    match cli.command {
         Commands::Import {
             download,
+            list,
+            tei,
             local,
             index,
+            dir,
             lang,
+            format,
+            word_column,
+            definition_column,
+            edge_ngrams,
+            dry_run,
+            preview,
+            download_audio,
+            license,
         } => {
-            let manager = IndexManager::default()?;
+            if list {
+                let dictionaries = tokio::task::spawn_blocking(freedict::fetch_database).await??;
+                print_freedict_list(&dictionaries);
+                return Ok(());
+            }
+
+            let explicit_lang = lang.clone();
+            // A BGL file names its own language pair, used unless --lang is given
+            let lang = match (&lang, &local, format.parse::<ImportFormat>()) {
+                (None, Some(path), Ok(ImportFormat::Bgl)) => bgl::declared_language(path)?,
+                _ => lang,
+            }
+            .unwrap_or_else(|| default_lang.clone());
+            let columns = Columns {
+                word: word_column,
+                definition: definition_column,
+            };
+            let manager = IndexManager::new(&settings.data_dir)?
+                .with_index_options(IndexOptions { edge_ngrams })
+                .with_columns((columns != Columns::default()).then_some(columns))
+                .with_license(license)
+                .with_progress(Progress::new(progress_format));
+            let webhooks = Webhooks::new(settings.config.webhooks.clone());
+
+            if dry_run {
+                let dict_path = local.unwrap_or_default();
+                let result = match (format.parse::<ImportFormat>()?, index) {
+                    (ImportFormat::Dictd, Some(index_path)) => {
+                        manager.preview_local(&dict_path, &index_path, &lang, preview)?
+                    }
+                    (ImportFormat::Dictd, None) => {
+                        eprintln!("Error: --index is required for dictd dictionaries");
+                        std::process::exit(1);
+                    }
+                    (format, _) => manager.preview_file(&dict_path, format, &lang, preview)?,
+                };
+                print_import_preview(&result, &lang);
+                return Ok(());
+            }
 
             // Show data directory location
-            let home = dirs::home_dir().unwrap_or_default();
-            let data_dir = home.join(".dictv");
-            println!("📁 Data directory: {}", data_dir.display());
-            println!("   - Dictionaries: {}/data", data_dir.display());
-            println!("   - Search index: {}/index\n", data_dir.display());
+            let data_dir = &settings.data_dir;
+            if human {
+                println!("📁 Data directory: {}", data_dir.display());
+                println!("   - Dictionaries: {}/data", data_dir.display());
+                println!("   - Search index: {}/index\n", data_dir.display());
+            }
 
             if let Some(dict_name) = download {
                 info!("Downloading dictionary: {}", dict_name);
-                manager.import_freedict(&dict_name)?;
-                println!("✓ Successfully imported {}", dict_name);
-            } else if let (Some(dict_path), Some(index_path)) = (local, index) {
+                let name = dict_name.clone();
+                let language = tokio::task::spawn_blocking(move || {
+                    if tei {
+                        manager.import_freedict_tei(&name)
+                    } else {
+                        manager.import_freedict(&name)
+                    }
+                })
+                .await??;
+                if human {
+                    println!("✓ Successfully imported {}", dict_name);
+                }
+                webhooks
+                    .send(&WebhookEvent::ImportCompleted {
+                        source: dict_name,
+                        language,
+                    })
+                    .await;
+            } else if let Some(dict_path) = local {
                 info!("Importing local dictionary: {}", dict_path);
-                manager.import_local(&dict_path, &index_path, &lang)?;
-                println!("✓ Successfully imported dictionary");
+                match (format.parse::<ImportFormat>()?, index) {
+                    (ImportFormat::Dictd, Some(index_path)) => {
+                        manager.import_local(&dict_path, &index_path, &lang)?
+                    }
+                    (ImportFormat::Dictd, None) => {
+                        eprintln!("Error: --index is required for dictd dictionaries");
+                        std::process::exit(1);
+                    }
+                    (format, _) => manager.import_file(&dict_path, format, &lang)?,
+                }
+                if human {
+                    println!("✓ Successfully imported dictionary");
+                }
+                if download_audio {
+                    download_recordings(&manager, human).await?;
+                }
+                webhooks
+                    .send(&WebhookEvent::ImportCompleted {
+                        source: manifest::source_name(dict_path.as_ref()),
+                        language: lang,
+                    })
+                    .await;
+            } else if let Some(dir) = dir {
+                let mut pairs = find_dictd_pairs(&dir)?;
+                if pairs.is_empty() {
+                    anyhow::bail!(
+                        "No .dict or .dict.dz files with a matching .index in {}",
+                        dir.display()
+                    );
+                }
+
+                // Settle every language pair before anything is imported
+                let explicit_lang = explicit_lang
+                    .map(|l| l.parse::<LanguagePair>())
+                    .transpose()?;
+                let interactive = std::io::stdin().is_terminal();
+                for pair in &mut pairs {
+                    if let Some(language) = explicit_lang {
+                        pair.language = Some(language);
+                    } else if pair.language.is_none() {
+                        if !interactive {
+                            anyhow::bail!(
+                                "Cannot tell the language pair of {} from its name, pass --lang",
+                                pair.dict_path.display()
+                            );
+                        }
+                        let language = onboarding::prompt_language(
+                            &mut std::io::stdin().lock(),
+                            &mut std::io::stdout(),
+                            &pair.dict_path,
+                        )?;
+                        pair.language = Some(language);
+                    }
+                }
+
+                let report = manager.import_dictd_pairs(&pairs)?;
+                let mut results = Vec::new();
+                for pair in &pairs {
+                    let path = std::fs::canonicalize(&pair.dict_path)?;
+                    let result = match report.succeeded.iter().find(|(p, _)| *p == path) {
+                        Some((_, count)) => Ok(*count),
+                        None => Err(report
+                            .failed
+                            .iter()
+                            .find(|(p, _)| *p == path)
+                            .map_or_else(|| "not indexed".to_string(), |(_, e)| e.clone())),
+                    };
+                    let name = manifest::source_name(&pair.dict_path);
+                    let language = pair.language.map(String::from).unwrap_or_default();
+                    if result.is_ok() {
+                        webhooks
+                            .send(&WebhookEvent::ImportCompleted {
+                                source: name.clone(),
+                                language: language.clone(),
+                            })
+                            .await;
+                    }
+                    results.push((name, language, result));
+                }
+
+                if human {
+                    print_import_summary(&results);
+                }
+                if results.iter().all(|(_, _, result)| result.is_err()) {
+                    anyhow::bail!("No dictionary in {} could be imported", dir.display());
+                }
             } else {
-                eprintln!("Error: Either --download or both --local and --index must be provided");
+                eprintln!("Error: Either --download, --local or --dir must be provided");
                 std::process::exit(1);
             }
         }
 
-        Commands::Rebuild => {
-            let manager = IndexManager::default()?;
+        Commands::ImportFrequency { file, lang } => {
+            let language: LanguagePair = lang.map_or(Ok(settings.default_lang), |l| l.parse())?;
+            let manager = IndexManager::new(&settings.data_dir)?;
+            let count = manager.import_frequency(&file, language.as_str())?;
+            println!(
+                "✓ Imported {} frequency ranks for {}",
+                count,
+                language.as_str()
+            );
+        }
 
-            let home = dirs::home_dir().unwrap_or_default();
-            let data_dir = home.join(".dictv");
-            println!("📁 Data directory: {}", data_dir.display());
+        Commands::Rebuild { edge_ngrams } => {
+            let manager = IndexManager::new(&settings.data_dir)?
+                .with_index_options(IndexOptions { edge_ngrams })
+                .with_progress(Progress::new(progress_format));
+
+            if human {
+                println!("📁 Data directory: {}", settings.data_dir.display());
+            }
 
             info!("Rebuilding index...");
-            manager.rebuild()?;
-            println!("✓ Index rebuilt successfully");
+            let webhooks = Webhooks::new(settings.config.webhooks.clone());
+            let report = match manager.rebuild() {
+                Ok(report) => report,
+                Err(e) => {
+                    webhooks
+                        .send(&WebhookEvent::RebuildFailed {
+                            error: format!("{:#}", e),
+                        })
+                        .await;
+                    return Err(e);
+                }
+            };
+            let event = if report.all_failed() {
+                WebhookEvent::RebuildFailed {
+                    error: "every dictionary failed to parse".to_string(),
+                }
+            } else {
+                WebhookEvent::RebuildCompleted {
+                    indexed: report.indexed,
+                    succeeded: report.succeeded.len(),
+                    failed: report
+                        .failed
+                        .iter()
+                        .map(|(path, _)| manifest::source_name(path))
+                        .collect(),
+                }
+            };
+            webhooks.send(&event).await;
+            if report.all_failed() {
+                eprintln!("Error: every dictionary failed to parse");
+                std::process::exit(1);
+            }
+            if !human {
+                return Ok(());
+            }
+            for (path, entries) in &report.succeeded {
+                println!("✓ {} ({} entries)", path.display(), entries);
+            }
+            for (path, error) in &report.failed {
+                println!("✗ {}: {}", path.display(), error);
+            }
+
+            if report.failed.is_empty() {
+                println!("✓ Index rebuilt successfully ({} entries)", report.indexed);
+            } else {
+                println!(
+                    "⚠ Index rebuilt with {} entries, {} of {} dictionaries failed",
+                    report.indexed,
+                    report.failed.len(),
+                    report.failed.len() + report.succeeded.len()
+                );
+            }
+        }
+
+        Commands::Clean => {
+            let manager = IndexManager::new(&settings.data_dir)?;
+            let report = manager.clean()?;
+
+            for path in &report.removed {
+                println!("  - {}", path.display());
+            }
+            println!(
+                "✓ Removed {} stale archives and directories, reclaimed {:.1} MB",
+                report.removed.len(),
+                report.reclaimed_bytes as f64 / 1_000_000.0
+            );
+        }
+
+        Commands::Purge {
+            source,
+            lang,
+            word_prefix,
+            dry_run,
+        } => {
+            let manager = IndexManager::new(&settings.data_dir)?;
+            let filter = PurgeFilter {
+                source,
+                language: lang.map(|l| l.parse::<LanguagePair>()).transpose()?,
+                word_prefix,
+            };
+            if filter.word_prefix.is_some() && filter.headword_prefix().is_none() {
+                anyhow::bail!("--word-prefix must not be blank");
+            }
+            let count = SearchEngine::purge(manager.index_dir(), &filter, dry_run)?;
+            if dry_run {
+                println!("{} entries match, nothing deleted (--dry-run)", count);
+            } else {
+                println!("✓ Purged {} entries", count);
+            }
         }
 
         Commands::Stats => {
-            let manager = IndexManager::default()?;
-            let (total, en_de, de_en, size) = manager.stats()?;
+            let manager = IndexManager::new(&settings.data_dir)?;
+            let (total, languages, size) = manager.stats()?;
 
-            let home = dirs::home_dir().unwrap_or_default();
-            let data_dir = home.join(".dictv");
+            let data_dir = &settings.data_dir;
 
             println!("📊 Dictionary Statistics:");
             println!("  Data directory: {}", data_dir.display());
             println!("  Total entries: {}", total);
-            println!("  English → German: {}", en_de);
-            println!("  German → English: {}", de_en);
+            for count in languages {
+                println!("  {}: {}", count.language, count.entries);
+            }
             println!("  Index size: {} MB", size / 1_000_000);
+
+            // Only the Tantivy index reports its segments and mapped files
+            if settings.config.backend == BackendKind::Tantivy {
+                let engine = manager.search_engine()?;
+                let index = engine.index_memory()?;
+                println!("\n💾 Memory:");
+                println!("  Segments: {}", index.segments);
+                println!(
+                    "  Memory-mapped index: {} MB",
+                    index.mapped_bytes / 1_000_000
+                );
+                println!(
+                    "  Headword lookup maps: {} MB",
+                    index.headword_map_bytes / 1_000_000
+                );
+                println!("  Frequency words: {}", engine.frequency_words());
+                // With the index open and frequency lists loaded, close to an idle server
+                if let Some(resident) = memory::resident_bytes() {
+                    println!("  Resident with index loaded: {} MB", resident / 1_000_000);
+                }
+            }
+        }
+
+        Commands::AnalyzeLog { file, top } => {
+            let path = match file {
+                Some(file) => file.into(),
+                None => IndexManager::new(&settings.data_dir)?.query_log_path(),
+            };
+            let analysis = querylog::analyze(&path, top)?;
+
+            println!("Queries:          {}", analysis.total_queries);
+            println!("Average latency:  {:.2} ms", analysis.average_latency_ms);
+
+            println!("\nZero-result queries:");
+            for (query, count) in &analysis.zero_result_queries {
+                println!("  {:>5}× {}", count, query);
+            }
+
+            println!("\nSlowest queries:");
+            for record in &analysis.slow_queries {
+                println!(
+                    "  {:>8.2} ms  {} ({}, {} results)",
+                    record.latency_ms,
+                    record.params.q,
+                    record.params.mode().as_str(),
+                    record.result_count
+                );
+            }
+        }
+
+        Commands::Warmup {
+            queries,
+            url,
+            api_key,
+            mode,
+            lang,
+        } => {
+            let request = PrimeRequest {
+                queries: std::fs::read_to_string(&queries)
+                    .with_context(|| format!("Failed to read {}", queries))?
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(str::to_string)
+                    .collect(),
+                mode: mode.map(|m| m.parse()).transpose()?,
+                lang: lang.map(|l| l.parse()).transpose()?,
+            };
+            let url = url.unwrap_or_else(|| format!("http://{}:{}", settings.host, settings.port));
+            let mut builder = reqwest::Client::new()
+                .post(format!("{}/admin/prime", url.trim_end_matches('/')))
+                .json(&request);
+            if let Some(key) = api_key {
+                builder = builder.header("X-API-Key", key);
+            }
+            let response: PrimeResponse = builder
+                .send()
+                .await
+                .with_context(|| format!("Failed to reach the server at {}", url))?
+                .error_for_status()?
+                .json()
+                .await?;
+
+            println!(
+                "✓ Primed {} queries ({} results) in {:.0} ms",
+                response.primed, response.results, response.took_ms
+            );
+            if response.failed > 0 {
+                println!("  {} queries failed", response.failed);
+            }
+        }
+
+        Commands::CheckUpdates => {
+            let manager = IndexManager::new(&settings.data_dir)?;
+            let webhooks = Webhooks::new(settings.config.webhooks.clone());
+            let updates = updates::check_freedict_updates(&manager.manifest()?).await?;
+
+            if updates.is_empty() {
+                println!("✓ All FreeDict dictionaries are up to date");
+            }
+            for update in updates {
+                println!(
+                    "⬆ {}: {} -> {}",
+                    update.dictionary, update.installed, update.available
+                );
+                webhooks
+                    .send(&WebhookEvent::UpdateAvailable {
+                        dictionary: update.dictionary,
+                        installed: update.installed,
+                        available: update.available,
+                    })
+                    .await;
+            }
+        }
+
+        Commands::Fav { action } => {
+            let manager = IndexManager::new(&settings.data_dir)?;
+            let path = manager.favorites_path();
+            let mut favorites = Favorites::load(&path)?;
+
+            match action {
+                FavAction::Add { word, lang } => {
+                    let language: LanguagePair =
+                        lang.map_or(Ok(settings.default_lang), |l| l.parse())?;
+                    let options = SearchOptions {
+                        mode: SearchMode::Exact,
+                        language,
+                        limit: 1,
+                        flip_direction: false,
+                        ..SearchOptions::default()
+                    };
+                    let engine = manager.search_engine()?;
+                    let Some(result) = engine.search_with_options(&word, &options)?.results.pop()
+                    else {
+                        eprintln!("Error: No entry for {}", word);
+                        std::process::exit(1);
+                    };
+                    println!("★ Saved {}", result.word);
+                    favorites.add(Favorite::from_result(result));
+                    favorites.save(&path)?;
+                }
+                FavAction::Remove { word, lang } => {
+                    let language: LanguagePair =
+                        lang.map_or(Ok(settings.default_lang), |l| l.parse())?;
+                    if !favorites.remove(&word, language.as_str()) {
+                        eprintln!("Error: {} is not saved", word);
+                        std::process::exit(1);
+                    }
+                    favorites.save(&path)?;
+                    println!("✓ Removed {}", word);
+                }
+                FavAction::Import { file, lang } => {
+                    let language: LanguagePair =
+                        lang.map_or(Ok(settings.default_lang), |l| l.parse())?;
+                    let list = std::fs::read_to_string(&file)?;
+                    let engine = manager.search_engine()?;
+                    let import =
+                        favorites::import_word_list(&engine, &mut favorites, &list, language)?;
+                    favorites.save(&path)?;
+
+                    for (word, matched) in &import.saved {
+                        if word.to_lowercase() == *matched {
+                            println!("★ {}", matched);
+                        } else {
+                            println!("★ {} → {}", word, matched);
+                        }
+                    }
+                    for word in &import.not_found {
+                        println!("✗ {} (not found, saved flagged)", word);
+                    }
+                    println!(
+                        "✓ Saved {} words, {} not found",
+                        import.saved.len(),
+                        import.not_found.len()
+                    );
+                }
+                FavAction::List => {
+                    for favorite in &favorites.favorites {
+                        if favorite.not_found {
+                            println!("{} ({}): (not found)", favorite.word, favorite.language);
+                            continue;
+                        }
+                        println!(
+                            "{} ({}): {}",
+                            favorite.word,
+                            favorite.language,
+                            favorite.definitions.join("; ")
+                        );
+                    }
+                }
+                FavAction::Export { format } => {
+                    favorites::write_sheet(
+                        &mut std::io::stdout().lock(),
+                        format.parse()?,
+                        &favorites.favorites,
+                    )?;
+                }
+            }
+        }
+
+        Commands::Disable { source } => {
+            IndexManager::new(&settings.data_dir)?.set_source_enabled(&source, false)?;
+            println!("✓ Disabled {} (its entries stay indexed)", source);
+        }
+
+        Commands::Enable { source } => {
+            IndexManager::new(&settings.data_dir)?.set_source_enabled(&source, true)?;
+            println!("✓ Enabled {}", source);
+        }
+
+        Commands::License { source, license } => {
+            IndexManager::new(&settings.data_dir)?.set_source_license(&source, &license)?;
+            println!("✓ Recorded {} as {}", source, license);
         }
 
-        Commands::Serve { daemon, port } => {
+        Commands::Diff {
+            source_a,
+            source_b,
+            json,
+        } => {
+            let manager = IndexManager::new(&settings.data_dir)?;
+            let manifest = manager.manifest()?;
+            for name in [&source_a, &source_b] {
+                if !manifest.sources.iter().any(|s| &s.name == name) {
+                    anyhow::bail!("Unknown dictionary: {}", name);
+                }
+            }
+
+            // Disabled dictionaries can be compared too
+            let engine = SearchEngine::new(manager.index_dir())?;
+            let mut entries = Vec::new();
+            for language in engine.languages()? {
+                entries.extend(engine.entries(language)?);
+            }
+            let diff = diff::diff_entries(entries, &source_a, &source_b);
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&diff)?);
+            } else {
+                println!("{}", diff.report());
+            }
+        }
+
+        Commands::Serve {
+            daemon,
+            query_log,
+            query_log_ip,
+            allow_empty,
+            watch_data,
+            ..
+        } => {
             if daemon {
                 println!("Daemon mode not yet implemented");
                 std::process::exit(1);
             }
 
-            let manager = IndexManager::default()?;
-            let engine = SearchEngine::new(manager.index_dir())?;
-
-            let home = dirs::home_dir().unwrap_or_default();
-            let data_dir = home.join(".dictv");
+            let data_dir = &settings.data_dir;
             println!("📁 Using data directory: {}", data_dir.display());
-            println!("🚀 Starting server on http://localhost:{}", port);
-            server::serve(engine, port).await?;
+
+            let dictionaries = settings.config.dictionaries.clone();
+            let provision_dir = data_dir.clone();
+            let added = tokio::task::spawn_blocking(move || {
+                IndexManager::new(provision_dir)?.provision(&dictionaries)
+            })
+            .await??;
+            for name in added {
+                println!("✓ Imported {} (listed in config.toml)", name);
+            }
+
+            if settings.config.backend != BackendKind::Tantivy {
+                anyhow::bail!(
+                    "The server needs the tantivy backend, config.toml selects {}",
+                    settings.config.backend.as_str()
+                );
+            }
+            let manager = IndexManager::new(&settings.data_dir)?;
+            if !manager.has_index() {
+                if !allow_empty {
+                    print_empty_index_help(data_dir);
+                    std::process::exit(1);
+                }
+                SearchEngine::build_index(manager.index_dir(), Vec::new())?;
+            }
+            let engine = manager
+                .search_engine()?
+                .with_ranking(settings.config.ranking.clone());
+            if engine.num_docs() == 0 {
+                if !allow_empty {
+                    print_empty_index_help(data_dir);
+                    std::process::exit(1);
+                }
+                println!("⚠ The index is empty, /health reports not_ready until you import");
+            }
+
+            let scheme = match settings.config.admin_tls {
+                Some(_) => "https",
+                None => "http",
+            };
+            println!(
+                "🚀 Starting server on {}://{}:{}",
+                scheme, settings.host, settings.port
+            );
+            let mut state = server::AppState::new(engine)
+                .with_abbreviations(manager.abbreviations()?)
+                .with_search_defaults(settings.config.search_defaults)
+                .with_default_language(settings.default_lang)
+                .with_api_keys(ApiKeys::new(settings.config.api_keys.clone()))
+                .with_favorites(manager.favorites_path())
+                .with_pool_sizes(&settings.config.pools)?;
+            if query_log {
+                let config = QueryLogConfig {
+                    include_ip: query_log_ip,
+                    ..QueryLogConfig::new(manager.query_log_path())
+                };
+                println!("📝 Logging queries to {}", config.path.display());
+                state = state.with_query_log(QueryLog::open(config)?);
+            }
+            let audio_dir = manager.audio_dir();
+            state = state.with_recordings(audio_dir.join("wiktionary"));
+            if let Some(tts) = settings.config.tts.clone() {
+                println!("🔊 Serving pronunciations at /audio/{{word}}");
+                state = state.with_tts(Tts::new(tts, audio_dir)?);
+            }
+            if watch_data {
+                println!(
+                    "👀 Watching {} for new dictionaries",
+                    data_dir.join("data").display()
+                );
+                DataWatcher::new(IndexManager::new(data_dir)?).spawn(watch::WATCH_INTERVAL);
+            }
+            match &settings.config.admin_tls {
+                Some(tls) => {
+                    let tls = tls.relative_to(data_dir);
+                    server::serve_tls(state, &settings.host, settings.port, &tls).await?
+                }
+                None => server::serve_on(state, &settings.host, settings.port).await?,
+            }
         }
 
         Commands::Query {
@@ -173,17 +1215,113 @@ async fn main() -> Result<()> {
             lang,
             max_distance,
             limit,
+            strict_diacritics,
+            no_auto_distance,
+            group,
+            min_frequency_rank,
+            register,
+            safe,
+            sources,
+            target_only,
+            no_dehyphenate,
+            filter,
+            within,
+            min_len,
+            max_len,
+            diversify,
+            strict_lang,
+            rank,
+            debug,
+            format,
+            notify,
+            notify_duration,
+            notify_urgency,
         } => {
-            let manager = IndexManager::default()?;
-            let engine = SearchEngine::new(manager.index_dir())?;
+            let manager = IndexManager::new(&settings.data_dir)?;
+            let backend: Box<dyn Backend> = match settings.config.backend {
+                BackendKind::Tantivy => Box::new(
+                    manager
+                        .search_engine()?
+                        .with_ranking(settings.config.ranking.clone()),
+                ),
+                BackendKind::Sqlite => manager.search_backend()?,
+            };
 
             let search_mode: SearchMode = mode.parse()?;
-            let language: Language = lang.parse()?;
+            let language: LanguagePair = lang.map_or(Ok(settings.default_lang), |l| l.parse())?;
+            let group: Grouping = group.parse()?;
+            let markdown = match format.as_str() {
+                "text" => false,
+                "md" | "markdown" => true,
+                _ => anyhow::bail!("Invalid output format: {}", format),
+            };
+
+            let options = SearchOptions {
+                mode: search_mode,
+                language,
+                max_distance,
+                limit,
+                strict_diacritics,
+                auto_distance: !no_auto_distance,
+                group,
+                min_frequency_rank,
+                register: register.map(|r| r.parse::<Register>()).transpose()?,
+                safe,
+                sources,
+                target_only,
+                dehyphenate: !no_dehyphenate,
+                filter: filter.as_deref().map(str::parse).transpose()?,
+                within: match within {
+                    Some(path) => std::fs::read_to_string(path)?
+                        .lines()
+                        .map(str::trim)
+                        .filter(|word| !word.is_empty())
+                        .map(str::to_string)
+                        .collect(),
+                    None => Vec::new(),
+                },
+                min_len,
+                max_len,
+                diversify,
+                flip_direction: !strict_lang,
+                rank: rank.parse()?,
+                cancellation: Cancellation::default(),
+                deadline: None,
+            };
+            let SearchOutput {
+                results,
+                notes,
+                timings,
+                merged_definitions,
+                ..
+            } = backend.search(&query, &options)?;
+
+            if notify && let Some(top) = results.first() {
+                let notify_options = NotifyOptions {
+                    duration_ms: notify_duration,
+                    urgency: notify_urgency.parse()?,
+                };
+                dictv::notify::notify(top, &notify_options)?;
+            }
 
-            let results = engine.search(&query, search_mode, language, max_distance, limit)?;
+            for note in notes {
+                println!("ℹ {}", note);
+            }
+            if debug {
+                println!(
+                    "⏱ query build {:.2} ms, search {:.2} ms, fetch {:.2} ms, group {:.2} ms",
+                    timings.query_build_ms, timings.search_ms, timings.fetch_ms, timings.group_ms
+                );
+                if merged_definitions > 0 {
+                    println!("⧉ {} duplicate definitions merged", merged_definitions);
+                }
+            }
 
             if results.is_empty() {
                 println!("No results found for '{}'", query);
+            } else if markdown {
+                let blocks: Vec<String> = results.iter().map(markdown::render_result).collect();
+                print!("{}", blocks.join("\n"));
             } else {
                 println!("Results for '{}':\n", query);
                 for result in results {
@@ -199,7 +1337,252 @@ async fn main() -> Result<()> {
                 }
             }
         }
+
+        Commands::Export {
+            format,
+            lang,
+            prefix,
+            output,
+        } => {
+            let manager = IndexManager::new(&settings.data_dir)?;
+            let engine = manager.search_engine()?;
+            let languages = match lang {
+                Some(lang) => vec![lang.parse::<LanguagePair>()?],
+                None => engine.languages()?,
+            };
+            let prefix = prefix.map(|prefix| prefix.to_lowercase());
+            let entries = |language| -> Result<Vec<_>> {
+                let mut entries = engine.entries(language)?;
+                if let Some(prefix) = &prefix {
+                    entries.retain(|entry| entry.word.to_lowercase().starts_with(prefix.as_str()));
+                }
+                Ok(entries)
+            };
+
+            if format != "dictd" {
+                let format: ExportFormat = format.parse()?;
+                let mut records = Vec::new();
+                for language in languages {
+                    records.extend(entries(language)?.into_iter().map(|entry| {
+                        let rank = engine.frequency_rank(language, &entry.word);
+                        export::ExportRecord::from_entry(entry, rank)
+                    }));
+                }
+                match output {
+                    Some(path) => {
+                        let file = std::fs::File::create(&path)
+                            .with_context(|| format!("Failed to create {}", path.display()))?;
+                        let mut writer = std::io::BufWriter::new(file);
+                        export::write_records(&mut writer, format, &records)?;
+                        writer.flush()?;
+                        println!("✓ Exported {} entries to {}", records.len(), path.display());
+                    }
+                    None => export::write_records(&mut std::io::stdout().lock(), format, &records)?,
+                }
+                return Ok(());
+            }
+
+            let Some(output) = output else {
+                anyhow::bail!("dictd export needs --output <dir>");
+            };
+            for language in languages {
+                let entries = entries(language)?;
+                if entries.is_empty() {
+                    println!("No {} entries to export", language);
+                    continue;
+                }
+                let (dict_path, index_path) = export::write_dictd(
+                    &output,
+                    &format!("dictv-{}", language),
+                    &format!("dictv {} export", language),
+                    &entries,
+                )?;
+                println!(
+                    "✓ Exported {} {} entries to {} and {}",
+                    entries.len(),
+                    language,
+                    index_path.display(),
+                    dict_path.display()
+                );
+            }
+        }
+
+        Commands::Note { word, out, lang } => {
+            let manager = IndexManager::new(&settings.data_dir)?;
+            let backend = manager.search_backend()?;
+            let options = SearchOptions {
+                mode: SearchMode::Exact,
+                language: lang.map_or(Ok(settings.default_lang), |l| l.parse())?,
+                ..SearchOptions::default()
+            };
+            let results = backend.search(&word, &options)?.results;
+            if results.is_empty() {
+                anyhow::bail!("No entry for '{}'", word);
+            }
+
+            std::fs::create_dir_all(&out)
+                .with_context(|| format!("Failed to create {}", out.display()))?;
+            let path = markdown::note_path(&out, &word);
+            let existing = std::fs::read_to_string(&path).ok();
+            let date = wotd::date(wotd::today());
+            let note = markdown::note(&word, &results, &date, existing.as_deref());
+            std::fs::write(&path, note)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            if existing.is_some() {
+                println!("✓ Updated {}", path.display());
+            } else {
+                println!("✓ Wrote {}", path.display());
+            }
+        }
+
+        Commands::Studylist {
+            lang,
+            pos,
+            frequency_top,
+            count,
+            format,
+            seed,
+        } => {
+            let manager = IndexManager::new(&settings.data_dir)?;
+            let engine = manager.search_engine()?;
+
+            let format: ExportFormat = format.parse()?;
+            let criteria = StudyListCriteria {
+                language: lang.map_or(Ok(settings.default_lang), |l| l.parse())?,
+                part_of_speech: pos.map(|p| p.parse::<PartOfSpeech>()).transpose()?,
+                frequency_top,
+                count,
+                seed: seed.unwrap_or_else(|| {
+                    std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .map(|d| d.as_nanos() as u64)
+                        .unwrap_or_default()
+                }),
+            };
+
+            let records = studylist::build_study_list(&engine, &criteria)?;
+            export::write_records(&mut std::io::stdout().lock(), format, &records)?;
+        }
     }
 
     Ok(())
 }
+
+/// Explain how to fill an empty data directory
+fn print_empty_index_help(data_dir: &std::path::Path) {
+    eprintln!(
+        "Error: No dictionary entries are indexed in {}",
+        data_dir.display()
+    );
+    eprintln!("Import a dictionary first, for example:");
+    eprintln!("  dictv import --download freedict-deu-eng");
+    eprintln!("or list dictionaries = [\"deu-eng\"] in config.toml,");
+    eprintln!("or pass --allow-empty to start anyway.");
+}
+
+/// Print the report for `import --dry-run`
+/// Table of the dictionaries imported by `import --dir`
+fn print_freedict_list(dictionaries: &[FreedictDictionary]) {
+    println!(
+        "{:<9}  {:<8}  {:>9}  Version",
+        "Name", "Language", "Headwords"
+    );
+    for dictionary in dictionaries {
+        let Some(release) = dictionary.release("dictd") else {
+            continue;
+        };
+        println!(
+            "{:<9}  {:<8}  {:>9}  {}",
+            dictionary.name,
+            dictionary
+                .language()
+                .map_or_else(|| "?".to_string(), String::from),
+            dictionary
+                .headwords()
+                .map_or_else(String::new, |count| count.to_string()),
+            release.version
+        );
+    }
+    println!("\nImport one with: dictv import --download <name>");
+}
+
+/// Download the recordings of indexed entries that are not saved yet
+async fn download_recordings(manager: &IndexManager, human: bool) -> Result<()> {
+    let engine = manager.search_engine()?;
+    let mut urls = Vec::new();
+    for language in engine.languages()? {
+        for entry in engine.entries(language)? {
+            urls.extend(entry.audio_urls);
+        }
+    }
+    urls.sort();
+    urls.dedup();
+    if urls.is_empty() {
+        if human {
+            println!("No recordings to download");
+        }
+        return Ok(());
+    }
+
+    let dir = manager.audio_dir().join("wiktionary");
+    if human {
+        println!("Downloading {} recordings to {}", urls.len(), dir.display());
+    }
+    let report = tokio::task::spawn_blocking(move || {
+        wiktionary::download_audio(&dir, urls.iter().map(String::as_str))
+    })
+    .await??;
+    for (url, error) in &report.failed {
+        warn!("Failed to download {}: {}", url, error);
+    }
+    if human {
+        println!(
+            "✓ Downloaded {} recordings ({} already saved, {} failed)",
+            report.downloaded,
+            report.present,
+            report.failed.len()
+        );
+    }
+    Ok(())
+}
+
+fn print_import_summary(results: &[(String, String, Result<usize, String>)]) {
+    let width = results
+        .iter()
+        .map(|(name, _, _)| name.chars().count())
+        .max()
+        .unwrap_or_default()
+        .max("Dictionary".len());
+    println!("\n{:<width$}  {:<8}  Result", "Dictionary", "Language");
+    for (name, language, imported) in results {
+        let result = match imported {
+            Ok(count) => format!("✓ {} entries", count),
+            Err(e) => format!("✗ {}", e),
+        };
+        println!("{:<width$}  {:<8}  {}", name, language, result);
+    }
+}
+
+fn print_import_preview(preview: &ImportPreview, requested_language: &str) {
+    println!("🔍 Dry run, the index was not modified\n");
+    println!("Entries:          {}", preview.entries);
+    match &preview.stats.detected_language {
+        Some(detected) if detected != requested_language => println!(
+            "Language pair:    {} (detected, --lang was {})",
+            detected, requested_language
+        ),
+        Some(detected) => println!("Language pair:    {} (detected)", detected),
+        None => println!("Language pair:    {} (from --lang)", requested_language),
+    }
+    println!("Malformed lines:  {}", preview.stats.malformed_lines);
+    for line in &preview.stats.malformed_examples {
+        println!("  ✗ {}", line);
+    }
+
+    if !preview.samples.is_empty() {
+        println!("\nSample entries:");
+        for entry in &preview.samples {
+            println!("• {}: {}", entry.word, entry.definition);
+        }
+    }
+}
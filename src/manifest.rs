@@ -0,0 +1,318 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::models::{ImportFormat, SourceLicense};
+use crate::stardict;
+use crate::wordlist::Columns;
+
+/// Imported dictionary sources, recorded so `rebuild` knows how to read them
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    #[serde(default)]
+    pub sources: Vec<ManifestSource>,
+    /// Imports that have started but not finished
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pending: Vec<PendingImport>,
+}
+
+/// An import in progress, with the files it has created so far. Found after
+/// the import was interrupted, the files are partial and get deleted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingImport {
+    pub name: String,
+    pub artifacts: Vec<PathBuf>,
+}
+
+/// One imported dictionary file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestSource {
+    /// Short name used to refer to the dictionary, derived from the file name
+    #[serde(default)]
+    pub name: String,
+    pub path: PathBuf,
+    /// The `.index` file of a dictd dictionary
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index_path: Option<PathBuf>,
+    pub format: ImportFormat,
+    pub language: String,
+    /// Word and definition columns of a CSV/TSV word list, when not the first two
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub columns: Option<Columns>,
+    /// Disabled dictionaries stay indexed but are excluded from search
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// License the dictionary is distributed under, e.g. "GPL" or "CC-BY-SA"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl ManifestSource {
+    pub fn new(path: PathBuf, format: ImportFormat, language: &str) -> Self {
+        Self {
+            name: source_name(&path),
+            path,
+            index_path: None,
+            format,
+            language: language.to_string(),
+            columns: None,
+            enabled: true,
+            license: None,
+        }
+    }
+
+    /// Set the `.index` file of a dictd dictionary
+    pub fn with_index_path(mut self, index_path: PathBuf) -> Self {
+        self.index_path = Some(index_path);
+        self
+    }
+
+    /// Set the column mapping of a CSV/TSV word list
+    pub fn with_columns(mut self, columns: Option<Columns>) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    /// Set the license the dictionary is distributed under
+    pub fn with_license(mut self, license: Option<String>) -> Self {
+        self.license = license;
+        self
+    }
+}
+
+/// Dictionary name for a file, e.g. "freedict-deu-eng-1.9-fd1" for
+/// "freedict-deu-eng-1.9-fd1.dict.dz"
+pub fn source_name(path: &Path) -> String {
+    let mut name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    while let Some(stem) = [
+        ".dz",
+        ".dict",
+        ".dsl",
+        ".txt",
+        ".tsv",
+        ".csv",
+        ".ifo",
+        ".gz",
+        ".xdxf",
+        ".tei",
+        ".jsonl",
+        ".bgl",
+        ".xml",
+        ".dictionary",
+        ".slob",
+        ".zip",
+        ".zim",
+    ]
+    .iter()
+    .find_map(|extension| name.strip_suffix(extension))
+    {
+        name = stem;
+    }
+    name.to_string()
+}
+
+impl Manifest {
+    /// Load a manifest, or an empty one if the file does not exist yet
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content =
+            fs::read_to_string(path).context(format!("Failed to read manifest: {:?}", path))?;
+        let mut manifest: Self =
+            serde_json::from_str(&content).context(format!("Invalid manifest: {:?}", path))?;
+        // Manifests written before sources had names
+        for source in &mut manifest.sources {
+            if source.name.is_empty() {
+                source.name = source_name(&source.path);
+            }
+        }
+        Ok(manifest)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        fs::write(path.as_ref(), serde_json::to_string_pretty(self)?)
+            .context(format!("Failed to write manifest: {:?}", path.as_ref()))
+    }
+
+    /// Add a source, replacing an earlier record of the same file.
+    /// A re-imported dictionary keeps its enabled flag, and its license
+    /// unless a new one is known.
+    pub fn record(&mut self, mut source: ManifestSource) {
+        if let Some(previous) = self.sources.iter().find(|s| s.path == source.path) {
+            source.enabled = previous.enabled;
+            if source.license.is_none() {
+                source.license = previous.license.clone();
+            }
+        }
+        self.sources.retain(|s| s.path != source.path);
+        self.sources.push(source);
+    }
+
+    /// Enable or disable every source with the given name
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> Result<()> {
+        let mut found = false;
+        for source in self.sources.iter_mut().filter(|s| s.name == name) {
+            source.enabled = enabled;
+            found = true;
+        }
+        if !found {
+            anyhow::bail!("Unknown dictionary: {}", name);
+        }
+        Ok(())
+    }
+
+    /// Set the license of every source with the given name
+    pub fn set_license(&mut self, name: &str, license: &str) -> Result<()> {
+        let mut found = false;
+        for source in self.sources.iter_mut().filter(|s| s.name == name) {
+            source.license = Some(license.to_string());
+            found = true;
+        }
+        if !found {
+            anyhow::bail!("Unknown dictionary: {}", name);
+        }
+        Ok(())
+    }
+
+    /// Licenses of the enabled sources, for attribution
+    pub fn licenses(&self) -> Vec<SourceLicense> {
+        self.sources
+            .iter()
+            .filter(|s| s.enabled)
+            .map(|s| SourceLicense {
+                source: s.name.clone(),
+                language: s.language.clone(),
+                license: s.license.clone(),
+            })
+            .collect()
+    }
+
+    /// Names of disabled sources
+    pub fn disabled(&self) -> Vec<String> {
+        self.sources
+            .iter()
+            .filter(|s| !s.enabled)
+            .map(|s| s.name.clone())
+            .collect()
+    }
+
+    /// Start tracking an import, replacing an earlier one of the same name
+    pub fn begin_import(&mut self, name: &str, artifacts: Vec<PathBuf>) {
+        self.pending.retain(|p| p.name != name);
+        self.pending.push(PendingImport {
+            name: name.to_string(),
+            artifacts,
+        });
+    }
+
+    /// Stop tracking an import, returning it
+    pub fn end_import(&mut self, name: &str) -> Option<PendingImport> {
+        let position = self.pending.iter().position(|p| p.name == name)?;
+        Some(self.pending.remove(position))
+    }
+
+    /// Whether `path` is a recorded dictionary or index file, or one of the
+    /// files of a recorded StarDict dictionary
+    pub fn contains(&self, path: &Path) -> bool {
+        self.sources.iter().any(|s| {
+            s.path == path
+                || s.index_path.as_deref() == Some(path)
+                || (s.format == ImportFormat::StarDict && stardict::is_part_of(&s.path, path))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_manifest_round_trip_replaces_same_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("manifest.json");
+
+        let mut manifest = Manifest::load(&path).unwrap();
+        assert!(manifest.sources.is_empty());
+
+        let words = PathBuf::from("/dicts/words.txt");
+        manifest.record(ManifestSource::new(
+            words.clone(),
+            ImportFormat::DictCc,
+            "de-en",
+        ));
+        manifest.set_enabled("words", false).unwrap();
+        manifest.record(ManifestSource::new(words, ImportFormat::DictCc, "en-de"));
+        manifest.save(&path).unwrap();
+
+        let loaded = Manifest::load(&path).unwrap();
+        assert_eq!(loaded.sources.len(), 1);
+        assert_eq!(loaded.sources[0].language, "en-de");
+        assert_eq!(loaded.disabled(), vec!["words"]);
+        assert!(loaded.contains(Path::new("/dicts/words.txt")));
+        assert!(loaded.clone().set_enabled("other", true).is_err());
+    }
+
+    #[test]
+    fn test_license_survives_reimport_and_disabled_sources_are_left_out() {
+        let mut manifest = Manifest::default();
+        let words = PathBuf::from("/dicts/words.txt");
+        manifest.record(
+            ManifestSource::new(words.clone(), ImportFormat::DictCc, "de-en")
+                .with_license(Some("personal-use".to_string())),
+        );
+        manifest.set_license("words", "CC-BY").unwrap();
+        manifest.record(ManifestSource::new(
+            words.clone(),
+            ImportFormat::DictCc,
+            "de-en",
+        ));
+        manifest.record(ManifestSource::new(
+            PathBuf::from("/dicts/lingvo.dsl"),
+            ImportFormat::Dsl,
+            "de-en",
+        ));
+        assert!(manifest.set_license("other", "GPL").is_err());
+
+        let licenses = manifest.licenses();
+        assert_eq!(licenses.len(), 2);
+        assert_eq!(licenses[0].source, "words");
+        assert_eq!(licenses[0].license.as_deref(), Some("CC-BY"));
+        assert_eq!(licenses[1].license, None);
+
+        manifest.set_enabled("lingvo", false).unwrap();
+        assert_eq!(manifest.licenses().len(), 1);
+    }
+
+    #[test]
+    fn test_pending_imports() {
+        let mut manifest = Manifest::default();
+        manifest.begin_import("freedict-deu-eng", vec![PathBuf::from("/data/.partial")]);
+        manifest.begin_import("freedict-deu-eng", vec![PathBuf::from("/data/deu-eng")]);
+        assert_eq!(manifest.pending.len(), 1);
+
+        let pending = manifest.end_import("freedict-deu-eng").unwrap();
+        assert_eq!(pending.artifacts, vec![PathBuf::from("/data/deu-eng")]);
+        assert!(manifest.pending.is_empty());
+        assert!(manifest.end_import("freedict-deu-eng").is_none());
+    }
+
+    #[test]
+    fn test_source_name_strips_dictionary_extensions() {
+        assert_eq!(
+            source_name(Path::new("/data/freedict-deu-eng-1.9-fd1.dict.dz")),
+            "freedict-deu-eng-1.9-fd1"
+        );
+        assert_eq!(source_name(Path::new("Lingvo.dsl.dz")), "Lingvo");
+    }
+}
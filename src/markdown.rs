@@ -0,0 +1,349 @@
+use std::path::{Path, PathBuf};
+
+use crate::favorites;
+use crate::models::{LanguagePair, SearchResult};
+
+/// Render a search result as a Markdown block for pasting into notes apps:
+///
+/// ```markdown
+/// ### das Haus
+///
+/// *noun, n* · /haʊs/ · archi.
+///
+/// 1. house [archi.]
+/// 2. home
+///    > Er ist zu Hause. - he is at home
+/// ```
+///
+/// German nouns get their article from the gender. A `/.../` or `|...|`
+/// transcription at the start of a definition is shown once as the IPA, and
+/// quoted text („...“, "...", «...») with its translation becomes an example
+/// under its sense.
+pub fn render_result(result: &SearchResult) -> String {
+    let german = result
+        .language
+        .parse::<LanguagePair>()
+        .is_ok_and(|language| language.source() == "de");
+    let article = german
+        .then(|| match result.genders.as_slice() {
+            [gender] => match gender.as_str() {
+                "m" => Some("der".to_string()),
+                "f" => Some("die".to_string()),
+                "n" => Some("das".to_string()),
+                _ => None,
+            },
+            _ => favorites::article(&result.definitions),
+        })
+        .flatten();
+
+    let mut markdown = match article {
+        Some(article) => format!("### {} {}\n", article, escape_markdown(&result.word)),
+        None => format!("### {}\n", escape_markdown(&result.word)),
+    };
+
+    let mut ipa = None;
+    let mut senses = Vec::new();
+    for definition in &result.definitions {
+        let (transcription, rest) = split_transcription(definition);
+        if ipa.is_none() {
+            ipa = transcription;
+        }
+        if !rest.is_empty() {
+            senses.push(split_example(rest));
+        }
+    }
+
+    let mut grammar: Vec<&str> = result
+        .parts_of_speech
+        .iter()
+        .map(|part_of_speech| part_of_speech.as_str())
+        .collect();
+    grammar.extend(result.genders.iter().map(String::as_str));
+    let mut details = Vec::new();
+    if !grammar.is_empty() {
+        details.push(format!("*{}*", grammar.join(", ")));
+    }
+    if let Some(ipa) = ipa {
+        details.push(format!("/{}/", ipa));
+    }
+    details.extend(result.domains.iter().map(|domain| escape_markdown(domain)));
+    if !details.is_empty() {
+        markdown.push_str(&format!("\n{}\n", details.join(" · ")));
+    }
+
+    if !senses.is_empty() {
+        markdown.push('\n');
+    }
+    for (number, (sense, example)) in senses.iter().enumerate() {
+        markdown.push_str(&format!("{}. {}\n", number + 1, escape_markdown(sense)));
+        if let Some(example) = example {
+            markdown.push_str(&format!("   > {}\n", escape_markdown(example)));
+        }
+    }
+    markdown
+}
+
+/// The transcription a definition starts with, if any, and the rest of it
+fn split_transcription(definition: &str) -> (Option<&str>, &str) {
+    let definition = definition.trim();
+    for delimiter in ['/', '|'] {
+        if let Some(rest) = definition.strip_prefix(delimiter)
+            && let Some((transcription, rest)) = rest.split_once(delimiter)
+            && !transcription.trim().is_empty()
+        {
+            let rest = rest.trim_start_matches([';', ',', ' ']);
+            return (Some(transcription.trim()), rest);
+        }
+    }
+    (None, definition)
+}
+
+/// A definition split at its first quoted example, which runs to the end
+fn split_example(definition: &str) -> (String, Option<String>) {
+    let Some(start) = definition.find(['„', '"', '«']) else {
+        return (definition.to_string(), None);
+    };
+    let sense = definition[..start].trim().trim_end_matches([';', ',', ':']);
+    let example: String = definition[start..]
+        .chars()
+        .filter(|c| !matches!(c, '„' | '“' | '"' | '«' | '»'))
+        .collect();
+    if sense.is_empty() {
+        return (example.trim().to_string(), None);
+    }
+    (sense.to_string(), Some(example.trim().to_string()))
+}
+
+/// Escape the characters that would start emphasis, code or HTML
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '`' | '<' | '#') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Start and end of the dictionary section of a note, which updates replace
+const NOTE_START: &str = "<!-- dictv -->";
+const NOTE_END: &str = "<!-- /dictv -->";
+
+/// Path of the note for `word` in `dir`: `Haus.md`, with path separators
+/// replaced
+pub fn note_path(dir: &Path, word: &str) -> PathBuf {
+    let name: String = word
+        .trim()
+        .chars()
+        .map(|c| {
+            if matches!(c, '/' | '\\' | ':') {
+                '-'
+            } else {
+                c
+            }
+        })
+        .collect();
+    dir.join(format!("{}.md", name))
+}
+
+/// A note for `word` in the style of Obsidian or Zettelkasten notes: YAML
+/// front matter (word, language, gender, part of speech, date) followed by
+/// the rendered results.
+///
+/// When updating an `existing` note, its other front matter fields, its
+/// original date and any text outside the dictionary section are kept.
+pub fn note(word: &str, results: &[SearchResult], date: &str, existing: Option<&str>) -> String {
+    let mut genders: Vec<&str> = Vec::new();
+    let mut parts_of_speech: Vec<&str> = Vec::new();
+    for result in results {
+        for gender in &result.genders {
+            if !genders.contains(&gender.as_str()) {
+                genders.push(gender);
+            }
+        }
+        for part_of_speech in &result.parts_of_speech {
+            if !parts_of_speech.contains(&part_of_speech.as_str()) {
+                parts_of_speech.push(part_of_speech.as_str());
+            }
+        }
+    }
+
+    let (front_matter, body) = existing.map_or((Vec::new(), ""), split_front_matter);
+    let old_date = front_matter
+        .iter()
+        .find_map(|line| line.strip_prefix("date:"))
+        .map(str::trim);
+    let mut fields = vec![format!("word: {}", yaml_value(word.trim()))];
+    if let Some(language) = results.first().map(|result| result.language.as_str()) {
+        fields.push(format!("language: {}", language));
+    }
+    if !genders.is_empty() {
+        fields.push(format!("gender: {}", yaml_list(&genders)));
+    }
+    if !parts_of_speech.is_empty() {
+        fields.push(format!("pos: {}", yaml_list(&parts_of_speech)));
+    }
+    fields.push(format!("date: {}", old_date.unwrap_or(date)));
+    // Fields the user added stay, after ours
+    let owned = ["word:", "language:", "gender:", "pos:", "date:"];
+    fields.extend(
+        front_matter
+            .iter()
+            .filter(|line| !owned.iter().any(|key| line.starts_with(key)))
+            .map(|line| line.to_string()),
+    );
+
+    let blocks: Vec<String> = results.iter().map(render_result).collect();
+    let section = format!("{}\n{}{}", NOTE_START, blocks.join("\n"), NOTE_END);
+    let body = match (body.find(NOTE_START), body.find(NOTE_END)) {
+        (Some(start), Some(end)) if start < end => format!(
+            "{}{}{}",
+            &body[..start],
+            section,
+            &body[end + NOTE_END.len()..]
+        ),
+        _ if body.trim().is_empty() => format!("{}\n", section),
+        _ => format!("{}\n\n{}", section, body.trim_start()),
+    };
+    format!("---\n{}\n---\n\n{}", fields.join("\n"), body.trim_start())
+}
+
+/// The front matter lines of a note and the text after them
+fn split_front_matter(note: &str) -> (Vec<&str>, &str) {
+    if let Some(rest) = note.strip_prefix("---\n")
+        && let Some(end) = rest.find("\n---")
+    {
+        let body = &rest[end + "\n---".len()..];
+        let body = body.strip_prefix('\n').unwrap_or(body);
+        return (rest[..end].lines().collect(), body);
+    }
+    (Vec::new(), note)
+}
+
+/// A YAML scalar, quoted when it would otherwise not read back as the same string
+fn yaml_value(value: &str) -> String {
+    let plain = !value.is_empty()
+        && !value.starts_with(['-', '?', '!', '&', '*', '@', '`', '\'', '"', '%', '|', '>'])
+        && !value.contains([':', '#', '[', ']', '{', '}', ',', '"', '\\']);
+    if plain {
+        value.to_string()
+    } else {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+/// One value as a scalar, several as a flow sequence
+fn yaml_list(values: &[&str]) -> String {
+    match values {
+        [value] => yaml_value(value),
+        _ => format!(
+            "[{}]",
+            values
+                .iter()
+                .map(|value| yaml_value(value))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::PartOfSpeech;
+
+    #[test]
+    fn test_render_result() {
+        let result = SearchResult {
+            word: "Haus".to_string(),
+            definitions: vec![
+                "/haʊs/ house [archi.]".to_string(),
+                "home; „Er ist zu Hause.“ - he is at home".to_string(),
+                "<n> R&D_unit *".to_string(),
+            ],
+            language: "de-en".to_string(),
+            edit_distance: Some(0),
+            score: None,
+            frequency_rank: None,
+            domains: vec!["archi.".to_string()],
+            registers: Vec::new(),
+            parts_of_speech: vec![PartOfSpeech::Noun],
+            genders: vec!["n".to_string()],
+            audio_urls: Vec::new(),
+            sources: Vec::new(),
+            html: None,
+        };
+
+        assert_eq!(
+            render_result(&result),
+            "### das Haus\n\
+             \n\
+             *noun, n* · /haʊs/ · archi.\n\
+             \n\
+             1. house [archi.]\n\
+             2. home\n   \
+             > Er ist zu Hause. - he is at home\n\
+             3. \\<n> R&D\\_unit \\*\n"
+        );
+
+        let result = SearchResult {
+            word: "house".to_string(),
+            definitions: vec!["Haus".to_string()],
+            language: "en-de".to_string(),
+            genders: vec!["n".to_string()],
+            domains: Vec::new(),
+            parts_of_speech: Vec::new(),
+            ..result
+        };
+        assert_eq!(render_result(&result), "### house\n\n*n*\n\n1. Haus\n");
+    }
+
+    #[test]
+    fn test_note_updates_dictionary_section() {
+        let result = SearchResult {
+            word: "haus".to_string(),
+            definitions: vec!["house".to_string()],
+            language: "de-en".to_string(),
+            edit_distance: Some(0),
+            score: None,
+            frequency_rank: None,
+            domains: Vec::new(),
+            registers: Vec::new(),
+            parts_of_speech: vec![PartOfSpeech::Noun],
+            genders: vec!["n".to_string()],
+            audio_urls: Vec::new(),
+            sources: Vec::new(),
+            html: None,
+        };
+
+        let created = note("Haus", std::slice::from_ref(&result), "2026-10-01", None);
+        assert_eq!(
+            created,
+            "---\nword: Haus\nlanguage: de-en\ngender: n\npos: noun\ndate: 2026-10-01\n---\n\n\
+             <!-- dictv -->\n### das haus\n\n*noun, n*\n\n1. house\n<!-- /dictv -->\n"
+        );
+
+        // The user's tags and own notes survive an update, and so does the date
+        let edited = created.replace("date: 2026-10-01\n", "date: 2026-10-01\ntags: [german]\n")
+            + "\nSeen in chapter 3.\n";
+        let result = SearchResult {
+            definitions: vec!["house".to_string(), "home".to_string()],
+            genders: vec!["n".to_string(), "m".to_string()],
+            ..result
+        };
+        assert_eq!(
+            note("Haus", &[result], "2026-10-15", Some(&edited)),
+            "---\nword: Haus\nlanguage: de-en\ngender: [n, m]\npos: noun\ndate: 2026-10-01\n\
+             tags: [german]\n---\n\n\
+             <!-- dictv -->\n### haus\n\n*noun, n, m*\n\n1. house\n2. home\n<!-- /dictv -->\n\
+             \nSeen in chapter 3.\n"
+        );
+
+        assert_eq!(
+            note_path(Path::new("notes"), "a/b"),
+            Path::new("notes").join("a-b.md")
+        );
+        assert_eq!(yaml_value("Haus: alt"), "\"Haus: alt\"");
+    }
+}
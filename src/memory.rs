@@ -0,0 +1,31 @@
+/// Resident set size of this process, read from `/proc/self/status` on Linux
+pub fn resident_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    parse_vm_rss(&status)
+}
+
+/// The `VmRSS:  1234 kB` line of a `/proc/<pid>/status` file, in bytes
+fn parse_vm_rss(status: &str) -> Option<u64> {
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let mut fields = line["VmRSS:".len()..].split_whitespace();
+    let value: u64 = fields.next()?.parse().ok()?;
+    match fields.next() {
+        Some("kB") => Some(value * 1024),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_vm_rss() {
+        let status = "Name:\tdictv\nVmPeak:\t  300000 kB\nVmRSS:\t   51200 kB\nThreads:\t9\n";
+        assert_eq!(parse_vm_rss(status), Some(51200 * 1024));
+        assert_eq!(parse_vm_rss("Name:\tdictv\n"), None);
+        if cfg!(target_os = "linux") {
+            assert!(resident_bytes().unwrap() > 0);
+        }
+    }
+}
@@ -1,52 +1,649 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 
-/// Language direction for dictionary lookup
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(rename_all = "kebab-case")]
-pub enum Language {
-    EnDe, // English to German
-    DeEn, // German to English
+/// Language pair of a dictionary: the language of its headwords, then the
+/// language its definitions are written in, as ISO 639 codes ("de-en",
+/// "fr-de"). Two-letter codes are used for languages that have one, so
+/// "deu-eng" and "german-english" are both de-en.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct LanguagePair {
+    /// "xx-yy" up to "xxx-yyy", zero padded
+    code: [u8; 7],
+    /// Length of the source code
+    split: u8,
+    len: u8,
 }
 
-impl Language {
-    pub fn as_str(&self) -> &'static str {
-        match self {
-            Language::EnDe => "en-de",
-            Language::DeEn => "de-en",
+/// ISO 639-1 codes with their ISO 639-2/3 codes and English names
+const LANGUAGE_CODES: &[(&str, &[&str])] = &[
+    ("af", &["afr", "afrikaans"]),
+    ("ar", &["ara", "arabic"]),
+    ("bg", &["bul", "bulgarian"]),
+    ("cs", &["ces", "cze", "czech"]),
+    ("cy", &["cym", "wel", "welsh"]),
+    ("da", &["dan", "danish"]),
+    ("de", &["deu", "ger", "german"]),
+    ("el", &["ell", "gre", "greek"]),
+    ("en", &["eng", "english"]),
+    ("eo", &["epo", "esperanto"]),
+    ("es", &["spa", "spanish"]),
+    ("et", &["est", "estonian"]),
+    ("fi", &["fin", "finnish"]),
+    ("fr", &["fra", "fre", "french"]),
+    ("ga", &["gle", "irish"]),
+    ("he", &["heb", "hebrew"]),
+    ("hr", &["hrv", "croatian"]),
+    ("hu", &["hun", "hungarian"]),
+    ("is", &["isl", "ice", "icelandic"]),
+    ("it", &["ita", "italian"]),
+    ("ja", &["jpn", "japanese"]),
+    ("ko", &["kor", "korean"]),
+    ("la", &["lat", "latin"]),
+    ("lt", &["lit", "lithuanian"]),
+    ("lv", &["lav", "latvian"]),
+    ("nl", &["nld", "dut", "dutch"]),
+    ("no", &["nor", "nob", "norwegian"]),
+    ("pl", &["pol", "polish"]),
+    ("pt", &["por", "portuguese"]),
+    ("ro", &["ron", "rum", "romanian"]),
+    ("ru", &["rus", "russian"]),
+    ("sk", &["slk", "slo", "slovak"]),
+    ("sl", &["slv", "slovenian"]),
+    ("sr", &["srp", "serbian"]),
+    ("sv", &["swe", "swedish"]),
+    ("tr", &["tur", "turkish"]),
+    ("uk", &["ukr", "ukrainian"]),
+    ("zh", &["zho", "chi", "chinese"]),
+];
+
+/// Two-letter code of a known language given by code or English name
+pub fn known_language_code(word: &str) -> Option<&'static str> {
+    let word = word.to_lowercase();
+    LANGUAGE_CODES
+        .iter()
+        .find(|(code, aliases)| *code == word || aliases.contains(&word.as_str()))
+        .map(|(code, _)| *code)
+}
+
+impl LanguagePair {
+    pub const DE_EN: LanguagePair = LanguagePair::from_codes("de", "en");
+    pub const EN_DE: LanguagePair = LanguagePair::from_codes("en", "de");
+
+    /// Pair of two lowercase codes of two or three letters
+    const fn from_codes(from: &str, to: &str) -> LanguagePair {
+        let (from, to) = (from.as_bytes(), to.as_bytes());
+        assert!(from.len() >= 2 && from.len() <= 3 && to.len() >= 2 && to.len() <= 3);
+        let mut code = [0; 7];
+        let mut i = 0;
+        while i < from.len() {
+            code[i] = from[i];
+            i += 1;
         }
+        code[from.len()] = b'-';
+        let mut j = 0;
+        while j < to.len() {
+            code[from.len() + 1 + j] = to[j];
+            j += 1;
+        }
+        LanguagePair {
+            code,
+            split: from.len() as u8,
+            len: (from.len() + 1 + to.len()) as u8,
+        }
+    }
+
+    /// Pair of two language codes or English names, None when either is not
+    /// a language code of two or three letters
+    pub fn new(from: &str, to: &str) -> Option<LanguagePair> {
+        let code = |word: &str| -> Option<String> {
+            if let Some(code) = known_language_code(word) {
+                return Some(code.to_string());
+            }
+            let valid =
+                (2..=3).contains(&word.len()) && word.chars().all(|c| c.is_ascii_alphabetic());
+            valid.then(|| word.to_ascii_lowercase())
+        };
+        let (from, to) = (code(from)?, code(to)?);
+        (from != to).then(|| LanguagePair::from_codes(&from, &to))
+    }
+
+    /// Canonical code, e.g. "de-en"
+    pub fn as_str(&self) -> &str {
+        std::str::from_utf8(&self.code[..self.len as usize]).unwrap_or_default()
+    }
+
+    /// Language of the headwords: "de" for de-en
+    pub fn source(&self) -> &str {
+        &self.as_str()[..self.split as usize]
+    }
+
+    /// Language definitions are written in: "en" for de-en
+    pub fn target(&self) -> &str {
+        &self.as_str()[self.split as usize + 1..]
+    }
+
+    /// The opposite direction: en-de for de-en
+    pub fn reverse(&self) -> LanguagePair {
+        LanguagePair::from_codes(self.target(), self.source())
     }
 }
 
-impl std::str::FromStr for Language {
+impl std::fmt::Display for LanguagePair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for LanguagePair {
     type Err = anyhow::Error;
 
+    /// Accepts any case and separator ("FR-DE", "fr_de"), three-letter codes
+    /// and English names ("fra-deu", "french-german"), and for German and
+    /// English the compact forms ("deen", "deueng") and the source language
+    /// alone ("de", "en")
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "en-de" => Ok(Language::EnDe),
-            "de-en" => Ok(Language::DeEn),
-            _ => Err(anyhow::anyhow!("Invalid language: {}", s)),
+        let compact: String = s
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect();
+        match compact.as_str() {
+            "ende" | "en" | "eng" | "english" | "engdeu" | "englishgerman" => {
+                return Ok(LanguagePair::EN_DE);
+            }
+            "deen" | "de" | "deu" | "ger" | "german" | "deueng" | "gereng" | "germanenglish" => {
+                return Ok(LanguagePair::DE_EN);
+            }
+            _ => {}
+        }
+
+        let words: Vec<&str> = s
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect();
+        if let [from, to] = words[..]
+            && let Some(pair) = LanguagePair::new(from, to)
+        {
+            return Ok(pair);
+        }
+        let suggestion = ["en-de", "de-en"]
+            .into_iter()
+            .map(|candidate| (strsim::levenshtein(&s.to_lowercase(), candidate), candidate))
+            .filter(|(distance, _)| *distance <= 2)
+            .min();
+        match suggestion {
+            Some((_, candidate)) => Err(anyhow::anyhow!(
+                "Invalid language: {} (did you mean {}?)",
+                s,
+                candidate
+            )),
+            None => Err(anyhow::anyhow!(
+                "Invalid language: {} (expected a language pair such as de-en or fr-de)",
+                s
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for LanguagePair {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<LanguagePair> for String {
+    fn from(pair: LanguagePair) -> Self {
+        pair.as_str().to_string()
+    }
+}
+
+/// Error for a parameter value that is not one of `valid`, suggesting the
+/// closest one when it looks like a typo
+fn invalid_value(kind: &str, value: &str, valid: &[&str]) -> anyhow::Error {
+    let lowercase = value.to_lowercase();
+    let suggestion = valid
+        .iter()
+        .map(|candidate| (strsim::levenshtein(&lowercase, candidate), candidate))
+        .filter(|(distance, _)| *distance <= 2)
+        .min();
+    match suggestion {
+        Some((_, candidate)) => {
+            anyhow::anyhow!("Invalid {}: {} (did you mean {}?)", kind, value, candidate)
         }
+        None => anyhow::anyhow!(
+            "Invalid {}: {} (expected one of {})",
+            kind,
+            value,
+            valid.join(", ")
+        ),
     }
 }
 
 /// Search mode for dictionary queries
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(rename_all = "lowercase")]
+#[serde(rename_all = "lowercase", try_from = "String")]
 pub enum SearchMode {
-    Exact,  // Exact word match
-    Fuzzy,  // Fuzzy match with edit distance
-    Prefix, // Prefix matching
+    Exact,      // Exact word match
+    Fuzzy,      // Fuzzy match with edit distance
+    Prefix,     // Prefix matching
+    Definition, // Boolean query on definitions (reverse lookup)
+    Pattern,    // Crossword pattern, "_" for one unknown letter ("h_u_")
+    Anagram,    // Headwords made of exactly the given letters
+    Rhyme,      // Headwords ending in the same rhyme ("Haus" -> "Maus")
+}
+
+impl SearchMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SearchMode::Exact => "exact",
+            SearchMode::Fuzzy => "fuzzy",
+            SearchMode::Prefix => "prefix",
+            SearchMode::Definition => "definition",
+            SearchMode::Pattern => "pattern",
+            SearchMode::Anagram => "anagram",
+            SearchMode::Rhyme => "rhyme",
+        }
+    }
+}
+
+impl SearchMode {
+    const NAMES: [&'static str; 7] = [
+        "exact",
+        "fuzzy",
+        "prefix",
+        "definition",
+        "pattern",
+        "anagram",
+        "rhyme",
+    ];
 }
 
 impl std::str::FromStr for SearchMode {
     type Err = anyhow::Error;
 
+    /// Accepts any case and the short forms "def" and "reverse" for definition search
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
+        match s.trim().to_lowercase().as_str() {
             "exact" => Ok(SearchMode::Exact),
             "fuzzy" => Ok(SearchMode::Fuzzy),
             "prefix" => Ok(SearchMode::Prefix),
-            _ => Err(anyhow::anyhow!("Invalid search mode: {}", s)),
+            "definition" | "def" | "reverse" => Ok(SearchMode::Definition),
+            "pattern" => Ok(SearchMode::Pattern),
+            "anagram" => Ok(SearchMode::Anagram),
+            "rhyme" => Ok(SearchMode::Rhyme),
+            _ => Err(invalid_value("search mode", s, &SearchMode::NAMES)),
+        }
+    }
+}
+
+impl TryFrom<String> for SearchMode {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// Order of search results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", try_from = "String")]
+pub enum Ranking {
+    /// Edit distance, then alphabetical: suits autocomplete
+    Distance,
+    /// Relevance score with the `[ranking]` adjustments, ignoring edit distance
+    Score,
+    /// Most frequent words first, then by score
+    Frequency,
+    /// Fuzzy results by edit distance, then by score (unless the configured
+    /// distance penalties fold distance into the score)
+    #[default]
+    Hybrid,
+}
+
+impl Ranking {
+    const NAMES: [&'static str; 4] = ["distance", "score", "frequency", "hybrid"];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Ranking::Distance => "distance",
+            Ranking::Score => "score",
+            Ranking::Frequency => "frequency",
+            Ranking::Hybrid => "hybrid",
+        }
+    }
+}
+
+impl std::str::FromStr for Ranking {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "distance" => Ok(Ranking::Distance),
+            "score" => Ok(Ranking::Score),
+            "frequency" => Ok(Ranking::Frequency),
+            "hybrid" => Ok(Ranking::Hybrid),
+            _ => Err(invalid_value("ranking", s, &Ranking::NAMES)),
+        }
+    }
+}
+
+impl TryFrom<String> for Ranking {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// How search results are grouped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Grouping {
+    Word, // One result per word with all its definitions
+    None, // One result per (word, definition) pair
+}
+
+impl std::str::FromStr for Grouping {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "word" => Ok(Grouping::Word),
+            "none" => Ok(Grouping::None),
+            _ => Err(anyhow::anyhow!("Invalid grouping: {}", s)),
+        }
+    }
+}
+
+/// How search results are returned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResultFormat {
+    #[default]
+    Json, // Fields only
+    Html, // Fields plus an `html` fragment per result
+}
+
+/// Order of browsed headwords
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BrowseDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl BrowseDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BrowseDirection::Asc => "asc",
+            BrowseDirection::Desc => "desc",
+        }
+    }
+}
+
+impl std::str::FromStr for BrowseDirection {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "asc" => Ok(BrowseDirection::Asc),
+            "desc" => Ok(BrowseDirection::Desc),
+            _ => Err(anyhow::anyhow!("Invalid direction: {}", s)),
+        }
+    }
+}
+
+/// Source format of a dictionary import
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    Dictd,      // .dict.dz + .index pair
+    DictCc,     // dict.cc TSV export
+    Dsl,        // Lingvo .dsl / .dsl.dz
+    StarDict,   // .ifo + .idx + .dict.dz
+    Xdxf,       // XDXF XML
+    Tei,        // TEI P5 XML, as in FreeDict sources
+    Csv,        // word list, comma-separated
+    Tsv,        // word list, tab-separated
+    Jsonl,      // JSON Lines, one entry object per line
+    Bgl,        // Babylon .bgl
+    Wiktionary, // Wiktionary XML dump or kaikki.org extract
+    AppleDict,  // macOS .dictionary bundle
+    Slob,       // Aard2 .slob
+    Yomichan,   // zipped Yomichan term banks
+    Zim,        // Kiwix .zim archive
+}
+
+impl ImportFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImportFormat::Dictd => "dictd",
+            ImportFormat::DictCc => "dictcc",
+            ImportFormat::Dsl => "dsl",
+            ImportFormat::StarDict => "stardict",
+            ImportFormat::Xdxf => "xdxf",
+            ImportFormat::Tei => "tei",
+            ImportFormat::Csv => "csv",
+            ImportFormat::Tsv => "tsv",
+            ImportFormat::Jsonl => "jsonl",
+            ImportFormat::Bgl => "bgl",
+            ImportFormat::Wiktionary => "wiktionary",
+            ImportFormat::AppleDict => "appledict",
+            ImportFormat::Slob => "slob",
+            ImportFormat::Yomichan => "yomichan",
+            ImportFormat::Zim => "zim",
+        }
+    }
+}
+
+impl std::str::FromStr for ImportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dictd" => Ok(ImportFormat::Dictd),
+            "dictcc" => Ok(ImportFormat::DictCc),
+            "dsl" => Ok(ImportFormat::Dsl),
+            "stardict" => Ok(ImportFormat::StarDict),
+            "xdxf" => Ok(ImportFormat::Xdxf),
+            "tei" => Ok(ImportFormat::Tei),
+            "csv" => Ok(ImportFormat::Csv),
+            "tsv" => Ok(ImportFormat::Tsv),
+            "jsonl" => Ok(ImportFormat::Jsonl),
+            "bgl" => Ok(ImportFormat::Bgl),
+            "wiktionary" => Ok(ImportFormat::Wiktionary),
+            "appledict" => Ok(ImportFormat::AppleDict),
+            "slob" => Ok(ImportFormat::Slob),
+            "yomichan" => Ok(ImportFormat::Yomichan),
+            "zim" => Ok(ImportFormat::Zim),
+            _ => Err(anyhow::anyhow!("Invalid import format: {}", s)),
+        }
+    }
+}
+
+/// Part of speech, as tagged in dictionary definitions (e.g. `<verb>`, `<n, fem>`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PartOfSpeech {
+    Noun,
+    Verb,
+    Adjective,
+    Adverb,
+    Pronoun,
+    Preposition,
+    Conjunction,
+    Interjection,
+}
+
+impl PartOfSpeech {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PartOfSpeech::Noun => "noun",
+            PartOfSpeech::Verb => "verb",
+            PartOfSpeech::Adjective => "adjective",
+            PartOfSpeech::Adverb => "adverb",
+            PartOfSpeech::Pronoun => "pronoun",
+            PartOfSpeech::Preposition => "preposition",
+            PartOfSpeech::Conjunction => "conjunction",
+            PartOfSpeech::Interjection => "interjection",
+        }
+    }
+
+    /// Detect the part of speech from the first `<...>` tag in a definition
+    pub fn detect(definition: &str) -> Option<Self> {
+        let start = definition.find('<')?;
+        let end = start + definition[start..].find('>')?;
+        let tag = definition[start + 1..end].split([',', ' ']).next()?;
+        Self::from_tag(tag)
+    }
+
+    /// Part of speech for a dictionary's tag, e.g. "n", "vt" or "adj"
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        match tag.trim().to_lowercase().as_str() {
+            "n" | "noun" => Some(PartOfSpeech::Noun),
+            "v" | "vi" | "vt" | "vr" | "verb" => Some(PartOfSpeech::Verb),
+            "adj" | "adjective" => Some(PartOfSpeech::Adjective),
+            "adv" | "adverb" => Some(PartOfSpeech::Adverb),
+            "pron" | "pronoun" => Some(PartOfSpeech::Pronoun),
+            "prep" | "preposition" => Some(PartOfSpeech::Preposition),
+            "conj" | "conjunction" => Some(PartOfSpeech::Conjunction),
+            "interj" | "int" | "interjection" => Some(PartOfSpeech::Interjection),
+            _ => None,
+        }
+    }
+}
+
+impl std::str::FromStr for PartOfSpeech {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "noun" => Ok(PartOfSpeech::Noun),
+            "verb" => Ok(PartOfSpeech::Verb),
+            "adjective" | "adj" => Ok(PartOfSpeech::Adjective),
+            "adverb" | "adv" => Ok(PartOfSpeech::Adverb),
+            "pronoun" => Ok(PartOfSpeech::Pronoun),
+            "preposition" => Ok(PartOfSpeech::Preposition),
+            "conjunction" => Ok(PartOfSpeech::Conjunction),
+            "interjection" => Ok(PartOfSpeech::Interjection),
+            _ => Err(anyhow::anyhow!("Invalid part of speech: {}", s)),
+        }
+    }
+}
+
+/// Usage register, as labelled in dictionary definitions (e.g. `[coll.]`, `(vulg.)`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Register {
+    Colloquial,
+    Formal,
+    Vulgar,
+    Offensive,
+}
+
+impl Register {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Register::Colloquial => "colloquial",
+            Register::Formal => "formal",
+            Register::Vulgar => "vulgar",
+            Register::Offensive => "offensive",
+        }
+    }
+
+    /// Registers hidden by safe search
+    pub fn is_unsafe(&self) -> bool {
+        matches!(self, Register::Vulgar | Register::Offensive)
+    }
+
+    /// Detect all register labels inside `[...]`, `(...)` or `<...>` groups of a definition
+    pub fn detect(definition: &str) -> Vec<Self> {
+        let mut registers = Vec::new();
+        let mut rest = definition;
+
+        while let Some(start) = rest.find(['[', '(', '<']) {
+            let close = match rest.as_bytes()[start] {
+                b'[' => ']',
+                b'(' => ')',
+                _ => '>',
+            };
+            let Some(length) = rest[start..].find(close) else {
+                break;
+            };
+            let group = &rest[start + 1..start + length];
+            rest = &rest[start + length..];
+
+            for label in group.split([',', ';', ' ']) {
+                let register = match label.trim().to_lowercase().as_str() {
+                    "coll." | "colloq." | "ugs." | "inf." | "informal" | "fam." | "sl."
+                    | "slang" => Register::Colloquial,
+                    "geh." | "form." | "formal" | "elev." => Register::Formal,
+                    "vulg." | "vulgar" | "derb" => Register::Vulgar,
+                    "pej." | "pejorative" | "derog." | "offensive" | "offens." => {
+                        Register::Offensive
+                    }
+                    _ => continue,
+                };
+                if !registers.contains(&register) {
+                    registers.push(register);
+                }
+            }
+        }
+
+        registers
+    }
+}
+
+impl std::str::FromStr for Register {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "colloquial" => Ok(Register::Colloquial),
+            "formal" => Ok(Register::Formal),
+            "vulgar" => Ok(Register::Vulgar),
+            "offensive" => Ok(Register::Offensive),
+            _ => Err(anyhow::anyhow!("Invalid register: {}", s)),
+        }
+    }
+}
+
+/// Filter on result definitions, applied while collecting: a case-insensitive
+/// substring, or a regular expression written as `/pattern/`
+#[derive(Debug, Clone)]
+pub enum DefinitionFilter {
+    Substring(String),
+    Regex(regex::Regex),
+}
+
+impl DefinitionFilter {
+    pub fn matches(&self, definition: &str) -> bool {
+        match self {
+            DefinitionFilter::Substring(needle) => definition.to_lowercase().contains(needle),
+            DefinitionFilter::Regex(regex) => regex.is_match(definition),
+        }
+    }
+}
+
+impl std::str::FromStr for DefinitionFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix('/').and_then(|s| s.strip_suffix('/')) {
+            Some(pattern) => regex::RegexBuilder::new(pattern)
+                .case_insensitive(true)
+                .build()
+                .map(DefinitionFilter::Regex)
+                .map_err(|e| anyhow::anyhow!("Invalid filter regex: {}", e)),
+            None if s.is_empty() => Err(anyhow::anyhow!("Filter cannot be empty")),
+            None => Ok(DefinitionFilter::Substring(s.to_lowercase())),
         }
     }
 }
@@ -57,6 +654,21 @@ pub struct DictionaryEntry {
     pub word: String,
     pub definition: String,
     pub language: String,
+    /// Subject domains (e.g. "archi.", "med.")
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub domains: Vec<String>,
+    /// Name of the dictionary this entry was imported from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// Part of speech, for sources that mark it up apart from the definition (TEI)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub part_of_speech: Option<PartOfSpeech>,
+    /// Grammatical gender of a noun: "m", "f" or "n"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gender: Option<String>,
+    /// Recordings of the headword being spoken (Wiktionary)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub audio_urls: Vec<String>,
 }
 
 impl DictionaryEntry {
@@ -65,8 +677,25 @@ impl DictionaryEntry {
             word,
             definition,
             language,
+            domains: Vec::new(),
+            source: None,
+            part_of_speech: None,
+            gender: None,
+            audio_urls: Vec::new(),
         }
     }
+
+    /// Set the subject domains of this entry
+    pub fn with_domains(mut self, domains: Vec<String>) -> Self {
+        self.domains = domains;
+        self
+    }
+
+    /// Set the dictionary this entry came from
+    pub fn with_source(mut self, source: impl Into<String>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
 }
 
 /// Search result with metadata
@@ -79,6 +708,170 @@ pub struct SearchResult {
     pub edit_distance: Option<u8>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub score: Option<f32>,
+    /// Frequency rank of the word (1 = most frequent), if frequency data is loaded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequency_rank: Option<u32>,
+    /// Subject domains of the definitions (e.g. "archi.", "med.")
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub domains: Vec<String>,
+    /// Usage registers labelled in the definitions
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub registers: Vec<Register>,
+    /// Parts of speech stored with the definitions, for sources that mark them up (TEI)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parts_of_speech: Vec<PartOfSpeech>,
+    /// Grammatical genders stored with the definitions: "m", "f" or "n"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub genders: Vec<String>,
+    /// Recordings of the word being spoken, from dictionaries that have them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub audio_urls: Vec<String>,
+    /// Dictionaries the definitions come from, with their licenses
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<ResultSource>,
+    /// The result rendered as an HTML fragment, included for `format=html`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub html: Option<String>,
+}
+
+/// A dictionary a search result's definitions come from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResultSource {
+    pub name: String,
+    /// License the dictionary is distributed under, if recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+}
+
+/// Options controlling a single search
+#[derive(Debug, Clone)]
+pub struct SearchOptions {
+    pub mode: SearchMode,
+    pub language: LanguagePair,
+    pub max_distance: u8,
+    pub limit: usize,
+    /// Match diacritics exactly instead of ASCII folding ("Maße" != "Masse")
+    pub strict_diacritics: bool,
+    /// Scale max_distance down for short queries
+    pub auto_distance: bool,
+    pub group: Grouping,
+    /// Only return words ranked within the top N most frequent
+    pub min_frequency_rank: Option<u32>,
+    /// Only return definitions labelled with this register
+    pub register: Option<Register>,
+    /// Hide definitions labelled vulgar or offensive
+    pub safe: bool,
+    /// Only search these dictionaries (all enabled ones when empty)
+    pub sources: Vec<String>,
+    /// Hide definitions detected as not written in the target language
+    pub target_only: bool,
+    /// Retry without hyphens ("Haus-tür" -> "haustür") when the query finds nothing
+    pub dehyphenate: bool,
+    /// Only keep definitions matching this filter
+    pub filter: Option<DefinitionFilter>,
+    /// Only return these headwords (any headword when empty)
+    pub within: Vec<String>,
+    /// Shortest headword to return, in characters
+    pub min_len: Option<u64>,
+    /// Longest headword to return, in characters
+    pub max_len: Option<u64>,
+    /// Keep only the best result per stem, so inflected forms of one word
+    /// ("Haus", "Hauses", "Häuser") do not crowd out other words
+    pub diversify: bool,
+    /// Search the reverse direction when the requested one finds nothing
+    pub flip_direction: bool,
+    /// Order of the results
+    pub rank: Ranking,
+    /// Stops the search early once cancelled
+    pub cancellation: Cancellation,
+    /// Stop collecting matches at this instant and return the results found
+    /// so far, flagged `partial`
+    pub deadline: Option<Instant>,
+}
+
+/// Shared flag a running search checks between segments and documents, set
+/// when its results are no longer wanted (e.g. the HTTP client disconnected)
+#[derive(Debug, Clone, Default)]
+pub struct Cancellation(Arc<AtomicBool>);
+
+impl Cancellation {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Server-side defaults for search parameters, from the `[search_defaults]`
+/// table of config.toml. The language comes from `default_lang`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SearchDefaults {
+    pub mode: SearchMode,
+    #[serde(skip_deserializing)]
+    pub lang: LanguagePair,
+    pub limit: usize,
+    pub max_distance: u8,
+}
+
+impl Default for SearchDefaults {
+    fn default() -> Self {
+        Self {
+            mode: default_search_mode(),
+            lang: default_language(),
+            limit: default_limit(),
+            max_distance: default_max_distance(),
+        }
+    }
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            mode: default_search_mode(),
+            language: default_language(),
+            max_distance: default_max_distance(),
+            limit: default_limit(),
+            strict_diacritics: false,
+            auto_distance: true,
+            group: default_grouping(),
+            min_frequency_rank: None,
+            register: None,
+            safe: false,
+            sources: Vec::new(),
+            target_only: false,
+            dehyphenate: true,
+            filter: None,
+            within: Vec::new(),
+            min_len: None,
+            max_len: None,
+            diversify: false,
+            flip_direction: true,
+            rank: Ranking::default(),
+            cancellation: Cancellation::default(),
+            deadline: None,
+        }
+    }
+}
+
+/// Time spent in each phase of a search, in milliseconds
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct SearchTimings {
+    pub query_build_ms: f64,
+    pub search_ms: f64,
+    pub fetch_ms: f64,
+    pub group_ms: f64,
+}
+
+impl std::ops::AddAssign for SearchTimings {
+    fn add_assign(&mut self, other: Self) {
+        self.query_build_ms += other.query_build_ms;
+        self.search_ms += other.search_ms;
+        self.fetch_ms += other.fetch_ms;
+        self.group_ms += other.group_ms;
+    }
 }
 
 /// Search response
@@ -87,44 +880,586 @@ pub struct SearchResponse {
     pub results: Vec<SearchResult>,
     pub query_time_ms: f64,
     pub total_results: usize,
+    /// Notes about how the query was interpreted (e.g. normalization applied)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+    /// Per-phase timings, included when `debug=true`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timings: Option<SearchTimings>,
+    /// Duplicate definitions dropped from grouped results, included when `debug=true`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub merged_definitions: Option<usize>,
+    /// The requested direction had no results, these are from the reverse one
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub flipped_direction: bool,
+    /// `budget_ms` ran out before every match was collected, so better
+    /// results may be missing
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub partial: bool,
+    /// Results of each prefix when several were requested (`q=hau,grü&mode=prefix`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefixes: Option<BTreeMap<String, Vec<SearchResult>>>,
+    /// Changes when the index contents change, so clients paging through
+    /// results know to start over
+    #[serde(default)]
+    pub index_generation: String,
+    /// Token of the pinned snapshot searched, returned for `pin=true` or `snapshot=...`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<String>,
 }
 
 /// Health check response
 #[derive(Debug, Serialize, Deserialize)]
 pub struct HealthResponse {
+    /// "ok", or "not_ready" while the index has no entries
     pub status: String,
     pub version: String,
+    /// Values used for search parameters a request omits
+    pub defaults: SearchDefaults,
 }
 
 /// Statistics response
 #[derive(Debug, Serialize, Deserialize)]
 pub struct StatsResponse {
     pub total_entries: usize,
-    pub en_de_entries: usize,
-    pub de_en_entries: usize,
+    /// Entry counts of the indexed language pairs
+    pub languages: Vec<LanguageCount>,
     pub index_size_bytes: u64,
+    pub reloads: ReloadStats,
+    pub memory: MemoryStats,
 }
 
-/// Search query parameters
+/// Number of indexed entries of a language pair
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LanguageCount {
+    pub language: LanguagePair,
+    pub entries: usize,
+}
+
+/// Memory the server holds, for sizing a deployment
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MemoryStats {
+    /// Resident set size of the process, where the platform reports it (Linux)
+    pub resident_bytes: Option<u64>,
+    pub index: IndexMemory,
+    pub caches: CacheSizes,
+}
+
+/// The index as the current searcher sees it
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IndexMemory {
+    pub segments: usize,
+    /// Size of the segment files, which are memory-mapped; the OS pages them
+    /// in as searches touch them
+    pub mapped_bytes: u64,
+    /// Decompressed document store blocks kept for fetching results
+    pub doc_store_cache_blocks: usize,
+    /// In-memory headword maps answering exact and prefix lookups
+    pub headword_map_bytes: u64,
+}
+
+/// Entries in the server's in-memory caches
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CacheSizes {
+    pub pinned_snapshots: usize,
+    pub sessions: usize,
+    /// Distinct queries counted for `/suggest/popular`
+    pub query_history: usize,
+    /// Distinct queries counted for `/analytics/misses`
+    pub missed_queries: usize,
+    /// API keys with counted usage
+    pub api_keys: usize,
+    pub frequency_words: usize,
+}
+
+/// Index reloads since the server started, each timed from opening the new
+/// segments through warming them to swapping the searcher in
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReloadStats {
+    pub reloads: u64,
+    /// Reloads that failed, leaving the previous searcher in place
+    pub failed: u64,
+    pub last_ms: Option<f64>,
+    pub max_ms: f64,
+    pub total_ms: f64,
+    /// When the last successful reload finished, in seconds since the Unix epoch
+    pub last_reload_unix: Option<u64>,
+}
+
+/// Word frequency response
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FrequencyResponse {
+    pub word: String,
+    pub language: String,
+    pub frequency_rank: u32,
+}
+
+/// Abbreviation glossary entry
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AbbreviationEntry {
+    pub abbreviation: String,
+    pub expansion: String,
+}
+
+/// Abbreviation glossary response
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AbbreviationsResponse {
+    pub abbreviations: Vec<AbbreviationEntry>,
+}
+
+/// How often a query was searched
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QueryCount {
+    pub query: String,
+    pub language: String,
+    pub count: u64,
+    /// Milliseconds since the Unix epoch
+    pub last_seen_ms: u64,
+}
+
+/// Zero-result queries response
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MissesResponse {
+    pub misses: Vec<QueryCount>,
+}
+
+/// A search made in a client session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Lookup {
+    pub query: String,
+    pub language: String,
+    pub total_results: usize,
+    /// Milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+}
+
+/// Session lookup history response
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HistoryResponse {
+    pub lookups: Vec<Lookup>,
+}
+
+/// Previously searched queries response
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PopularResponse {
+    pub queries: Vec<QueryCount>,
+}
+
+/// Request counts of one API key
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KeyUsage {
+    pub name: String,
+    pub total_requests: u64,
+    /// Requests since midnight UTC
+    pub requests_today: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub daily_quota: Option<u64>,
+}
+
+/// API key usage response
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UsageResponse {
+    pub keys: Vec<KeyUsage>,
+}
+
+/// Queries to run ahead of real traffic, in the server's default mode and language unless given
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrimeRequest {
+    pub queries: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<SearchMode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lang: Option<LanguagePair>,
+}
+
+/// Outcome of priming the caches
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PrimeResponse {
+    /// Queries that ran
+    pub primed: usize,
+    /// Queries that failed, e.g. malformed definition queries
+    pub failed: usize,
+    /// Results fetched across all queries
+    pub results: usize,
+    pub took_ms: f64,
+}
+
+/// Definitions of a headword from one dictionary
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SourceDefinitions {
+    /// Dictionary name, absent for entries imported without one
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    pub definitions: Vec<String>,
+}
+
+/// Side-by-side comparison of a headword across dictionaries
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CompareResponse {
+    pub word: String,
+    pub language: String,
+    pub sources: Vec<SourceDefinitions>,
+}
+
+/// Dictionary comparison parameters
 #[derive(Debug, Clone, Deserialize)]
-pub struct SearchQuery {
-    pub q: String,
-    #[serde(default = "default_search_mode")]
-    pub mode: SearchMode,
-    #[serde(default = "default_language")]
-    pub lang: Language,
-    #[serde(default = "default_max_distance")]
-    pub max_distance: u8,
+pub struct CompareQuery {
+    pub word: String,
+    /// Defaults to the server's default language
+    pub lang: Option<LanguagePair>,
+}
+
+/// Number of headwords starting with a letter
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LetterCount {
+    pub letter: String,
+    pub count: u64,
+}
+
+/// Letter index response
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LettersResponse {
+    pub language: String,
+    pub letters: Vec<LetterCount>,
+}
+
+/// Headword browsing parameters
+#[derive(Debug, Clone, Deserialize)]
+pub struct BrowseQuery {
+    /// Defaults to the server's default language
+    pub lang: Option<LanguagePair>,
+    /// First headword to return (inclusive), the beginning or end of the list when unset
+    pub start: Option<String>,
+    /// Stop before this headword (exclusive)
+    pub end: Option<String>,
+    #[serde(default)]
+    pub direction: BrowseDirection,
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+/// Headword browsing response
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BrowseResponse {
+    pub language: String,
+    pub direction: BrowseDirection,
+    pub headwords: Vec<String>,
+    /// `start` of the following page, absent on the last page
+    pub next: Option<String>,
+}
+
+/// Word of the day response
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WordOfTheDayResponse {
+    /// "YYYY-MM-DD", UTC
+    pub date: String,
+    pub entry: SearchResult,
+}
+
+/// Kind of questions a quiz asks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", try_from = "String")]
+pub enum QuizType {
+    /// The word with its translation among wrong answers
+    #[default]
+    MultipleChoice,
+}
+
+impl std::str::FromStr for QuizType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "multiple-choice" | "multiple_choice" | "mc" => Ok(QuizType::MultipleChoice),
+            _ => Err(invalid_value("quiz type", s, &["multiple-choice"])),
+        }
+    }
+}
+
+impl TryFrom<String> for QuizType {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// Quiz parameters
+#[derive(Debug, Clone, Deserialize)]
+pub struct QuizQuery {
+    /// Defaults to the server's default language
+    pub lang: Option<LanguagePair>,
+    #[serde(default = "default_quiz_count")]
+    pub count: usize,
+    #[serde(default, rename = "type")]
+    pub quiz_type: QuizType,
+    /// The same seed yields the same quiz, a random one is used when unset
+    pub seed: Option<u64>,
+}
+
+fn default_quiz_count() -> usize {
+    10
+}
+
+/// A multiple-choice question: which translation is the word's?
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuizQuestion {
+    pub word: String,
+    /// The correct translation
+    pub answer: String,
+    /// Translations of words with a similar frequency
+    pub distractors: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequency_rank: Option<u32>,
+}
+
+/// Quiz response
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QuizResponse {
+    pub language: String,
+    #[serde(rename = "type")]
+    pub quiz_type: QuizType,
+    /// Pass it back to get the same questions again
+    pub seed: u64,
+    pub questions: Vec<QuizQuestion>,
+}
+
+/// License of an imported dictionary, for attributing its definitions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceLicense {
+    /// Dictionary name, as in the `sources` of search results
+    pub source: String,
+    pub language: String,
+    /// Absent when the license is not known, set it with `dictv license`
+    pub license: Option<String>,
+}
+
+/// Licenses of the searchable dictionaries
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LicensesResponse {
+    pub licenses: Vec<SourceLicense>,
+}
+
+/// Favorites export parameters
+#[derive(Debug, Clone, Deserialize)]
+pub struct SheetQuery {
+    /// "md" or "csv"
+    #[serde(default = "default_sheet_format")]
+    pub format: String,
+    /// Only favorites of this language; all languages when absent
+    pub lang: Option<LanguagePair>,
+}
+
+/// Favorites parameters
+#[derive(Debug, Clone, Deserialize)]
+pub struct FavoritesQuery {
+    /// Only favorites of this language; all languages when absent
+    pub lang: Option<LanguagePair>,
+}
+
+fn default_sheet_format() -> String {
+    "md".to_string()
+}
+
+/// Parameters of endpoints that only take a language (letters, word of the day)
+#[derive(Debug, Clone, Deserialize)]
+pub struct LanguageQuery {
+    /// Defaults to the server's default language
+    pub lang: Option<LanguagePair>,
+}
+
+/// Zero-result queries parameters
+#[derive(Debug, Clone, Deserialize)]
+pub struct MissesQuery {
+    pub lang: Option<LanguagePair>,
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+/// Session lookup history parameters
+#[derive(Debug, Clone, Deserialize)]
+pub struct HistoryQuery {
+    /// Defaults to the session cookie
+    pub session: Option<String>,
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+/// Previously searched queries parameters
+#[derive(Debug, Clone, Deserialize)]
+pub struct PopularQuery {
+    #[serde(default)]
+    pub prefix: String,
+    pub lang: Option<LanguagePair>,
     #[serde(default = "default_limit")]
     pub limit: usize,
 }
 
+/// Frequency lookup query parameters
+#[derive(Debug, Clone, Deserialize)]
+pub struct FrequencyQuery {
+    /// Defaults to the server's default language
+    pub lang: Option<LanguagePair>,
+}
+
+/// Search query parameters
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchQuery {
+    pub q: String,
+    /// Defaults to the server's default mode
+    pub mode: Option<SearchMode>,
+    /// Defaults to the server's default language
+    pub lang: Option<LanguagePair>,
+    /// Defaults to the server's default maximum distance
+    pub max_distance: Option<u8>,
+    /// Defaults to the server's default limit
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub strict_diacritics: bool,
+    #[serde(default = "default_auto_distance")]
+    pub auto_distance: bool,
+    #[serde(default = "default_grouping")]
+    pub group: Grouping,
+    pub min_frequency_rank: Option<u32>,
+    /// Expand abbreviations such as "jdn." inline in definitions
+    #[serde(default)]
+    pub expand_abbrev: bool,
+    pub register: Option<Register>,
+    #[serde(default)]
+    pub safe: bool,
+    /// Comma-separated dictionary names to search
+    pub sources: Option<String>,
+    #[serde(default)]
+    pub target_only: bool,
+    /// Retry without hyphens when the query finds nothing
+    #[serde(default = "default_dehyphenate")]
+    pub dehyphenate: bool,
+    /// Only keep definitions containing this text, or matching `/regex/`
+    pub filter: Option<String>,
+    pub min_len: Option<u64>,
+    pub max_len: Option<u64>,
+    /// Keep only the best result per stem
+    #[serde(default)]
+    pub diversify: bool,
+    /// Never return results from the reverse direction
+    #[serde(default)]
+    pub strict_lang: bool,
+    /// Order of the results
+    #[serde(default)]
+    pub rank: Ranking,
+    /// Milliseconds the search may take before it returns the results found
+    /// so far, flagged `partial`
+    pub budget_ms: Option<u64>,
+    /// Add an HTML rendering to each result
+    #[serde(default)]
+    pub format: ResultFormat,
+    /// Include per-phase timings in the response
+    #[serde(default)]
+    pub debug: bool,
+    /// Session to record the lookup in, instead of the session cookie.
+    /// Never written to the query log.
+    #[serde(default, skip_serializing)]
+    pub session: Option<String>,
+    /// Pin the index snapshot searched and return a token for it in `snapshot`
+    #[serde(default, skip_serializing)]
+    pub pin: bool,
+    /// Search the snapshot pinned under this token instead of the current index
+    #[serde(default, skip_serializing)]
+    pub snapshot: Option<String>,
+}
+
+impl SearchQuery {
+    /// Requested language, or the built-in default
+    pub fn language(&self) -> LanguagePair {
+        self.lang.unwrap_or_else(default_language)
+    }
+
+    /// Requested mode, or the built-in default
+    pub fn mode(&self) -> SearchMode {
+        self.mode.unwrap_or_else(default_search_mode)
+    }
+
+    /// Fill in omitted parameters from `defaults`
+    pub fn apply_defaults(&mut self, defaults: &SearchDefaults) {
+        self.mode.get_or_insert(defaults.mode);
+        self.lang.get_or_insert(defaults.lang);
+        self.limit.get_or_insert(defaults.limit);
+        self.max_distance.get_or_insert(defaults.max_distance);
+    }
+
+    /// The prefixes of a multi-prefix search (`q=hau,grü&mode=prefix`),
+    /// empty for a single query
+    pub fn prefixes(&self) -> Vec<&str> {
+        if self.mode() != SearchMode::Prefix || !self.q.contains(',') {
+            return Vec::new();
+        }
+        let mut prefixes = Vec::new();
+        for prefix in self.q.split(',').map(str::trim) {
+            if !prefix.is_empty() && !prefixes.contains(&prefix) {
+                prefixes.push(prefix);
+            }
+        }
+        prefixes
+    }
+
+    /// Search options described by these query parameters
+    pub fn options(&self) -> SearchOptions {
+        SearchOptions {
+            mode: self.mode(),
+            language: self.language(),
+            max_distance: self.max_distance.unwrap_or_else(default_max_distance),
+            limit: self.limit.unwrap_or_else(default_limit),
+            strict_diacritics: self.strict_diacritics,
+            auto_distance: self.auto_distance,
+            group: self.group,
+            min_frequency_rank: self.min_frequency_rank,
+            register: self.register,
+            safe: self.safe,
+            sources: self
+                .sources
+                .as_deref()
+                .map(|sources| {
+                    sources
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|s| !s.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            target_only: self.target_only,
+            dehyphenate: self.dehyphenate,
+            // Parsed by the caller, which reports invalid patterns
+            filter: None,
+            within: Vec::new(),
+            min_len: self.min_len,
+            max_len: self.max_len,
+            diversify: self.diversify,
+            flip_direction: !self.strict_lang,
+            rank: self.rank,
+            cancellation: Cancellation::default(),
+            deadline: self
+                .budget_ms
+                .map(|budget| Instant::now() + Duration::from_millis(budget)),
+        }
+    }
+}
+
+/// Wordlist-constrained search body: the usual search parameters plus the candidate words
+#[derive(Debug, Clone, Deserialize)]
+pub struct WithinRequest {
+    #[serde(flatten)]
+    pub query: SearchQuery,
+    pub words: Vec<String>,
+}
+
 fn default_search_mode() -> SearchMode {
     SearchMode::Fuzzy
 }
 
-fn default_language() -> Language {
-    Language::DeEn
+fn default_language() -> LanguagePair {
+    LanguagePair::DE_EN
 }
 
 fn default_max_distance() -> u8 {
@@ -135,15 +1470,74 @@ fn default_limit() -> usize {
     20
 }
 
+fn default_auto_distance() -> bool {
+    true
+}
+
+fn default_dehyphenate() -> bool {
+    true
+}
+
+fn default_grouping() -> Grouping {
+    Grouping::Word
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_language_from_str() {
-        assert_eq!("en-de".parse::<Language>().unwrap(), Language::EnDe);
-        assert_eq!("de-en".parse::<Language>().unwrap(), Language::DeEn);
-        assert!("invalid".parse::<Language>().is_err());
+        assert_eq!(
+            "en-de".parse::<LanguagePair>().unwrap(),
+            LanguagePair::EN_DE
+        );
+        assert_eq!(
+            "de-en".parse::<LanguagePair>().unwrap(),
+            LanguagePair::DE_EN
+        );
+        assert!("invalid".parse::<LanguagePair>().is_err());
+    }
+
+    #[test]
+    fn test_language_pairs() {
+        for alias in ["fr-de", "FRA_DEU", "fre-ger", "French-German"] {
+            let pair: LanguagePair = alias.parse().unwrap();
+            assert_eq!(pair.as_str(), "fr-de");
+            assert_eq!((pair.source(), pair.target()), ("fr", "de"));
+        }
+        // Languages without a two-letter code keep their three-letter one
+        let pair: LanguagePair = "kha-eng".parse().unwrap();
+        assert_eq!(pair.as_str(), "kha-en");
+        assert_eq!(pair.reverse().as_str(), "en-kha");
+        assert_eq!(LanguagePair::DE_EN.reverse(), LanguagePair::EN_DE);
+
+        assert!("fr-fr".parse::<LanguagePair>().is_err());
+        assert!("fr-de-en".parse::<LanguagePair>().is_err());
+        assert!("fr-d3".parse::<LanguagePair>().is_err());
+    }
+
+    #[test]
+    fn test_language_aliases_and_suggestions() {
+        for alias in ["DE-EN", "deen", "de_en", "de", "deu-eng", "German-English"] {
+            assert_eq!(alias.parse::<LanguagePair>().unwrap(), LanguagePair::DE_EN);
+        }
+        for alias in ["EN-DE", "en", "eng-deu", "english"] {
+            assert_eq!(alias.parse::<LanguagePair>().unwrap(), LanguagePair::EN_DE);
+        }
+        assert_eq!(
+            "de-e".parse::<LanguagePair>().unwrap_err().to_string(),
+            "Invalid language: de-e (did you mean de-en?)"
+        );
+        assert_eq!(
+            "french".parse::<LanguagePair>().unwrap_err().to_string(),
+            "Invalid language: french (expected a language pair such as de-en or fr-de)"
+        );
+
+        // Deserializing, e.g. from query strings and the config file, accepts the same
+        let language: LanguagePair = serde_json::from_str("\"DE-EN\"").unwrap();
+        assert_eq!(language, LanguagePair::DE_EN);
+        assert_eq!(serde_json::to_string(&language).unwrap(), "\"de-en\"");
     }
 
     #[test]
@@ -151,6 +1545,124 @@ mod tests {
         assert_eq!("exact".parse::<SearchMode>().unwrap(), SearchMode::Exact);
         assert_eq!("fuzzy".parse::<SearchMode>().unwrap(), SearchMode::Fuzzy);
         assert_eq!("prefix".parse::<SearchMode>().unwrap(), SearchMode::Prefix);
+        assert_eq!(
+            "definition".parse::<SearchMode>().unwrap(),
+            SearchMode::Definition
+        );
         assert!("invalid".parse::<SearchMode>().is_err());
+        assert_eq!("Fuzzy".parse::<SearchMode>().unwrap(), SearchMode::Fuzzy);
+        assert_eq!("def".parse::<SearchMode>().unwrap(), SearchMode::Definition);
+        assert_eq!(
+            "fuzy".parse::<SearchMode>().unwrap_err().to_string(),
+            "Invalid search mode: fuzy (did you mean fuzzy?)"
+        );
+        let mode: SearchMode = serde_json::from_str("\"PREFIX\"").unwrap();
+        assert_eq!(mode, SearchMode::Prefix);
+    }
+
+    #[test]
+    fn test_ranking_from_str() {
+        assert_eq!("Distance".parse::<Ranking>().unwrap(), Ranking::Distance);
+        assert_eq!("hybrid".parse::<Ranking>().unwrap(), Ranking::Hybrid);
+        assert_eq!(
+            "scroe".parse::<Ranking>().unwrap_err().to_string(),
+            "Invalid ranking: scroe (did you mean score?)"
+        );
+        let query: SearchQuery = serde_json::from_str(r#"{"q": "Haus"}"#).unwrap();
+        assert_eq!(query.options().rank, Ranking::Hybrid);
+    }
+
+    #[test]
+    fn test_part_of_speech_detect() {
+        assert_eq!(
+            PartOfSpeech::detect("Haus /haʊs/ <n, neut> house"),
+            Some(PartOfSpeech::Noun)
+        );
+        assert_eq!(
+            PartOfSpeech::detect("gehen <vi> to go"),
+            Some(PartOfSpeech::Verb)
+        );
+        assert_eq!(PartOfSpeech::detect("house, building"), None);
+        assert_eq!(PartOfSpeech::detect("<unknown> x"), None);
+    }
+
+    #[test]
+    fn test_register_detect() {
+        assert_eq!(
+            Register::detect("Bulle <m> [coll.] [pej.] cop"),
+            vec![Register::Colloquial, Register::Offensive]
+        );
+        assert_eq!(
+            Register::detect("(geh.) to pass away"),
+            vec![Register::Formal]
+        );
+        assert!(Register::detect("<n, neut> house (building)").is_empty());
+        assert_eq!("vulgar".parse::<Register>().unwrap(), Register::Vulgar);
+        assert!("rude".parse::<Register>().is_err());
+    }
+
+    #[test]
+    fn test_definition_filter() {
+        let substring: DefinitionFilter = "Building".parse().unwrap();
+        assert!(substring.matches("house, building"));
+        assert!(!substring.matches("car"));
+
+        let regex: DefinitionFilter = "/^(house|home)\\b/".parse().unwrap();
+        assert!(regex.matches("House, building"));
+        assert!(!regex.matches("a house"));
+
+        assert!("/(/".parse::<DefinitionFilter>().is_err());
+        assert!("".parse::<DefinitionFilter>().is_err());
+    }
+
+    #[test]
+    fn test_search_query_prefixes() {
+        let query = |q: &str, mode| SearchQuery {
+            mode: Some(mode),
+            ..serde_json::from_value(serde_json::json!({ "q": q })).unwrap()
+        };
+        assert_eq!(
+            query("hau, grü,,hau", SearchMode::Prefix).prefixes(),
+            ["hau", "grü"]
+        );
+        assert!(query("hau", SearchMode::Prefix).prefixes().is_empty());
+        assert!(query("hau,grü", SearchMode::Fuzzy).prefixes().is_empty());
+    }
+
+    #[test]
+    fn test_search_query_apply_defaults() {
+        let mut params: SearchQuery =
+            serde_json::from_value(serde_json::json!({ "q": "Haus", "limit": 3 })).unwrap();
+        params.apply_defaults(&SearchDefaults {
+            mode: SearchMode::Exact,
+            lang: LanguagePair::EN_DE,
+            limit: 50,
+            max_distance: 1,
+        });
+        let options = params.options();
+        assert_eq!(options.mode, SearchMode::Exact);
+        assert_eq!(options.language, LanguagePair::EN_DE);
+        assert_eq!(options.limit, 3);
+        assert_eq!(options.max_distance, 1);
+    }
+
+    #[test]
+    fn test_import_format_from_str() {
+        assert_eq!(
+            "dictd".parse::<ImportFormat>().unwrap(),
+            ImportFormat::Dictd
+        );
+        assert_eq!(
+            "dictcc".parse::<ImportFormat>().unwrap(),
+            ImportFormat::DictCc
+        );
+        assert!("invalid".parse::<ImportFormat>().is_err());
+    }
+
+    #[test]
+    fn test_grouping_from_str() {
+        assert_eq!("word".parse::<Grouping>().unwrap(), Grouping::Word);
+        assert_eq!("none".parse::<Grouping>().unwrap(), Grouping::None);
+        assert!("invalid".parse::<Grouping>().is_err());
     }
 }
@@ -0,0 +1,138 @@
+use unicode_normalization::UnicodeNormalization;
+
+/// Canonical form of text copied from PDFs and ebooks: NFC-composed, with
+/// typographic apostrophes, dashes, ligatures and unusual spaces replaced by
+/// their plain equivalents, zero-width characters and soft hyphens removed and
+/// words split across lines ("Haus-\ntür") joined again. Applied to headwords
+/// and definitions at index time and to queries before searching.
+pub fn normalize(text: &str) -> String {
+    let mut normalized = String::with_capacity(text.len());
+    for c in text.nfc() {
+        match c {
+            '\u{2019}' | '\u{2018}' | '\u{02BC}' | '\u{2032}' | '\u{00B4}' | '`' => {
+                normalized.push('\'')
+            }
+            '\u{2010}' | '\u{2011}' | '\u{2012}' | '\u{2013}' | '\u{2014}' | '\u{2212}' => {
+                normalized.push('-')
+            }
+            '\u{00A0}' | '\u{2007}' | '\u{2009}' | '\u{200A}' | '\u{202F}' | '\u{3000}' => {
+                normalized.push(' ')
+            }
+            '\u{00AD}' | '\u{200B}' | '\u{200C}' | '\u{200D}' | '\u{2060}' | '\u{FEFF}' => {}
+            '\u{FB00}' => normalized.push_str("ff"),
+            '\u{FB01}' => normalized.push_str("fi"),
+            '\u{FB02}' => normalized.push_str("fl"),
+            '\u{FB03}' => normalized.push_str("ffi"),
+            '\u{FB04}' => normalized.push_str("ffl"),
+            _ => normalized.push(c),
+        }
+    }
+    rejoin_line_breaks(&normalized)
+}
+
+/// Join words hyphenated at a line break: "Haus-\n  tür" -> "Haustür"
+fn rejoin_line_breaks(text: &str) -> String {
+    if !text.contains('\n') {
+        return text.to_string();
+    }
+    let mut joined = String::with_capacity(text.len());
+    let mut lines = text.split('\n').peekable();
+    while let Some(line) = lines.next() {
+        let Some(next) = lines.peek_mut() else {
+            joined.push_str(line);
+            break;
+        };
+        match line.trim_end().strip_suffix('-') {
+            // Only a hyphen between two words is a line-break split
+            Some(word) if word.ends_with(char::is_alphanumeric) => {
+                joined.push_str(word);
+                *next = next.trim_start();
+            }
+            _ => {
+                joined.push_str(line);
+                joined.push('\n');
+            }
+        }
+    }
+    joined
+}
+
+/// The query with hyphens between words removed ("Haus-tür" -> "haustür"),
+/// if it has any
+pub fn dehyphenate(query: &str) -> Option<String> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut dehyphenated = String::with_capacity(query.len());
+    for (i, &c) in chars.iter().enumerate() {
+        let between_letters = i > 0
+            && chars[i - 1].is_alphanumeric()
+            && chars.get(i + 1).is_some_and(|next| next.is_alphanumeric());
+        if !(c == '-' && between_letters) {
+            dehyphenated.push(c);
+        }
+    }
+    (dehyphenated != query).then_some(dehyphenated)
+}
+
+/// Alternative German spellings of a lowercased word that differ from it:
+/// ß written as "ss", umlauts written as "ae", "oe", "ue", and both.
+/// "straße" -> ["strasse"], "größe" -> ["grösse", "groeße", "groesse"]
+pub fn spelling_variants(word: &str) -> Vec<String> {
+    let sharp_s = |text: &str| text.replace('ß', "ss");
+    let umlauts = |text: &str| {
+        text.replace('ä', "ae")
+            .replace('ö', "oe")
+            .replace('ü', "ue")
+    };
+
+    let mut variants = Vec::new();
+    for variant in [sharp_s(word), umlauts(word), umlauts(&sharp_s(word))] {
+        if variant != word && !variants.contains(&variant) {
+            variants.push(variant);
+        }
+    }
+    variants
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("Gro\u{0308}\u{DF}e"), "Größe");
+        assert_eq!(normalize("o\u{2019}clock"), "o'clock");
+        assert_eq!(normalize("zu\u{00A0}Hause"), "zu Hause");
+        assert_eq!(normalize("Auf\u{200B}gabe"), "Aufgabe");
+        assert_eq!(normalize("\u{FB01}nden \u{2013} find"), "finden - find");
+        assert_eq!(normalize("Haus"), "Haus");
+    }
+
+    #[test]
+    fn test_normalize_hyphenation() {
+        assert_eq!(normalize("Haus\u{00AD}tür"), "Haustür");
+        assert_eq!(normalize("Haus-\ntür"), "Haustür");
+        assert_eq!(normalize("Haus- \r\n   tür"), "Haustür");
+        assert_eq!(normalize("Haus\u{2010}\ntür"), "Haustür");
+        assert_eq!(normalize("Haus -\ntür"), "Haus -\ntür");
+        assert_eq!(normalize("Haus\ntür"), "Haus\ntür");
+    }
+
+    #[test]
+    fn test_dehyphenate() {
+        assert_eq!(dehyphenate("haus-tür").as_deref(), Some("haustür"));
+        assert_eq!(
+            dehyphenate("e-mail-adresse").as_deref(),
+            Some("emailadresse")
+        );
+        assert_eq!(dehyphenate("-haus"), None);
+        assert_eq!(dehyphenate("haus"), None);
+    }
+
+    #[test]
+    fn test_spelling_variants() {
+        assert_eq!(spelling_variants("straße"), ["strasse"]);
+        assert_eq!(spelling_variants("müller"), ["mueller"]);
+        assert_eq!(spelling_variants("größe"), ["grösse", "groeße", "groesse"]);
+        assert!(spelling_variants("haus").is_empty());
+    }
+}
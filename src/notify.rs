@@ -0,0 +1,114 @@
+use anyhow::Result;
+
+use crate::models::SearchResult;
+
+/// Notification urgency (only honoured by freedesktop notification servers)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Urgency {
+    Low,
+    Normal,
+    Critical,
+}
+
+impl std::str::FromStr for Urgency {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "low" => Ok(Urgency::Low),
+            "normal" => Ok(Urgency::Normal),
+            "critical" => Ok(Urgency::Critical),
+            _ => Err(anyhow::anyhow!("Invalid urgency: {}", s)),
+        }
+    }
+}
+
+/// Desktop notification settings
+#[derive(Debug, Clone, Copy)]
+pub struct NotifyOptions {
+    /// How long the notification stays visible, in milliseconds
+    pub duration_ms: u32,
+    pub urgency: Urgency,
+}
+
+impl Default for NotifyOptions {
+    fn default() -> Self {
+        Self {
+            duration_ms: 5000,
+            urgency: Urgency::Normal,
+        }
+    }
+}
+
+/// Summary and body of the notification for a lookup result
+pub fn format_notification(result: &SearchResult) -> (String, String) {
+    let top_definition = result.definitions.first().cloned().unwrap_or_default();
+    (result.word.clone(), top_definition)
+}
+
+/// Show a desktop notification with the word and its top definition
+#[cfg(feature = "notifications")]
+pub fn notify(result: &SearchResult, options: &NotifyOptions) -> Result<()> {
+    let (summary, body) = format_notification(result);
+
+    let mut notification = notify_rust::Notification::new();
+    notification
+        .appname("dictv")
+        .summary(&summary)
+        .body(&body)
+        .timeout(notify_rust::Timeout::Milliseconds(options.duration_ms));
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    notification.urgency(match options.urgency {
+        Urgency::Low => notify_rust::Urgency::Low,
+        Urgency::Normal => notify_rust::Urgency::Normal,
+        Urgency::Critical => notify_rust::Urgency::Critical,
+    });
+
+    notification.show()?;
+    Ok(())
+}
+
+/// Show a desktop notification with the word and its top definition
+#[cfg(not(feature = "notifications"))]
+pub fn notify(_result: &SearchResult, _options: &NotifyOptions) -> Result<()> {
+    anyhow::bail!(
+        "dictv was built without desktop notification support (enable the `notifications` feature)"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_notification_uses_top_definition() {
+        let result = SearchResult {
+            word: "haus".to_string(),
+            definitions: vec!["house".to_string(), "home".to_string()],
+            language: "de-en".to_string(),
+            edit_distance: None,
+            score: None,
+            frequency_rank: None,
+            domains: Vec::new(),
+            registers: Vec::new(),
+            parts_of_speech: Vec::new(),
+            genders: Vec::new(),
+            audio_urls: Vec::new(),
+            sources: Vec::new(),
+            html: None,
+        };
+
+        assert_eq!(
+            format_notification(&result),
+            ("haus".to_string(), "house".to_string())
+        );
+    }
+
+    #[test]
+    fn test_urgency_from_str() {
+        assert_eq!("low".parse::<Urgency>().unwrap(), Urgency::Low);
+        assert_eq!("critical".parse::<Urgency>().unwrap(), Urgency::Critical);
+        assert!("loud".parse::<Urgency>().is_err());
+    }
+}
@@ -0,0 +1,230 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use crate::models::LanguagePair;
+
+/// FreeDict dictionaries offered during setup
+pub const FREEDICT_DICTIONARIES: &[(&str, &str)] = &[
+    ("deu-eng", "German → English"),
+    ("eng-deu", "English → German"),
+];
+
+/// Answers given in the setup wizard
+#[derive(Debug, Clone, PartialEq)]
+pub struct OnboardingChoices {
+    pub data_dir: PathBuf,
+    pub dictionaries: Vec<String>,
+    pub install_service: bool,
+}
+
+/// Ask where to store data, which dictionaries to download and whether to
+/// install a service. Empty answers take the defaults shown in brackets.
+pub fn prompt_choices(
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+    default_data_dir: &Path,
+) -> Result<OnboardingChoices> {
+    writeln!(
+        output,
+        "Welcome to dictv! Let's set up your dictionaries.\n"
+    )?;
+
+    let answer = ask(
+        input,
+        output,
+        &format!("Data directory [{}]: ", default_data_dir.display()),
+    )?;
+    let data_dir = if answer.is_empty() {
+        default_data_dir.to_path_buf()
+    } else {
+        PathBuf::from(answer)
+    };
+
+    writeln!(output, "\nAvailable dictionaries:")?;
+    for (name, description) in FREEDICT_DICTIONARIES {
+        writeln!(output, "  {:<8} {}", name, description)?;
+    }
+    let all: Vec<String> = FREEDICT_DICTIONARIES
+        .iter()
+        .map(|(name, _)| name.to_string())
+        .collect();
+    let dictionaries = loop {
+        let answer = ask(
+            input,
+            output,
+            &format!("Dictionaries to download [{}]: ", all.join(",")),
+        )?;
+        if answer.is_empty() {
+            break all.clone();
+        }
+        let chosen: Vec<String> = answer
+            .split(',')
+            .map(|name| name.trim().trim_start_matches("freedict-").to_string())
+            .filter(|name| !name.is_empty())
+            .collect();
+        match chosen.iter().find(|name| !all.contains(name)) {
+            Some(unknown) => writeln!(output, "Unknown dictionary: {}", unknown)?,
+            None => break chosen,
+        }
+    };
+
+    let answer = ask(
+        input,
+        output,
+        "\nInstall a service that starts the server on login? [y/N]: ",
+    )?;
+    let install_service = matches!(answer.to_lowercase().as_str(), "y" | "yes");
+
+    Ok(OnboardingChoices {
+        data_dir,
+        dictionaries,
+        install_service,
+    })
+}
+
+/// Ask for the language pair of a dictionary file until a valid one is given
+pub fn prompt_language(
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+    file: &Path,
+) -> Result<LanguagePair> {
+    loop {
+        let answer = ask(
+            input,
+            output,
+            &format!("Language pair of {} (e.g. de-en, fr-de): ", file.display()),
+        )?;
+        if answer.is_empty() {
+            anyhow::bail!("No language pair given for {}", file.display());
+        }
+        match answer.parse() {
+            Ok(language) => return Ok(language),
+            Err(e) => writeln!(output, "{}", e)?,
+        }
+    }
+}
+
+/// Print `prompt` and read one trimmed line; end of input counts as an empty answer
+fn ask(input: &mut impl BufRead, output: &mut impl Write, prompt: &str) -> Result<String> {
+    write!(output, "{}", prompt)?;
+    output.flush()?;
+    let mut line = String::new();
+    input.read_line(&mut line)?;
+    Ok(line.trim().to_string())
+}
+
+/// Write a `config.toml` listing `dictionaries`, unless one already exists.
+/// Returns whether a file was written.
+pub fn write_config(data_dir: &Path, dictionaries: &[String]) -> Result<bool> {
+    let path = data_dir.join("config.toml");
+    if path.exists() {
+        return Ok(false);
+    }
+    fs::create_dir_all(data_dir)?;
+    let list: Vec<String> = dictionaries.iter().map(|d| format!("{:?}", d)).collect();
+    fs::write(
+        &path,
+        format!(
+            "# FreeDict dictionaries that `dictv serve` imports when missing\ndictionaries = [{}]\n",
+            list.join(", ")
+        ),
+    )
+    .context(format!("Failed to write config: {:?}", path))?;
+    Ok(true)
+}
+
+/// systemd user unit running `dictv serve` on the given data directory
+pub fn systemd_unit(binary: &Path, data_dir: &Path) -> String {
+    format!(
+        "[Unit]\n\
+         Description=dictv dictionary server\n\
+         \n\
+         [Service]\n\
+         Environment=DICTV_DATA={}\n\
+         ExecStart={} serve\n\
+         Restart=on-failure\n\
+         \n\
+         [Install]\n\
+         WantedBy=default.target\n",
+        data_dir.display(),
+        binary.display()
+    )
+}
+
+/// Install the systemd user unit, returning its path
+pub fn install_systemd_unit(data_dir: &Path) -> Result<PathBuf> {
+    if !cfg!(target_os = "linux") {
+        anyhow::bail!("Service installation is only supported with systemd on Linux");
+    }
+    let unit_dir = dirs::config_dir()
+        .context("Could not find config directory")?
+        .join("systemd/user");
+    fs::create_dir_all(&unit_dir)?;
+    let path = unit_dir.join("dictv.service");
+    let binary = std::env::current_exe()?;
+    fs::write(&path, systemd_unit(&binary, data_dir))
+        .context(format!("Failed to write service: {:?}", path))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use std::io::Cursor;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_prompt_choices_reprompts_unknown_dictionary() {
+        let mut input = Cursor::new("/srv/dictv\nfra-eng\nfreedict-deu-eng\ny\n");
+        let mut output = Vec::new();
+
+        let choices = prompt_choices(&mut input, &mut output, Path::new("/home/u/.dictv")).unwrap();
+
+        assert_eq!(
+            choices,
+            OnboardingChoices {
+                data_dir: PathBuf::from("/srv/dictv"),
+                dictionaries: vec!["deu-eng".to_string()],
+                install_service: true,
+            }
+        );
+        assert!(
+            String::from_utf8(output)
+                .unwrap()
+                .contains("Unknown dictionary: fra-eng")
+        );
+    }
+
+    #[test]
+    fn test_prompt_language_reprompts_invalid_answer() {
+        let mut input = Cursor::new("french\nde-en\n");
+        let mut output = Vec::new();
+        let language = prompt_language(&mut input, &mut output, Path::new("mydict.dict.dz"));
+        assert_eq!(language.unwrap(), LanguagePair::DE_EN);
+        assert!(
+            String::from_utf8(output)
+                .unwrap()
+                .contains("Invalid language: french")
+        );
+
+        let mut input = Cursor::new("");
+        assert!(prompt_language(&mut input, &mut Vec::new(), Path::new("mydict.dict.dz")).is_err());
+    }
+
+    #[test]
+    fn test_defaults_and_config_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut input = Cursor::new("");
+        let choices = prompt_choices(&mut input, &mut Vec::new(), temp_dir.path()).unwrap();
+        assert_eq!(choices.dictionaries, vec!["deu-eng", "eng-deu"]);
+        assert!(!choices.install_service);
+
+        assert!(write_config(&choices.data_dir, &choices.dictionaries).unwrap());
+        assert!(!write_config(&choices.data_dir, &[]).unwrap());
+        let config = Config::load(temp_dir.path().join("config.toml")).unwrap();
+        assert_eq!(config.dictionaries, vec!["deu-eng", "eng-deu"]);
+    }
+}
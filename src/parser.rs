@@ -1,57 +1,127 @@
 use anyhow::{Context, Result};
 use flate2::read::GzDecoder;
+use rayon::prelude::*;
 use std::fs::File;
 use std::io::{BufRead, BufReader, Read};
 use std::path::Path;
 
-use crate::models::DictionaryEntry;
+use crate::models::{DictionaryEntry, ImportFormat, LanguagePair, known_language_code};
+use crate::wordlist::{self, Columns};
+use crate::{
+    appledict, bgl, dictcc, dsl, jsonl, slob, stardict, tei, wiktionary, xdxf, yomichan, zim,
+};
+
+/// Number of malformed lines kept as examples in `ParseStats`
+const MAX_MALFORMED_EXAMPLES: usize = 5;
+
+/// Statistics gathered while parsing a dictionary source
+#[derive(Debug, Clone, Default)]
+pub struct ParseStats {
+    /// Lines or records that could not be parsed and were skipped
+    pub malformed_lines: usize,
+    /// The first few malformed lines, for reporting
+    pub malformed_examples: Vec<String>,
+    /// Language pair declared by the source itself (header, file name), if any
+    pub detected_language: Option<String>,
+}
+
+impl ParseStats {
+    /// Count a skipped line, keeping it as an example if there is room
+    pub fn record_malformed(&mut self, line: &str) {
+        self.malformed_lines += 1;
+        if self.malformed_examples.len() < MAX_MALFORMED_EXAMPLES {
+            self.malformed_examples.push(line.to_string());
+        }
+    }
+}
 
 /// DICTD index entry
 #[derive(Debug, Clone)]
-struct IndexEntry {
-    word: String,
-    offset: u64,
-    length: u64,
+pub struct IndexEntry {
+    pub(crate) word: String,
+    pub(crate) offset: u64,
+    pub(crate) length: u64,
 }
 
 /// Parse DICTD .index file (supports both numeric and base64-encoded offsets)
 pub fn parse_index<P: AsRef<Path>>(path: P) -> Result<Vec<IndexEntry>> {
+    parse_index_with_stats(path, &mut ParseStats::default())
+}
+
+/// Parse DICTD .index file, skipping and counting malformed lines
+fn parse_index_with_stats<P: AsRef<Path>>(
+    path: P,
+    stats: &mut ParseStats,
+) -> Result<Vec<IndexEntry>> {
     let file = File::open(path.as_ref())
         .context(format!("Failed to open index file: {:?}", path.as_ref()))?;
     let reader = BufReader::new(file);
-    let mut entries = Vec::new();
+    let mut rows = Vec::new();
 
     for line in reader.lines() {
         let line = line?;
-        let parts: Vec<&str> = line.split('\t').collect();
-
-        if parts.len() >= 3 {
-            let word = parts[0].to_string();
-
-            // Try to parse as number first, then as base64
-            let offset = match parts[1].parse::<u64>() {
-                Ok(n) => n,
-                Err(_) => decode_base64_offset(parts[1])
-                    .context(format!("Invalid offset in index: {}", parts[1]))?,
-            };
+        if line.trim().is_empty() {
+            continue;
+        }
+        if line.split('\t').count() < 3 {
+            stats.record_malformed(&line);
+            continue;
+        }
+        rows.push(line);
+    }
 
-            let length = match parts[2].parse::<u64>() {
-                Ok(n) => n,
-                Err(_) => decode_base64_offset(parts[2])
-                    .context(format!("Invalid length in index: {}", parts[2]))?,
-            };
+    // Offsets are base64 in dictd files, but a few tools write decimal
+    // numbers. Digits are base64 characters too ("00" is 3380), so the
+    // encoding is decided for the whole file: decimal only if every
+    // well-formed field is a number. Fields valid in neither encoding are
+    // malformed either way and must not tip the decision.
+    let decimal = rows
+        .iter()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('\t').skip(1).take(2).collect();
+            fields
+                .iter()
+                .all(|field| is_base64_offset(field))
+                .then_some(fields)
+        })
+        .all(|fields| {
+            fields
+                .iter()
+                .all(|field| field.bytes().all(|b| b.is_ascii_digit()))
+        });
+    let decode = |field: &str| {
+        if decimal {
+            field.parse::<u64>().map_err(anyhow::Error::from)
+        } else {
+            decode_base64_offset(field)
+        }
+    };
 
-            entries.push(IndexEntry {
-                word,
+    let mut entries = Vec::with_capacity(rows.len());
+    for line in rows {
+        let parts: Vec<&str> = line.split('\t').collect();
+        match (decode(parts[1]), decode(parts[2])) {
+            (Ok(offset), Ok(length)) => entries.push(IndexEntry {
+                word: parts[0].to_string(),
                 offset,
                 length,
-            });
+            }),
+            _ => stats.record_malformed(&line),
         }
     }
 
     Ok(entries)
 }
 
+/// Whether a field could be a dictd offset: non-empty and made only of base64
+/// characters, which include the digits of decimal offsets
+fn is_base64_offset(field: &str) -> bool {
+    !field.is_empty()
+        && field
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'+' || b == b'/')
+}
+
 /// Decode base64-encoded offset used in FreeDict index files
 fn decode_base64_offset(encoded: &str) -> Result<u64> {
     // FreeDict uses standard base64 encoding for offsets
@@ -71,13 +141,68 @@ fn decode_base64_offset(encoded: &str) -> Result<u64> {
     Ok(result)
 }
 
+/// Guess the language pair from a file name like `freedict-eng-deu-1.9.dict.dz`,
+/// `fra-deu.tei` or `german_english.dict`: the first two adjacent words that
+/// are known language codes or names
+pub fn language_from_file_name(name: &str) -> Option<LanguagePair> {
+    let words: Vec<&str> = name
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect();
+    words.windows(2).find_map(|pair| {
+        let from = known_language_code(pair[0])?;
+        let to = known_language_code(pair[1])?;
+        LanguagePair::new(from, to)
+    })
+}
+
+/// Number of index entries each parser task extracts and cleans
+pub const PARSE_CHUNK_SIZE: usize = 4096;
+
 /// Parse DICTD .dict.dz (gzipped dictionary file)
 pub fn parse_dict<P: AsRef<Path>>(
     dict_path: P,
     index_path: P,
     language: &str,
 ) -> Result<Vec<DictionaryEntry>> {
-    let index_entries = parse_index(index_path)?;
+    let mut entries = Vec::new();
+    parse_dict_batches(
+        dict_path,
+        index_path,
+        language,
+        &mut ParseStats::default(),
+        |batch| {
+            entries.extend(batch);
+            Ok(())
+        },
+    )?;
+    Ok(entries)
+}
+
+/// Parse DICTD .dict.dz, handing entries to `sink` in batches as they are ready.
+///
+/// Definition extraction and cleaning run on the rayon pool, one chunk of
+/// index entries per task. Batches arrive in index order.
+pub fn parse_dict_batches<P, F>(
+    dict_path: P,
+    index_path: P,
+    language: &str,
+    stats: &mut ParseStats,
+    mut sink: F,
+) -> Result<usize>
+where
+    P: AsRef<Path>,
+    F: FnMut(Vec<DictionaryEntry>) -> Result<()>,
+{
+    if let Some(language) = dict_path
+        .as_ref()
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(language_from_file_name)
+    {
+        stats.detected_language = Some(language.to_string());
+    }
+    let index_entries = parse_index_with_stats(index_path, stats)?;
 
     // Open and decompress the dictionary file
     let file = File::open(dict_path.as_ref()).context(format!(
@@ -89,7 +214,41 @@ pub fn parse_dict<P: AsRef<Path>>(
     let mut content = Vec::new();
     decoder.read_to_end(&mut content)?;
 
+    // Work on a few chunks per thread at a time so the sink can start
+    // indexing while later chunks are still being parsed
+    let window = PARSE_CHUNK_SIZE * rayon::current_num_threads().max(1) * 2;
+    let mut total = 0;
+
+    for window_entries in index_entries.chunks(window) {
+        let batches: Vec<(Vec<DictionaryEntry>, Vec<&IndexEntry>)> = window_entries
+            .par_chunks(PARSE_CHUNK_SIZE)
+            .map(|chunk| extract_entries(chunk, &content, language))
+            .collect();
+
+        for (batch, out_of_range) in batches {
+            for index_entry in out_of_range {
+                stats.record_malformed(&format!(
+                    "{}\t{}\t{} (beyond end of dictionary)",
+                    index_entry.word, index_entry.offset, index_entry.length
+                ));
+            }
+            total += batch.len();
+            sink(batch)?;
+        }
+    }
+
+    Ok(total)
+}
+
+/// Extract and clean the definitions for a chunk of index entries, also
+/// returning the index entries that point past the end of the content
+fn extract_entries<'a>(
+    index_entries: &'a [IndexEntry],
+    content: &[u8],
+    language: &str,
+) -> (Vec<DictionaryEntry>, Vec<&'a IndexEntry>) {
     let mut entries = Vec::with_capacity(index_entries.len());
+    let mut out_of_range = Vec::new();
 
     for index_entry in index_entries {
         let start = index_entry.offset as usize;
@@ -104,10 +263,44 @@ pub fn parse_dict<P: AsRef<Path>>(
                 clean_definition(&definition),
                 language.to_string(),
             ));
+        } else {
+            out_of_range.push(index_entry);
         }
     }
 
-    Ok(entries)
+    (entries, out_of_range)
+}
+
+/// Parse a single-file dictionary source in the given format
+pub fn parse_file<P: AsRef<Path>>(
+    path: P,
+    format: ImportFormat,
+    language: &str,
+    stats: &mut ParseStats,
+) -> Result<Vec<DictionaryEntry>> {
+    match format {
+        ImportFormat::Dictd => {
+            anyhow::bail!("dictd dictionaries need both a .dict.dz and an .index file")
+        }
+        ImportFormat::DictCc => dictcc::parse_dictcc(path, language, stats),
+        ImportFormat::Dsl => Ok(dsl::parse_dsl(path, language, stats)?.entries),
+        ImportFormat::StarDict => Ok(stardict::parse_stardict(path, language, stats)?.entries),
+        ImportFormat::Xdxf => Ok(xdxf::parse_xdxf(path, language, stats)?.entries),
+        ImportFormat::Tei => Ok(tei::parse_tei(path, language, stats)?.entries),
+        ImportFormat::Jsonl => jsonl::parse_jsonl(path, language, stats),
+        ImportFormat::Bgl => Ok(bgl::parse_bgl(path, language, stats)?.entries),
+        // Entries carry the direction of their language section
+        ImportFormat::Wiktionary => wiktionary::parse_wiktionary(path, stats),
+        ImportFormat::AppleDict => {
+            Ok(appledict::parse_apple_dictionary(path, language, stats)?.entries)
+        }
+        ImportFormat::Slob => Ok(slob::parse_slob(path, language, stats)?.entries),
+        ImportFormat::Yomichan => Ok(yomichan::parse_yomichan(path, language, stats)?.entries),
+        ImportFormat::Zim => Ok(zim::parse_zim(path, language, stats)?.entries),
+        ImportFormat::Csv | ImportFormat::Tsv => {
+            wordlist::parse_word_list(path, format, &Columns::default(), language, stats)
+        }
+    }
 }
 
 /// Clean up DICTD definition formatting
@@ -132,6 +325,87 @@ fn clean_definition(def: &str) -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_index_skips_malformed_lines() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"Haus\t0\t5\nbroken\nEi\t?!\t3\nTee\tF\tD\n")
+            .unwrap();
+
+        let mut stats = ParseStats::default();
+        let entries = parse_index_with_stats(file.path(), &mut stats).unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].offset, 5);
+        assert_eq!(stats.malformed_lines, 2);
+        assert_eq!(stats.malformed_examples, ["broken", "Ei\t?!\t3"]);
+    }
+
+    #[test]
+    fn test_parse_index_decides_offset_encoding_per_file() {
+        let index = |content: &[u8]| {
+            let mut file = tempfile::NamedTempFile::new().unwrap();
+            std::io::Write::write_all(&mut file, content).unwrap();
+            let entries = parse_index(file.path()).unwrap();
+            entries
+                .iter()
+                .map(|e| (e.offset, e.length))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(index(b"Haus\t0\t5\nTee\t5\t12\n"), [(0, 5), (5, 12)]);
+        // "00" is base64 for 3380 once another field shows the file is base64
+        assert_eq!(index(b"Haus\tA\tF\nTee\t00\t12\n"), [(0, 5), (3380, 3446)]);
+        // A row that is malformed in both encodings leaves a decimal file decimal
+        assert_eq!(
+            index(b"Haus\t0\t5\nEi\t?!\t3\nTee\t5\t12\n"),
+            [(0, 5), (5, 12)]
+        );
+    }
+
+    #[test]
+    fn test_parse_dict_decodes_digit_only_base64_offsets() {
+        // Laid out as dictfmt writes a dictionary: the database info article
+        // first, and an article at byte 3380, whose base64 offset is "00"
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let info = "00-database-info\nGerman-English dictionary\n";
+        let article = "Haus\nhouse, building\n";
+        let body = format!("{}{}{}", info, " ".repeat(3380 - info.len()), article);
+        let dict_path = temp_dir.path().join("deu-eng.dict.dz");
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut encoder, body.as_bytes()).unwrap();
+        std::fs::write(&dict_path, encoder.finish().unwrap()).unwrap();
+        let index_path = temp_dir.path().join("deu-eng.index");
+        std::fs::write(&index_path, "00-database-info\tA\tr\nHaus\t00\tV\n").unwrap();
+
+        let entries = parse_dict(&dict_path, &index_path, "de-en").unwrap();
+        let haus = entries.iter().find(|entry| entry.word == "Haus").unwrap();
+        assert_eq!(haus.definition, "Haus house, building");
+    }
+
+    #[test]
+    fn test_language_from_file_name() {
+        assert_eq!(
+            language_from_file_name("freedict-eng-deu-1.9-fd1.dict.dz"),
+            Some(LanguagePair::EN_DE)
+        );
+        assert_eq!(
+            language_from_file_name("German_English.dict"),
+            Some(LanguagePair::DE_EN)
+        );
+        assert_eq!(
+            language_from_file_name("de-en.txt"),
+            Some(LanguagePair::DE_EN)
+        );
+        assert_eq!(language_from_file_name("dender.dict.dz"), None);
+        assert_eq!(
+            language_from_file_name("freedict-deu-fra.dict.dz").map(String::from),
+            Some("de-fr".to_string())
+        );
+        assert_eq!(
+            language_from_file_name("spa-eng.tei").map(String::from),
+            Some("es-en".to_string())
+        );
+    }
+
     #[test]
     fn test_clean_definition() {
         let input = "  house, building  \n  home  \n\n";
@@ -0,0 +1,167 @@
+use anyhow::{Context, Result};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::sync::{Semaphore, oneshot};
+use tracing::warn;
+
+use crate::models::SearchMode;
+
+/// Sizes of the search thread pools, from the `[pools]` table of config.toml
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PoolSizes {
+    /// Threads for exact, prefix, fuzzy, anagram and rhyme lookups
+    pub light: usize,
+    /// Threads for definition and pattern searches and searches with a `filter`
+    pub heavy: usize,
+    /// Heavy searches that may wait for a thread, more are turned away
+    pub heavy_queue: usize,
+}
+
+impl Default for PoolSizes {
+    fn default() -> Self {
+        let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+        Self {
+            light: cpus,
+            heavy: (cpus / 4).max(1),
+            heavy_queue: 16,
+        }
+    }
+}
+
+/// Cost class of a search, deciding the pool it runs on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryClass {
+    /// Looks up a few headword terms, latency matters (autocomplete)
+    Light,
+    /// Scans definitions or many headword terms, or fetches extra documents to filter
+    Heavy,
+}
+
+impl QueryClass {
+    pub fn of(mode: SearchMode, filtered: bool) -> Self {
+        match mode {
+            SearchMode::Definition | SearchMode::Pattern => QueryClass::Heavy,
+            _ if filtered => QueryClass::Heavy,
+            _ => QueryClass::Light,
+        }
+    }
+}
+
+/// All heavy threads are busy and the queue is full
+#[derive(Debug, thiserror::Error)]
+#[error("Too many expensive searches in progress, try again shortly")]
+pub struct PoolBusy;
+
+/// Separate thread pools for light and heavy searches, so a burst of heavy
+/// ones cannot hold up autocomplete lookups
+pub struct SearchPools {
+    light: ThreadPool,
+    heavy: ThreadPool,
+    /// Heavy searches running or queued
+    heavy_slots: Arc<Semaphore>,
+}
+
+impl SearchPools {
+    pub fn new(sizes: &PoolSizes) -> Result<Self> {
+        if sizes.light == 0 || sizes.heavy == 0 {
+            anyhow::bail!("Search pools need at least one thread each");
+        }
+        let pool = |name: &'static str, threads: usize| {
+            ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .thread_name(move |i| format!("dictv-{}-{}", name, i))
+                // Rayon aborts on a panic otherwise; the waiting request gets an error instead
+                .panic_handler(|_| warn!("A search thread panicked"))
+                .build()
+                .context(format!("Failed to start the {} search pool", name))
+        };
+        Ok(Self {
+            light: pool("light", sizes.light)?,
+            heavy: pool("heavy", sizes.heavy)?,
+            heavy_slots: Arc::new(Semaphore::new(sizes.heavy + sizes.heavy_queue)),
+        })
+    }
+
+    /// Run `job` on the pool for `class` and wait for its result. Heavy jobs
+    /// beyond the pool's threads and queue fail with `PoolBusy`.
+    pub async fn run<T, F>(&self, class: QueryClass, job: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+        match class {
+            QueryClass::Light => self.light.spawn(move || {
+                let _ = sender.send(job());
+            }),
+            QueryClass::Heavy => {
+                let slot = self
+                    .heavy_slots
+                    .clone()
+                    .try_acquire_owned()
+                    .map_err(|_| PoolBusy)?;
+                self.heavy.spawn(move || {
+                    let result = job();
+                    drop(slot);
+                    let _ = sender.send(result);
+                });
+            }
+        }
+        receiver.await.context("Search thread panicked")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_query_class() {
+        assert_eq!(QueryClass::of(SearchMode::Prefix, false), QueryClass::Light);
+        assert_eq!(QueryClass::of(SearchMode::Fuzzy, false), QueryClass::Light);
+        assert_eq!(QueryClass::of(SearchMode::Fuzzy, true), QueryClass::Heavy);
+        assert_eq!(
+            QueryClass::of(SearchMode::Pattern, false),
+            QueryClass::Heavy
+        );
+    }
+
+    #[tokio::test]
+    async fn test_heavy_queue_is_bounded() {
+        let pools = SearchPools::new(&PoolSizes {
+            light: 1,
+            heavy: 1,
+            heavy_queue: 0,
+        })
+        .unwrap();
+
+        // Occupy the only heavy thread
+        let (release, blocked) = mpsc::channel::<()>();
+        let (started_sender, started) = oneshot::channel();
+        let pools = Arc::new(pools);
+        let running = tokio::spawn({
+            let pools = pools.clone();
+            async move {
+                pools
+                    .run(QueryClass::Heavy, move || {
+                        let _ = started_sender.send(());
+                        blocked.recv().ok()
+                    })
+                    .await
+            }
+        });
+        started.await.unwrap();
+
+        let error = pools.run(QueryClass::Heavy, || ()).await.unwrap_err();
+        assert!(error.downcast_ref::<PoolBusy>().is_some());
+        // Light searches still run
+        assert_eq!(pools.run(QueryClass::Light, || 2 + 2).await.unwrap(), 4);
+
+        release.send(()).unwrap();
+        assert_eq!(running.await.unwrap().unwrap(), Some(()));
+        assert_eq!(pools.run(QueryClass::Heavy, || 1).await.unwrap(), 1);
+    }
+}
@@ -0,0 +1,101 @@
+use serde::Serialize;
+use std::io::Write;
+use std::sync::Arc;
+
+/// How import and rebuild report their progress
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressFormat {
+    /// Human-readable log lines
+    Text,
+    /// Newline-delimited JSON events on stderr
+    Json,
+}
+
+impl std::str::FromStr for ProgressFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(ProgressFormat::Text),
+            "json" => Ok(ProgressFormat::Json),
+            _ => Err(anyhow::anyhow!("Invalid progress format: {}", s)),
+        }
+    }
+}
+
+/// One step of a long-running operation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProgressEvent {
+    /// "download", "extract", "parse" or "done"
+    pub stage: String,
+    /// Estimated completion of the stage, 0 to 100
+    pub percent: u8,
+    pub message: String,
+}
+
+type ProgressSink = dyn Fn(&ProgressEvent) + Send + Sync;
+
+/// Receiver of progress events; silent by default
+#[derive(Clone, Default)]
+pub struct Progress {
+    sink: Option<Arc<ProgressSink>>,
+}
+
+impl Progress {
+    /// Reporter for `format`: JSON lines on stderr, or silent for text since
+    /// the log already tells humans what is happening
+    pub fn new(format: ProgressFormat) -> Self {
+        match format {
+            ProgressFormat::Text => Self::default(),
+            ProgressFormat::Json => Self::with_sink(|event| {
+                if let Ok(line) = serde_json::to_string(event) {
+                    let _ = writeln!(std::io::stderr().lock(), "{}", line);
+                }
+            }),
+        }
+    }
+
+    /// Reporter that hands every event to `sink`
+    pub fn with_sink(sink: impl Fn(&ProgressEvent) + Send + Sync + 'static) -> Self {
+        Self {
+            sink: Some(Arc::new(sink)),
+        }
+    }
+
+    pub fn report(&self, stage: &str, percent: usize, message: impl Into<String>) {
+        if let Some(sink) = &self.sink {
+            sink(&ProgressEvent {
+                stage: stage.to_string(),
+                percent: percent.min(100) as u8,
+                message: message.into(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_progress_reports_to_sink() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let collected = events.clone();
+        let progress = Progress::with_sink(move |event| {
+            collected.lock().unwrap().push(event.clone());
+        });
+
+        progress.report("parse", 150, "Parsing");
+        Progress::default().report("parse", 0, "ignored");
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].percent, 100);
+        assert_eq!(
+            serde_json::to_string(&events[0]).unwrap(),
+            r#"{"stage":"parse","percent":100,"message":"Parsing"}"#
+        );
+        assert!("xml".parse::<ProgressFormat>().is_err());
+    }
+}
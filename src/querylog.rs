@@ -0,0 +1,239 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::models::SearchQuery;
+
+/// Query log settings
+#[derive(Debug, Clone)]
+pub struct QueryLogConfig {
+    /// Current log file, rotated files get a `.1`, `.2`, ... suffix
+    pub path: PathBuf,
+    /// Rotate once the current file reaches this size
+    pub max_bytes: u64,
+    /// Number of rotated files to keep
+    pub max_files: usize,
+    /// Record the client IP address (off by default)
+    pub include_ip: bool,
+}
+
+impl QueryLogConfig {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            max_bytes: 10 * 1024 * 1024,
+            max_files: 5,
+            include_ip: false,
+        }
+    }
+}
+
+/// One logged search, written as a JSON line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryLogRecord {
+    /// Milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    pub params: SearchQuery,
+    pub result_count: usize,
+    pub latency_ms: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip: Option<String>,
+}
+
+impl QueryLogRecord {
+    pub fn new(params: SearchQuery, result_count: usize, latency_ms: f64) -> Self {
+        Self {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+            params,
+            result_count,
+            latency_ms,
+            ip: None,
+        }
+    }
+}
+
+/// Append-only JSONL query log with size-based rotation
+pub struct QueryLog {
+    config: QueryLogConfig,
+    file: Mutex<File>,
+}
+
+impl QueryLog {
+    pub fn open(config: QueryLogConfig) -> Result<Self> {
+        if let Some(parent) = config.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = open_append(&config.path)?;
+        Ok(Self {
+            config,
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Whether client IPs should be recorded
+    pub fn include_ip(&self) -> bool {
+        self.config.include_ip
+    }
+
+    /// Append a record, rotating the file first if it is full
+    pub fn log(&self, record: &QueryLogRecord) -> Result<()> {
+        let mut file = self
+            .file
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Query log lock poisoned"))?;
+
+        if file.metadata()?.len() >= self.config.max_bytes {
+            rotate(&self.config.path, self.config.max_files)?;
+            *file = open_append(&self.config.path)?;
+        }
+
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
+
+fn open_append(path: &Path) -> Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .context(format!("Failed to open query log: {:?}", path))
+}
+
+/// Path of the `n`th rotated file
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+/// Shift `path` to `path.1`, `path.1` to `path.2`, ..., dropping the oldest
+fn rotate(path: &Path, max_files: usize) -> Result<()> {
+    if max_files == 0 {
+        fs::remove_file(path)?;
+        return Ok(());
+    }
+    let oldest = rotated_path(path, max_files);
+    if oldest.exists() {
+        fs::remove_file(&oldest)?;
+    }
+    for n in (1..max_files).rev() {
+        let from = rotated_path(path, n);
+        if from.exists() {
+            fs::rename(&from, rotated_path(path, n + 1))?;
+        }
+    }
+    fs::rename(path, rotated_path(path, 1))?;
+    Ok(())
+}
+
+/// Summary of a query log
+#[derive(Debug, Default)]
+pub struct LogAnalysis {
+    pub total_queries: usize,
+    pub average_latency_ms: f64,
+    /// Queries that returned nothing, most frequent first
+    pub zero_result_queries: Vec<(String, usize)>,
+    /// Slowest searches, slowest first
+    pub slow_queries: Vec<QueryLogRecord>,
+}
+
+/// Summarize the log at `path` and its rotated files, keeping the `top` entries of each list
+pub fn analyze<P: AsRef<Path>>(path: P, top: usize) -> Result<LogAnalysis> {
+    let path = path.as_ref();
+    let mut files = vec![path.to_path_buf()];
+    files.extend(
+        (1..)
+            .map(|n| rotated_path(path, n))
+            .take_while(|rotated| rotated.exists()),
+    );
+
+    let mut analysis = LogAnalysis::default();
+    let mut misses: HashMap<String, usize> = HashMap::new();
+    let mut records = Vec::new();
+    let mut total_latency = 0.0;
+
+    for file in files.iter().filter(|f| f.exists()) {
+        let reader = BufReader::new(
+            File::open(file).context(format!("Failed to open query log: {:?}", file))?,
+        );
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: QueryLogRecord = serde_json::from_str(&line)
+                .context(format!("Invalid query log line in {:?}", file))?;
+
+            analysis.total_queries += 1;
+            total_latency += record.latency_ms;
+            if record.result_count == 0 {
+                *misses.entry(record.params.q.to_lowercase()).or_default() += 1;
+            }
+            records.push(record);
+        }
+    }
+
+    if analysis.total_queries > 0 {
+        analysis.average_latency_ms = total_latency / analysis.total_queries as f64;
+    }
+
+    let mut misses: Vec<(String, usize)> = misses.into_iter().collect();
+    misses.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    misses.truncate(top);
+    analysis.zero_result_queries = misses;
+
+    records.sort_by(|a, b| b.latency_ms.total_cmp(&a.latency_ms));
+    records.truncate(top);
+    analysis.slow_queries = records;
+
+    Ok(analysis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn record(q: &str, result_count: usize, latency_ms: f64) -> QueryLogRecord {
+        let params: SearchQuery = serde_json::from_value(serde_json::json!({ "q": q })).unwrap();
+        QueryLogRecord::new(params, result_count, latency_ms)
+    }
+
+    #[test]
+    fn test_log_rotates_and_analyze_reads_all_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("queries.jsonl");
+        let log = QueryLog::open(QueryLogConfig {
+            max_bytes: 1,
+            max_files: 2,
+            ..QueryLogConfig::new(path.clone())
+        })
+        .unwrap();
+
+        log.log(&record("Haus", 1, 2.0)).unwrap();
+        log.log(&record("Hauss", 0, 9.0)).unwrap();
+        log.log(&record("hauss", 0, 4.0)).unwrap();
+        log.log(&record("Xyz", 0, 1.0)).unwrap();
+
+        // Every write after the first rotates, only two rotated files are kept
+        assert!(rotated_path(&path, 2).exists());
+        assert!(!rotated_path(&path, 3).exists());
+
+        let analysis = analyze(&path, 1).unwrap();
+        assert_eq!(analysis.total_queries, 3);
+        assert_eq!(analysis.zero_result_queries, vec![("hauss".to_string(), 2)]);
+        assert_eq!(analysis.slow_queries[0].params.q, "Hauss");
+        assert!(analysis.slow_queries[0].ip.is_none());
+    }
+}
@@ -0,0 +1,259 @@
+use anyhow::Result;
+use std::collections::HashSet;
+
+use crate::models::{LanguagePair, QuizQuestion};
+use crate::search::SearchEngine;
+use crate::studylist::shuffle;
+
+/// Wrong answers offered with each multiple-choice question
+pub const DISTRACTORS: usize = 3;
+
+/// Words on either side of a question's word, by frequency, that its
+/// distractors are picked from
+const DISTRACTOR_WINDOW: usize = 8;
+
+/// A headword with the translation a quiz asks for
+struct Candidate {
+    word: String,
+    translation: String,
+    frequency_rank: Option<u32>,
+}
+
+/// Entries sampled from the index per question asked for, to pick the
+/// questions and their distractors from
+const SAMPLE_PER_QUESTION: usize = 16;
+
+/// Multiple-choice questions for `count` words sampled from a language's
+/// headwords, with the word's first translation as the answer.
+///
+/// Distractors are translations of sampled words with a similar frequency
+/// rank, so common words are not paired with obscure ones; without frequency
+/// data they are random headwords. The same seed yields the same quiz.
+/// Non-empty `sources` limit the dictionaries the words come from.
+pub fn build_quiz(
+    engine: &SearchEngine,
+    language: LanguagePair,
+    sources: &[String],
+    count: usize,
+    seed: u64,
+) -> Result<Vec<QuizQuestion>> {
+    let sample = count
+        .saturating_mul(SAMPLE_PER_QUESTION)
+        .max(DISTRACTOR_WINDOW * 2 + 1);
+    let mut seen = HashSet::new();
+    let mut candidates: Vec<Candidate> = engine
+        .sample_entries(language, sources, sample, seed)?
+        .into_iter()
+        .filter(|entry| seen.insert(entry.word.clone()))
+        .filter_map(|entry| {
+            Some(Candidate {
+                translation: translation(&entry.definition)?,
+                frequency_rank: engine.frequency_rank(language, &entry.word),
+                word: entry.word,
+            })
+        })
+        .collect();
+
+    // Shuffle before the stable sort, so words without a rank end up in a
+    // random order after the ranked ones
+    shuffle(&mut candidates, seed);
+    candidates.sort_by_key(|candidate| candidate.frequency_rank.unwrap_or(u32::MAX));
+
+    let mut order: Vec<usize> = (0..candidates.len()).collect();
+    shuffle(&mut order, seed.wrapping_add(1));
+
+    let mut questions = Vec::new();
+    for (question, &position) in order.iter().enumerate() {
+        if questions.len() == count {
+            break;
+        }
+        let candidate = &candidates[position];
+        let start = position.saturating_sub(DISTRACTOR_WINDOW);
+        let end = (position + DISTRACTOR_WINDOW + 1).min(candidates.len());
+        let mut nearby: Vec<&Candidate> = candidates[start..end]
+            .iter()
+            .filter(|other| {
+                !other
+                    .translation
+                    .eq_ignore_ascii_case(&candidate.translation)
+            })
+            .collect();
+        shuffle(&mut nearby, seed ^ question as u64);
+
+        let mut distractors: Vec<String> = Vec::new();
+        for other in nearby {
+            if distractors.len() == DISTRACTORS {
+                break;
+            }
+            if !distractors
+                .iter()
+                .any(|distractor| distractor.eq_ignore_ascii_case(&other.translation))
+            {
+                distractors.push(other.translation.clone());
+            }
+        }
+        if distractors.len() < DISTRACTORS {
+            continue;
+        }
+
+        questions.push(QuizQuestion {
+            word: candidate.word.clone(),
+            answer: candidate.translation.clone(),
+            distractors,
+            frequency_rank: candidate.frequency_rank,
+        });
+    }
+    Ok(questions)
+}
+
+/// First sense of a definition without its grammar and domain labels:
+/// "house" for "<n, neut> house [archi.], building"
+fn translation(definition: &str) -> Option<String> {
+    let mut plain = String::with_capacity(definition.len());
+    let mut depth = 0usize;
+    for c in definition.chars() {
+        match c {
+            '<' | '[' | '{' | '(' => depth += 1,
+            '>' | ']' | '}' | ')' => depth = depth.saturating_sub(1),
+            _ if depth == 0 => plain.push(c),
+            _ => {}
+        }
+    }
+    plain
+        .split([';', ','])
+        .map(|sense| sense.split_whitespace().collect::<Vec<_>>().join(" "))
+        .find(|sense| !sense.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frequency::FrequencyList;
+    use crate::models::DictionaryEntry;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_translation() {
+        assert_eq!(
+            translation("<n, neut> house [archi.], building").as_deref(),
+            Some("house")
+        );
+        assert_eq!(translation("(sich) ; to wash").as_deref(), Some("to wash"));
+        assert_eq!(translation("<n> [archi.]"), None);
+    }
+
+    #[test]
+    fn test_build_quiz_picks_distractors_of_similar_frequency() {
+        let temp_dir = TempDir::new().unwrap();
+        let index_dir = temp_dir.path().join("index");
+        let frequency_dir = temp_dir.path().join("frequency");
+        std::fs::create_dir_all(&frequency_dir).unwrap();
+        let words = [
+            ("sein", "to be"),
+            ("haben", "to have"),
+            ("gehen", "to go"),
+            ("Haus", "house"),
+            ("Hund", "dog"),
+            ("Katze", "cat"),
+            ("Baum", "tree"),
+            ("Buch", "book"),
+            ("Tisch", "table"),
+            ("Stuhl", "chair"),
+            ("Wald", "forest"),
+            ("Fenster", "window"),
+        ];
+        let ranked: Vec<String> = words.iter().map(|(w, _)| w.to_lowercase()).collect();
+        std::fs::write(frequency_dir.join("de-en.txt"), ranked.join("\n")).unwrap();
+
+        let mut entries: Vec<DictionaryEntry> = words
+            .iter()
+            .map(|(word, definition)| {
+                DictionaryEntry::new(word.to_string(), definition.to_string(), "de-en".into())
+            })
+            .collect();
+        entries.push(DictionaryEntry::new(
+            "Fenster".into(),
+            "<n> pane".into(),
+            "de-en".into(),
+        ));
+        entries.push(DictionaryEntry::new(
+            "Ofen".into(),
+            "<m> [tech.]".into(),
+            "de-en".into(),
+        ));
+        SearchEngine::build_index(&index_dir, entries).unwrap();
+        let engine = SearchEngine::new(&index_dir)
+            .unwrap()
+            .with_frequencies(FrequencyList::load_dir(&frequency_dir).unwrap());
+
+        let quiz = build_quiz(&engine, LanguagePair::DE_EN, &[], 5, 42).unwrap();
+        assert_eq!(quiz.len(), 5);
+        assert_eq!(
+            quiz,
+            build_quiz(&engine, LanguagePair::DE_EN, &[], 5, 42).unwrap()
+        );
+
+        let rank_of = |translation: &str| {
+            words
+                .iter()
+                .position(|(_, definition)| *definition == translation)
+                .unwrap() as i64
+                + 1
+        };
+        for question in &quiz {
+            assert_ne!(question.word, "ofen");
+            assert_eq!(question.distractors.len(), DISTRACTORS);
+            assert!(!question.distractors.contains(&question.answer));
+            assert_ne!(question.answer, "pane");
+            let rank = question.frequency_rank.unwrap() as i64;
+            assert_eq!(rank_of(&question.answer), rank);
+            for distractor in &question.distractors {
+                assert!((rank_of(distractor) - rank).abs() <= DISTRACTOR_WINDOW as i64);
+            }
+        }
+
+        // Twelve words cannot fill more than twelve questions
+        assert_eq!(
+            build_quiz(&engine, LanguagePair::DE_EN, &[], 50, 1)
+                .unwrap()
+                .len(),
+            12
+        );
+        assert!(
+            build_quiz(&engine, LanguagePair::EN_DE, &[], 5, 1)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_build_quiz_asks_only_words_of_the_searched_dictionaries() {
+        let temp_dir = TempDir::new().unwrap();
+        let entries = [
+            ("Haus", "house", "ding"),
+            ("Hund", "dog", "ding"),
+            ("Katze", "cat", "ding"),
+            ("Baum", "tree", "ding"),
+            ("Buch", "book", "freedict"),
+            ("Tisch", "table", "freedict"),
+        ]
+        .iter()
+        .map(|(word, definition, source)| {
+            let mut entry =
+                DictionaryEntry::new(word.to_string(), definition.to_string(), "de-en".into());
+            entry.source = Some(source.to_string());
+            entry
+        })
+        .collect();
+        SearchEngine::build_index(temp_dir.path(), entries).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let quiz = build_quiz(&engine, LanguagePair::DE_EN, &["ding".to_string()], 10, 7).unwrap();
+        assert_eq!(quiz.len(), 4);
+        for question in &quiz {
+            for translation in question.distractors.iter().chain([&question.answer]) {
+                assert!(["house", "dog", "cat", "tree"].contains(&translation.as_str()));
+            }
+        }
+    }
+}
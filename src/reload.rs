@@ -0,0 +1,91 @@
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tantivy::{Searcher, SearcherGeneration, Warmer};
+
+use crate::models::ReloadStats;
+
+/// Reads the term dictionaries and fast fields of every segment of a new
+/// searcher. The reader only swaps a searcher in once its warmers finished,
+/// so the first searches after a reload do not pay for cold pages.
+pub struct IndexWarmer;
+
+impl Warmer for IndexWarmer {
+    fn warm(&self, searcher: &Searcher) -> tantivy::Result<()> {
+        let schema = searcher.schema();
+        for segment_reader in searcher.segment_readers() {
+            for (field, entry) in schema.fields() {
+                if entry.is_indexed() {
+                    let inverted_index = segment_reader.inverted_index(field)?;
+                    let mut terms = inverted_index.terms().stream()?;
+                    while terms.advance() {}
+                }
+                if entry.is_fast() {
+                    for column in segment_reader
+                        .fast_fields()
+                        .dynamic_column_handles(entry.name())?
+                    {
+                        column.open()?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn garbage_collect(&self, _live_generations: &[&SearcherGeneration]) {}
+}
+
+/// Durations of the reloads since the engine opened the index
+#[derive(Default)]
+pub struct ReloadMetrics {
+    stats: Mutex<ReloadStats>,
+}
+
+impl ReloadMetrics {
+    /// Count a reload that took `duration`, or that failed and kept the old searcher
+    pub fn record(&self, duration: Duration, succeeded: bool) {
+        let Ok(mut stats) = self.stats.lock() else {
+            return;
+        };
+        if !succeeded {
+            stats.failed += 1;
+            return;
+        }
+        let millis = duration.as_secs_f64() * 1000.0;
+        stats.reloads += 1;
+        stats.total_ms += millis;
+        stats.last_ms = Some(millis);
+        stats.max_ms = stats.max_ms.max(millis);
+        stats.last_reload_unix = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .ok()
+            .map(|since| since.as_secs());
+    }
+
+    pub fn stats(&self) -> ReloadStats {
+        self.stats
+            .lock()
+            .map(|stats| stats.clone())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reload_metrics() {
+        let metrics = ReloadMetrics::default();
+        metrics.record(Duration::from_millis(30), true);
+        metrics.record(Duration::from_millis(10), true);
+        metrics.record(Duration::from_millis(500), false);
+
+        let stats = metrics.stats();
+        assert_eq!((stats.reloads, stats.failed), (2, 1));
+        assert_eq!(stats.last_ms, Some(10.0));
+        assert_eq!(stats.max_ms, 30.0);
+        assert_eq!(stats.total_ms, 40.0);
+        assert!(stats.last_reload_unix.is_some());
+    }
+}
@@ -0,0 +1,56 @@
+/// Letters treated as vowels when splitting a word into syllables
+fn is_vowel(c: char) -> bool {
+    matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'y' | 'ä' | 'ö' | 'ü')
+}
+
+/// The part of a word that has to match for another word to rhyme with it:
+/// the last vowel group and everything after it, reaching back one more
+/// vowel group when the last one is an unstressed "e" ("Hause" -> "ause",
+/// "Haus" -> "aus", "singen" -> "ingen"). Empty when the word has no vowel.
+pub fn rhyme_suffix(word: &str) -> &str {
+    let vowel_groups = vowel_group_starts(word);
+    let Some(&(last, last_vowels)) = vowel_groups.last() else {
+        return "";
+    };
+    let start = match vowel_groups.len() {
+        n if n > 1 && last_vowels == "e" => vowel_groups[n - 2].0,
+        _ => last,
+    };
+    &word[start..]
+}
+
+/// Byte offset and text of every run of consecutive vowels
+fn vowel_group_starts(word: &str) -> Vec<(usize, &str)> {
+    let mut groups = Vec::new();
+    let mut start = None;
+    for (i, c) in word.char_indices() {
+        match (is_vowel(c), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                groups.push((s, &word[s..i]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        groups.push((s, &word[s..]));
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rhyme_suffix() {
+        assert_eq!(rhyme_suffix("haus"), "aus");
+        assert_eq!(rhyme_suffix("hause"), "ause");
+        assert_eq!(rhyme_suffix("singen"), "ingen");
+        assert_eq!(rhyme_suffix("leiter"), "eiter");
+        assert_eq!(rhyme_suffix("see"), "ee");
+        assert_eq!(rhyme_suffix("häuser"), "äuser");
+        assert_eq!(rhyme_suffix("psst"), "");
+    }
+}
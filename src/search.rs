@@ -1,46 +1,442 @@
-use anyhow::{Context, Result};
+use anyhow::Result;
+use serde::Deserialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::hash::{Hash, Hasher};
+use std::ops::Bound;
 use std::path::Path;
-use tantivy::collector::TopDocs;
-use tantivy::query::{BooleanQuery, FuzzyTermQuery, Occur, Query, RegexQuery};
-use tantivy::schema::{STORED, STRING, Schema, TextFieldIndexing, TextOptions, Value};
-use tantivy::tokenizer::{AsciiFoldingFilter, LowerCaser, SimpleTokenizer, TextAnalyzer};
-use tantivy::{Index, IndexReader, IndexWriter, ReloadPolicy, TantivyDocument, Term, doc};
-use tracing::info;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::Instant;
+use tantivy::collector::{Collector, Count, DocSetCollector, SegmentCollector, TopDocs};
+use tantivy::directory::{Directory, WatchCallback, WatchHandle};
+use tantivy::postings::TermInfo;
+use tantivy::query::{
+    BooleanQuery, ConstScoreQuery, EmptyQuery, FuzzyTermQuery, Occur, Query, QueryParser,
+    RangeQuery, RegexQuery, TermQuery, Weight,
+};
+use tantivy::schema::{
+    FAST, Field, INDEXED, IndexRecordOption, STORED, STRING, Schema, TextFieldIndexing,
+    TextOptions, Value,
+};
+use tantivy::store::StoreReader;
+use tantivy::termdict::TermMerger;
+use tantivy::tokenizer::{
+    AsciiFoldingFilter, LowerCaser, NgramTokenizer, RawTokenizer, SimpleTokenizer, TextAnalyzer,
+};
+use tantivy::{
+    DocAddress, DocId, DocSet, Index, IndexReader, IndexWriter, InvertedIndexReader, ReloadPolicy,
+    Score, Searcher, SegmentReader, TERMINATED, TantivyDocument, Term, Warmer, doc,
+};
+use tracing::{info, warn};
+
+use crate::frequency::FrequencyList;
+use crate::glosslang;
+use crate::headwords::{HeadwordLookup, HeadwordQuery};
+use crate::models::{
+    BrowseDirection, Cancellation, DictionaryEntry, Grouping, IndexMemory, LanguageCount,
+    LanguagePair, Ranking, Register, ReloadStats, ResultSource, SearchMode, SearchOptions,
+    SearchResult, SearchTimings, SourceLicense,
+};
+use crate::normalize::{self, normalize};
+use crate::reload::{IndexWarmer, ReloadMetrics};
+use crate::rhyme;
+use crate::stem::stem;
+use crate::studylist::shuffle;
+
+/// Minimum length of an edge n-gram indexed in the autocomplete field
+pub const EDGE_NGRAM_MIN: usize = 2;
+
+/// Maximum length of an edge n-gram indexed in the autocomplete field
+pub const EDGE_NGRAM_MAX: usize = 15;
+
+/// Queries shorter than this are capped at an edit distance of 1
+pub const SHORT_QUERY_LEN: usize = 4;
+
+/// Options controlling how the index is built
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IndexOptions {
+    /// Index an edge n-gram field for fast prefix/autocomplete lookups.
+    /// Disabled by default because it noticeably increases index size.
+    pub edge_ngrams: bool,
+}
+
+/// Relevance knobs, read from the `[ranking]` table of config.toml.
+///
+/// The defaults leave Tantivy's ranking untouched.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RankingWeights {
+    /// Added to the score of a result whose headword equals the query
+    pub exact_boost: f32,
+    /// Subtracted from fuzzy scores, indexed by edit distance; the last entry
+    /// applies to larger distances. Unset, fuzzy results sort by distance first.
+    pub edit_distance_penalty: Option<Vec<f32>>,
+    /// Scales a bonus of 1 / (1 + log10(rank)) for words with a frequency rank
+    pub frequency_weight: f32,
+    /// Dictionary names, most preferred first; orders equally scored results
+    pub source_priority: Vec<String>,
+    /// Added per place a result's dictionary is ahead of those not in `source_priority`
+    pub source_weight: f32,
+}
+
+impl RankingWeights {
+    /// Position of a dictionary in `source_priority`, unlisted ones share the last place
+    fn source_position(&self, source: &str) -> usize {
+        self.source_priority
+            .iter()
+            .position(|s| s == source)
+            .unwrap_or(self.source_priority.len())
+    }
+
+    /// Score adjustment for a result
+    fn adjustment(
+        &self,
+        exact: bool,
+        edit_distance: Option<u8>,
+        frequency_rank: Option<u32>,
+        source_position: usize,
+    ) -> f32 {
+        let mut adjustment = 0.0;
+        if exact {
+            adjustment += self.exact_boost;
+        }
+        if let (Some(penalties), Some(distance)) = (&self.edit_distance_penalty, edit_distance)
+            && let Some(last) = penalties.last()
+        {
+            adjustment -= penalties.get(distance as usize).unwrap_or(last);
+        }
+        if let Some(rank) = frequency_rank {
+            adjustment += self.frequency_weight / (1.0 + (rank.max(1) as f32).log10());
+        }
+        adjustment += self.source_weight * (self.source_priority.len() - source_position) as f32;
+        adjustment
+    }
+}
+
+/// A query the user wrote that cannot be parsed, as opposed to a failure of the index
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid definition query: {0}")]
+pub struct InvalidQuery(String);
+
+/// The search was stopped through `SearchOptions::cancellation`
+#[derive(Debug, thiserror::Error)]
+#[error("Search cancelled")]
+pub struct SearchCancelled;
+
+/// Matches scored between two checks of a search's deadline and cancellation
+const DEADLINE_CHECK_INTERVAL: u32 = 1024;
+
+/// Wraps a collector so a cancelled search stops collecting, even partway
+/// through a segment, and one past its deadline stops collecting, keeping the
+/// matches collected so far
+struct Cancellable<C> {
+    inner: C,
+    cancellation: Cancellation,
+    deadline: Option<Instant>,
+    /// Set once the deadline cut collection short
+    partial: AtomicBool,
+}
+
+impl<C: Collector> Collector for Cancellable<C> {
+    type Fruit = C::Fruit;
+    type Child = C::Child;
+
+    fn for_segment(&self, segment_ord: u32, segment: &SegmentReader) -> tantivy::Result<C::Child> {
+        self.inner.for_segment(segment_ord, segment)
+    }
+
+    fn requires_scoring(&self) -> bool {
+        self.inner.requires_scoring()
+    }
+
+    fn merge_fruits(
+        &self,
+        fruits: Vec<<C::Child as SegmentCollector>::Fruit>,
+    ) -> tantivy::Result<C::Fruit> {
+        self.inner.merge_fruits(fruits)
+    }
+
+    fn collect_segment(
+        &self,
+        weight: &dyn Weight,
+        segment_ord: u32,
+        reader: &SegmentReader,
+    ) -> tantivy::Result<<C::Child as SegmentCollector>::Fruit> {
+        let cancelled = || tantivy::TantivyError::InternalError(SearchCancelled.to_string());
+        let past_deadline = || {
+            self.deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+        };
+        if self.cancellation.is_cancelled() {
+            return Err(cancelled());
+        }
+
+        // Walk the scorer ourselves so collection can stop partway through a segment
+        let mut child = self.inner.for_segment(segment_ord, reader)?;
+        if past_deadline() {
+            self.partial.store(true, Ordering::Relaxed);
+            return Ok(child.harvest());
+        }
+        let mut scorer = weight.scorer(reader, 1.0)?;
+        let alive_bitset = reader.alive_bitset();
+        let mut doc = scorer.doc();
+        let mut scored = 0u32;
+        while doc != TERMINATED {
+            if alive_bitset.is_none_or(|alive| alive.is_alive(doc)) {
+                child.collect(doc, scorer.score());
+            }
+            scored += 1;
+            if scored.is_multiple_of(DEADLINE_CHECK_INTERVAL) {
+                if self.cancellation.is_cancelled() {
+                    return Err(cancelled());
+                }
+                if past_deadline() {
+                    self.partial.store(true, Ordering::Relaxed);
+                    break;
+                }
+            }
+            doc = scorer.advance();
+        }
+        Ok(child.harvest())
+    }
+}
+
+/// Blocks of stored documents each segment's filter keeps decompressed
+const FILTER_STORE_CACHE_BLOCKS: usize = 100;
+
+/// Check on a candidate's stored document, deciding whether it is collected
+type DocPredicate = Arc<dyn Fn(&TantivyDocument) -> bool + Send + Sync>;
+
+/// Wraps a collector so only documents passing `keep` reach it. Candidates
+/// are checked before they can take a place among the top hits, so hits
+/// failing a filter cannot crowd out the ones passing it.
+struct Filtered<C> {
+    inner: C,
+    keep: Option<DocPredicate>,
+}
+
+struct FilteredSegment<S> {
+    inner: S,
+    /// With the predicate, absent when every candidate is kept
+    store: Option<(StoreReader, DocPredicate)>,
+}
+
+impl<C: Collector> Collector for Filtered<C> {
+    type Fruit = C::Fruit;
+    type Child = FilteredSegment<C::Child>;
+
+    fn for_segment(
+        &self,
+        segment_ord: u32,
+        segment: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        let store = match &self.keep {
+            Some(keep) => Some((
+                segment.get_store_reader(FILTER_STORE_CACHE_BLOCKS)?,
+                keep.clone(),
+            )),
+            None => None,
+        };
+        Ok(FilteredSegment {
+            inner: self.inner.for_segment(segment_ord, segment)?,
+            store,
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        self.inner.requires_scoring()
+    }
+
+    fn merge_fruits(
+        &self,
+        fruits: Vec<<C::Child as SegmentCollector>::Fruit>,
+    ) -> tantivy::Result<C::Fruit> {
+        self.inner.merge_fruits(fruits)
+    }
+}
+
+impl<S: SegmentCollector> SegmentCollector for FilteredSegment<S> {
+    type Fruit = S::Fruit;
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        if let Some((store, keep)) = &self.store {
+            let kept = store
+                .get::<TantivyDocument>(doc)
+                .is_ok_and(|document| keep(&document));
+            if !kept {
+                return;
+            }
+        }
+        self.inner.collect(doc, score);
+    }
+
+    fn harvest(self) -> S::Fruit {
+        self.inner.harvest()
+    }
+}
+
+/// Results of a search along with notes about how the query was interpreted
+#[derive(Debug, Clone, Default)]
+pub struct SearchOutput {
+    pub results: Vec<SearchResult>,
+    pub notes: Vec<String>,
+    pub timings: SearchTimings,
+    /// The results are from the reverse language direction, the requested one had none
+    pub flipped_direction: bool,
+    /// Duplicate definitions dropped while grouping results by word
+    pub merged_definitions: usize,
+    /// Index contents the results come from, see `index_generation`
+    pub index_generation: String,
+    /// The deadline stopped collection early, better matches may be missing
+    pub partial: bool,
+}
+
+/// Entries removed by `SearchEngine::purge`, every given criterion must match
+#[derive(Debug, Clone, Default)]
+pub struct PurgeFilter {
+    /// Name of the dictionary the entries were imported from
+    pub source: Option<String>,
+    pub language: Option<LanguagePair>,
+    /// Start of the headword, compared after normalizing and lowercasing it
+    pub word_prefix: Option<String>,
+}
+
+impl PurgeFilter {
+    fn is_empty(&self) -> bool {
+        self.source.is_none() && self.language.is_none() && self.headword_prefix().is_none()
+    }
 
-use crate::models::{DictionaryEntry, Language, SearchMode, SearchResult};
+    /// The normalized word prefix, absent when none or a blank one was given,
+    /// since a blank prefix would match every headword
+    pub fn headword_prefix(&self) -> Option<String> {
+        self.word_prefix
+            .as_deref()
+            .map(|prefix| normalize(prefix).trim().to_lowercase())
+            .filter(|prefix| !prefix.is_empty())
+    }
+}
 
 /// Search engine powered by Tantivy
 pub struct SearchEngine {
-    #[allow(dead_code)]
     index: Index,
     reader: IndexReader,
     schema: Schema,
+    /// Shared with the frequency rank filter of running searches
+    frequencies: Arc<FrequencyList>,
+    /// Dictionaries excluded from every search
+    disabled_sources: Vec<String>,
+    /// Licenses of the imported dictionaries, attached to results
+    licenses: Vec<SourceLicense>,
+    ranking: RankingWeights,
+    /// Kept alive for as long as the reader should warm new searchers
+    _warmer: Arc<IndexWarmer>,
+    /// Serves exact and prefix lookups without running a query
+    headwords: Arc<HeadwordLookup>,
+    reload_metrics: Arc<ReloadMetrics>,
+    /// Reloads the reader when a commit changes the index
+    _watch: WatchHandle,
 }
 
 impl SearchEngine {
     /// Create a new search engine with the given index directory
     pub fn new<P: AsRef<Path>>(index_path: P) -> Result<Self> {
-        let schema = build_schema();
         let mut index = Index::open_in_dir(index_path)?;
+        // Use the on-disk schema, optional fields depend on how the index was built
+        let schema = index.schema();
 
         // Register custom tokenizer with ASCII folding for diacritic support
         register_tokenizer(&mut index);
 
-        let reader = index
+        // New searchers are warmed before the reader hands them out
+        let warmer = Arc::new(IndexWarmer);
+        let headwords = Arc::new(HeadwordLookup::new(
+            schema.get_field("word")?,
+            schema.get_field("word_variants").ok(),
+            schema.get_field("language")?,
+        ));
+        let reader: IndexReader = index
             .reader_builder()
-            .reload_policy(ReloadPolicy::OnCommitWithDelay)
+            .reload_policy(ReloadPolicy::Manual)
+            .warmers(vec![
+                Arc::downgrade(&warmer) as Weak<dyn Warmer>,
+                Arc::downgrade(&headwords) as Weak<dyn Warmer>,
+            ])
             .try_into()?;
 
+        let reload_metrics = Arc::new(ReloadMetrics::default());
+        let watch = index.directory().watch(WatchCallback::new({
+            let (reader, reload_metrics) = (reader.clone(), reload_metrics.clone());
+            move || {
+                if let Err(e) = timed_reload(&reader, &reload_metrics) {
+                    warn!(
+                        "Reloading the index failed, still serving the previous contents: {}",
+                        e
+                    );
+                }
+            }
+        }))?;
+
         Ok(Self {
             index,
             reader,
             schema,
+            frequencies: Arc::new(FrequencyList::default()),
+            disabled_sources: Vec::new(),
+            licenses: Vec::new(),
+            ranking: RankingWeights::default(),
+            _warmer: warmer,
+            headwords,
+            reload_metrics,
+            _watch: watch,
         })
     }
 
+    /// Attach word frequency data used to annotate and filter results
+    pub fn with_frequencies(mut self, frequencies: FrequencyList) -> Self {
+        self.frequencies = Arc::new(frequencies);
+        self
+    }
+
+    /// Exclude the named dictionaries from search results
+    pub fn with_disabled_sources(mut self, disabled_sources: Vec<String>) -> Self {
+        self.disabled_sources = disabled_sources;
+        self
+    }
+
+    /// Attach the licenses of the imported dictionaries to results from them
+    pub fn with_licenses(mut self, licenses: Vec<SourceLicense>) -> Self {
+        self.licenses = licenses;
+        self
+    }
+
+    /// Licenses of the searchable dictionaries
+    pub fn licenses(&self) -> &[SourceLicense] {
+        &self.licenses
+    }
+
+    /// License of the named dictionary, if recorded
+    pub fn license(&self, source: &str) -> Option<String> {
+        self.licenses
+            .iter()
+            .find(|l| l.source == source)
+            .and_then(|l| l.license.clone())
+    }
+
+    /// Adjust result ranking with configured weights
+    pub fn with_ranking(mut self, ranking: RankingWeights) -> Self {
+        self.ranking = ranking;
+        self
+    }
+
+    /// Frequency rank of a word (1 = most frequent), if known
+    pub fn frequency_rank(&self, language: LanguagePair, word: &str) -> Option<u32> {
+        self.frequencies.rank(language.as_str(), word)
+    }
+
     /// Create a new index at the given path
     pub fn _create_index<P: AsRef<Path>>(index_path: P) -> Result<Index> {
-        let schema = build_schema();
+        let schema = build_schema(&IndexOptions::default());
         std::fs::create_dir_all(index_path.as_ref())?;
         let index = Index::create_in_dir(index_path, schema)?;
         Ok(index)
@@ -48,33 +444,113 @@ impl SearchEngine {
 
     /// Build the index from dictionary entries
     pub fn build_index<P: AsRef<Path>>(index_path: P, entries: Vec<DictionaryEntry>) -> Result<()> {
+        Self::build_index_with_options(index_path, entries, &IndexOptions::default())
+    }
+
+    /// Build the index from dictionary entries using the given options
+    pub fn build_index_with_options<P: AsRef<Path>>(
+        index_path: P,
+        entries: Vec<DictionaryEntry>,
+        options: &IndexOptions,
+    ) -> Result<()> {
         info!("Building index with {} entries", entries.len());
+        Self::build_index_from_batches(index_path, std::iter::once(entries), options)?;
+        Ok(())
+    }
 
-        let schema = build_schema();
+    /// Build the index from batches of entries as they arrive (e.g. from a
+    /// parser thread), returning the number of entries indexed
+    pub fn build_index_from_batches<P, I>(
+        index_path: P,
+        batches: I,
+        options: &IndexOptions,
+    ) -> Result<usize>
+    where
+        P: AsRef<Path>,
+        I: IntoIterator<Item = Vec<DictionaryEntry>>,
+    {
+        let schema = build_schema(options);
         std::fs::create_dir_all(index_path.as_ref())?;
-        let mut index = Index::create_in_dir(index_path, schema.clone())?;
+        let mut index = Index::create_in_dir(index_path, schema)?;
 
         // Register custom tokenizer with ASCII folding for diacritic support
         register_tokenizer(&mut index);
 
-        let word_field = schema.get_field("word").unwrap();
-        let definition_field = schema.get_field("definition").unwrap();
-        let language_field = schema.get_field("language").unwrap();
+        let total = write_entries(&index, batches)?;
+        info!("Index built successfully with {} entries", total);
 
-        let mut writer: IndexWriter = index.writer(100_000_000)?;
+        Ok(total)
+    }
 
-        for entry in entries {
-            writer.add_document(doc!(
-                word_field => entry.word.to_lowercase(),
-                definition_field => entry.definition,
-                language_field => entry.language,
-            ))?;
-        }
+    /// Add batches of entries to an existing index, returning the number of
+    /// entries added. Searchers over the index see them once they reload.
+    pub fn add_to_index_from_batches<P, I>(index_path: P, batches: I) -> Result<usize>
+    where
+        P: AsRef<Path>,
+        I: IntoIterator<Item = Vec<DictionaryEntry>>,
+    {
+        let mut index = Index::open_in_dir(index_path)?;
+        register_tokenizer(&mut index);
 
-        writer.commit()?;
-        info!("Index built successfully");
+        let total = write_entries(&index, batches)?;
+        info!("Added {} entries to the index", total);
 
-        Ok(())
+        Ok(total)
+    }
+
+    /// Delete the entries matching `filter` from an existing index without
+    /// rebuilding it, returning how many matched. With `dry_run` they are only
+    /// counted. Searchers over the index see the deletion once they reload.
+    pub fn purge<P: AsRef<Path>>(
+        index_path: P,
+        filter: &PurgeFilter,
+        dry_run: bool,
+    ) -> Result<usize> {
+        if filter.is_empty() {
+            anyhow::bail!("Refusing to purge every entry, give a source, language or word prefix");
+        }
+        let mut index = Index::open_in_dir(index_path)?;
+        register_tokenizer(&mut index);
+        let schema = index.schema();
+        let searcher = index.reader()?.searcher();
+
+        let term_query = |field: &str, value: &str| -> Result<Box<dyn Query>> {
+            let term = Term::from_field_text(schema.get_field(field)?, value);
+            Ok(Box::new(TermQuery::new(term, IndexRecordOption::Basic)))
+        };
+        let mut clauses = Vec::new();
+        if let Some(source) = &filter.source {
+            clauses.push((Occur::Must, term_query("source", source)?));
+        }
+        if let Some(language) = filter.language {
+            clauses.push((Occur::Must, term_query("language", language.as_str())?));
+        }
+        if let Some(prefix) = filter.headword_prefix() {
+            let keys = headword_keys_with_prefix(
+                &searcher,
+                schema.get_field("headword")?,
+                filter.language,
+                &prefix,
+            )?;
+            if keys.is_empty() {
+                return Ok(0);
+            }
+            let headwords = keys
+                .iter()
+                .map(|key| Ok((Occur::Should, term_query("headword", key)?)))
+                .collect::<Result<Vec<_>>>()?;
+            clauses.push((Occur::Must, Box::new(BooleanQuery::new(headwords))));
+        }
+        let query = BooleanQuery::new(clauses);
+
+        let count = searcher.search(&query, &Count)?;
+        if !dry_run && count > 0 {
+            let mut writer: IndexWriter = index.writer(50_000_000)?;
+            writer.delete_query(Box::new(query))?;
+            writer.commit()?;
+            info!("Purged {} entries from the index", count);
+        }
+        Ok(count)
     }
 
     /// Search for a query
@@ -82,31 +558,171 @@ impl SearchEngine {
         &self,
         query: &str,
         mode: SearchMode,
-        language: Language,
+        language: LanguagePair,
         max_distance: u8,
         limit: usize,
     ) -> Result<Vec<SearchResult>> {
-        let searcher = self.reader.searcher();
+        let options = SearchOptions {
+            mode,
+            language,
+            max_distance,
+            limit,
+            ..SearchOptions::default()
+        };
+        Ok(self.search_with_options(query, &options)?.results)
+    }
+
+    /// Search for a query, reporting any normalization applied to it
+    pub fn search_with_options(
+        &self,
+        query: &str,
+        options: &SearchOptions,
+    ) -> Result<SearchOutput> {
+        self.search_snapshot(&self.reader.searcher(), query, options)
+    }
+
+    /// Searcher over the index contents as of the last reload
+    pub fn searcher(&self) -> Searcher {
+        self.reader.searcher()
+    }
+
+    /// Search the index contents seen by `searcher`, which may be older than
+    /// the latest reload
+    pub fn search_snapshot(
+        &self,
+        searcher: &Searcher,
+        query: &str,
+        options: &SearchOptions,
+    ) -> Result<SearchOutput> {
+        let SearchOptions {
+            mode,
+            language,
+            max_distance,
+            limit,
+            strict_diacritics,
+            auto_distance,
+            group,
+            min_frequency_rank,
+            register,
+            safe,
+            ref sources,
+            target_only,
+            dehyphenate,
+            ref filter,
+            ref within,
+            min_len,
+            max_len,
+            diversify,
+            flip_direction,
+            rank,
+            ref cancellation,
+            deadline,
+        } = *options;
+        if cancellation.is_cancelled() {
+            return Err(SearchCancelled.into());
+        }
+        let mut notes = Vec::new();
+        let mut timings = SearchTimings::default();
+        let started = Instant::now();
+        let raw_query = query;
+
+        let index_generation = index_generation(searcher);
 
         let word_field = self.schema.get_field("word").unwrap();
         let definition_field = self.schema.get_field("definition").unwrap();
         let language_field = self.schema.get_field("language").unwrap();
 
-        let normalized_query = query.to_lowercase();
+        let mut normalized_query = normalize(query).to_lowercase();
         let lang_str = language.as_str();
+        if normalized_query != query.to_lowercase() {
+            notes.push(format!(
+                "Normalized Unicode and typographic punctuation: '{}' -> '{}'",
+                query, normalized_query
+            ));
+        }
+
+        if mode == SearchMode::Exact {
+            // Text copied from prose often carries surrounding punctuation ("Haus.")
+            let stripped = strip_surrounding_punctuation(&normalized_query);
+            if stripped != normalized_query {
+                notes.push(format!(
+                    "Stripped surrounding whitespace and punctuation: '{}' -> '{}'",
+                    query, stripped
+                ));
+                normalized_query = stripped.to_string();
+            }
+            if normalized_query.is_empty() {
+                return Ok(SearchOutput {
+                    notes,
+                    timings,
+                    index_generation,
+                    ..SearchOutput::default()
+                });
+            }
+        }
+
+        // Strict matching bypasses ASCII folding by querying the unfolded field
+        let strict_field = if strict_diacritics {
+            let field = self.schema.get_field("word_strict").ok();
+            if field.is_none() {
+                notes.push(
+                    "Index has no unfolded word field, strict_diacritics ignored (rebuild the index)"
+                        .to_string(),
+                );
+            }
+            field
+        } else {
+            None
+        };
+        let (match_field, term_text) = match strict_field {
+            Some(field) => (field, normalized_query.clone()),
+            None => (word_field, fold_diacritics(&normalized_query)),
+        };
+
+        // Short queries at a high distance match large parts of the dictionary
+        let max_distance = if auto_distance && mode == SearchMode::Fuzzy {
+            let scaled = scale_max_distance(term_text.chars().count(), max_distance);
+            if scaled < max_distance {
+                notes.push(format!(
+                    "max_distance reduced from {} to {} for a short query",
+                    max_distance, scaled
+                ));
+            }
+            scaled
+        } else {
+            max_distance
+        };
 
         let query: Box<dyn Query> = match mode {
             SearchMode::Exact => {
                 // Exact match query
-                let term = Term::from_field_text(word_field, &normalized_query);
-                Box::new(tantivy::query::TermQuery::new(
+                let term = Term::from_field_text(match_field, &term_text);
+                let exact: Box<dyn Query> = Box::new(tantivy::query::TermQuery::new(
                     term,
                     tantivy::schema::IndexRecordOption::Basic,
-                ))
+                ));
+                match self.schema.get_field("word_variants") {
+                    // "Strasse" finds "Straße" and "Müller" finds "Mueller": the query
+                    // and its variants are looked up among the headwords' variants
+                    Ok(variants_field) if strict_field.is_none() => {
+                        let mut spellings = normalize::spelling_variants(&normalized_query);
+                        spellings.push(normalized_query.clone());
+                        let mut clauses = vec![(Occur::Should, exact)];
+                        clauses.extend(spellings.iter().map(|spelling| {
+                            let variant: Box<dyn Query> = Box::new(tantivy::query::TermQuery::new(
+                                Term::from_field_text(variants_field, spelling),
+                                tantivy::schema::IndexRecordOption::Basic,
+                            ));
+                            (Occur::Should, variant)
+                        }));
+                        Box::new(BooleanQuery::new(clauses))
+                    }
+                    _ => exact,
+                }
             }
             SearchMode::Fuzzy => {
                 // Combined query: exact match (boosted) + fuzzy match
-                let term = Term::from_field_text(word_field, &normalized_query);
+                let term = Term::from_field_text(match_field, &term_text);
 
                 // Exact match query (will be prioritized by ranking)
                 let exact_query = tantivy::query::TermQuery::new(
@@ -124,29 +740,268 @@ impl SearchEngine {
                 ]))
             }
             SearchMode::Prefix => {
-                // Prefix query using regex
-                let regex_pattern = format!("{}.*", regex::escape(&normalized_query));
-                Box::new(
-                    RegexQuery::from_pattern(&regex_pattern, word_field)
-                        .context("Failed to create prefix regex query")?,
-                )
+                let prefix_len = term_text.chars().count();
+                match self.schema.get_field("word_ngram") {
+                    // Edge n-grams turn prefix lookup into a single term lookup
+                    Ok(ngram_field)
+                        if strict_field.is_none()
+                            && (EDGE_NGRAM_MIN..=EDGE_NGRAM_MAX).contains(&prefix_len) =>
+                    {
+                        let term = Term::from_field_text(ngram_field, &term_text);
+                        Box::new(tantivy::query::TermQuery::new(
+                            term,
+                            tantivy::schema::IndexRecordOption::Basic,
+                        ))
+                    }
+                    // Otherwise scan the term range [prefix, prefix + U+10FFFF]
+                    _ => {
+                        let upper = format!("{}\u{10FFFF}", term_text);
+                        Box::new(RangeQuery::new_str_bounds(
+                            self.schema.get_field_name(match_field).to_string(),
+                            Bound::Included(term_text.as_str()),
+                            Bound::Included(upper.as_str()),
+                        ))
+                    }
+                }
+            }
+            SearchMode::Definition => {
+                // Not lowercased, "AND", "OR" and "NOT" are only operators in capitals
+                let mut parser = QueryParser::for_index(&self.index, vec![definition_field]);
+                parser.set_conjunction_by_default();
+                let parsed = parser
+                    .parse_query(normalize(query).trim())
+                    .map_err(|e| InvalidQuery(e.to_string()))?;
+                lift_negations(parsed)
             }
+            SearchMode::Pattern => match self.schema.get_field("headword") {
+                Ok(headword_field) => {
+                    let pattern = format!(
+                        "{}{}",
+                        regex::escape(&headword_key(lang_str, "")),
+                        pattern_regex(normalized_query.trim())
+                    );
+                    Box::new(
+                        RegexQuery::from_pattern(&pattern, headword_field)
+                            .map_err(|e| InvalidQuery(e.to_string()))?,
+                    )
+                }
+                Err(_) => {
+                    notes.push(
+                        "Index has no headword keys, pattern search unavailable (rebuild the index)"
+                            .to_string(),
+                    );
+                    Box::new(EmptyQuery)
+                }
+            },
+            SearchMode::Anagram => match self.schema.get_field("anagram_key") {
+                Ok(anagram_field) => Box::new(tantivy::query::TermQuery::new(
+                    Term::from_field_text(anagram_field, &anagram_key(lang_str, &normalized_query)),
+                    tantivy::schema::IndexRecordOption::Basic,
+                )),
+                Err(_) => {
+                    notes.push(
+                        "Index has no anagram keys, anagram search unavailable (rebuild the index)"
+                            .to_string(),
+                    );
+                    Box::new(EmptyQuery)
+                }
+            },
+            SearchMode::Rhyme => {
+                let word = normalized_query.trim();
+                let suffix = rhyme::rhyme_suffix(word);
+                match self.schema.get_field("reversed_headword") {
+                    // Words ending in the rhyme are those whose reversed form starts with it
+                    Ok(reversed_field) if !suffix.is_empty() => {
+                        let lower = reversed_key(lang_str, suffix);
+                        let upper = format!("{}\u{10FFFF}", lower);
+                        let rhymes: Box<dyn Query> = Box::new(RangeQuery::new_str_bounds(
+                            self.schema.get_field_name(reversed_field).to_string(),
+                            Bound::Included(lower.as_str()),
+                            Bound::Included(upper.as_str()),
+                        ));
+                        // The query word rhymes with itself
+                        let itself: Box<dyn Query> = Box::new(tantivy::query::TermQuery::new(
+                            Term::from_field_text(reversed_field, &reversed_key(lang_str, word)),
+                            tantivy::schema::IndexRecordOption::Basic,
+                        ));
+                        Box::new(BooleanQuery::new(vec![
+                            (Occur::Must, rhymes),
+                            (Occur::MustNot, itself),
+                        ]))
+                    }
+                    Ok(_) => Box::new(EmptyQuery),
+                    Err(_) => {
+                        notes.push(
+                            "Index has no reversed headwords, rhyme search unavailable (rebuild the index)"
+                                .to_string(),
+                        );
+                        Box::new(EmptyQuery)
+                    }
+                }
+            }
+        };
+
+        // Filtered inside the query, so hits in other directions cannot crowd out the requested one
+        let query: Box<dyn Query> = Box::new(BooleanQuery::new(vec![
+            (Occur::Must, query),
+            (
+                Occur::Must,
+                Box::new(ConstScoreQuery::new(
+                    Box::new(tantivy::query::TermQuery::new(
+                        Term::from_field_text(language_field, lang_str),
+                        tantivy::schema::IndexRecordOption::Basic,
+                    )),
+                    0.0,
+                )),
+            ),
+        ]));
+        let query = self.restrict_sources(query, sources, &mut notes);
+        let query = self.restrict_words(query, language, within, &mut notes);
+        let query = self.restrict_length(query, min_len, max_len, &mut notes);
+        let query = self.restrict_gloss(query, language, target_only, &mut notes);
+        let register_field = self.schema.get_field("register").ok();
+        let query = match register_field {
+            Some(field) => restrict_registers(query, field, register, safe),
+            None => query,
+        };
+        timings.query_build_ms = elapsed_ms(started);
+
+        // Checks on the stored document, run on each candidate while collecting
+        let mut predicates: Vec<DocPredicate> = Vec::new();
+        if let Some(filter) = filter.clone() {
+            predicates.push(Arc::new(move |document: &TantivyDocument| {
+                filter.matches(
+                    document
+                        .get_first(definition_field)
+                        .and_then(|v| v.as_str())
+                        .unwrap_or(""),
+                )
+            }));
+        }
+        // Ranks are kept outside the index, frequent words must not drop out of the window
+        if let Some(max_rank) = min_frequency_rank {
+            let frequencies = self.frequencies.clone();
+            let language = lang_str.to_string();
+            predicates.push(Arc::new(move |document: &TantivyDocument| {
+                document
+                    .get_first(word_field)
+                    .and_then(|v| v.as_str())
+                    .and_then(|word| frequencies.rank(&language, word))
+                    .is_some_and(|rank| rank <= max_rank)
+            }));
+        }
+        // Indexes built before registers were indexed detect them per candidate
+        if register_field.is_none() && (register.is_some() || safe) {
+            predicates.push(Arc::new(move |document: &TantivyDocument| {
+                let registers = Register::detect(
+                    document
+                        .get_first(definition_field)
+                        .and_then(|v| v.as_str())
+                        .unwrap_or(""),
+                );
+                register.is_none_or(|r| registers.contains(&r))
+                    && !(safe && registers.iter().any(Register::is_unsafe))
+            }));
+        }
+        let keep: Option<DocPredicate> = match predicates.len() {
+            0 => None,
+            1 => predicates.pop(),
+            _ => Some(Arc::new(move |document: &TantivyDocument| {
+                predicates.iter().all(|keep| keep(document))
+            })),
         };
 
         // Execute search - collect more results for better ranking
-        let search_limit = if mode == SearchMode::Fuzzy {
+        let search_limit = if mode == SearchMode::Fuzzy || diversify {
             limit * 10 // Collect more for fuzzy to find best matches
         } else {
             limit * 2
         };
-        let top_docs = searcher.search(&query, &TopDocs::with_limit(search_limit))?;
+        let search_started = Instant::now();
+        // Unrestricted exact and prefix lookups are answered from the headword maps
+        let unrestricted = strict_field.is_none()
+            && sources.is_empty()
+            && self.disabled_sources.is_empty()
+            && within.is_empty()
+            && min_len.is_none()
+            && max_len.is_none()
+            && register.is_none()
+            && !safe
+            && !target_only
+            && keep.is_none();
+        let spellings = {
+            let mut spellings = normalize::spelling_variants(&normalized_query);
+            spellings.push(normalized_query.clone());
+            spellings
+        };
+        let headword_query = match mode {
+            SearchMode::Exact if unrestricted => Some(HeadwordQuery::Exact {
+                word: &term_text,
+                spellings: &spellings,
+            }),
+            // Edge n-grams already turn these into a single term lookup
+            SearchMode::Prefix
+                if unrestricted
+                    && !(self.schema.get_field("word_ngram").is_ok()
+                        && (EDGE_NGRAM_MIN..=EDGE_NGRAM_MAX)
+                            .contains(&term_text.chars().count())) =>
+            {
+                Some(HeadwordQuery::Prefix(&term_text))
+            }
+            _ => None,
+        };
+        let fast_docs = match headword_query {
+            Some(headword_query) => {
+                self.headwords
+                    .search(searcher, headword_query, lang_str, search_limit)?
+            }
+            None => None,
+        };
+        let mut partial = false;
+        let top_docs = match fast_docs {
+            Some(docs) => Ok(docs),
+            None => {
+                let collector = Cancellable {
+                    inner: Filtered {
+                        inner: TopDocs::with_limit(search_limit),
+                        keep,
+                    },
+                    cancellation: cancellation.clone(),
+                    deadline,
+                    partial: AtomicBool::new(false),
+                };
+                let top_docs = searcher.search(&query, &collector);
+                partial = collector.partial.into_inner();
+                top_docs
+            }
+        };
+        if cancellation.is_cancelled() {
+            return Err(SearchCancelled.into());
+        }
+        let top_docs = top_docs?;
+        timings.search_ms = elapsed_ms(search_started);
+        let group_started = Instant::now();
 
-        // Collect results and group by word
+        // Collect results, grouping by word unless one row per definition was requested.
+        // Rows keep Tantivy's ranking order, the map only locates a word's row.
         use std::collections::HashMap;
-        let mut grouped_results: HashMap<String, (Vec<String>, f32, Option<u8>)> = HashMap::new();
-
+        let mut results: Vec<SearchResult> = Vec::new();
+        let mut row_by_word: HashMap<String, usize> = HashMap::new();
+        let mut merged_definitions = 0;
+        // Best source_priority position among each row's definitions
+        let mut source_positions: Vec<usize> = Vec::new();
+        let domain_field = self.schema.get_field("domain").ok();
+        let source_field = self.schema.get_field("source").ok();
+        let pos_field = self.schema.get_field("part_of_speech").ok();
+        let gender_field = self.schema.get_field("gender").ok();
+        let audio_field = self.schema.get_field("audio_url").ok();
         for (tantivy_score, doc_address) in top_docs {
+            if cancellation.is_cancelled() {
+                return Err(SearchCancelled.into());
+            }
+            let fetch_started = Instant::now();
             let retrieved_doc: TantivyDocument = searcher.doc(doc_address)?;
+            timings.fetch_ms += elapsed_ms(fetch_started);
 
             let word = retrieved_doc
                 .get_first(word_field)
@@ -160,16 +1015,39 @@ impl SearchEngine {
                 .unwrap_or("")
                 .to_string();
 
-            let doc_language = retrieved_doc
-                .get_first(language_field)
-                .and_then(|v| v.as_str())
-                .unwrap_or("")
-                .to_string();
-
-            // Filter by language
-            if doc_language != lang_str {
-                continue;
-            }
+            let registers = Register::detect(&definition);
+
+            let stored_all = |field: Option<Field>| -> Vec<String> {
+                field
+                    .map(|field| {
+                        retrieved_doc
+                            .get_all(field)
+                            .filter_map(|v| v.as_str())
+                            .map(str::to_string)
+                            .collect()
+                    })
+                    .unwrap_or_default()
+            };
+            let domains = stored_all(domain_field);
+            let audio_urls = stored_all(audio_field);
+
+            let stored = |field: Option<Field>| {
+                field
+                    .and_then(|field| retrieved_doc.get_first(field))
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string)
+            };
+            let part_of_speech = stored(pos_field).and_then(|pos| pos.parse().ok());
+            let gender = stored(gender_field);
+
+            let source = stored(source_field).filter(|s| !s.is_empty());
+            let source_position = self
+                .ranking
+                .source_position(source.as_deref().unwrap_or(""));
+            let source = source.map(|name| ResultSource {
+                license: self.license(&name),
+                name,
+            });
 
             // Calculate edit distance for fuzzy search
             let edit_distance = if mode == SearchMode::Fuzzy {
@@ -179,106 +1057,1253 @@ impl SearchEngine {
             };
 
             // Group definitions by word
-            grouped_results
-                .entry(word.clone())
-                .and_modify(|(defs, score, dist)| {
-                    defs.push(definition.clone());
+            if group == Grouping::Word {
+                if let Some(&row) = row_by_word.get(&word) {
+                    let result = &mut results[row];
+                    // The same gloss often comes from several dictionaries
+                    let key = definition_key(&definition);
+                    if result.definitions.iter().any(|d| definition_key(d) == key) {
+                        merged_definitions += 1;
+                    } else {
+                        result.definitions.push(definition);
+                    }
+                    for domain in domains {
+                        if !result.domains.contains(&domain) {
+                            result.domains.push(domain);
+                        }
+                    }
+                    for register in registers {
+                        if !result.registers.contains(&register) {
+                            result.registers.push(register);
+                        }
+                    }
+                    if let Some(pos) = part_of_speech
+                        && !result.parts_of_speech.contains(&pos)
+                    {
+                        result.parts_of_speech.push(pos);
+                    }
+                    if let Some(gender) = gender
+                        && !result.genders.contains(&gender)
+                    {
+                        result.genders.push(gender);
+                    }
+                    for url in audio_urls {
+                        if !result.audio_urls.contains(&url) {
+                            result.audio_urls.push(url);
+                        }
+                    }
+                    if let Some(source) = source
+                        && !result.sources.iter().any(|s| s.name == source.name)
+                    {
+                        result.sources.push(source);
+                    }
                     // Keep the best score and distance
-                    *score = score.max(tantivy_score);
+                    result.score = result.score.map(|score| score.max(tantivy_score));
                     if let Some(ed) = edit_distance {
-                        *dist = Some(dist.map_or(ed, |d| d.min(ed)));
+                        result.edit_distance = Some(result.edit_distance.map_or(ed, |d| d.min(ed)));
                     }
-                })
-                .or_insert((vec![definition], tantivy_score, edit_distance));
-        }
-
-        // Convert grouped results to SearchResult vec
-        let mut results: Vec<SearchResult> = grouped_results
-            .into_iter()
-            .map(|(word, (definitions, score, edit_distance))| SearchResult {
+                    source_positions[row] = source_positions[row].min(source_position);
+                    continue;
+                }
+                row_by_word.insert(word.clone(), results.len());
+            }
+            source_positions.push(source_position);
+            results.push(SearchResult {
+                frequency_rank: self.frequencies.rank(lang_str, &word),
                 word,
-                definitions,
+                definitions: vec![definition],
                 language: lang_str.to_string(),
                 edit_distance,
-                score: Some(score),
-            })
-            .collect();
+                score: Some(tantivy_score),
+                domains,
+                registers,
+                parts_of_speech: part_of_speech.into_iter().collect(),
+                genders: gender.into_iter().collect(),
+                audio_urls,
+                sources: source.into_iter().collect(),
+                html: None,
+            });
+        }
 
-        // Sort by relevance before limiting
-        if mode == SearchMode::Fuzzy {
-            // Sort by edit distance first (exact matches at top), then by Tantivy score
-            results.sort_by(|a, b| {
-                let dist_a = a.edit_distance.unwrap_or(255);
-                let dist_b = b.edit_distance.unwrap_or(255);
-
-                match dist_a.cmp(&dist_b) {
-                    std::cmp::Ordering::Equal => {
-                        // If edit distances are equal, use Tantivy score (higher is better)
-                        let score_a = a.score.unwrap_or(0.0);
-                        let score_b = b.score.unwrap_or(0.0);
-                        score_b
-                            .partial_cmp(&score_a)
-                            .unwrap_or(std::cmp::Ordering::Equal)
-                    }
-                    other => other,
+        let mut ranked: Vec<(SearchResult, usize)> =
+            results.into_iter().zip(source_positions).collect();
+        for (result, source_position) in &mut ranked {
+            let adjustment = self.ranking.adjustment(
+                normalize(&result.word).to_lowercase() == normalized_query,
+                result.edit_distance,
+                result.frequency_rank,
+                *source_position,
+            );
+            result.score = result.score.map(|score| score + adjustment);
+        }
+
+        // Sort by the requested ranking before limiting
+        ranked.sort_by(|(a, source_a), (b, source_b)| {
+            let by_score = || {
+                // Higher is better
+                let score_a = a.score.unwrap_or(0.0);
+                let score_b = b.score.unwrap_or(0.0);
+                score_b
+                    .partial_cmp(&score_a)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            };
+            let by_distance = || {
+                a.edit_distance
+                    .unwrap_or(0)
+                    .cmp(&b.edit_distance.unwrap_or(0))
+            };
+
+            let order = match rank {
+                Ranking::Distance => by_distance(),
+                Ranking::Score => by_score(),
+                // Words without a rank are rarer than any ranked one
+                Ranking::Frequency => a
+                    .frequency_rank
+                    .unwrap_or(u32::MAX)
+                    .cmp(&b.frequency_rank.unwrap_or(u32::MAX))
+                    .then_with(by_score),
+                // Fuzzy sorts by edit distance first (exact matches at top),
+                // unless the configured penalties fold distance into the score
+                Ranking::Hybrid
+                    if mode == SearchMode::Fuzzy
+                        && self.ranking.edit_distance_penalty.is_none() =>
+                {
+                    by_distance().then_with(by_score)
                 }
-            });
+                Ranking::Hybrid => by_score(),
+            };
+
+            order
+                // Equally relevant results from a preferred dictionary come first,
+                // unless only distance and spelling matter
+                .then_with(|| match rank {
+                    Ranking::Distance => std::cmp::Ordering::Equal,
+                    _ => source_a.cmp(source_b),
+                })
+                // Alphabetical tie-break so equal results keep the same order across requests
+                .then_with(|| a.word.cmp(&b.word))
+        });
+        let mut results: Vec<SearchResult> = ranked.into_iter().map(|(result, _)| result).collect();
+
+        // Results are sorted, so the first of each stem is the best one
+        if diversify {
+            let mut seen_stems = std::collections::HashSet::new();
+            results.retain(|r| seen_stems.insert(stem(&r.word)));
         }
 
         // Limit results after sorting
         results.truncate(limit);
+        // Grouping, filtering and sorting, without the document fetches
+        timings.group_ms = (elapsed_ms(group_started) - timings.fetch_ms).max(0.0);
+
+        // Compounds are often hyphenated in running text ("Haus-tür") but not in the dictionary
+        if results.is_empty()
+            && !partial
+            && dehyphenate
+            && let Some(dehyphenated) = normalize::dehyphenate(&normalized_query)
+        {
+            let retry_options = SearchOptions {
+                dehyphenate: false,
+                flip_direction: false,
+                ..options.clone()
+            };
+            let mut retry = self.search_snapshot(searcher, &dehyphenated, &retry_options)?;
+            if !retry.results.is_empty() {
+                notes.push(format!(
+                    "No results for '{}', showing results for '{}'",
+                    normalized_query, dehyphenated
+                ));
+                notes.append(&mut retry.notes);
+                return Ok(SearchOutput {
+                    notes,
+                    timings,
+                    flipped_direction: false,
+                    ..retry
+                });
+            }
+        }
+
+        // Users often forget to switch direction, look the word up the other way round
+        if results.is_empty() && !partial && flip_direction {
+            let reverse = language.reverse();
+            let retry_options = SearchOptions {
+                language: reverse,
+                flip_direction: false,
+                ..options.clone()
+            };
+            let mut retry = self.search_snapshot(searcher, raw_query, &retry_options)?;
+            if !retry.results.is_empty() {
+                notes.push(format!(
+                    "No results in {}, showing results from {}",
+                    lang_str,
+                    reverse.as_str()
+                ));
+                notes.append(&mut retry.notes);
+                return Ok(SearchOutput {
+                    notes,
+                    timings,
+                    flipped_direction: true,
+                    ..retry
+                });
+            }
+        }
 
-        Ok(results)
+        Ok(SearchOutput {
+            results,
+            notes,
+            timings,
+            flipped_direction: false,
+            merged_definitions,
+            index_generation,
+            partial,
+        })
     }
 
-    /// Get index statistics
-    pub fn get_stats(&self) -> Result<(usize, usize, usize)> {
+    /// Language pairs with entries in the index, in code order
+    pub fn languages(&self) -> Result<Vec<LanguagePair>> {
+        let field = self.schema.get_field("language").unwrap();
         let searcher = self.reader.searcher();
-        let language_field = self.schema.get_field("language").unwrap();
-
-        // Count total documents
-        let total = searcher.num_docs() as usize;
+        let inverted_indexes = searcher
+            .segment_readers()
+            .iter()
+            .map(|segment| segment.inverted_index(field))
+            .collect::<tantivy::Result<Vec<_>>>()?;
+        let streams = inverted_indexes
+            .iter()
+            .map(|inverted_index| inverted_index.terms().stream())
+            .collect::<std::io::Result<Vec<_>>>()?;
+
+        let mut languages = Vec::new();
+        let mut merger = TermMerger::new(streams);
+        while merger.advance() {
+            // Entries imported with an unrecognized language are skipped
+            if let Ok(language) = String::from_utf8_lossy(merger.key()).parse() {
+                languages.push(language);
+            }
+        }
+        Ok(languages)
+    }
 
-        // Count by language (approximate)
-        let en_de_query = Term::from_field_text(language_field, "en-de");
-        let de_en_query = Term::from_field_text(language_field, "de-en");
+    /// All indexed entries for a language direction, in index order
+    pub fn entries(&self, language: LanguagePair) -> Result<Vec<DictionaryEntry>> {
+        let language_field = self.schema.get_field("language").unwrap();
+        let query = self.restrict_sources(
+            Box::new(tantivy::query::TermQuery::new(
+                Term::from_field_text(language_field, language.as_str()),
+                tantivy::schema::IndexRecordOption::Basic,
+            )),
+            &[],
+            &mut Vec::new(),
+        );
+        let searcher = self.reader.searcher();
+        let addresses = matching_docs(&searcher, &query)?;
+        self.stored_entries(&searcher, &addresses, language)
+    }
 
-        let en_de_count = searcher
-            .search(
-                &tantivy::query::TermQuery::new(
-                    en_de_query,
-                    tantivy::schema::IndexRecordOption::Basic,
-                ),
-                &TopDocs::with_limit(1),
-            )?
-            .len();
+    /// One headword of a language with all its definitions, looked up by its
+    /// headword key. Non-empty `sources` limit the dictionaries they come from.
+    pub fn headword_entry(
+        &self,
+        language: LanguagePair,
+        word: &str,
+        sources: &[String],
+    ) -> Result<Option<SearchResult>> {
+        let headword_field = self
+            .schema
+            .get_field("headword")
+            .map_err(|_| anyhow::anyhow!("The index has no headword keys, run `dictv rebuild`"))?;
+        let key = headword_key(language.as_str(), &normalize(word.trim()).to_lowercase());
+        let query = self.restrict_sources(
+            Box::new(tantivy::query::TermQuery::new(
+                Term::from_field_text(headword_field, &key),
+                tantivy::schema::IndexRecordOption::Basic,
+            )),
+            sources,
+            &mut Vec::new(),
+        );
 
-        let de_en_count = searcher
-            .search(
-                &tantivy::query::TermQuery::new(
-                    de_en_query,
-                    tantivy::schema::IndexRecordOption::Basic,
-                ),
-                &TopDocs::with_limit(1),
-            )?
-            .len();
+        let searcher = self.reader.searcher();
+        let addresses = matching_docs(&searcher, &query)?;
+        let mut entries = self
+            .stored_entries(&searcher, &addresses, language)?
+            .into_iter();
+        let Some(first) = entries.next() else {
+            return Ok(None);
+        };
+        let mut result = SearchResult {
+            frequency_rank: self.frequencies.rank(language.as_str(), &first.word),
+            registers: Register::detect(&first.definition),
+            word: first.word,
+            definitions: vec![first.definition],
+            language: language.as_str().to_string(),
+            edit_distance: None,
+            score: None,
+            domains: first.domains,
+            parts_of_speech: first.part_of_speech.into_iter().collect(),
+            genders: first.gender.into_iter().collect(),
+            audio_urls: first.audio_urls,
+            sources: Vec::new(),
+            html: None,
+        };
+        let mut source_names: Vec<String> = first.source.into_iter().collect();
+        for entry in entries {
+            // The same gloss often comes from several dictionaries
+            let key = definition_key(&entry.definition);
+            for register in Register::detect(&entry.definition) {
+                if !result.registers.contains(&register) {
+                    result.registers.push(register);
+                }
+            }
+            if !result.definitions.iter().any(|d| definition_key(d) == key) {
+                result.definitions.push(entry.definition);
+            }
+            for domain in entry.domains {
+                if !result.domains.contains(&domain) {
+                    result.domains.push(domain);
+                }
+            }
+            if let Some(pos) = entry.part_of_speech
+                && !result.parts_of_speech.contains(&pos)
+            {
+                result.parts_of_speech.push(pos);
+            }
+            if let Some(gender) = entry.gender
+                && !result.genders.contains(&gender)
+            {
+                result.genders.push(gender);
+            }
+            for url in entry.audio_urls {
+                if !result.audio_urls.contains(&url) {
+                    result.audio_urls.push(url);
+                }
+            }
+            if let Some(source) = entry.source
+                && !source_names.contains(&source)
+            {
+                source_names.push(source);
+            }
+        }
+        result.sources = source_names
+            .into_iter()
+            .map(|name| ResultSource {
+                license: self.license(&name),
+                name,
+            })
+            .collect();
+        Ok(Some(result))
+    }
 
-        Ok((total, en_de_count, de_en_count))
+    /// Up to `limit` entries of a language picked at random from the searched
+    /// dictionaries, in index order. Only the picked entries are read from the
+    /// document store, and the same seed picks the same entries.
+    pub fn sample_entries(
+        &self,
+        language: LanguagePair,
+        sources: &[String],
+        limit: usize,
+        seed: u64,
+    ) -> Result<Vec<DictionaryEntry>> {
+        let language_field = self.schema.get_field("language").unwrap();
+        let query = self.restrict_sources(
+            Box::new(tantivy::query::TermQuery::new(
+                Term::from_field_text(language_field, language.as_str()),
+                tantivy::schema::IndexRecordOption::Basic,
+            )),
+            sources,
+            &mut Vec::new(),
+        );
+        let searcher = self.reader.searcher();
+        let mut addresses = matching_docs(&searcher, &query)?;
+        shuffle(&mut addresses, seed);
+        addresses.truncate(limit);
+        addresses.sort();
+        self.stored_entries(&searcher, &addresses, language)
     }
-}
 
-/// Register custom tokenizer with ASCII folding for diacritic support
-fn register_tokenizer(index: &mut Index) {
+    /// The stored entries of the given documents, in that order
+    fn stored_entries(
+        &self,
+        searcher: &Searcher,
+        addresses: &[DocAddress],
+        language: LanguagePair,
+    ) -> Result<Vec<DictionaryEntry>> {
+        let word_field = self.schema.get_field("word").unwrap();
+        let definition_field = self.schema.get_field("definition").unwrap();
+
+        let domain_field = self.schema.get_field("domain").ok();
+        let source_field = self.schema.get_field("source").ok();
+        let pos_field = self.schema.get_field("part_of_speech").ok();
+        let gender_field = self.schema.get_field("gender").ok();
+        let audio_field = self.schema.get_field("audio_url").ok();
+
+        let mut entries = Vec::with_capacity(addresses.len());
+        for &address in addresses {
+            let doc: TantivyDocument = searcher.doc(address)?;
+            let text = |field| {
+                doc.get_first(field)
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("")
+                    .to_string()
+            };
+            let all = |field: Option<Field>| -> Vec<String> {
+                field
+                    .map(|field| {
+                        doc.get_all(field)
+                            .filter_map(|v| v.as_str())
+                            .map(str::to_string)
+                            .collect()
+                    })
+                    .unwrap_or_default()
+            };
+            let mut entry = DictionaryEntry::new(
+                text(word_field),
+                text(definition_field),
+                language.as_str().to_string(),
+            )
+            .with_domains(all(domain_field));
+            entry.source = source_field.map(text).filter(|s| !s.is_empty());
+            entry.part_of_speech = pos_field.and_then(|field| text(field).parse().ok());
+            entry.gender = gender_field.map(text).filter(|g| !g.is_empty());
+            entry.audio_urls = all(audio_field);
+            entries.push(entry);
+        }
+
+        Ok(entries)
+    }
+
+    /// Definitions of one headword from each enabled dictionary, in index order,
+    /// keyed by dictionary name (empty for entries imported without a name).
+    /// Non-empty `sources` limit the dictionaries compared.
+    pub fn compare(
+        &self,
+        language: LanguagePair,
+        word: &str,
+        sources: &[String],
+    ) -> Result<BTreeMap<String, Vec<String>>> {
+        let headword_field = self
+            .schema
+            .get_field("headword")
+            .map_err(|_| anyhow::anyhow!("The index has no headword keys, run `dictv rebuild`"))?;
+        let definition_field = self.schema.get_field("definition").unwrap();
+        let source_field = self.schema.get_field("source").ok();
+
+        let key = headword_key(language.as_str(), &normalize(word.trim()).to_lowercase());
+        let query = self.restrict_sources(
+            Box::new(tantivy::query::TermQuery::new(
+                Term::from_field_text(headword_field, &key),
+                tantivy::schema::IndexRecordOption::Basic,
+            )),
+            sources,
+            &mut Vec::new(),
+        );
+        let searcher = self.reader.searcher();
+        let mut addresses: Vec<_> = searcher
+            .search(&query, &DocSetCollector)?
+            .into_iter()
+            .collect();
+        addresses.sort();
+
+        let mut by_source: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for address in addresses {
+            let doc: TantivyDocument = searcher.doc(address)?;
+            let text = |field| {
+                doc.get_first(field)
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("")
+                    .to_string()
+            };
+            let source = source_field.map(text).unwrap_or_default();
+            by_source
+                .entry(source)
+                .or_default()
+                .push(text(definition_field));
+        }
+        Ok(by_source)
+    }
+
+    /// Limit `query` to the requested dictionaries and drop disabled ones.
+    /// The filter clauses do not contribute to the score.
+    fn restrict_sources(
+        &self,
+        query: Box<dyn Query>,
+        sources: &[String],
+        notes: &mut Vec<String>,
+    ) -> Box<dyn Query> {
+        if sources.is_empty() && self.disabled_sources.is_empty() {
+            return query;
+        }
+        let Ok(source_field) = self.schema.get_field("source") else {
+            notes.push(
+                "Index has no source field, dictionary filters ignored (rebuild the index)"
+                    .to_string(),
+            );
+            return query;
+        };
+        let source_query = |source: &str| -> Box<dyn Query> {
+            Box::new(tantivy::query::TermQuery::new(
+                Term::from_field_text(source_field, source),
+                tantivy::schema::IndexRecordOption::Basic,
+            ))
+        };
+
+        let mut clauses = vec![(Occur::Must, query)];
+        if !sources.is_empty() {
+            let any_source = BooleanQuery::new(
+                sources
+                    .iter()
+                    .map(|source| (Occur::Should, source_query(source)))
+                    .collect(),
+            );
+            clauses.push((
+                Occur::Must,
+                Box::new(ConstScoreQuery::new(Box::new(any_source), 0.0)),
+            ));
+        }
+        for source in &self.disabled_sources {
+            clauses.push((Occur::MustNot, source_query(source)));
+        }
+        Box::new(BooleanQuery::new(clauses))
+    }
+
+    /// Limit `query` to the given headwords, without affecting the score
+    fn restrict_words(
+        &self,
+        query: Box<dyn Query>,
+        language: LanguagePair,
+        words: &[String],
+        notes: &mut Vec<String>,
+    ) -> Box<dyn Query> {
+        if words.is_empty() {
+            return query;
+        }
+        let Ok(headword_field) = self.schema.get_field("headword") else {
+            notes.push(
+                "Index has no headword keys, word list ignored (rebuild the index)".to_string(),
+            );
+            return query;
+        };
+        let any_word = BooleanQuery::new(
+            words
+                .iter()
+                .map(|word| {
+                    let key =
+                        headword_key(language.as_str(), &normalize(word.trim()).to_lowercase());
+                    let word_query: Box<dyn Query> = Box::new(tantivy::query::TermQuery::new(
+                        Term::from_field_text(headword_field, &key),
+                        tantivy::schema::IndexRecordOption::Basic,
+                    ));
+                    (Occur::Should, word_query)
+                })
+                .collect(),
+        );
+        Box::new(BooleanQuery::new(vec![
+            (Occur::Must, query),
+            (
+                Occur::Must,
+                Box::new(ConstScoreQuery::new(Box::new(any_word), 0.0)),
+            ),
+        ]))
+    }
+
+    /// Drop definitions detected to be in another language than the target
+    /// of `language`. Definitions without a detected language are kept.
+    fn restrict_gloss(
+        &self,
+        query: Box<dyn Query>,
+        language: LanguagePair,
+        target_only: bool,
+        notes: &mut Vec<String>,
+    ) -> Box<dyn Query> {
+        if !target_only {
+            return query;
+        }
+        let Ok(gloss_field) = self.schema.get_field("gloss_language") else {
+            notes.push(
+                "Index has no gloss language field, target_only ignored (rebuild the index)"
+                    .to_string(),
+            );
+            return query;
+        };
+        let mut clauses = vec![(Occur::Must, query)];
+        for gloss in glosslang::LANGUAGES {
+            if *gloss != language.target() {
+                let other: Box<dyn Query> = Box::new(TermQuery::new(
+                    Term::from_field_text(gloss_field, gloss),
+                    IndexRecordOption::Basic,
+                ));
+                clauses.push((Occur::MustNot, other));
+            }
+        }
+        Box::new(BooleanQuery::new(clauses))
+    }
+
+    /// Limit `query` to headwords of `min_len..=max_len` characters, using
+    /// the word length fast field
+    fn restrict_length(
+        &self,
+        query: Box<dyn Query>,
+        min_len: Option<u64>,
+        max_len: Option<u64>,
+        notes: &mut Vec<String>,
+    ) -> Box<dyn Query> {
+        if min_len.is_none() && max_len.is_none() {
+            return query;
+        }
+        if self.schema.get_field("word_length").is_err() {
+            notes.push(
+                "Index has no word lengths, min_len and max_len ignored (rebuild the index)"
+                    .to_string(),
+            );
+            return query;
+        }
+        let lengths = RangeQuery::new_u64_bounds(
+            "word_length".to_string(),
+            min_len.map_or(Bound::Unbounded, Bound::Included),
+            max_len.map_or(Bound::Unbounded, Bound::Included),
+        );
+        Box::new(BooleanQuery::new(vec![
+            (Occur::Must, query),
+            (
+                Occur::Must,
+                Box::new(ConstScoreQuery::new(Box::new(lengths), 0.0)),
+            ),
+        ]))
+    }
+
+    /// Number of indexed entries
+    pub fn num_docs(&self) -> u64 {
+        self.reader.searcher().num_docs()
+    }
+
+    /// Pick up index changes now instead of waiting for the automatic reload
+    pub fn reload(&self) -> Result<()> {
+        timed_reload(&self.reader, &self.reload_metrics)
+    }
+
+    /// Number and durations of the reloads so far
+    pub fn reload_stats(&self) -> ReloadStats {
+        self.reload_metrics.stats()
+    }
+
+    /// Segments, mapped file size and document cache of the current searcher
+    pub fn index_memory(&self) -> Result<IndexMemory> {
+        let searcher = self.reader.searcher();
+        Ok(IndexMemory {
+            segments: searcher.segment_readers().len(),
+            mapped_bytes: searcher.space_usage()?.total().get_bytes(),
+            doc_store_cache_blocks: searcher.doc_store_cache_stats().num_entries,
+            headword_map_bytes: self.headwords.size_bytes() as u64,
+        })
+    }
+
+    /// Ranked words loaded from the frequency lists
+    pub fn frequency_words(&self) -> usize {
+        self.frequencies.word_count()
+    }
+
+    /// Number of distinct headwords per initial letter, read from the term dictionary,
+    /// counting only headwords with a definition in the searched dictionaries.
+    /// Umlauts count under their base letter, non-letters under "#".
+    pub fn letter_counts(
+        &self,
+        language: LanguagePair,
+        sources: &[String],
+    ) -> Result<Vec<(String, u64)>> {
+        let mut counts: BTreeMap<String, u64> = BTreeMap::new();
+        self.for_each_headword(language, sources, |word| {
+            *counts.entry(initial_letter(word)).or_default() += 1;
+            true
+        })?;
+        Ok(counts.into_iter().collect())
+    }
+
+    /// Number of distinct headwords of a language with definitions in the
+    /// searched dictionaries (all enabled ones when `sources` is empty)
+    pub fn headword_count(&self, language: LanguagePair, sources: &[String]) -> Result<u64> {
+        let mut count = 0;
+        self.for_each_headword(language, sources, |_| {
+            count += 1;
+            true
+        })?;
+        Ok(count)
+    }
+
+    /// The headwords at the given positions in term order, counted like
+    /// `headword_count`, read in one pass over the term dictionary
+    pub fn nth_headwords(
+        &self,
+        language: LanguagePair,
+        sources: &[String],
+        positions: &BTreeSet<u64>,
+    ) -> Result<BTreeMap<u64, String>> {
+        let Some(&last) = positions.last() else {
+            return Ok(BTreeMap::new());
+        };
+        let mut position = 0;
+        let mut found = BTreeMap::new();
+        self.for_each_headword(language, sources, |word| {
+            if positions.contains(&position) {
+                found.insert(position, word.to_string());
+            }
+            position += 1;
+            position <= last
+        })?;
+        Ok(found)
+    }
+
+    /// Call `f` with every distinct headword of a language that has a definition
+    /// in the searched dictionaries, in term order, until it returns false
+    fn for_each_headword(
+        &self,
+        language: LanguagePair,
+        sources: &[String],
+        mut f: impl FnMut(&str) -> bool,
+    ) -> Result<()> {
+        let field = self
+            .schema
+            .get_field("headword")
+            .map_err(|_| anyhow::anyhow!("The index has no headword keys, run `dictv rebuild`"))?;
+        let prefix = headword_key(language.as_str(), "");
+        let searcher = self.reader.searcher();
+        let in_scope = self.scoped_docs(&searcher, language, sources)?;
+        let inverted_indexes = searcher
+            .segment_readers()
+            .iter()
+            .map(|segment| segment.inverted_index(field))
+            .collect::<tantivy::Result<Vec<_>>>()?;
+        let streams = inverted_indexes
+            .iter()
+            .map(|inverted_index| {
+                inverted_index
+                    .terms()
+                    .range()
+                    .ge(&prefix)
+                    .lt(format!("{};", language.as_str()))
+                    .into_stream()
+            })
+            .collect::<std::io::Result<Vec<_>>>()?;
+
+        // The merger yields each headword once, however many segments hold it
+        let mut merger = TermMerger::new(streams);
+        while merger.advance() {
+            if let Some(docs) = &in_scope {
+                let mut found = false;
+                for (segment_ord, term_info) in merger.current_segment_ords_and_term_infos() {
+                    if term_has_doc_in(
+                        &inverted_indexes[segment_ord],
+                        segment_ord as u32,
+                        &term_info,
+                        docs,
+                    )? {
+                        found = true;
+                        break;
+                    }
+                }
+                if !found {
+                    continue;
+                }
+            }
+            if !f(&String::from_utf8_lossy(&merger.key()[prefix.len()..])) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// The live documents of a language in the searched dictionaries, or None
+    /// when no dictionary is requested or disabled and every document counts
+    fn scoped_docs(
+        &self,
+        searcher: &Searcher,
+        language: LanguagePair,
+        sources: &[String],
+    ) -> Result<Option<HashSet<DocAddress>>> {
+        if sources.is_empty() && self.disabled_sources.is_empty() {
+            return Ok(None);
+        }
+        let language_field = self.schema.get_field("language").unwrap();
+        let query = self.restrict_sources(
+            Box::new(tantivy::query::TermQuery::new(
+                Term::from_field_text(language_field, language.as_str()),
+                tantivy::schema::IndexRecordOption::Basic,
+            )),
+            sources,
+            &mut Vec::new(),
+        );
+        Ok(Some(searcher.search(&query, &DocSetCollector)?))
+    }
+
+    /// Up to `limit` headwords in term order, from `start` (inclusive) towards
+    /// `end` (exclusive), read from the term dictionary. Only headwords with a
+    /// definition in the searched dictionaries are listed (all enabled ones
+    /// when `sources` is empty).
+    pub fn browse(
+        &self,
+        language: LanguagePair,
+        sources: &[String],
+        start: Option<&str>,
+        end: Option<&str>,
+        direction: BrowseDirection,
+        limit: usize,
+    ) -> Result<Vec<String>> {
+        let field = self
+            .schema
+            .get_field("headword")
+            .map_err(|_| anyhow::anyhow!("The index has no headword keys, run `dictv rebuild`"))?;
+        let prefix = headword_key(language.as_str(), "");
+        let past_prefix = format!("{};", language.as_str());
+        let key = |word: &str| headword_key(language.as_str(), &word.to_lowercase());
+
+        let searcher = self.reader.searcher();
+        let in_scope = self.scoped_docs(&searcher, language, sources)?;
+        let mut headwords = BTreeSet::new();
+        for (segment_ord, segment) in searcher.segment_readers().iter().enumerate() {
+            let inverted_index = segment.inverted_index(field)?;
+            let range = inverted_index.terms().range();
+            let range = match direction {
+                BrowseDirection::Asc => {
+                    let range = match start {
+                        Some(start) => range.ge(key(start)),
+                        None => range.ge(&prefix),
+                    };
+                    match end {
+                        Some(end) => range.lt(key(end)),
+                        None => range.lt(&past_prefix),
+                    }
+                }
+                BrowseDirection::Desc => {
+                    let range = match start {
+                        Some(start) => range.le(key(start)),
+                        None => range.lt(&past_prefix),
+                    };
+                    let range = match end {
+                        Some(end) => range.gt(key(end)),
+                        None => range.ge(&prefix),
+                    };
+                    range.backward()
+                }
+            };
+
+            // The first `limit` terms of every segment contain the first `limit` overall
+            let mut stream = range.into_stream()?;
+            let mut taken = 0;
+            while taken < limit && stream.advance() {
+                if let Some(docs) = &in_scope
+                    && !term_has_doc_in(&inverted_index, segment_ord as u32, stream.value(), docs)?
+                {
+                    continue;
+                }
+                let word = String::from_utf8_lossy(&stream.key()[prefix.len()..]).into_owned();
+                headwords.insert(word);
+                taken += 1;
+            }
+        }
+
+        let headwords: Vec<String> = match direction {
+            BrowseDirection::Asc => headwords.into_iter().take(limit).collect(),
+            BrowseDirection::Desc => headwords.into_iter().rev().take(limit).collect(),
+        };
+        Ok(headwords)
+    }
+
+    /// Total entry count and the entry count of each indexed language pair
+    pub fn get_stats(&self) -> Result<(usize, Vec<LanguageCount>)> {
+        let searcher = self.reader.searcher();
+        let language_field = self.schema.get_field("language").unwrap();
+
+        let total = searcher.num_docs() as usize;
+        let mut languages = Vec::new();
+        for language in self.languages()? {
+            let entries = searcher.search(
+                &tantivy::query::TermQuery::new(
+                    Term::from_field_text(language_field, language.as_str()),
+                    tantivy::schema::IndexRecordOption::Basic,
+                ),
+                &Count,
+            )?;
+            languages.push(LanguageCount { language, entries });
+        }
+
+        Ok((total, languages))
+    }
+}
+
+/// Cap the edit distance for short queries
+fn scale_max_distance(query_len: usize, max_distance: u8) -> u8 {
+    if query_len < SHORT_QUERY_LEN {
+        max_distance.min(1)
+    } else {
+        max_distance
+    }
+}
+
+/// Turn `+(-b)` clauses, which match nothing, into `-b`. The query parser
+/// produces them for "a AND NOT b".
+fn lift_negations(query: Box<dyn Query>) -> Box<dyn Query> {
+    let Some(boolean) = query.downcast_ref::<BooleanQuery>() else {
+        return query;
+    };
+    let mut clauses = Vec::new();
+    for (occur, clause) in boolean.clauses() {
+        let clause = lift_negations(clause.box_clone());
+        match clause.downcast_ref::<BooleanQuery>() {
+            Some(negation)
+                if *occur == Occur::Must
+                    && negation
+                        .clauses()
+                        .iter()
+                        .all(|(occur, _)| *occur == Occur::MustNot) =>
+            {
+                clauses.extend(
+                    negation
+                        .clauses()
+                        .iter()
+                        .map(|(occur, clause)| (*occur, clause.box_clone())),
+                );
+            }
+            _ => clauses.push((*occur, clause)),
+        }
+    }
+    Box::new(BooleanQuery::new(clauses))
+}
+
+/// Milliseconds since `start`
+fn elapsed_ms(start: Instant) -> f64 {
+    start.elapsed().as_secs_f64() * 1000.0
+}
+
+/// Strip leading and trailing whitespace and punctuation from a query
+fn strip_surrounding_punctuation(query: &str) -> &str {
+    query.trim_matches(|c: char| c.is_whitespace() || c.is_ascii_punctuation() || is_quote(c))
+}
+
+/// Typographic quotes and ellipses that are not ASCII punctuation
+fn is_quote(c: char) -> bool {
+    matches!(
+        c,
+        '„' | '“' | '”' | '‚' | '‘' | '’' | '«' | '»' | '‹' | '›' | '…'
+    )
+}
+
+/// The live documents matching `query`, in index order
+fn matching_docs(searcher: &Searcher, query: &dyn Query) -> Result<Vec<DocAddress>> {
+    let mut addresses: Vec<_> = searcher
+        .search(query, &DocSetCollector)?
+        .into_iter()
+        .collect();
+    addresses.sort();
+    Ok(addresses)
+}
+
+/// Whether any document indexed under a term is in `docs`
+fn term_has_doc_in(
+    inverted_index: &InvertedIndexReader,
+    segment_ord: u32,
+    term_info: &TermInfo,
+    docs: &HashSet<DocAddress>,
+) -> Result<bool> {
+    let mut postings =
+        inverted_index.read_postings_from_terminfo(term_info, IndexRecordOption::Basic)?;
+    let mut doc = postings.doc();
+    while doc != TERMINATED {
+        if docs.contains(&DocAddress::new(segment_ord, doc)) {
+            return Ok(true);
+        }
+        doc = postings.advance();
+    }
+    Ok(false)
+}
+
+/// Term of the headword field: "de-en:haus"
+fn headword_key(language: &str, word: &str) -> String {
+    format!("{}:{}", language, word)
+}
+
+/// Term of the anagram field: the headword's letters, folded and sorted ("de-en:aehsu")
+fn anagram_key(language: &str, word: &str) -> String {
+    let mut letters: Vec<char> = fold_diacritics(&word.to_lowercase())
+        .chars()
+        .filter(|c| c.is_alphabetic())
+        .collect();
+    letters.sort_unstable();
+    headword_key(language, &letters.into_iter().collect::<String>())
+}
+
+/// Term of the reversed headword field, for suffix lookups: "de-en:suah"
+fn reversed_key(language: &str, word: &str) -> String {
+    headword_key(
+        language,
+        &fold_diacritics(word).chars().rev().collect::<String>(),
+    )
+}
+
+/// Regex for a crossword pattern: "_" and "?" stand for one character, the rest is literal
+fn pattern_regex(pattern: &str) -> String {
+    pattern
+        .chars()
+        .map(|c| match c {
+            '_' | '?' => ".".to_string(),
+            c => regex::escape(&c.to_string()),
+        })
+        .collect()
+}
+
+/// Navigation letter of a headword: "Ärger" -> "A", "ßeta" -> "S", "3D" -> "#"
+fn initial_letter(word: &str) -> String {
+    let first = word.chars().next().map(String::from).unwrap_or_default();
+    match fold_diacritics(&first).chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => c.to_ascii_uppercase().to_string(),
+        _ => "#".to_string(),
+    }
+}
+
+/// Definitions with the same key are considered the same: compared
+/// case-insensitively and ignoring punctuation and spacing
+fn definition_key(definition: &str) -> String {
+    definition
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Apply the same ASCII folding the word field uses at index time
+fn fold_diacritics(text: &str) -> String {
+    let mut analyzer = TextAnalyzer::builder(RawTokenizer::default())
+        .filter(AsciiFoldingFilter)
+        .build();
+    let mut stream = analyzer.token_stream(text);
+    let mut folded = String::with_capacity(text.len());
+    while let Some(token) = stream.next() {
+        folded.push_str(&token.text);
+    }
+    folded
+}
+
+/// Open, warm and swap in a searcher over the latest commit
+fn timed_reload(reader: &IndexReader, metrics: &ReloadMetrics) -> Result<()> {
+    let started = Instant::now();
+    let result = reader.reload();
+    metrics.record(started.elapsed(), result.is_ok());
+    if result.is_ok() {
+        info!("Reloaded the index in {:?}", started.elapsed());
+    }
+    Ok(result?)
+}
+
+/// Identifier of the index contents a searcher sees. It changes whenever
+/// entries are added or the index is rebuilt (or its segments merged), and
+/// stays the same across server restarts.
+fn index_generation(searcher: &Searcher) -> String {
+    let mut segments: Vec<String> = searcher
+        .segment_readers()
+        .iter()
+        .map(|segment| segment.segment_id().uuid_string())
+        .collect();
+    segments.sort();
+    let mut hasher = DefaultHasher::new();
+    segments.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Headword keys ("de-en:haus") of the headwords starting with `prefix`, in
+/// `language` or else in any language
+fn headword_keys_with_prefix(
+    searcher: &Searcher,
+    field: Field,
+    language: Option<LanguagePair>,
+    prefix: &str,
+) -> Result<Vec<String>> {
+    let start = language
+        .map(|language| headword_key(language.as_str(), prefix))
+        .unwrap_or_default();
+    let inverted_indexes = searcher
+        .segment_readers()
+        .iter()
+        .map(|segment| segment.inverted_index(field))
+        .collect::<tantivy::Result<Vec<_>>>()?;
+    let streams = inverted_indexes
+        .iter()
+        .map(|inverted_index| inverted_index.terms().range().ge(&start).into_stream())
+        .collect::<std::io::Result<Vec<_>>>()?;
+
+    let mut keys = Vec::new();
+    let mut merger = TermMerger::new(streams);
+    while merger.advance() {
+        let key = String::from_utf8_lossy(merger.key());
+        if !key.starts_with(&start) {
+            break;
+        }
+        if key
+            .split_once(':')
+            .is_some_and(|(_, word)| word.starts_with(prefix))
+        {
+            keys.push(key.into_owned());
+        }
+    }
+    Ok(keys)
+}
+
+/// Limit `query` to definitions labelled with `register`, and without
+/// vulgar or offensive labels for safe search, using the register field
+fn restrict_registers(
+    query: Box<dyn Query>,
+    register_field: Field,
+    register: Option<Register>,
+    safe: bool,
+) -> Box<dyn Query> {
+    if register.is_none() && !safe {
+        return query;
+    }
+    let register_query = |register: Register| -> Box<dyn Query> {
+        Box::new(TermQuery::new(
+            Term::from_field_text(register_field, register.as_str()),
+            IndexRecordOption::Basic,
+        ))
+    };
+    let mut clauses = vec![(Occur::Must, query)];
+    if let Some(register) = register {
+        clauses.push((
+            Occur::Must,
+            Box::new(ConstScoreQuery::new(register_query(register), 0.0)),
+        ));
+    }
+    if safe {
+        for register in [Register::Vulgar, Register::Offensive] {
+            clauses.push((Occur::MustNot, register_query(register)));
+        }
+    }
+    Box::new(BooleanQuery::new(clauses))
+}
+
+/// Index entries with one writer and commit them
+fn write_entries<I>(index: &Index, batches: I) -> Result<usize>
+where
+    I: IntoIterator<Item = Vec<DictionaryEntry>>,
+{
+    // The index's own schema: optional fields depend on how it was built.
+    // Indexes too old to have the required ones are rejected before any entry
+    // is read, so `--watch-data` logs what to do instead of a bare field error.
+    let schema = index.schema();
+    let required = |name: &str| {
+        schema.get_field(name).map_err(|_| {
+            anyhow::anyhow!(
+                "The index was built by an older version and has no {} field, run `dictv rebuild`",
+                name
+            )
+        })
+    };
+    let word_field = required("word")?;
+    let definition_field = required("definition")?;
+    let language_field = required("language")?;
+    let strict_field = required("word_strict")?;
+    let domain_field = required("domain")?;
+    let source_field = required("source")?;
+    let headword_field = required("headword")?;
+    let gloss_field = required("gloss_language")?;
+    let anagram_field = required("anagram_key")?;
+    let reversed_field = required("reversed_headword")?;
+    let length_field = required("word_length")?;
+    let variants_field = required("word_variants")?;
+    let ngram_field = schema.get_field("word_ngram").ok();
+    // Absent from indexes built before they were added
+    let pos_field = schema.get_field("part_of_speech").ok();
+    let gender_field = schema.get_field("gender").ok();
+    let audio_field = schema.get_field("audio_url").ok();
+    let register_field = schema.get_field("register").ok();
+
+    let mut writer: IndexWriter = index.writer(100_000_000)?;
+    let mut total = 0;
+
+    for entry in batches.into_iter().flatten() {
+        total += 1;
+        let word = normalize(&entry.word).to_lowercase();
+        let definition = normalize(&entry.definition);
+        let gloss = glosslang::detect(&definition);
+        let registers = Register::detect(&definition);
+        let mut document = doc!(
+            word_field => word.clone(),
+            strict_field => word.clone(),
+            headword_field => headword_key(&entry.language, &word),
+            anagram_field => anagram_key(&entry.language, &word),
+            reversed_field => reversed_key(&entry.language, &word),
+            length_field => word.chars().count() as u64,
+            definition_field => definition,
+            language_field => entry.language,
+        );
+        for domain in &entry.domains {
+            document.add_text(domain_field, domain);
+        }
+        if let Some(source) = &entry.source {
+            document.add_text(source_field, source);
+        }
+        if let Some(gloss) = gloss {
+            document.add_text(gloss_field, gloss);
+        }
+        if let (Some(field), Some(pos)) = (pos_field, entry.part_of_speech) {
+            document.add_text(field, pos.as_str());
+        }
+        if let (Some(field), Some(gender)) = (gender_field, &entry.gender) {
+            document.add_text(field, gender);
+        }
+        if let Some(field) = audio_field {
+            for url in &entry.audio_urls {
+                document.add_text(field, url);
+            }
+        }
+        if let Some(field) = register_field {
+            for register in registers {
+                document.add_text(field, register.as_str());
+            }
+        }
+        document.add_text(variants_field, &word);
+        for variant in normalize::spelling_variants(&word) {
+            document.add_text(variants_field, variant);
+        }
+        if let Some(ngram_field) = ngram_field {
+            document.add_text(ngram_field, word);
+        }
+        writer.add_document(document)?;
+    }
+
+    writer.commit()?;
+
+    Ok(total)
+}
+
+/// Register custom tokenizer with ASCII folding for diacritic support
+fn register_tokenizer(index: &mut Index) {
     let tokenizer = TextAnalyzer::builder(SimpleTokenizer::default())
         .filter(LowerCaser)
         .filter(AsciiFoldingFilter)
         .build();
 
     index.tokenizers().register("custom_tokenizer", tokenizer);
+
+    // Same tokenization without folding, for strict diacritic matching
+    let strict_tokenizer = TextAnalyzer::builder(SimpleTokenizer::default())
+        .filter(LowerCaser)
+        .build();
+
+    index
+        .tokenizers()
+        .register("strict_tokenizer", strict_tokenizer);
+
+    // Edge n-gram tokenizer for the optional autocomplete field
+    let edge_ngram = TextAnalyzer::builder(
+        NgramTokenizer::prefix_only(EDGE_NGRAM_MIN, EDGE_NGRAM_MAX)
+            .expect("valid edge n-gram bounds"),
+    )
+    .filter(LowerCaser)
+    .filter(AsciiFoldingFilter)
+    .build();
+
+    index.tokenizers().register("edge_ngram", edge_ngram);
 }
 
 /// Build the Tantivy schema
-fn build_schema() -> Schema {
+fn build_schema(options: &IndexOptions) -> Schema {
     let mut schema_builder = Schema::builder();
 
     // Word field: searchable and stored with custom tokenizer
@@ -295,15 +2320,78 @@ fn build_schema() -> Schema {
     // Definition field: searchable and stored with custom tokenizer
     schema_builder.add_text_field("definition", text_options);
 
+    // Unfolded word field: indexed only, used when diacritics must match exactly
+    let strict_indexing = TextFieldIndexing::default()
+        .set_tokenizer("strict_tokenizer")
+        .set_index_option(tantivy::schema::IndexRecordOption::WithFreqsAndPositions);
+    schema_builder.add_text_field(
+        "word_strict",
+        TextOptions::default().set_indexing_options(strict_indexing),
+    );
+
+    // The headword and its alternative spellings (ß as "ss", umlauts as "ae",
+    // "oe", "ue"), indexed only, unfolded so exact search does not depend on folding
+    let variants_indexing = TextFieldIndexing::default()
+        .set_tokenizer("strict_tokenizer")
+        .set_index_option(tantivy::schema::IndexRecordOption::Basic);
+    schema_builder.add_text_field(
+        "word_variants",
+        TextOptions::default().set_indexing_options(variants_indexing),
+    );
+
     // Language field: filterable and stored
     schema_builder.add_text_field("language", STRING | STORED);
 
+    // Domain/subject labels (e.g. "archi."), filterable and stored, may repeat
+    schema_builder.add_text_field("domain", STRING | STORED);
+
+    // Name of the dictionary the entry was imported from, filterable and stored
+    schema_builder.add_text_field("source", STRING | STORED);
+
+    // Part of speech and gender ("m", "f", "n"), stored for sources that mark them up
+    schema_builder.add_text_field("part_of_speech", STRING | STORED);
+    schema_builder.add_text_field("gender", STRING | STORED);
+
+    // URLs of recordings of the headword, stored only, may repeat
+    schema_builder.add_text_field("audio_url", STORED);
+
+    // "language:headword" keys, indexed only, so the term dictionary lists the
+    // headwords of each language in order
+    schema_builder.add_text_field("headword", STRING);
+
+    // Headword length in characters, a fast field for length filters
+    schema_builder.add_u64_field("word_length", INDEXED | FAST);
+
+    // "language:reversed headword" keys, indexed only, for rhyme search
+    schema_builder.add_text_field("reversed_headword", STRING);
+
+    // "language:sorted letters" keys, indexed only, for anagram search
+    schema_builder.add_text_field("anagram_key", STRING);
+
+    // Usage registers labelled in the definition ("colloquial"), indexed only, may repeat
+    schema_builder.add_text_field("register", STRING);
+
+    // Detected language of the definition ("en" or "de"), stored, absent when unclear
+    schema_builder.add_text_field("gloss_language", STRING | STORED);
+
+    // Autocomplete field: edge n-grams of the headword, indexed only
+    if options.edge_ngrams {
+        let ngram_indexing = TextFieldIndexing::default()
+            .set_tokenizer("edge_ngram")
+            .set_index_option(tantivy::schema::IndexRecordOption::Basic);
+        schema_builder.add_text_field(
+            "word_ngram",
+            TextOptions::default().set_indexing_options(ngram_indexing),
+        );
+    }
+
     schema_builder.build()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::PartOfSpeech;
     use tempfile::TempDir;
 
     fn create_test_entries() -> Vec<DictionaryEntry> {
@@ -340,7 +2428,7 @@ mod tests {
         let engine = SearchEngine::new(temp_dir.path()).unwrap();
 
         let results = engine
-            .search("Haus", SearchMode::Exact, Language::DeEn, 2, 10)
+            .search("Haus", SearchMode::Exact, LanguagePair::DE_EN, 2, 10)
             .unwrap();
 
         assert_eq!(results.len(), 1);
@@ -348,6 +2436,169 @@ mod tests {
         assert!(results[0].definitions[0].contains("house"));
     }
 
+    #[test]
+    fn test_index_generation_changes_with_contents() {
+        let temp_dir = TempDir::new().unwrap();
+        SearchEngine::build_index(temp_dir.path(), create_test_entries()).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+        let generation = |engine: &SearchEngine| {
+            engine
+                .search_with_options("Haus", &SearchOptions::default())
+                .unwrap()
+                .index_generation
+        };
+
+        let first = generation(&engine);
+        assert_eq!(first.len(), 16);
+        assert_eq!(generation(&engine), first);
+        // Reopening the same index (e.g. after a restart) keeps it
+        assert_eq!(
+            generation(&SearchEngine::new(temp_dir.path()).unwrap()),
+            first
+        );
+
+        let added = DictionaryEntry::new("Baum".into(), "tree".into(), "de-en".into());
+        SearchEngine::add_to_index_from_batches(temp_dir.path(), vec![vec![added]]).unwrap();
+        engine.reload().unwrap();
+        assert_ne!(generation(&engine), first);
+    }
+
+    #[test]
+    fn test_adding_to_an_old_index_asks_for_a_rebuild() {
+        let temp_dir = TempDir::new().unwrap();
+        // The fields of an index built before headword keys and the rest were added
+        let mut builder = Schema::builder();
+        builder.add_text_field("word", STRING | STORED);
+        builder.add_text_field("definition", STRING | STORED);
+        builder.add_text_field("language", STRING | STORED);
+        Index::create_in_dir(temp_dir.path(), builder.build()).unwrap();
+
+        let added = DictionaryEntry::new("Baum".into(), "tree".into(), "de-en".into());
+        let error = SearchEngine::add_to_index_from_batches(temp_dir.path(), vec![vec![added]])
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("run `dictv rebuild`"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_commits_are_reloaded_and_timed() {
+        let temp_dir = TempDir::new().unwrap();
+        SearchEngine::build_index(temp_dir.path(), create_test_entries()).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+        let (before, reloads) = (engine.num_docs(), engine.reload_stats().reloads);
+
+        let added = DictionaryEntry::new("Baum".into(), "tree".into(), "de-en".into());
+        SearchEngine::add_to_index_from_batches(temp_dir.path(), vec![vec![added]]).unwrap();
+        // The directory watch picks up the commit without an explicit reload
+        let deadline = Instant::now() + std::time::Duration::from_secs(10);
+        while (engine.num_docs() == before || engine.reload_stats().reloads == reloads)
+            && Instant::now() < deadline
+        {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        assert_eq!(engine.num_docs(), before + 1);
+
+        let stats = engine.reload_stats();
+        assert!(stats.reloads > reloads);
+        assert_eq!(stats.failed, 0);
+        assert!(stats.last_ms.is_some());
+        assert!(stats.last_reload_unix.is_some());
+    }
+
+    #[test]
+    fn test_structured_fields_are_returned() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut entry = DictionaryEntry::new("Haus".into(), "house".into(), "de-en".into());
+        entry.part_of_speech = Some(PartOfSpeech::Noun);
+        entry.gender = Some("n".to_string());
+        entry.audio_urls = vec!["https://example.org/De-Haus.ogg".to_string()];
+        let plain = DictionaryEntry::new("Haus".into(), "home".into(), "de-en".into());
+        SearchEngine::build_index(temp_dir.path(), vec![entry, plain]).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let results = engine
+            .search("Haus", SearchMode::Exact, LanguagePair::DE_EN, 0, 10)
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].parts_of_speech, [PartOfSpeech::Noun]);
+        assert_eq!(results[0].genders, ["n"]);
+        assert_eq!(results[0].audio_urls, ["https://example.org/De-Haus.ogg"]);
+
+        let entries = engine.entries(LanguagePair::DE_EN).unwrap();
+        assert_eq!(entries[0].part_of_speech, Some(PartOfSpeech::Noun));
+        assert_eq!(entries[0].gender.as_deref(), Some("n"));
+        assert_eq!(entries[1].part_of_speech, None);
+        assert_eq!(entries[0].audio_urls.len(), 1);
+        assert!(entries[1].audio_urls.is_empty());
+    }
+
+    #[test]
+    fn test_purge_deletes_matching_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let entry = |word: &str, language: &str, source: &str| DictionaryEntry {
+            source: Some(source.to_string()),
+            ..DictionaryEntry::new(word.into(), "x".into(), language.into())
+        };
+        let entries = vec![
+            entry("00-database-info", "de-en", "ding"),
+            entry("00-database-url", "de-en", "ding"),
+            entry("00-database-info", "en-de", "ding"),
+            entry("00-database-info", "de-en", "freedict"),
+            entry("Haus", "de-en", "ding"),
+        ];
+        SearchEngine::build_index(temp_dir.path(), entries).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let filter = PurgeFilter {
+            source: Some("ding".to_string()),
+            language: Some(LanguagePair::DE_EN),
+            word_prefix: Some("00-".to_string()),
+        };
+        assert_eq!(
+            SearchEngine::purge(temp_dir.path(), &filter, true).unwrap(),
+            2
+        );
+        engine.reload().unwrap();
+        assert_eq!(engine.num_docs(), 5);
+
+        assert_eq!(
+            SearchEngine::purge(temp_dir.path(), &filter, false).unwrap(),
+            2
+        );
+        engine.reload().unwrap();
+        assert_eq!(engine.num_docs(), 3);
+        assert_eq!(
+            SearchEngine::purge(temp_dir.path(), &filter, false).unwrap(),
+            0
+        );
+
+        // A blank prefix is no criterion, it must not match every headword
+        let filter = PurgeFilter {
+            word_prefix: Some(" ".to_string()),
+            ..PurgeFilter::default()
+        };
+        assert!(SearchEngine::purge(temp_dir.path(), &filter, false).is_err());
+        engine.reload().unwrap();
+        assert_eq!(engine.num_docs(), 3);
+
+        // Without a language the prefix matches headwords of every language
+        let filter = PurgeFilter {
+            word_prefix: Some("00-DATABASE".to_string()),
+            ..PurgeFilter::default()
+        };
+        assert_eq!(
+            SearchEngine::purge(temp_dir.path(), &filter, false).unwrap(),
+            2
+        );
+        engine.reload().unwrap();
+        assert_eq!(engine.num_docs(), 1);
+
+        assert!(SearchEngine::purge(temp_dir.path(), &PurgeFilter::default(), false).is_err());
+    }
+
     #[test]
     fn test_search_fuzzy() {
         let temp_dir = TempDir::new().unwrap();
@@ -358,7 +2609,7 @@ mod tests {
 
         // Search with a typo
         let results = engine
-            .search("Hauss", SearchMode::Fuzzy, Language::DeEn, 2, 10)
+            .search("Hauss", SearchMode::Fuzzy, LanguagePair::DE_EN, 2, 10)
             .unwrap();
 
         assert!(!results.is_empty());
@@ -374,10 +2625,1177 @@ mod tests {
         let engine = SearchEngine::new(temp_dir.path()).unwrap();
 
         let results = engine
-            .search("Ha", SearchMode::Prefix, Language::DeEn, 2, 10)
+            .search("Ha", SearchMode::Prefix, LanguagePair::DE_EN, 2, 10)
             .unwrap();
 
         assert!(!results.is_empty());
         assert!(results.iter().any(|r| r.word == "haus"));
     }
+
+    #[test]
+    fn test_exact_strips_surrounding_punctuation() {
+        let temp_dir = TempDir::new().unwrap();
+        SearchEngine::build_index(temp_dir.path(), create_test_entries()).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        for query in ["Haus.", "Haus ", " „Haus“, "] {
+            let options = SearchOptions {
+                mode: SearchMode::Exact,
+                ..SearchOptions::default()
+            };
+            let output = engine.search_with_options(query, &options).unwrap();
+            assert_eq!(output.results.len(), 1, "query {:?}", query);
+            assert_eq!(output.results[0].word, "haus");
+            assert_eq!(output.notes.len(), 1);
+        }
+
+        let options = SearchOptions {
+            mode: SearchMode::Exact,
+            ..SearchOptions::default()
+        };
+        let output = engine.search_with_options("Haus", &options).unwrap();
+        assert!(output.notes.is_empty());
+    }
+
+    #[test]
+    fn test_normalizes_copied_text_at_query_and_index_time() {
+        let temp_dir = TempDir::new().unwrap();
+        let entries = vec![
+            DictionaryEntry::new("Ha\u{0308}user".into(), "houses".into(), "de-en".into()),
+            DictionaryEntry::new("Gra\u{FB01}k".into(), "graphic".into(), "de-en".into()),
+            DictionaryEntry::new("find".into(), "finden".into(), "en-de".into()),
+        ];
+        SearchEngine::build_index(temp_dir.path(), entries).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let exact = |query: &str, language| {
+            let options = SearchOptions {
+                mode: SearchMode::Exact,
+                language,
+                strict_diacritics: true,
+                ..SearchOptions::default()
+            };
+            engine.search_with_options(query, &options).unwrap()
+        };
+
+        assert_eq!(exact("Häuser", LanguagePair::DE_EN).results.len(), 1);
+        let decomposed = exact("Ha\u{0308}user", LanguagePair::DE_EN);
+        assert_eq!(decomposed.results[0].word, "häuser");
+        assert_eq!(decomposed.notes.len(), 1);
+        assert_eq!(exact("Grafik", LanguagePair::DE_EN).results.len(), 1);
+        assert_eq!(exact("\u{FB01}nd", LanguagePair::EN_DE).results.len(), 1);
+    }
+
+    #[test]
+    fn test_hyphenated_queries() {
+        let temp_dir = TempDir::new().unwrap();
+        let entries = vec![DictionaryEntry::new(
+            "Haustür".into(),
+            "front door".into(),
+            "de-en".into(),
+        )];
+        SearchEngine::build_index(temp_dir.path(), entries).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let mut options = SearchOptions {
+            mode: SearchMode::Exact,
+            ..SearchOptions::default()
+        };
+        for query in ["Haus\u{00AD}tür", "Haus-\ntür", "Haus-tür"] {
+            let output = engine.search_with_options(query, &options).unwrap();
+            assert_eq!(output.results.len(), 1, "query {:?}", query);
+            assert_eq!(output.results[0].word, "haustür");
+        }
+
+        options.dehyphenate = false;
+        assert!(
+            engine
+                .search_with_options("Haus-tür", &options)
+                .unwrap()
+                .results
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_definition_mode_boolean_queries() {
+        let temp_dir = TempDir::new().unwrap();
+        SearchEngine::build_index(temp_dir.path(), create_test_entries()).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+        let words = |query: &str| {
+            let options = SearchOptions {
+                mode: SearchMode::Definition,
+                ..SearchOptions::default()
+            };
+            let mut words: Vec<String> = engine
+                .search_with_options(query, &options)
+                .unwrap()
+                .results
+                .into_iter()
+                .map(|r| r.word)
+                .collect();
+            words.sort();
+            words
+        };
+
+        assert_eq!(words("house"), ["haus"]);
+        assert_eq!(words("house OR car"), ["auto", "haus"]);
+        assert_eq!(words("buildings AND NOT houses"), Vec::<String>::new());
+        assert_eq!(words("building -car"), ["haus"]);
+        assert_eq!(words("building AND NOT car"), ["haus"]);
+        assert_eq!(words("building NOT car"), ["haus"]);
+        assert_eq!(words("\"house, building\""), ["haus"]);
+
+        let options = SearchOptions {
+            mode: SearchMode::Definition,
+            ..SearchOptions::default()
+        };
+        let error = engine
+            .search_with_options("house AND (", &options)
+            .unwrap_err();
+        assert!(error.downcast_ref::<InvalidQuery>().is_some());
+    }
+
+    #[test]
+    fn test_search_within_word_list() {
+        let temp_dir = TempDir::new().unwrap();
+        SearchEngine::build_index(temp_dir.path(), create_test_entries()).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let mut options = SearchOptions {
+            mode: SearchMode::Prefix,
+            within: vec!["Häuser".to_string(), "Auto".to_string()],
+            ..SearchOptions::default()
+        };
+        let output = engine.search_with_options("Ha", &options).unwrap();
+        assert_eq!(output.results.len(), 1);
+        assert_eq!(output.results[0].word, "häuser");
+
+        options.within = vec!["Auto".to_string()];
+        let output = engine.search_with_options("Ha", &options).unwrap();
+        assert!(output.results.is_empty());
+    }
+
+    #[test]
+    fn test_pattern_and_anagram_modes() {
+        let temp_dir = TempDir::new().unwrap();
+        let entries = ["Haus", "Hase", "Hose", "Haut", "Schau", "Häuser", "Auto"]
+            .iter()
+            .map(|word| DictionaryEntry::new(word.to_string(), "x".into(), "de-en".into()))
+            .chain(std::iter::once(DictionaryEntry::new(
+                "hose".into(),
+                "x".into(),
+                "en-de".into(),
+            )))
+            .collect();
+        SearchEngine::build_index(temp_dir.path(), entries).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+        let words = |query: &str, mode| {
+            let options = SearchOptions {
+                mode,
+                ..SearchOptions::default()
+            };
+            engine
+                .search_with_options(query, &options)
+                .unwrap()
+                .results
+                .into_iter()
+                .map(|r| r.word)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(words("h_s_", SearchMode::Pattern), ["hase", "hose"]);
+        assert_eq!(words("Ha??", SearchMode::Pattern), ["hase", "haus", "haut"]);
+        assert_eq!(words("h.u.", SearchMode::Pattern), Vec::<String>::new());
+        assert_eq!(words("sahu", SearchMode::Anagram), ["haus"]);
+        assert_eq!(words("Chaus", SearchMode::Anagram), ["schau"]);
+        assert_eq!(words("reuhas", SearchMode::Anagram), ["häuser"]);
+        assert_eq!(words("shau x", SearchMode::Anagram), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_rhyme_mode() {
+        let temp_dir = TempDir::new().unwrap();
+        let entries = ["Haus", "Maus", "Klaus", "Hase", "Hause", "Pause", "Laus"]
+            .iter()
+            .map(|word| DictionaryEntry::new(word.to_string(), "x".into(), "de-en".into()))
+            .collect();
+        SearchEngine::build_index(temp_dir.path(), entries).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+        let words = |query: &str| {
+            let options = SearchOptions {
+                mode: SearchMode::Rhyme,
+                ..SearchOptions::default()
+            };
+            engine
+                .search_with_options(query, &options)
+                .unwrap()
+                .results
+                .into_iter()
+                .map(|r| r.word)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(words("Haus"), ["klaus", "laus", "maus"]);
+        assert_eq!(words("Hause"), ["pause"]);
+        assert_eq!(words("Graus"), ["haus", "klaus", "laus", "maus"]);
+        assert!(words("psst").is_empty());
+    }
+
+    #[test]
+    fn test_word_length_filters() {
+        let temp_dir = TempDir::new().unwrap();
+        SearchEngine::build_index(temp_dir.path(), create_test_entries()).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let mut options = SearchOptions {
+            mode: SearchMode::Prefix,
+            min_len: Some(5),
+            ..SearchOptions::default()
+        };
+        let output = engine.search_with_options("Ha", &options).unwrap();
+        assert_eq!(output.results.len(), 1);
+        assert_eq!(output.results[0].word, "häuser");
+
+        options.min_len = None;
+        options.max_len = Some(4);
+        let output = engine.search_with_options("Ha", &options).unwrap();
+        assert_eq!(output.results.len(), 1);
+        assert_eq!(output.results[0].word, "haus");
+
+        options.min_len = Some(5);
+        let output = engine.search_with_options("Ha", &options).unwrap();
+        assert!(output.results.is_empty());
+    }
+
+    #[test]
+    fn test_diversify_keeps_one_result_per_stem() {
+        let temp_dir = TempDir::new().unwrap();
+        let entries = ["Haus", "Hause", "Hauses", "Häuser", "Laus", "Maus"]
+            .iter()
+            .map(|word| DictionaryEntry::new(word.to_string(), "x".into(), "de-en".into()))
+            .collect();
+        SearchEngine::build_index(temp_dir.path(), entries).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let mut options = SearchOptions {
+            limit: 3,
+            ..SearchOptions::default()
+        };
+        let crowded = engine.search_with_options("Hause", &options).unwrap();
+        assert_eq!(crowded.results.len(), 3);
+        assert!(crowded.results.iter().all(|r| stem(&r.word) == "hau"));
+
+        options.diversify = true;
+        let diverse = engine.search_with_options("Hause", &options).unwrap();
+        let words: Vec<&str> = diverse.results.iter().map(|r| r.word.as_str()).collect();
+        assert_eq!(words, ["hause", "laus", "maus"]);
+    }
+
+    #[test]
+    fn test_ranking_weights() {
+        let temp_dir = TempDir::new().unwrap();
+        let entries = vec![
+            DictionaryEntry::new("Haus".into(), "house".into(), "de-en".into())
+                .with_source("freedict"),
+            DictionaryEntry::new("Maus".into(), "mouse".into(), "de-en".into()).with_source("ding"),
+        ];
+        SearchEngine::build_index(temp_dir.path(), entries).unwrap();
+        let words = |engine: &SearchEngine| -> Vec<String> {
+            let output = engine
+                .search_with_options("Haus", &SearchOptions::default())
+                .unwrap();
+            output.results.into_iter().map(|r| r.word).collect()
+        };
+
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+        assert_eq!(words(&engine), ["haus", "maus"]);
+
+        // Distance no longer sorts first, so the preferred dictionary wins
+        let mut ranking = RankingWeights {
+            edit_distance_penalty: Some(vec![0.0]),
+            source_priority: vec!["ding".into()],
+            source_weight: 100.0,
+            ..RankingWeights::default()
+        };
+        let engine = SearchEngine::new(temp_dir.path())
+            .unwrap()
+            .with_ranking(ranking.clone());
+        assert_eq!(words(&engine), ["maus", "haus"]);
+
+        ranking.exact_boost = 1000.0;
+        let engine = SearchEngine::new(temp_dir.path())
+            .unwrap()
+            .with_ranking(ranking);
+        assert_eq!(words(&engine), ["haus", "maus"]);
+    }
+
+    #[test]
+    fn test_source_priority_breaks_ties() {
+        let temp_dir = TempDir::new().unwrap();
+        let entries = vec![
+            DictionaryEntry::new("Haus".into(), "house".into(), "de-en".into())
+                .with_source("freedict"),
+            DictionaryEntry::new("Maus".into(), "mouse".into(), "de-en".into()).with_source("ding"),
+        ];
+        SearchEngine::build_index(temp_dir.path(), entries).unwrap();
+        let options = SearchOptions {
+            mode: SearchMode::Pattern,
+            ..SearchOptions::default()
+        };
+        let words = |engine: &SearchEngine| -> Vec<String> {
+            let output = engine.search_with_options("?aus", &options).unwrap();
+            output.results.into_iter().map(|r| r.word).collect()
+        };
+
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+        assert_eq!(words(&engine), ["haus", "maus"]);
+
+        let engine = SearchEngine::new(temp_dir.path())
+            .unwrap()
+            .with_ranking(RankingWeights {
+                source_priority: vec!["ding".into(), "freedict".into()],
+                ..RankingWeights::default()
+            });
+        assert_eq!(words(&engine), ["maus", "haus"]);
+    }
+
+    #[test]
+    fn test_exact_search_matches_spelling_variants() {
+        let temp_dir = TempDir::new().unwrap();
+        let entries = vec![
+            DictionaryEntry::new("Straße".into(), "street".into(), "de-en".into()),
+            DictionaryEntry::new("Mueller".into(), "miller".into(), "de-en".into()),
+            DictionaryEntry::new("Größe".into(), "size".into(), "de-en".into()),
+        ];
+        SearchEngine::build_index(temp_dir.path(), entries).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let exact = |query: &str, strict_diacritics: bool| -> Vec<String> {
+            let options = SearchOptions {
+                mode: SearchMode::Exact,
+                strict_diacritics,
+                flip_direction: false,
+                ..SearchOptions::default()
+            };
+            let output = engine.search_with_options(query, &options).unwrap();
+            output.results.into_iter().map(|r| r.word).collect()
+        };
+        assert_eq!(exact("Strasse", false), ["straße"]);
+        assert_eq!(exact("Müller", false), ["mueller"]);
+        assert_eq!(exact("Groesse", false), ["größe"]);
+        // Strict matching keeps "Strasse" and "Straße" apart
+        assert!(exact("Strasse", true).is_empty());
+    }
+
+    #[test]
+    fn test_grouping_merges_duplicate_definitions() {
+        let temp_dir = TempDir::new().unwrap();
+        let entries = ["house", "House.", "house ", "building", "house, home"]
+            .iter()
+            .map(|definition| {
+                DictionaryEntry::new("Haus".into(), definition.to_string(), "de-en".into())
+            })
+            .collect();
+        SearchEngine::build_index(temp_dir.path(), entries).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let options = SearchOptions {
+            mode: SearchMode::Exact,
+            ..SearchOptions::default()
+        };
+        let output = engine.search_with_options("Haus", &options).unwrap();
+        assert_eq!(output.results.len(), 1);
+        let definitions = &output.results[0].definitions;
+        assert_eq!(definitions.len(), 3);
+        assert!(definitions.contains(&"building".to_string()));
+        assert!(definitions.contains(&"house, home".to_string()));
+        assert_eq!(output.merged_definitions, 2);
+
+        let options = SearchOptions {
+            group: Grouping::None,
+            ..options
+        };
+        let output = engine.search_with_options("Haus", &options).unwrap();
+        assert_eq!(output.results.len(), 5);
+        assert_eq!(output.merged_definitions, 0);
+    }
+
+    #[test]
+    fn test_flips_direction_when_nothing_is_found() {
+        let temp_dir = TempDir::new().unwrap();
+        SearchEngine::build_index(temp_dir.path(), create_test_entries()).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let mut options = SearchOptions {
+            mode: SearchMode::Exact,
+            language: LanguagePair::DE_EN,
+            ..SearchOptions::default()
+        };
+        let output = engine.search_with_options("house", &options).unwrap();
+        assert!(output.flipped_direction);
+        assert_eq!(output.results[0].word, "house");
+        assert_eq!(output.results[0].language, "en-de");
+
+        let output = engine.search_with_options("Haus", &options).unwrap();
+        assert!(!output.flipped_direction);
+
+        options.flip_direction = false;
+        let output = engine.search_with_options("house", &options).unwrap();
+        assert!(output.results.is_empty());
+        assert!(!output.flipped_direction);
+    }
+
+    #[test]
+    fn test_filter_definitions_within_results() {
+        let temp_dir = TempDir::new().unwrap();
+        SearchEngine::build_index(temp_dir.path(), create_test_entries()).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let mut options = SearchOptions {
+            mode: SearchMode::Prefix,
+            filter: Some("BUILDING".parse().unwrap()),
+            ..SearchOptions::default()
+        };
+        let output = engine.search_with_options("Ha", &options).unwrap();
+        let words: Vec<&str> = output.results.iter().map(|r| r.word.as_str()).collect();
+        assert_eq!(words, ["haus", "häuser"]);
+
+        options.filter = Some("/^houses\\b/".parse().unwrap());
+        let output = engine.search_with_options("Ha", &options).unwrap();
+        assert_eq!(output.results.len(), 1);
+        assert_eq!(output.results[0].word, "häuser");
+    }
+
+    /// Entries "ha000", "ha001"... that match prefix searches for "ha"
+    fn prefix_fillers(count: usize) -> Vec<DictionaryEntry> {
+        (0..count)
+            .map(|i| DictionaryEntry::new(format!("ha{:03}", i), "x".into(), "de-en".into()))
+            .collect()
+    }
+
+    #[test]
+    fn test_filter_applies_while_collecting() {
+        let temp_dir = TempDir::new().unwrap();
+        let options = SearchOptions {
+            mode: SearchMode::Prefix,
+            limit: 10,
+            filter: Some("harbour".parse().unwrap()),
+            ..SearchOptions::default()
+        };
+        // More non-matching candidates than any collection window
+        let mut entries = prefix_fillers(options.limit * 10 + 50);
+        entries.push(DictionaryEntry::new(
+            "Hafen".into(),
+            "harbour, port".into(),
+            "de-en".into(),
+        ));
+        SearchEngine::build_index(temp_dir.path(), entries).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let output = engine.search_with_options("ha", &options).unwrap();
+        let words: Vec<&str> = output.results.iter().map(|r| r.word.as_str()).collect();
+        assert_eq!(words, ["hafen"]);
+    }
+
+    #[test]
+    fn test_grouping_none_returns_one_row_per_definition() {
+        let temp_dir = TempDir::new().unwrap();
+        let entries = vec![
+            DictionaryEntry::new("Bank".to_string(), "bench".to_string(), "de-en".to_string()),
+            DictionaryEntry::new("Bank".to_string(), "bank".to_string(), "de-en".to_string()),
+        ];
+        SearchEngine::build_index(temp_dir.path(), entries).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let mut options = SearchOptions {
+            mode: SearchMode::Exact,
+            ..SearchOptions::default()
+        };
+        let grouped = engine.search_with_options("Bank", &options).unwrap();
+        assert_eq!(grouped.results.len(), 1);
+        assert_eq!(grouped.results[0].definitions.len(), 2);
+
+        options.group = Grouping::None;
+        let rows = engine.search_with_options("Bank", &options).unwrap();
+        assert_eq!(rows.results.len(), 2);
+        assert!(rows.results.iter().all(|r| r.definitions.len() == 1));
+    }
+
+    #[test]
+    fn test_equal_scores_sorted_alphabetically() {
+        let temp_dir = TempDir::new().unwrap();
+        let entries = ["Hauswand", "Haus", "Hausarzt", "Haustür", "Hausboot"]
+            .iter()
+            .map(|word| {
+                DictionaryEntry::new(word.to_string(), "x".to_string(), "de-en".to_string())
+            })
+            .collect();
+        SearchEngine::build_index(temp_dir.path(), entries).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let results = engine
+            .search("Haus", SearchMode::Prefix, LanguagePair::DE_EN, 2, 10)
+            .unwrap();
+        let words: Vec<&str> = results.iter().map(|r| r.word.as_str()).collect();
+        assert_eq!(
+            words,
+            ["haus", "hausarzt", "hausboot", "haustür", "hauswand"]
+        );
+    }
+
+    #[test]
+    fn test_frequency_rank_annotation_and_filter() {
+        let temp_dir = TempDir::new().unwrap();
+        let index_dir = temp_dir.path().join("index");
+        let frequency_dir = temp_dir.path().join("frequency");
+        std::fs::create_dir_all(&frequency_dir).unwrap();
+        std::fs::write(frequency_dir.join("de-en.txt"), "haus\nauto\nhäuser\n").unwrap();
+
+        SearchEngine::build_index(&index_dir, create_test_entries()).unwrap();
+        let engine = SearchEngine::new(&index_dir)
+            .unwrap()
+            .with_frequencies(FrequencyList::load_dir(&frequency_dir).unwrap());
+
+        assert_eq!(engine.frequency_rank(LanguagePair::DE_EN, "Auto"), Some(2));
+
+        let mut options = SearchOptions {
+            mode: SearchMode::Prefix,
+            ..SearchOptions::default()
+        };
+        let output = engine.search_with_options("Ha", &options).unwrap();
+        assert_eq!(output.results.len(), 2);
+        assert!(output.results.iter().any(|r| r.frequency_rank == Some(1)));
+
+        options.min_frequency_rank = Some(2);
+        let output = engine.search_with_options("Ha", &options).unwrap();
+        assert_eq!(output.results.len(), 1);
+        assert_eq!(output.results[0].word, "haus");
+    }
+
+    #[test]
+    fn test_frequency_rank_filter_applies_while_collecting() {
+        let temp_dir = TempDir::new().unwrap();
+        let index_dir = temp_dir.path().join("index");
+        let frequency_dir = temp_dir.path().join("frequency");
+        std::fs::create_dir_all(&frequency_dir).unwrap();
+        std::fs::write(frequency_dir.join("de-en.txt"), "hafen\n").unwrap();
+
+        let options = SearchOptions {
+            mode: SearchMode::Prefix,
+            limit: 10,
+            min_frequency_rank: Some(5),
+            ..SearchOptions::default()
+        };
+        // Unranked words that would fill the collected window
+        let mut entries = prefix_fillers(options.limit * 10 + 50);
+        entries.push(DictionaryEntry::new(
+            "Hafen".into(),
+            "harbour".into(),
+            "de-en".into(),
+        ));
+        SearchEngine::build_index(&index_dir, entries).unwrap();
+        let engine = SearchEngine::new(&index_dir)
+            .unwrap()
+            .with_frequencies(FrequencyList::load_dir(&frequency_dir).unwrap());
+
+        let output = engine.search_with_options("ha", &options).unwrap();
+        let words: Vec<&str> = output.results.iter().map(|r| r.word.as_str()).collect();
+        assert_eq!(words, ["hafen"]);
+        assert_eq!(output.results[0].frequency_rank, Some(1));
+    }
+
+    #[test]
+    fn test_cancelled_search_stops() {
+        let temp_dir = TempDir::new().unwrap();
+        SearchEngine::build_index(temp_dir.path(), create_test_entries()).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let options = SearchOptions::default();
+        assert!(
+            !engine
+                .search_with_options("Haus", &options)
+                .unwrap()
+                .results
+                .is_empty()
+        );
+
+        options.cancellation.cancel();
+        let error = engine.search_with_options("Haus", &options).unwrap_err();
+        assert!(error.downcast_ref::<SearchCancelled>().is_some());
+
+        // Cancelling between segments stops the collector too
+        let collector = Cancellable {
+            inner: Count,
+            cancellation: options.cancellation.clone(),
+            deadline: None,
+            partial: AtomicBool::new(false),
+        };
+        let all = tantivy::query::AllQuery;
+        assert!(engine.searcher().search(&all, &collector).is_err());
+    }
+
+    #[test]
+    fn test_cancelling_stops_within_a_segment() {
+        let temp_dir = TempDir::new().unwrap();
+        let fillers = prefix_fillers(2 * DEADLINE_CHECK_INTERVAL as usize);
+        SearchEngine::build_index(temp_dir.path(), fillers).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        // Cancelled by the first match, with no deadline set
+        let cancellation = Cancellation::default();
+        let cancel = cancellation.clone();
+        let collector = Cancellable {
+            inner: Filtered {
+                inner: Count,
+                keep: Some(Arc::new(move |_: &TantivyDocument| {
+                    cancel.cancel();
+                    true
+                })),
+            },
+            cancellation,
+            deadline: None,
+            partial: AtomicBool::new(false),
+        };
+        let all = tantivy::query::AllQuery;
+        assert!(engine.searcher().search(&all, &collector).is_err());
+    }
+
+    #[test]
+    fn test_deadline_returns_partial_results() {
+        let temp_dir = TempDir::new().unwrap();
+        SearchEngine::build_index(temp_dir.path(), create_test_entries()).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+        let all = tantivy::query::AllQuery;
+        let count = |deadline| {
+            let collector = Cancellable {
+                inner: Count,
+                cancellation: Cancellation::default(),
+                deadline: Some(deadline),
+                partial: AtomicBool::new(false),
+            };
+            let count = engine.searcher().search(&all, &collector).unwrap();
+            (count, collector.partial.into_inner())
+        };
+        let later = Instant::now() + std::time::Duration::from_secs(60);
+        assert_eq!(count(later), (engine.num_docs() as usize, false));
+        assert_eq!(count(Instant::now()), (0, true));
+
+        // An elapsed budget returns what was found, without trying the reverse direction
+        let options = SearchOptions {
+            mode: SearchMode::Fuzzy,
+            language: LanguagePair::DE_EN,
+            deadline: Some(Instant::now()),
+            ..SearchOptions::default()
+        };
+        let output = engine.search_with_options("Haus", &options).unwrap();
+        assert!(output.partial);
+        assert!(output.results.is_empty());
+        assert!(!output.flipped_direction);
+
+        let options = SearchOptions {
+            deadline: Some(later),
+            ..options
+        };
+        let output = engine.search_with_options("Haus", &options).unwrap();
+        assert!(!output.partial);
+        assert!(!output.results.is_empty());
+    }
+
+    #[test]
+    fn test_rank_overrides_result_order() {
+        let temp_dir = TempDir::new().unwrap();
+        let index_dir = temp_dir.path().join("index");
+        let frequency_dir = temp_dir.path().join("frequency");
+        std::fs::create_dir_all(&frequency_dir).unwrap();
+        std::fs::write(frequency_dir.join("de-en.txt"), "hauswand\nhaus\n").unwrap();
+        let entries = ["Hausboot", "Haus", "Hauswand", "Hausarzt"]
+            .iter()
+            .map(|word| DictionaryEntry::new(word.to_string(), "x".into(), "de-en".into()))
+            .collect();
+        SearchEngine::build_index(&index_dir, entries).unwrap();
+        let engine = SearchEngine::new(&index_dir)
+            .unwrap()
+            .with_frequencies(FrequencyList::load_dir(&frequency_dir).unwrap());
+
+        let words = |rank| {
+            let options = SearchOptions {
+                mode: SearchMode::Prefix,
+                rank,
+                ..SearchOptions::default()
+            };
+            let output = engine.search_with_options("Haus", &options).unwrap();
+            output
+                .results
+                .into_iter()
+                .map(|r| r.word)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            words(Ranking::Distance),
+            ["haus", "hausarzt", "hausboot", "hauswand"]
+        );
+        assert_eq!(
+            words(Ranking::Frequency),
+            ["hauswand", "haus", "hausarzt", "hausboot"]
+        );
+    }
+
+    #[test]
+    fn test_target_only_hides_definitions_in_the_source_language() {
+        let temp_dir = TempDir::new().unwrap();
+        let entries = vec![
+            DictionaryEntry::new("Haus".into(), "the house".into(), "de-en".into()),
+            DictionaryEntry::new(
+                "Haus".into(),
+                "das Gebäude zum Wohnen".into(),
+                "de-en".into(),
+            ),
+            DictionaryEntry::new("Haus".into(), "<n, neut> home".into(), "de-en".into()),
+        ];
+        SearchEngine::build_index(temp_dir.path(), entries).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let mut options = SearchOptions {
+            mode: SearchMode::Exact,
+            ..SearchOptions::default()
+        };
+        let all = engine.search_with_options("Haus", &options).unwrap();
+        assert_eq!(all.results[0].definitions.len(), 3);
+
+        options.target_only = true;
+        let target = engine.search_with_options("Haus", &options).unwrap();
+        let mut definitions = target.results[0].definitions.clone();
+        definitions.sort();
+        assert_eq!(definitions, vec!["<n, neut> home", "the house"]);
+    }
+
+    #[test]
+    fn test_target_only_applies_before_collecting() {
+        let temp_dir = TempDir::new().unwrap();
+        let options = SearchOptions {
+            mode: SearchMode::Prefix,
+            limit: 10,
+            target_only: true,
+            ..SearchOptions::default()
+        };
+        // German glosses in a de-en dictionary, enough to fill the window
+        let mut entries: Vec<DictionaryEntry> = prefix_fillers(options.limit * 10 + 50)
+            .into_iter()
+            .map(|entry| DictionaryEntry {
+                definition: "das Ding".to_string(),
+                ..entry
+            })
+            .collect();
+        entries.push(DictionaryEntry::new(
+            "Hafen".into(),
+            "the harbour".into(),
+            "de-en".into(),
+        ));
+        SearchEngine::build_index(temp_dir.path(), entries).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let output = engine.search_with_options("ha", &options).unwrap();
+        let words: Vec<&str> = output.results.iter().map(|r| r.word.as_str()).collect();
+        assert_eq!(words, ["hafen"]);
+    }
+
+    #[test]
+    fn test_register_filter_and_safe_search() {
+        let temp_dir = TempDir::new().unwrap();
+        let entries = vec![
+            DictionaryEntry::new("Kopf".to_string(), "head".to_string(), "de-en".to_string()),
+            DictionaryEntry::new(
+                "Kopf".to_string(),
+                "[coll.] noggin".to_string(),
+                "de-en".to_string(),
+            ),
+            DictionaryEntry::new(
+                "Kopf".to_string(),
+                "[vulg.] knob".to_string(),
+                "de-en".to_string(),
+            ),
+        ];
+        SearchEngine::build_index(temp_dir.path(), entries).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let mut options = SearchOptions {
+            mode: SearchMode::Exact,
+            ..SearchOptions::default()
+        };
+        let all = engine.search_with_options("Kopf", &options).unwrap();
+        assert_eq!(all.results[0].definitions.len(), 3);
+        assert_eq!(
+            all.results[0].registers,
+            vec![Register::Colloquial, Register::Vulgar]
+        );
+
+        options.safe = true;
+        let safe = engine.search_with_options("Kopf", &options).unwrap();
+        assert_eq!(safe.results[0].definitions, vec!["head", "[coll.] noggin"]);
+
+        options.register = Some(Register::Colloquial);
+        let colloquial = engine.search_with_options("Kopf", &options).unwrap();
+        assert_eq!(colloquial.results[0].definitions, vec!["[coll.] noggin"]);
+    }
+
+    #[test]
+    fn test_register_filter_applies_before_collecting() {
+        let temp_dir = TempDir::new().unwrap();
+        let options = SearchOptions {
+            mode: SearchMode::Prefix,
+            limit: 10,
+            register: Some(Register::Colloquial),
+            ..SearchOptions::default()
+        };
+        let mut entries = prefix_fillers(options.limit * 10 + 50);
+        entries.push(DictionaryEntry::new(
+            "Halunke".into(),
+            "(ugs.) rascal".into(),
+            "de-en".into(),
+        ));
+        entries.push(DictionaryEntry::new(
+            "Hackfresse".into(),
+            "[vulg.] ugly mug".into(),
+            "de-en".into(),
+        ));
+        SearchEngine::build_index(temp_dir.path(), entries).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let output = engine.search_with_options("ha", &options).unwrap();
+        let words: Vec<&str> = output.results.iter().map(|r| r.word.as_str()).collect();
+        assert_eq!(words, ["halunke"]);
+
+        let options = SearchOptions {
+            mode: SearchMode::Exact,
+            safe: true,
+            ..SearchOptions::default()
+        };
+        let output = engine.search_with_options("Hackfresse", &options).unwrap();
+        assert!(output.results.is_empty());
+    }
+
+    #[test]
+    fn test_source_filter_and_disabled_sources() {
+        let temp_dir = TempDir::new().unwrap();
+        let entry = |definition: &str, source: &str| {
+            DictionaryEntry::new(
+                "Haus".to_string(),
+                definition.to_string(),
+                "de-en".to_string(),
+            )
+            .with_source(source)
+        };
+        let entries = vec![
+            entry("house", "freedict"),
+            entry("home", "dictcc"),
+            entry("building", "lingvo"),
+        ];
+        SearchEngine::build_index(temp_dir.path(), entries).unwrap();
+
+        let mut options = SearchOptions {
+            mode: SearchMode::Exact,
+            sources: vec!["freedict".to_string(), "lingvo".to_string()],
+            ..SearchOptions::default()
+        };
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+        let output = engine.search_with_options("Haus", &options).unwrap();
+        assert_eq!(output.results[0].definitions, vec!["house", "building"]);
+
+        options.sources.clear();
+        let engine = engine.with_disabled_sources(vec!["freedict".to_string()]);
+        let output = engine.search_with_options("Haus", &options).unwrap();
+        assert_eq!(output.results[0].definitions, vec!["home", "building"]);
+        assert_eq!(
+            output.results[0].sources,
+            vec![
+                ResultSource {
+                    name: "dictcc".to_string(),
+                    license: None,
+                },
+                ResultSource {
+                    name: "lingvo".to_string(),
+                    license: None,
+                },
+            ]
+        );
+        assert!(
+            engine
+                .entries(LanguagePair::DE_EN)
+                .unwrap()
+                .iter()
+                .all(|e| e.source.as_deref() != Some("freedict"))
+        );
+    }
+
+    #[test]
+    fn test_results_carry_source_licenses() {
+        let temp_dir = TempDir::new().unwrap();
+        let entry = |definition: &str, source: &str| {
+            DictionaryEntry::new("Haus".into(), definition.into(), "de-en".into())
+                .with_source(source)
+        };
+        let entries = vec![
+            entry("house", "freedict"),
+            entry("home", "wiktionary"),
+            entry("building", "freedict"),
+        ];
+        SearchEngine::build_index(temp_dir.path(), entries).unwrap();
+        let license = |source: &str, license: &str| SourceLicense {
+            source: source.to_string(),
+            language: "de-en".to_string(),
+            license: Some(license.to_string()),
+        };
+        let engine = SearchEngine::new(temp_dir.path())
+            .unwrap()
+            .with_licenses(vec![
+                license("freedict", "GPL"),
+                license("wiktionary", "CC-BY-SA"),
+            ]);
+
+        let options = SearchOptions {
+            mode: SearchMode::Exact,
+            ..SearchOptions::default()
+        };
+        let output = engine.search_with_options("Haus", &options).unwrap();
+        let sources: Vec<_> = output.results[0]
+            .sources
+            .iter()
+            .map(|s| (s.name.as_str(), s.license.as_deref()))
+            .collect();
+        assert_eq!(
+            sources,
+            vec![("freedict", Some("GPL")), ("wiktionary", Some("CC-BY-SA"))]
+        );
+        assert_eq!(engine.license("dictcc"), None);
+    }
+
+    #[test]
+    fn test_compare_groups_definitions_by_source() {
+        let temp_dir = TempDir::new().unwrap();
+        let entry = |word: &str, definition: &str, source: &str| {
+            DictionaryEntry::new(word.into(), definition.into(), "de-en".into()).with_source(source)
+        };
+        let entries = vec![
+            entry("Haus", "house", "freedict"),
+            entry("Haus", "home", "ding"),
+            entry("Haus", "building", "freedict"),
+            entry("Auto", "car", "ding"),
+        ];
+        SearchEngine::build_index(temp_dir.path(), entries).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let compared = engine.compare(LanguagePair::DE_EN, "HAUS", &[]).unwrap();
+        assert_eq!(
+            compared.into_iter().collect::<Vec<_>>(),
+            vec![
+                ("ding".to_string(), vec!["home".to_string()]),
+                (
+                    "freedict".to_string(),
+                    vec!["house".to_string(), "building".to_string()]
+                ),
+            ]
+        );
+        assert!(
+            engine
+                .compare(LanguagePair::EN_DE, "Haus", &[])
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_letter_counts_groups_umlauts() {
+        let temp_dir = TempDir::new().unwrap();
+        let entry = |word: &str, language: &str| {
+            DictionaryEntry::new(word.to_string(), "x".to_string(), language.to_string())
+        };
+        let entries = vec![
+            entry("Apfel", "de-en"),
+            entry("Ärger", "de-en"),
+            entry("Ärger", "de-en"),
+            entry("Straße", "de-en"),
+            entry("3D-Drucker", "de-en"),
+            entry("apple", "en-de"),
+        ];
+        SearchEngine::build_index(temp_dir.path(), entries).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        assert_eq!(
+            engine.letter_counts(LanguagePair::DE_EN, &[]).unwrap(),
+            vec![
+                ("#".to_string(), 1),
+                ("A".to_string(), 2),
+                ("S".to_string(), 1)
+            ]
+        );
+        assert_eq!(
+            engine.letter_counts(LanguagePair::EN_DE, &[]).unwrap(),
+            vec![("A".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn test_browse_in_both_directions_with_bounds() {
+        let temp_dir = TempDir::new().unwrap();
+        let entries = ["Apfel", "Birne", "Haus", "Haus", "Kirsche", "Zitrone"]
+            .iter()
+            .map(|word| DictionaryEntry::new(word.to_string(), "x".into(), "de-en".into()))
+            .collect();
+        SearchEngine::build_index(temp_dir.path(), entries).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+        let browse = |start, end, direction, limit| {
+            engine
+                .browse(LanguagePair::DE_EN, &[], start, end, direction, limit)
+                .unwrap()
+        };
+
+        assert_eq!(
+            browse(Some("Birne"), None, BrowseDirection::Asc, 2),
+            vec!["birne", "haus"]
+        );
+        assert_eq!(
+            browse(Some("haus"), Some("apfel"), BrowseDirection::Desc, 10),
+            vec!["haus", "birne"]
+        );
+        assert_eq!(
+            browse(None, None, BrowseDirection::Desc, 1),
+            vec!["zitrone"]
+        );
+        assert_eq!(
+            browse(None, Some("haus"), BrowseDirection::Asc, 10),
+            vec!["apfel", "birne"]
+        );
+        assert!(
+            engine
+                .browse(
+                    LanguagePair::EN_DE,
+                    &[],
+                    None,
+                    None,
+                    BrowseDirection::Asc,
+                    10
+                )
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_browse_lists_only_headwords_of_the_searched_dictionaries() {
+        let temp_dir = TempDir::new().unwrap();
+        let entries = [("Apfel", "ding"), ("Birne", "freedict"), ("Haus", "ding")]
+            .iter()
+            .map(|(word, source)| {
+                let mut entry = DictionaryEntry::new(word.to_string(), "x".into(), "de-en".into());
+                entry.source = Some(source.to_string());
+                entry
+            })
+            .collect();
+        SearchEngine::build_index(temp_dir.path(), entries).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        assert_eq!(
+            engine
+                .browse(
+                    LanguagePair::DE_EN,
+                    &["ding".to_string()],
+                    None,
+                    None,
+                    BrowseDirection::Asc,
+                    10
+                )
+                .unwrap(),
+            vec!["apfel", "haus"]
+        );
+    }
+
+    #[test]
+    fn test_scale_max_distance() {
+        assert_eq!(scale_max_distance(3, 2), 1);
+        assert_eq!(scale_max_distance(3, 0), 0);
+        assert_eq!(scale_max_distance(4, 2), 2);
+    }
+
+    #[test]
+    fn test_auto_distance_for_short_queries() {
+        let temp_dir = TempDir::new().unwrap();
+        let entries = vec![
+            DictionaryEntry::new("Ei".to_string(), "egg".to_string(), "de-en".to_string()),
+            DictionaryEntry::new("Eis".to_string(), "ice".to_string(), "de-en".to_string()),
+            DictionaryEntry::new("Tee".to_string(), "tea".to_string(), "de-en".to_string()),
+        ];
+        SearchEngine::build_index(temp_dir.path(), entries).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let mut options = SearchOptions::default();
+        let scaled = engine.search_with_options("Tes", &options).unwrap();
+        assert!(scaled.results.iter().any(|r| r.word == "tee"));
+        assert!(!scaled.results.iter().any(|r| r.word == "eis"));
+        assert_eq!(scaled.notes.len(), 1);
+
+        options.auto_distance = false;
+        let unscaled = engine.search_with_options("Tes", &options).unwrap();
+        assert!(unscaled.results.iter().any(|r| r.word == "eis"));
+        assert!(unscaled.notes.is_empty());
+    }
+
+    #[test]
+    fn test_strict_diacritics() {
+        let temp_dir = TempDir::new().unwrap();
+        let entries = vec![
+            DictionaryEntry::new("Masse".to_string(), "mass".to_string(), "de-en".to_string()),
+            DictionaryEntry::new(
+                "Maße".to_string(),
+                "measurements".to_string(),
+                "de-en".to_string(),
+            ),
+        ];
+        SearchEngine::build_index(temp_dir.path(), entries).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let mut options = SearchOptions {
+            mode: SearchMode::Exact,
+            ..SearchOptions::default()
+        };
+        let folded = engine.search_with_options("Maße", &options).unwrap();
+        assert_eq!(folded.results.len(), 2);
+
+        options.strict_diacritics = true;
+        let strict = engine.search_with_options("Maße", &options).unwrap();
+        assert_eq!(strict.results.len(), 1);
+        assert_eq!(strict.results[0].word, "maße");
+
+        let strict = engine.search_with_options("Masse", &options).unwrap();
+        assert_eq!(strict.results.len(), 1);
+        assert_eq!(strict.results[0].word, "masse");
+    }
+
+    #[test]
+    fn test_search_prefix_edge_ngrams() {
+        let temp_dir = TempDir::new().unwrap();
+        let entries = create_test_entries();
+        let options = IndexOptions { edge_ngrams: true };
+
+        SearchEngine::build_index_with_options(temp_dir.path(), entries, &options).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let results = engine
+            .search("Ha", SearchMode::Prefix, LanguagePair::DE_EN, 2, 10)
+            .unwrap();
+        assert!(results.iter().any(|r| r.word == "haus"));
+        assert!(!results.iter().any(|r| r.word == "auto"));
+
+        // The n-grams are folded like the word field
+        let results = engine
+            .search("Hä", SearchMode::Prefix, LanguagePair::DE_EN, 2, 10)
+            .unwrap();
+        assert!(results.iter().any(|r| r.word == "häuser"));
+
+        // Single character prefixes fall back to the term range
+        let results = engine
+            .search("a", SearchMode::Prefix, LanguagePair::DE_EN, 2, 10)
+            .unwrap();
+        assert!(results.iter().any(|r| r.word == "auto"));
+    }
 }
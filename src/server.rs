@@ -1,47 +1,357 @@
 use axum::{
     Router,
-    extract::{Query, State},
-    http::StatusCode,
-    response::{IntoResponse, Json},
-    routing::get,
+    extract::{ConnectInfo, Path, Query, RawQuery, Request, State},
+    http::{HeaderMap, StatusCode, header},
+    middleware::{self, Next},
+    response::{IntoResponse, Json, Response},
+    routing::{get, post},
 };
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Instant;
 use tower_http::trace::TraceLayer;
-use tracing::info;
+use tracing::{info, warn};
 
-use crate::models::{HealthResponse, SearchQuery, SearchResponse, StatsResponse};
-use crate::search::SearchEngine;
+use crate::abbreviations::Abbreviations;
+use crate::analytics::QueryCounter;
+use crate::apikeys::ApiKeys;
+use crate::config::AdminTls;
+use crate::favorites::{Favorites, SheetFormat, write_sheet};
+use crate::history::{self, SessionHistory};
+use crate::html;
+use crate::memory;
+use crate::models::{
+    AbbreviationEntry, AbbreviationsResponse, BrowseQuery, BrowseResponse, CacheSizes,
+    Cancellation, CompareQuery, CompareResponse, FavoritesQuery, FrequencyQuery, FrequencyResponse,
+    HealthResponse, HistoryQuery, HistoryResponse, LanguagePair, LanguageQuery, LetterCount,
+    LettersResponse, LicensesResponse, MemoryStats, MissesQuery, MissesResponse, PopularQuery,
+    PopularResponse, PrimeRequest, PrimeResponse, QuizQuery, QuizResponse, ResultFormat,
+    SearchDefaults, SearchMode, SearchOptions, SearchQuery, SearchResponse, SheetQuery,
+    SourceDefinitions, StatsResponse, UsageResponse, WithinRequest, WordOfTheDayResponse,
+};
+use crate::pools::{PoolBusy, PoolSizes, QueryClass, SearchPools};
+use crate::querylog::{QueryLog, QueryLogRecord};
+use crate::quiz;
+use crate::search::{InvalidQuery, SearchEngine, SearchOutput};
+use crate::snapshot::SnapshotPins;
+use crate::tts::Tts;
+use crate::usage::UsageTracker;
+use crate::wiktionary;
+use crate::wotd;
+
+/// Most candidate words accepted by `/search/within`
+const MAX_WITHIN_WORDS: usize = 10_000;
+
+/// Most queries accepted by `/admin/prime` in one request
+pub const MAX_PRIME_QUERIES: usize = 10_000;
+
+/// Most questions `/quiz` returns in one request
+const MAX_QUIZ_QUESTIONS: usize = 100;
 
 /// Application state
 #[derive(Clone)]
 pub struct AppState {
     search_engine: Arc<SearchEngine>,
+    abbreviations: Arc<Abbreviations>,
+    query_log: Option<Arc<QueryLog>>,
+    misses: Arc<QueryCounter>,
+    /// Searches that found something, for suggestions from the query history
+    history: Arc<QueryCounter>,
+    sessions: Arc<SessionHistory>,
+    snapshots: Arc<SnapshotPins>,
+    /// Values for search parameters a request omits, including the language
+    defaults: SearchDefaults,
+    api_keys: Arc<ApiKeys>,
+    usage: Arc<UsageTracker>,
+    favorites_path: Option<Arc<PathBuf>>,
+    /// Synthesizes `/audio` pronunciations of words without a recording
+    tts: Option<Arc<Tts>>,
+    /// Downloaded recordings `/audio` plays before falling back to TTS
+    recordings_dir: Option<Arc<PathBuf>>,
+    /// Threads searches run on, apart from the async workers
+    pools: Arc<SearchPools>,
+    /// Admin routes only answer connections that presented a trusted client certificate
+    client_cert_required: bool,
 }
 
+/// Request extension marking a connection whose client certificate was verified
+#[derive(Debug, Clone, Copy)]
+pub struct ClientCertificate;
+
 impl AppState {
     pub fn new(search_engine: SearchEngine) -> Self {
         Self {
             search_engine: Arc::new(search_engine),
+            abbreviations: Arc::new(Abbreviations::default()),
+            query_log: None,
+            misses: Arc::new(QueryCounter::default()),
+            history: Arc::new(QueryCounter::default()),
+            sessions: Arc::new(SessionHistory::default()),
+            snapshots: Arc::new(SnapshotPins::default()),
+            defaults: SearchDefaults::default(),
+            api_keys: Arc::new(ApiKeys::default()),
+            usage: Arc::new(UsageTracker::default()),
+            favorites_path: None,
+            tts: None,
+            recordings_dir: None,
+            pools: Arc::new(
+                SearchPools::new(&PoolSizes::default()).expect("default search pools start"),
+            ),
+            client_cert_required: false,
         }
     }
+
+    /// Run searches on pools of these sizes
+    pub fn with_pool_sizes(mut self, sizes: &PoolSizes) -> anyhow::Result<Self> {
+        self.pools = Arc::new(SearchPools::new(sizes)?);
+        Ok(self)
+    }
+
+    /// Serve the favorites saved in this file
+    pub fn with_favorites(mut self, path: PathBuf) -> Self {
+        self.favorites_path = Some(Arc::new(path));
+        self
+    }
+
+    /// Serve pronunciations synthesized by this backend
+    pub fn with_tts(mut self, tts: Tts) -> Self {
+        self.tts = Some(Arc::new(tts));
+        self
+    }
+
+    /// Play the recordings downloaded to this directory (`import --download-audio`)
+    pub fn with_recordings(mut self, dir: PathBuf) -> Self {
+        self.recordings_dir = Some(Arc::new(dir));
+        self
+    }
+
+    /// Require one of these keys, in the `X-API-Key` header, for searches
+    pub fn with_api_keys(mut self, api_keys: ApiKeys) -> Self {
+        self.api_keys = Arc::new(api_keys);
+        self
+    }
+
+    /// Language used when a request does not specify `lang`
+    pub fn with_default_language(mut self, language: LanguagePair) -> Self {
+        self.defaults.lang = language;
+        self
+    }
+
+    /// Use these values for omitted search parameters, keeping the default language
+    pub fn with_search_defaults(mut self, defaults: SearchDefaults) -> Self {
+        self.defaults = SearchDefaults {
+            lang: self.defaults.lang,
+            ..defaults
+        };
+        self
+    }
+
+    /// Record every search in the given query log
+    pub fn with_query_log(mut self, query_log: QueryLog) -> Self {
+        self.query_log = Some(Arc::new(query_log));
+        self
+    }
+
+    /// Use a custom abbreviation glossary instead of the built-in one
+    pub fn with_abbreviations(mut self, abbreviations: Abbreviations) -> Self {
+        self.abbreviations = Arc::new(abbreviations);
+        self
+    }
 }
 
 /// Create the HTTP server router
 pub fn create_router(state: AppState) -> Router {
     Router::new()
         .route("/search", get(search_handler))
+        .route("/search/within", post(search_within_handler))
         .route("/health", get(health_handler))
         .route("/stats", get(stats_handler))
+        .route("/frequency/:word", get(frequency_handler))
+        .route("/abbreviations", get(abbreviations_handler))
+        .route("/licenses", get(licenses_handler))
+        .route("/letters", get(letters_handler))
+        .route("/browse", get(browse_handler))
+        .route("/compare", get(compare_handler))
+        .route("/favorites", get(favorites_handler))
+        .route("/favorites/export", get(favorites_export_handler))
+        .route("/wotd", get(wotd_handler))
+        .route("/wotd/feed.xml", get(wotd_feed_handler))
+        .route("/quiz", get(quiz_handler))
+        .route("/audio/:word", get(audio_handler))
+        .route("/analytics/misses", get(misses_handler))
+        .route("/suggest/popular", get(popular_handler))
+        .route("/history", get(history_handler))
+        .merge(admin_router(state.clone()))
         .layer(TraceLayer::new_for_http())
         .with_state(state)
 }
 
+/// Routes under /admin, guarded by a client certificate when serving HTTPS
+fn admin_router(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/admin/usage", get(usage_handler))
+        .route("/admin/prime", post(prime_handler))
+        .route_layer(middleware::from_fn_with_state(state, require_client_cert))
+}
+
+/// Reject requests on connections without a verified client certificate,
+/// regardless of API keys
+async fn require_client_cert(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    if state.client_cert_required && request.extensions().get::<ClientCertificate>().is_none() {
+        return Err(AppError::Forbidden(
+            "A trusted client certificate is required".to_string(),
+        ));
+    }
+    Ok(next.run(request).await)
+}
+
 /// Search endpoint handler
 async fn search_handler(
     State(state): State<AppState>,
-    Query(params): Query<SearchQuery>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    RawQuery(query): RawQuery,
 ) -> Result<Json<SearchResponse>, AppError> {
+    let params = parse_search_query(query.as_deref().unwrap_or_default())?;
+    search_blocking(state, connect_info, headers, params, Vec::new())
+        .await
+        .map(Json)
+}
+
+/// Search parameters from a query string. Repeated `q` parameters are
+/// joined with commas, so `q=hau&q=grü` means `q=hau,grü`.
+fn parse_search_query(query: &str) -> Result<SearchQuery, AppError> {
+    let bad_request = |e: serde_urlencoded::de::Error| AppError::BadRequest(e.to_string());
+    let pairs: Vec<(String, String)> = serde_urlencoded::from_str(query).map_err(bad_request)?;
+    if pairs.iter().filter(|(key, _)| key == "q").count() < 2 {
+        return serde_urlencoded::from_str(query).map_err(bad_request);
+    }
+
+    let q = pairs
+        .iter()
+        .filter(|(key, _)| key == "q")
+        .map(|(_, value)| value.as_str())
+        .collect::<Vec<_>>()
+        .join(",");
+    let mut merged: Vec<(&str, &str)> = vec![("q", &q)];
+    merged.extend(
+        pairs
+            .iter()
+            .filter(|(key, _)| key != "q")
+            .map(|(key, value)| (key.as_str(), value.as_str())),
+    );
+    let query =
+        serde_urlencoded::to_string(merged).map_err(|e| AppError::BadRequest(e.to_string()))?;
+    serde_urlencoded::from_str(&query).map_err(bad_request)
+}
+
+/// Wordlist-constrained search endpoint handler: only matches among `words` are returned
+async fn search_within_handler(
+    State(state): State<AppState>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    Json(request): Json<WithinRequest>,
+) -> Result<Json<SearchResponse>, AppError> {
+    if request.words.is_empty() {
+        return Err(AppError::BadRequest("words cannot be empty".to_string()));
+    }
+    if request.words.len() > MAX_WITHIN_WORDS {
+        return Err(AppError::BadRequest(format!(
+            "At most {} words are accepted",
+            MAX_WITHIN_WORDS
+        )));
+    }
+    search_blocking(state, connect_info, headers, request.query, request.words)
+        .await
+        .map(Json)
+}
+
+/// Cancels a search when dropped. Axum drops a handler's future when its
+/// client disconnects, taking the guard with it.
+struct CancelOnDrop(Cancellation);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.cancel();
+    }
+}
+
+/// Run `search` on the light or heavy search pool, stopping it if the client goes away
+async fn search_blocking(
+    state: AppState,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    mut params: SearchQuery,
+    within: Vec<String>,
+) -> Result<SearchResponse, AppError> {
+    params.apply_defaults(&state.defaults);
+    let class = QueryClass::of(params.mode(), params.filter.is_some());
+    let cancellation = Cancellation::default();
+    let _guard = CancelOnDrop(cancellation.clone());
+    let pools = state.pools.clone();
+    pools
+        .run(class, move || {
+            search(&state, connect_info, &headers, params, within, cancellation)
+        })
+        .await
+        .map_err(|e| match e.downcast_ref::<PoolBusy>() {
+            Some(busy) => AppError::TooManyRequests(busy.to_string()),
+            None => AppError::Internal(e.to_string()),
+        })?
+}
+
+/// Check the request's API key when keys are configured: `options` are limited
+/// to the key's scope and the request counts against its daily quota
+fn authorize(
+    state: &AppState,
+    headers: &HeaderMap,
+    options: &mut SearchOptions,
+) -> Result<(), AppError> {
+    if !state.api_keys.required() {
+        return Ok(());
+    }
+    let key = state
+        .api_keys
+        .from_headers(headers)
+        .ok_or_else(|| AppError::Unauthorized("Missing or unknown API key".to_string()))?;
+    key.restrict(options).map_err(AppError::Forbidden)?;
+    if !state.usage.record(key) {
+        return Err(AppError::TooManyRequests(
+            "Daily quota exceeded for this API key".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// The API key scope of a request for `language` outside the search endpoints
+fn key_scope(
+    state: &AppState,
+    headers: &HeaderMap,
+    language: LanguagePair,
+) -> Result<SearchOptions, AppError> {
+    let mut scope = SearchOptions {
+        language,
+        ..SearchOptions::default()
+    };
+    authorize(state, headers, &mut scope)?;
+    Ok(scope)
+}
+
+/// Run a search for either search endpoint: validation, API key checks, logging
+fn search(
+    state: &AppState,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: &HeaderMap,
+    params: SearchQuery,
+    within: Vec<String>,
+    cancellation: Cancellation,
+) -> Result<SearchResponse, AppError> {
     let start = Instant::now();
 
     // Validate query
@@ -49,63 +359,663 @@ async fn search_handler(
         return Err(AppError::BadRequest("Query cannot be empty".to_string()));
     }
 
-    if params.max_distance > 2 {
+    if params.max_distance.is_some_and(|distance| distance > 2) {
         return Err(AppError::BadRequest("max_distance must be 0-2".to_string()));
     }
 
-    // Perform search
-    let results = state
-        .search_engine
-        .search(
-            &params.q,
-            params.mode,
-            params.lang,
-            params.max_distance,
-            params.limit,
-        )
-        .map_err(|e| AppError::Internal(e.to_string()))?;
+    if let (Some(min_len), Some(max_len)) = (params.min_len, params.max_len)
+        && min_len > max_len
+    {
+        return Err(AppError::BadRequest(
+            "min_len cannot be greater than max_len".to_string(),
+        ));
+    }
+
+    let mut options = params.options();
+    options.filter = params
+        .filter
+        .as_deref()
+        .map(str::parse)
+        .transpose()
+        .map_err(|e: anyhow::Error| AppError::BadRequest(e.to_string()))?;
+    options.within = within;
+    options.cancellation = cancellation;
+    authorize(state, headers, &mut options)?;
+
+    // Later pages of a pinned search read the same index contents as the first
+    let (searcher, snapshot) = match &params.snapshot {
+        Some(token) => {
+            let searcher = state.snapshots.get(token).ok_or_else(|| {
+                AppError::Gone("Snapshot expired or unknown, search again without it".to_string())
+            })?;
+            (searcher, Some(token.clone()))
+        }
+        None => {
+            let searcher = state.search_engine.searcher();
+            let token = params.pin.then(|| state.snapshots.pin(searcher.clone()));
+            (searcher, token)
+        }
+    };
+
+    // Perform search, once per prefix when several were requested
+    let prefixes = params.prefixes();
+    let queries = if prefixes.is_empty() {
+        vec![params.q.as_str()]
+    } else {
+        prefixes.clone()
+    };
+    let mut output = SearchOutput::default();
+    let mut by_prefix = BTreeMap::new();
+    for query in queries {
+        let mut query_output = state
+            .search_engine
+            .search_snapshot(&searcher, query, &options)
+            .map_err(|e| match e.downcast_ref::<InvalidQuery>() {
+                Some(invalid) => AppError::BadRequest(invalid.to_string()),
+                None => AppError::Internal(e.to_string()),
+            })?;
+
+        if params.expand_abbrev {
+            for result in &mut query_output.results {
+                for definition in &mut result.definitions {
+                    *definition = state.abbreviations.expand(definition);
+                }
+            }
+        }
+        if params.format == ResultFormat::Html {
+            for result in &mut query_output.results {
+                result.html = Some(html::render_result(result));
+            }
+        }
+
+        if !prefixes.is_empty() {
+            by_prefix.insert(query.to_string(), query_output.results.clone());
+        }
+        output.results.append(&mut query_output.results);
+        output.notes.append(&mut query_output.notes);
+        output.timings += query_output.timings;
+        output.flipped_direction |= query_output.flipped_direction;
+        output.merged_definitions += query_output.merged_definitions;
+        output.partial |= query_output.partial;
+        output.index_generation = query_output.index_generation;
+    }
 
     let query_time_ms = start.elapsed().as_secs_f64() * 1000.0;
-    let total_results = results.len();
+    let total_results = output.results.len();
 
-    Ok(Json(SearchResponse {
-        results,
+    if total_results > 0 {
+        state.history.record(params.language().as_str(), &params.q);
+    } else if !output.partial {
+        // A search cut short by its budget may have missed the word
+        state.misses.record(params.language().as_str(), &params.q);
+    }
+    if let Some(session) = history::session_id(params.session.as_deref(), headers) {
+        state.sessions.record(
+            &session,
+            &params.q,
+            params.language().as_str(),
+            total_results,
+        );
+    }
+
+    if let Some(query_log) = &state.query_log {
+        let mut record = QueryLogRecord::new(params.clone(), total_results, query_time_ms);
+        if query_log.include_ip() {
+            record.ip = connect_info.map(|ConnectInfo(addr)| addr.ip().to_string());
+        }
+        if let Err(e) = query_log.log(&record) {
+            warn!("Failed to write query log: {:#}", e);
+        }
+    }
+
+    Ok(SearchResponse {
+        results: output.results,
         query_time_ms,
         total_results,
-    }))
+        notes: output.notes,
+        timings: params.debug.then_some(output.timings),
+        merged_definitions: params.debug.then_some(output.merged_definitions),
+        flipped_direction: output.flipped_direction,
+        partial: output.partial,
+        prefixes: (!prefixes.is_empty()).then_some(by_prefix),
+        index_generation: output.index_generation,
+        snapshot,
+    })
 }
 
 /// Health check endpoint handler
-async fn health_handler() -> Json<HealthResponse> {
-    Json(HealthResponse {
-        status: "ok".to_string(),
-        version: env!("CARGO_PKG_VERSION").to_string(),
-    })
+async fn health_handler(State(state): State<AppState>) -> (StatusCode, Json<HealthResponse>) {
+    let (status, code) = if state.search_engine.num_docs() > 0 {
+        ("ok", StatusCode::OK)
+    } else {
+        ("not_ready", StatusCode::SERVICE_UNAVAILABLE)
+    };
+    (
+        code,
+        Json(HealthResponse {
+            status: status.to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            defaults: state.defaults,
+        }),
+    )
 }
 
 /// Statistics endpoint handler
 async fn stats_handler(State(state): State<AppState>) -> Result<Json<StatsResponse>, AppError> {
-    let (total_entries, en_de_entries, de_en_entries) = state
+    let (total_entries, languages) = state
         .search_engine
         .get_stats()
         .map_err(|e| AppError::Internal(e.to_string()))?;
 
+    let index = state
+        .search_engine
+        .index_memory()
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+
     // Get index size (approximate)
     let index_size_bytes = 0; // TODO: Implement actual size calculation
 
     Ok(Json(StatsResponse {
         total_entries,
-        en_de_entries,
-        de_en_entries,
+        languages,
         index_size_bytes,
+        reloads: state.search_engine.reload_stats(),
+        memory: MemoryStats {
+            resident_bytes: memory::resident_bytes(),
+            index,
+            caches: CacheSizes {
+                pinned_snapshots: state.snapshots.pinned(),
+                sessions: state.sessions.tracked(),
+                query_history: state.history.tracked(),
+                missed_queries: state.misses.tracked(),
+                api_keys: state.usage.tracked(),
+                frequency_words: state.search_engine.frequency_words(),
+            },
+        },
+    }))
+}
+
+/// Word frequency endpoint handler
+async fn frequency_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(word): Path<String>,
+    Query(params): Query<FrequencyQuery>,
+) -> Result<Json<FrequencyResponse>, AppError> {
+    let language = params.lang.unwrap_or(state.defaults.lang);
+    key_scope(&state, &headers, language)?;
+    let frequency_rank = state
+        .search_engine
+        .frequency_rank(language, &word)
+        .ok_or_else(|| AppError::NotFound(format!("No frequency data for '{}'", word)))?;
+
+    Ok(Json(FrequencyResponse {
+        word: word.to_lowercase(),
+        language: language.as_str().to_string(),
+        frequency_rank,
+    }))
+}
+
+/// Letter index endpoint handler: headword counts per initial letter
+async fn letters_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<LanguageQuery>,
+) -> Result<Json<LettersResponse>, AppError> {
+    let language = params.lang.unwrap_or(state.defaults.lang);
+    let scope = key_scope(&state, &headers, language)?;
+    let letters = state
+        .search_engine
+        .letter_counts(language, &scope.sources)
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(Json(LettersResponse {
+        language: language.as_str().to_string(),
+        letters: letters
+            .into_iter()
+            .map(|(letter, count)| LetterCount { letter, count })
+            .collect(),
+    }))
+}
+
+/// Headword browsing endpoint handler
+async fn browse_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<BrowseQuery>,
+) -> Result<Json<BrowseResponse>, AppError> {
+    let language = params.lang.unwrap_or(state.defaults.lang);
+    let scope = key_scope(&state, &headers, language)?;
+    // Fetch one extra headword to tell whether another page follows
+    let mut headwords = state
+        .search_engine
+        .browse(
+            language,
+            &scope.sources,
+            params.start.as_deref(),
+            params.end.as_deref(),
+            params.direction,
+            params.limit + 1,
+        )
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+    let next = if headwords.len() > params.limit {
+        headwords.pop()
+    } else {
+        None
+    };
+
+    Ok(Json(BrowseResponse {
+        language: language.as_str().to_string(),
+        direction: params.direction,
+        headwords,
+        next,
+    }))
+}
+
+/// Dictionary comparison endpoint handler: a headword's definitions per dictionary
+async fn compare_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<CompareQuery>,
+) -> Result<Json<CompareResponse>, AppError> {
+    let language = params.lang.unwrap_or(state.defaults.lang);
+    let scope = key_scope(&state, &headers, language)?;
+    let sources = state
+        .search_engine
+        .compare(language, &params.word, &scope.sources)
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+    if sources.is_empty() {
+        return Err(AppError::NotFound(format!(
+            "No {} entry for '{}'",
+            language.as_str(),
+            params.word
+        )));
+    }
+
+    Ok(Json(CompareResponse {
+        word: params.word.trim().to_lowercase(),
+        language: language.as_str().to_string(),
+        sources: sources
+            .into_iter()
+            .map(|(source, definitions)| SourceDefinitions {
+                license: state.search_engine.license(&source),
+                source: (!source.is_empty()).then_some(source),
+                definitions,
+            })
+            .collect(),
+    }))
+}
+
+/// Saved favorites, re-read on every request so CLI changes show up
+fn load_favorites(state: &AppState) -> Result<Favorites, AppError> {
+    match &state.favorites_path {
+        Some(path) => Favorites::load(path.as_ref()).map_err(|e| AppError::Internal(e.to_string())),
+        None => Ok(Favorites::default()),
+    }
+}
+
+/// Saved favorites of `language`, or of all languages. With API keys
+/// configured, the language defaults to the server's and must be in the key's scope.
+fn scoped_favorites(
+    state: &AppState,
+    headers: &HeaderMap,
+    language: Option<LanguagePair>,
+) -> Result<Favorites, AppError> {
+    let language = language.or(state.api_keys.required().then_some(state.defaults.lang));
+    if let Some(language) = language {
+        key_scope(state, headers, language)?;
+    }
+
+    let mut favorites = load_favorites(state)?;
+    if let Some(language) = language {
+        favorites
+            .favorites
+            .retain(|favorite| favorite.language == language.as_str());
+    }
+    Ok(favorites)
+}
+
+/// Favorites endpoint handler
+async fn favorites_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<FavoritesQuery>,
+) -> Result<Json<Favorites>, AppError> {
+    Ok(Json(scoped_favorites(&state, &headers, params.lang)?))
+}
+
+/// Favorites study sheet endpoint handler
+async fn favorites_export_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<SheetQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let format: SheetFormat = params
+        .format
+        .parse()
+        .map_err(|e: anyhow::Error| AppError::BadRequest(e.to_string()))?;
+    let favorites = scoped_favorites(&state, &headers, params.lang)?;
+
+    let mut sheet = Vec::new();
+    write_sheet(&mut sheet, format, &favorites.favorites)
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+    let content_type = match format {
+        SheetFormat::Csv => "text/csv; charset=utf-8",
+        SheetFormat::Markdown => "text/markdown; charset=utf-8",
+    };
+    Ok(([(header::CONTENT_TYPE, content_type)], sheet))
+}
+
+/// Word of the day endpoint handler
+async fn wotd_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<LanguageQuery>,
+) -> Result<Json<WordOfTheDayResponse>, AppError> {
+    let language = params.lang.unwrap_or(state.defaults.lang);
+    let scope = key_scope(&state, &headers, language)?;
+    let word = wotd::word_of_the_day(
+        &state.search_engine,
+        language,
+        &scope.sources,
+        wotd::today(),
+    )
+    .map_err(|e| AppError::Internal(e.to_string()))?
+    .ok_or_else(|| AppError::NotFound(format!("No {} entries", language.as_str())))?;
+
+    Ok(Json(WordOfTheDayResponse {
+        date: wotd::date(word.day),
+        entry: word.entry,
     }))
 }
 
+/// Atom feed of the recent words of the day
+async fn wotd_feed_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<LanguageQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let language = params.lang.unwrap_or(state.defaults.lang);
+    let scope = key_scope(&state, &headers, language)?;
+    let words = wotd::recent(
+        &state.search_engine,
+        language,
+        &scope.sources,
+        wotd::today(),
+    )
+    .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok((
+        [(header::CONTENT_TYPE, "application/atom+xml; charset=utf-8")],
+        wotd::atom_feed(language, &words),
+    ))
+}
+
+/// Vocabulary quiz endpoint handler
+async fn quiz_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<QuizQuery>,
+) -> Result<Json<QuizResponse>, AppError> {
+    if params.count == 0 || params.count > MAX_QUIZ_QUESTIONS {
+        return Err(AppError::BadRequest(format!(
+            "count must be between 1 and {}",
+            MAX_QUIZ_QUESTIONS
+        )));
+    }
+    let language = params.lang.unwrap_or(state.defaults.lang);
+    let scope = key_scope(&state, &headers, language)?;
+    let seed = params.seed.unwrap_or_else(|| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default()
+    });
+
+    let engine = state.search_engine.clone();
+    let questions = tokio::task::spawn_blocking(move || {
+        quiz::build_quiz(&engine, language, &scope.sources, params.count, seed)
+    })
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?
+    .map_err(|e| AppError::Internal(e.to_string()))?;
+    if questions.is_empty() {
+        return Err(AppError::NotFound(format!(
+            "Not enough {} entries for a quiz",
+            language.as_str()
+        )));
+    }
+
+    Ok(Json(QuizResponse {
+        language: language.as_str().to_string(),
+        quiz_type: params.quiz_type,
+        seed,
+        questions,
+    }))
+}
+
+/// Pronunciation endpoint handler: a downloaded recording of the word, or
+/// audio from the TTS backend. Only headwords of the dictionary are
+/// synthesized, so the audio cache cannot be filled with arbitrary text.
+async fn audio_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(word): Path<String>,
+    Query(params): Query<LanguageQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    if state.tts.is_none() && state.recordings_dir.is_none() {
+        return Err(AppError::NotFound(
+            "No recordings or text-to-speech configured".to_string(),
+        ));
+    }
+    let language = params.lang.unwrap_or(state.defaults.lang);
+    let mut options = SearchOptions {
+        mode: SearchMode::Exact,
+        language,
+        limit: 1,
+        ..SearchOptions::default()
+    };
+    authorize(&state, &headers, &mut options)?;
+
+    let engine = state.search_engine.clone();
+    let audio = tokio::task::spawn_blocking(move || -> anyhow::Result<_> {
+        let Some(entry) = engine
+            .search_with_options(word.trim(), &options)?
+            .results
+            .pop()
+        else {
+            return Ok(None);
+        };
+        if let Some(dir) = &state.recordings_dir
+            && let Some(path) = entry
+                .audio_urls
+                .iter()
+                .map(|url| wiktionary::audio_path(dir, url))
+                .find(|path| path.is_file())
+        {
+            let content_type = wiktionary::audio_content_type(&path).to_string();
+            return Ok(Some((content_type, std::fs::read(&path)?)));
+        }
+        match &state.tts {
+            Some(tts) => Ok(Some((
+                tts.content_type().to_string(),
+                tts.speak(language.source(), &entry.word)?,
+            ))),
+            None => Ok(None),
+        }
+    })
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?
+    .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    let (content_type, audio) = audio.ok_or_else(|| {
+        AppError::NotFound(format!(
+            "No {} pronunciation of this word",
+            language.as_str()
+        ))
+    })?;
+    Ok(([(header::CONTENT_TYPE, content_type)], audio))
+}
+
+/// Licenses endpoint handler, for attributing the dictionaries results come from
+async fn licenses_handler(State(state): State<AppState>) -> Json<LicensesResponse> {
+    Json(LicensesResponse {
+        licenses: state.search_engine.licenses().to_vec(),
+    })
+}
+
+/// Abbreviation glossary endpoint handler
+async fn abbreviations_handler(State(state): State<AppState>) -> Json<AbbreviationsResponse> {
+    Json(AbbreviationsResponse {
+        abbreviations: state
+            .abbreviations
+            .entries()
+            .map(|(abbreviation, expansion)| AbbreviationEntry {
+                abbreviation: abbreviation.to_string(),
+                expansion: expansion.to_string(),
+            })
+            .collect(),
+    })
+}
+
+/// Zero-result queries endpoint handler, restricted to admin keys
+async fn misses_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<MissesQuery>,
+) -> Result<Json<MissesResponse>, AppError> {
+    require_admin_key(&state, &headers)?;
+
+    Ok(Json(MissesResponse {
+        misses: state
+            .misses
+            .top(params.lang.as_ref().map(|lang| lang.as_str()), params.limit),
+    }))
+}
+
+/// Query history suggestions endpoint handler
+async fn popular_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<PopularQuery>,
+) -> Result<Json<PopularResponse>, AppError> {
+    // With API keys configured, a key only sees the searches of one of its languages
+    let language = params
+        .lang
+        .or(state.api_keys.required().then_some(state.defaults.lang));
+    if let Some(language) = language {
+        key_scope(&state, &headers, language)?;
+    }
+
+    Ok(Json(PopularResponse {
+        queries: state.history.with_prefix(
+            language.as_ref().map(|lang| lang.as_str()),
+            &params.prefix,
+            params.limit,
+        ),
+    }))
+}
+
+/// Session lookup history endpoint handler
+async fn history_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<HistoryQuery>,
+) -> Result<Json<HistoryResponse>, AppError> {
+    let session = history::session_id(params.session.as_deref(), &headers).ok_or_else(|| {
+        AppError::BadRequest(format!(
+            "A session parameter or {} cookie is required",
+            history::SESSION_COOKIE
+        ))
+    })?;
+    Ok(Json(HistoryResponse {
+        lookups: state.sessions.recent(&session, params.limit),
+    }))
+}
+
+/// API key usage endpoint handler, restricted to admin keys
+async fn usage_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<UsageResponse>, AppError> {
+    require_admin_key(&state, &headers)?;
+
+    Ok(Json(UsageResponse {
+        keys: state.usage.report(state.api_keys.iter()),
+    }))
+}
+
+/// Run a list of queries after a deploy or reload, so the index pages and
+/// document blocks they touch are loaded before real users search. The
+/// queries are not logged or counted in analytics.
+async fn prime_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<PrimeRequest>,
+) -> Result<Json<PrimeResponse>, AppError> {
+    require_admin_key(&state, &headers)?;
+    if request.queries.len() > MAX_PRIME_QUERIES {
+        return Err(AppError::BadRequest(format!(
+            "At most {} queries are accepted",
+            MAX_PRIME_QUERIES
+        )));
+    }
+
+    let options = SearchOptions {
+        mode: request.mode.unwrap_or(state.defaults.mode),
+        language: request.lang.unwrap_or(state.defaults.lang),
+        max_distance: state.defaults.max_distance,
+        limit: state.defaults.limit,
+        ..SearchOptions::default()
+    };
+    let engine = state.search_engine.clone();
+    let started = Instant::now();
+    let mut response = tokio::task::spawn_blocking(move || {
+        let mut response = PrimeResponse::default();
+        for query in request.queries.iter().filter(|q| !q.trim().is_empty()) {
+            match engine.search_with_options(query, &options) {
+                Ok(output) => {
+                    response.primed += 1;
+                    response.results += output.results.len();
+                }
+                Err(_) => response.failed += 1,
+            }
+        }
+        response
+    })
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?;
+    response.took_ms = started.elapsed().as_secs_f64() * 1000.0;
+    info!(
+        "Primed caches with {} queries in {:.0} ms",
+        response.primed, response.took_ms
+    );
+    Ok(Json(response))
+}
+
+/// With API keys configured, only admin keys may use admin routes
+fn require_admin_key(state: &AppState, headers: &HeaderMap) -> Result<(), AppError> {
+    if state.api_keys.required() {
+        let key = state
+            .api_keys
+            .from_headers(headers)
+            .ok_or_else(|| AppError::Unauthorized("Missing or unknown API key".to_string()))?;
+        if !key.admin {
+            return Err(AppError::Forbidden("Admin API key required".to_string()));
+        }
+    }
+    Ok(())
+}
+
 /// Custom error type for HTTP handlers
 #[derive(Debug)]
 pub enum AppError {
     BadRequest(String),
-    _NotFound(String),
+    Unauthorized(String),
+    Forbidden(String),
+    NotFound(String),
+    Gone(String),
+    TooManyRequests(String),
     Internal(String),
 }
 
@@ -113,7 +1023,11 @@ impl IntoResponse for AppError {
     fn into_response(self) -> axum::response::Response {
         let (status, message) = match self {
             AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
-            AppError::_NotFound(msg) => (StatusCode::NOT_FOUND, msg),
+            AppError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg),
+            AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
+            AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
+            AppError::Gone(msg) => (StatusCode::GONE, msg),
+            AppError::TooManyRequests(msg) => (StatusCode::TOO_MANY_REQUESTS, msg),
             AppError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
         };
 
@@ -126,26 +1040,193 @@ impl IntoResponse for AppError {
 }
 
 /// Start the HTTP server
-pub async fn serve(search_engine: SearchEngine, port: u16) -> anyhow::Result<()> {
-    let state = AppState::new(search_engine);
+pub async fn serve(state: AppState, port: u16) -> anyhow::Result<()> {
+    serve_on(state, "127.0.0.1", port).await
+}
+
+/// Start the HTTP server on the given host and port
+pub async fn serve_on(state: AppState, host: &str, port: u16) -> anyhow::Result<()> {
     let app = create_router(state);
 
-    let addr = format!("127.0.0.1:{}", port);
+    let addr = format!("{}:{}", host, port);
     info!("Starting server on {}", addr);
 
     let listener = tokio::net::TcpListener::bind(&addr).await?;
-    axum::serve(listener, app).await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
 
     Ok(())
 }
 
+/// Start an HTTPS server whose admin routes require a client certificate
+/// signed by (or equal to) one in `tls.client_ca`. Other routes accept any client.
+#[cfg(feature = "tls")]
+pub async fn serve_tls(
+    mut state: AppState,
+    host: &str,
+    port: u16,
+    tls: &AdminTls,
+) -> anyhow::Result<()> {
+    use hyper_util::rt::{TokioExecutor, TokioIo};
+    use hyper_util::server::conn::auto;
+    use tower::Service;
+
+    let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(tls_config(tls)?));
+    state.client_cert_required = true;
+    let app = create_router(state);
+
+    let addr = format!("{}:{}", host, port);
+    info!("Starting HTTPS server on {}", addr);
+
+    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let acceptor = acceptor.clone();
+        let app = app.clone();
+        tokio::spawn(async move {
+            let stream = match acceptor.accept(stream).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    warn!("TLS handshake with {} failed: {}", peer, e);
+                    return;
+                }
+            };
+            // Presented certificates were verified during the handshake
+            let verified = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .is_some_and(|certs| !certs.is_empty());
+            let service = hyper::service::service_fn(move |mut request: Request<_>| {
+                request.extensions_mut().insert(ConnectInfo(peer));
+                if verified {
+                    request.extensions_mut().insert(ClientCertificate);
+                }
+                // A router is always ready, no need to poll it first
+                app.clone().call(request)
+            });
+            if let Err(e) = auto::Builder::new(TokioExecutor::new())
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                warn!("Connection from {} failed: {}", peer, e);
+            }
+        });
+    }
+}
+
+/// Start an HTTPS server whose admin routes require a client certificate
+#[cfg(not(feature = "tls"))]
+pub async fn serve_tls(
+    _state: AppState,
+    _host: &str,
+    _port: u16,
+    _tls: &AdminTls,
+) -> anyhow::Result<()> {
+    anyhow::bail!("dictv was built without TLS support (enable the `tls` feature)")
+}
+
+/// Server configuration asking every client for a certificate without requiring one
+#[cfg(feature = "tls")]
+fn tls_config(tls: &AdminTls) -> anyhow::Result<tokio_rustls::rustls::ServerConfig> {
+    use anyhow::Context;
+    use rustls_pki_types::pem::PemObject;
+    use rustls_pki_types::{CertificateDer, PrivateKeyDer};
+    use tokio_rustls::rustls::{self, RootCertStore, server::WebPkiClientVerifier};
+
+    let read_certs = |path: &std::path::Path| {
+        CertificateDer::pem_file_iter(path)
+            .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+            .with_context(|| format!("Failed to read certificates: {:?}", path))
+    };
+    let certs = read_certs(&tls.cert)?;
+    let key = PrivateKeyDer::from_pem_file(&tls.key)
+        .with_context(|| format!("Failed to read private key: {:?}", tls.key))?;
+    let mut roots = RootCertStore::empty();
+    for cert in read_certs(&tls.client_ca)? {
+        roots.add(cert)?;
+    }
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+        .allow_unauthenticated()
+        .build()?;
+    Ok(rustls::ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()?
+        .with_client_cert_verifier(verifier)
+        .with_single_cert(certs, key)?)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use crate::models::DictionaryEntry;
+    use tempfile::TempDir;
+
+    fn state_with(entries: Vec<DictionaryEntry>) -> (TempDir, AppState) {
+        let temp_dir = TempDir::new().unwrap();
+        SearchEngine::build_index(temp_dir.path(), entries).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+        (temp_dir, AppState::new(engine))
+    }
+
+    #[test]
+    fn test_parse_search_query_joins_repeated_q() {
+        let params = parse_search_query("q=hau&mode=prefix&q=gr%C3%BC").unwrap();
+        assert_eq!(params.q, "hau,grü");
+        assert_eq!(params.prefixes(), ["hau", "grü"]);
+
+        let params = parse_search_query("q=Haus&limit=5").unwrap();
+        assert_eq!(params.q, "Haus");
+        assert_eq!(params.limit, Some(5));
+
+        assert!(parse_search_query("mode=prefix").is_err());
+    }
+
     #[tokio::test]
     async fn test_health_endpoint() {
-        let response = health_handler().await;
+        let entry = DictionaryEntry::new("Haus".into(), "house".into(), "de-en".into());
+        let (_temp_dir, state) = state_with(vec![entry]);
+        let (code, response) = health_handler(State(state)).await;
+        assert_eq!(code, StatusCode::OK);
         assert_eq!(response.0.status, "ok");
     }
+
+    #[tokio::test]
+    async fn test_admin_routes_require_client_certificate() {
+        use axum::body::Body;
+        use tower::Service;
+
+        let (_temp_dir, mut state) = state_with(Vec::new());
+        let usage = |certificate: bool| {
+            let mut request = Request::get("/admin/usage").body(Body::empty()).unwrap();
+            if certificate {
+                request.extensions_mut().insert(ClientCertificate);
+            }
+            request
+        };
+
+        let mut app = create_router(state.clone());
+        let response = app.call(usage(false)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        state.client_cert_required = true;
+        let mut app = create_router(state);
+        let response = app.call(usage(false)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = app.call(usage(true)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_health_not_ready_for_empty_index() {
+        let (_temp_dir, state) = state_with(Vec::new());
+        let (code, response) = health_handler(State(state)).await;
+        assert_eq!(code, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.0.status, "not_ready");
+    }
 }
@@ -0,0 +1,458 @@
+use anyhow::{Context, Result};
+use flate2::read::ZlibDecoder;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+
+use crate::models::DictionaryEntry;
+use crate::parser::ParseStats;
+use crate::stardict;
+
+/// Bytes a slob file starts with
+const MAGIC: &[u8; 8] = b"!-1SLOB\x1F";
+
+/// A parsed Aard2 slob dictionary
+#[derive(Debug, Clone)]
+pub struct SlobDictionary {
+    /// The `label` tag
+    pub name: Option<String>,
+    pub entries: Vec<DictionaryEntry>,
+}
+
+/// Parse an Aard2 `.slob` file.
+///
+/// A slob holds a sorted list of keys, each pointing at an item in one of
+/// the compressed bins of its store. Every key whose item is HTML or plain
+/// text becomes an entry with the item, stripped of markup, as its
+/// definition; stylesheets, images and other items are skipped. Bins may be
+/// zlib or LZMA2 compressed, or stored as is.
+pub fn parse_slob<P: AsRef<Path>>(
+    path: P,
+    language: &str,
+    stats: &mut ParseStats,
+) -> Result<SlobDictionary> {
+    let path = path.as_ref();
+    let bytes = std::fs::read(path).context(format!("Failed to read slob file: {:?}", path))?;
+    parse_slob_bytes(&bytes, language, stats).context(format!("Invalid slob file: {:?}", path))
+}
+
+fn parse_slob_bytes(
+    bytes: &[u8],
+    language: &str,
+    stats: &mut ParseStats,
+) -> Result<SlobDictionary> {
+    if !bytes.starts_with(MAGIC) {
+        anyhow::bail!("Not an Aard2 slob file");
+    }
+    let mut header = Cursor::new(bytes, MAGIC.len());
+    header.skip(16)?; // UUID
+    let encoding = header.tiny_text()?;
+    if !encoding.eq_ignore_ascii_case("utf-8") {
+        anyhow::bail!("Unsupported text encoding: {}", encoding);
+    }
+    let compression = header.tiny_text()?;
+    let mut name = None;
+    for _ in 0..header.u8()? {
+        let key = header.tiny_text()?;
+        // Tag values are padded with NULs so they can be edited in place
+        let value = header.tiny_text()?.trim_end_matches('\0').to_string();
+        if key == "label" && !value.is_empty() {
+            name = Some(value);
+        }
+    }
+    let content_types = (0..header.u8()?)
+        .map(|_| header.text())
+        .collect::<Result<Vec<_>>>()?;
+    let _blob_count = header.u32()?;
+    let store_offset = header.u64()? as usize;
+    let _size = header.u64()?;
+
+    // Keys by the (bin, item) they point at
+    let mut keys: HashMap<(usize, usize), Vec<String>> = HashMap::new();
+    let refs = ItemList::read(bytes, header.position)?;
+    for index in 0..refs.count {
+        let mut item = refs.item(index)?;
+        let key = item.text()?;
+        let bin = item.u32()? as usize;
+        let position = item.u16()? as usize;
+        keys.entry((bin, position)).or_default().push(key);
+    }
+
+    let mut dictionary = SlobDictionary {
+        name,
+        entries: Vec::new(),
+    };
+    let store = ItemList::read(bytes, store_offset)?;
+    for bin_index in 0..store.count {
+        let mut bin = store.item(bin_index)?;
+        let item_count = bin.u32()? as usize;
+        let type_ids = bin.bytes(item_count)?;
+        let length = bin.u32()? as usize;
+        let compressed = bin.bytes(length)?;
+        let Some(data) = decompress(&compression, compressed, stats)? else {
+            continue;
+        };
+        let items = ItemList {
+            data: &data,
+            count: item_count,
+            positions: 0,
+            position_size: 4,
+        };
+        for (position, &type_id) in type_ids.iter().enumerate() {
+            let Some(keys) = keys.remove(&(bin_index, position)) else {
+                continue;
+            };
+            let content_type = content_types
+                .get(type_id as usize)
+                .map(String::as_str)
+                .unwrap_or_default();
+            if !content_type.starts_with("text/html") && !content_type.starts_with("text/plain") {
+                continue;
+            }
+            let mut item = items.item(position)?;
+            let length = item.u32()? as usize;
+            let content = String::from_utf8_lossy(item.bytes(length)?);
+            let definition = to_definition(&content);
+            for key in keys {
+                if definition.is_empty() {
+                    stats.record_malformed(&key);
+                    continue;
+                }
+                dictionary.entries.push(DictionaryEntry::new(
+                    key,
+                    definition.clone(),
+                    language.to_string(),
+                ));
+            }
+        }
+    }
+    Ok(dictionary)
+}
+
+/// Decompressed bin, or None when it is corrupt
+fn decompress(compression: &str, data: &[u8], stats: &mut ParseStats) -> Result<Option<Vec<u8>>> {
+    let mut plain = Vec::new();
+    let decompressed = match compression {
+        "" => {
+            plain.extend_from_slice(data);
+            true
+        }
+        "zlib" => ZlibDecoder::new(data).read_to_end(&mut plain).is_ok(),
+        "lzma2" => lzma_rs::lzma2_decompress(&mut &data[..], &mut plain).is_ok(),
+        other => anyhow::bail!(
+            "Unsupported slob compression: {} (zlib and lzma2 are supported)",
+            other
+        ),
+    };
+    if !decompressed {
+        stats.record_malformed(&format!("compressed bin of {} bytes", data.len()));
+        return Ok(None);
+    }
+    Ok(Some(plain))
+}
+
+/// Plain text of an article: markup, stylesheets and scripts removed and
+/// one line per paragraph, joined with "; "
+pub(crate) fn to_definition(content: &str) -> String {
+    let mut markup = content.to_string();
+    for element in ["style", "script"] {
+        let open = format!("<{}", element);
+        let close = format!("</{}>", element);
+        while let Some(start) = markup.find(&open) {
+            let end = markup[start..]
+                .find(&close)
+                .map_or(markup.len(), |end| start + end + close.len());
+            markup.replace_range(start..end, "");
+        }
+    }
+    stardict::strip_tags(&markup)
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// A list of items: a count, a table of item offsets and the items
+/// themselves, the offsets counting from the end of the table
+struct ItemList<'a> {
+    data: &'a [u8],
+    count: usize,
+    /// Start of the offset table
+    positions: usize,
+    /// Size of an offset: 8 in the file, 4 inside a bin
+    position_size: usize,
+}
+
+impl<'a> ItemList<'a> {
+    /// The list at `offset`, with a 32-bit count and 64-bit offsets
+    fn read(data: &'a [u8], offset: usize) -> Result<Self> {
+        let mut cursor = Cursor::new(data, offset);
+        let count = cursor.u32()? as usize;
+        Ok(Self {
+            data,
+            count,
+            positions: cursor.position,
+            position_size: 8,
+        })
+    }
+
+    /// Cursor at the start of item `index`. Corrupt counts and offsets that
+    /// point past the end of the data are errors.
+    fn item(&self, index: usize) -> Result<Cursor<'a>> {
+        let entry = index
+            .checked_mul(self.position_size)
+            .and_then(|offset| offset.checked_add(self.positions))
+            .with_context(|| format!("Offset of item {} out of range", index))?;
+        let mut table = Cursor::new(self.data, entry);
+        let offset = match self.position_size {
+            8 => usize::try_from(table.u64()?)?,
+            _ => table.u32()? as usize,
+        };
+        let position = self
+            .count
+            .checked_mul(self.position_size)
+            .and_then(|table| table.checked_add(self.positions))
+            .and_then(|start| start.checked_add(offset))
+            .filter(|&position| position <= self.data.len())
+            .with_context(|| format!("Item {} lies past the end of the data", index))?;
+        Ok(Cursor::new(self.data, position))
+    }
+}
+
+/// Big-endian reader over a byte slice
+struct Cursor<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(data: &'a [u8], position: usize) -> Self {
+        Self { data, position }
+    }
+
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .position
+            .checked_add(length)
+            .and_then(|end| self.data.get(self.position..end))
+            .context(format!("Data ends early at offset {}", self.position))?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, length: usize) -> Result<()> {
+        self.bytes(length).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_be_bytes(self.bytes(2)?.try_into()?))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_be_bytes(self.bytes(4)?.try_into()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_be_bytes(self.bytes(8)?.try_into()?))
+    }
+
+    /// Text with a one-byte length
+    fn tiny_text(&mut self) -> Result<String> {
+        let length = self.u8()? as usize;
+        Ok(String::from_utf8_lossy(self.bytes(length)?).into_owned())
+    }
+
+    /// Text with a two-byte length
+    fn text(&mut self) -> Result<String> {
+        let length = self.u16()? as usize;
+        Ok(String::from_utf8_lossy(self.bytes(length)?).into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::ZlibEncoder;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    fn tiny_text(out: &mut Vec<u8>, text: &str) {
+        out.push(text.len() as u8);
+        out.extend(text.as_bytes());
+    }
+
+    fn text(out: &mut Vec<u8>, text: &str) {
+        out.extend((text.len() as u16).to_be_bytes());
+        out.extend(text.as_bytes());
+    }
+
+    /// A list of items with offsets of `position_size` bytes
+    fn item_list(items: &[Vec<u8>], position_size: usize) -> Vec<u8> {
+        let mut positions = Vec::new();
+        let mut data: Vec<u8> = Vec::new();
+        for item in items {
+            positions.extend(&(data.len() as u64).to_be_bytes()[8 - position_size..]);
+            data.extend(item);
+        }
+        positions.extend(data);
+        positions
+    }
+
+    /// A slob of `bins`, each a list of (content type id, content) items,
+    /// and refs of (key, bin, item)
+    fn slob(
+        compression: &str,
+        compress: fn(&[u8]) -> Vec<u8>,
+        bins: &[&[(u8, &str)]],
+        refs: &[(&str, u32, u16)],
+    ) -> Vec<u8> {
+        let mut header = MAGIC.to_vec();
+        header.extend([7; 16]);
+        tiny_text(&mut header, "utf-8");
+        tiny_text(&mut header, compression);
+        header.push(1);
+        tiny_text(&mut header, "label");
+        tiny_text(&mut header, &format!("{:\0<255}", "Deutsch-Englisch"));
+        header.push(2);
+        text(&mut header, "text/html; charset=utf-8");
+        text(&mut header, "text/css");
+
+        let refs: Vec<Vec<u8>> = refs
+            .iter()
+            .map(|(key, bin, item)| {
+                let mut out = Vec::new();
+                text(&mut out, key);
+                out.extend(bin.to_be_bytes());
+                out.extend(item.to_be_bytes());
+                tiny_text(&mut out, "");
+                out
+            })
+            .collect();
+        let mut refs_list = (refs.len() as u32).to_be_bytes().to_vec();
+        refs_list.extend(item_list(&refs, 8));
+
+        let bins: Vec<Vec<u8>> = bins
+            .iter()
+            .map(|items| {
+                let contents: Vec<Vec<u8>> = items
+                    .iter()
+                    .map(|(_, content)| {
+                        let mut out = (content.len() as u32).to_be_bytes().to_vec();
+                        out.extend(content.as_bytes());
+                        out
+                    })
+                    .collect();
+                let compressed = compress(&item_list(&contents, 4));
+                let mut out = (items.len() as u32).to_be_bytes().to_vec();
+                out.extend(items.iter().map(|(type_id, _)| type_id));
+                out.extend((compressed.len() as u32).to_be_bytes());
+                out.extend(compressed);
+                out
+            })
+            .collect();
+        let mut store = (bins.len() as u32).to_be_bytes().to_vec();
+        store.extend(item_list(&bins, 8));
+
+        let store_offset = header.len() + 4 + 8 + 8 + refs_list.len();
+        let size = store_offset + store.len();
+        header.extend((refs.len() as u32).to_be_bytes());
+        header.extend((store_offset as u64).to_be_bytes());
+        header.extend((size as u64).to_be_bytes());
+        header.extend(refs_list);
+        header.extend(store);
+        header
+    }
+
+    fn zlib(data: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn lzma2(data: &[u8]) -> Vec<u8> {
+        let mut compressed = Vec::new();
+        lzma_rs::lzma2_compress(&mut &data[..], &mut compressed).unwrap();
+        compressed
+    }
+
+    #[test]
+    fn test_parse_slob() {
+        let bins: &[&[(u8, &str)]] = &[
+            &[
+                (
+                    0,
+                    "<html><head><style>h1 { color: red }</style></head><body><h1>Haus</h1><p>house, home</p></body></html>",
+                ),
+                (1, "h1 { color: red }"),
+            ],
+            &[(0, "<p>tree</p>"), (0, "<p></p>")],
+        ];
+        let refs = [
+            ("Baum", 1, 0),
+            ("Haus", 0, 0),
+            ("Häuser", 0, 0),
+            ("leer", 1, 1),
+            ("style.css", 0, 1),
+        ];
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("de-en.slob");
+        std::fs::write(&path, slob("zlib", zlib, bins, &refs)).unwrap();
+        let mut stats = ParseStats::default();
+        let dictionary = parse_slob(&path, "de-en", &mut stats).unwrap();
+
+        assert_eq!(dictionary.name.as_deref(), Some("Deutsch-Englisch"));
+        let entries: Vec<(&str, &str)> = dictionary
+            .entries
+            .iter()
+            .map(|e| (e.word.as_str(), e.definition.as_str()))
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                ("Haus", "Haus; house, home"),
+                ("Häuser", "Haus; house, home"),
+                ("Baum", "tree"),
+            ]
+        );
+        assert_eq!(stats.malformed_lines, 1);
+
+        std::fs::write(&path, slob("lzma2", lzma2, bins, &refs)).unwrap();
+        let dictionary = parse_slob(&path, "de-en", &mut ParseStats::default()).unwrap();
+        assert_eq!(dictionary.entries.len(), 3);
+
+        std::fs::write(&path, b"not a slob").unwrap();
+        assert!(parse_slob(&path, "de-en", &mut ParseStats::default()).is_err());
+    }
+
+    #[test]
+    fn test_parse_slob_rejects_offsets_past_the_end() {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend([7; 16]);
+        tiny_text(&mut bytes, "utf-8");
+        tiny_text(&mut bytes, "zlib");
+        bytes.extend([0, 0]); // no tags, no content types
+        bytes.extend(1u32.to_be_bytes());
+        bytes.extend(0u64.to_be_bytes());
+        bytes.extend(0u64.to_be_bytes());
+        // One ref whose offset wraps around the address space
+        bytes.extend(1u32.to_be_bytes());
+        bytes.extend(u64::MAX.to_be_bytes());
+
+        let error = parse_slob_bytes(&bytes, "de-en", &mut ParseStats::default()).unwrap_err();
+        assert!(error.to_string().contains("past the end"), "{}", error);
+
+        // A count whose offset table runs past the end of the data
+        let refs_start = bytes.len() - 12;
+        bytes.truncate(refs_start);
+        bytes.extend(u32::MAX.to_be_bytes());
+        bytes.extend(0u64.to_be_bytes());
+        assert!(parse_slob_bytes(&bytes, "de-en", &mut ParseStats::default()).is_err());
+    }
+}
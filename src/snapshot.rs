@@ -0,0 +1,97 @@
+use ring::rand::{SecureRandom, SystemRandom};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tantivy::Searcher;
+
+/// How long a pinned snapshot lives after its last use
+pub const SNAPSHOT_TTL: Duration = Duration::from_secs(60);
+
+/// Snapshots pinned at once, the least recently used one is dropped beyond this.
+/// Each one keeps the files of its index generation from being deleted.
+const MAX_SNAPSHOTS: usize = 1_000;
+
+/// Index snapshots pinned by clients paging through results, so later pages
+/// come from the same index contents as the first even across a reload
+#[derive(Default)]
+pub struct SnapshotPins {
+    pins: Mutex<HashMap<String, (Searcher, Instant)>>,
+}
+
+impl SnapshotPins {
+    /// Number of snapshots pinned, including expired ones not yet dropped
+    pub fn pinned(&self) -> usize {
+        self.pins.lock().map_or(0, |pins| pins.len())
+    }
+
+    /// Pin `searcher`, returning the token that refers to it
+    pub fn pin(&self, searcher: Searcher) -> String {
+        let mut bytes = [0u8; 16];
+        // Without randomness tokens are still unique, only guessable
+        if SystemRandom::new().fill(&mut bytes).is_err() {
+            let nanos = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos();
+            bytes = nanos.to_be_bytes();
+        }
+        let token: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+
+        let Ok(mut pins) = self.pins.lock() else {
+            return token;
+        };
+        let now = Instant::now();
+        pins.retain(|_, (_, used)| now.duration_since(*used) < SNAPSHOT_TTL);
+        if pins.len() >= MAX_SNAPSHOTS
+            && let Some(oldest) = pins
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(token, _)| token.clone())
+        {
+            pins.remove(&oldest);
+        }
+        pins.insert(token.clone(), (searcher, now));
+        token
+    }
+
+    /// The searcher pinned under `token`, unless it expired. Using a snapshot
+    /// extends its lifetime.
+    pub fn get(&self, token: &str) -> Option<Searcher> {
+        let mut pins = self.pins.lock().ok()?;
+        let now = Instant::now();
+        let (searcher, used) = pins.get_mut(token)?;
+        if now.duration_since(*used) >= SNAPSHOT_TTL {
+            pins.remove(token);
+            return None;
+        }
+        *used = now;
+        Some(searcher.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::DictionaryEntry;
+    use crate::search::SearchEngine;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_pinned_snapshot_survives_reload() {
+        let temp_dir = TempDir::new().unwrap();
+        let entry = |word: &str| DictionaryEntry::new(word.into(), "x".into(), "de-en".into());
+        SearchEngine::build_index(temp_dir.path(), vec![entry("Haus")]).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let pins = SnapshotPins::default();
+        let token = pins.pin(engine.searcher());
+        assert_eq!(token.len(), 32);
+
+        SearchEngine::add_to_index_from_batches(temp_dir.path(), [vec![entry("Hausboot")]])
+            .unwrap();
+        engine.reload().unwrap();
+        assert_eq!(engine.num_docs(), 2);
+        assert_eq!(pins.get(&token).unwrap().num_docs(), 1);
+        assert!(pins.get("unknown").is_none());
+    }
+}
@@ -0,0 +1,469 @@
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OpenFlags, params};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::info;
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+
+use crate::backend::{Backend, BackendKind};
+use crate::models::{
+    DictionaryEntry, LanguageCount, PartOfSpeech, SearchMode, SearchOptions, SearchResult,
+};
+use crate::normalize::normalize;
+use crate::search::{SHORT_QUERY_LEN, SearchOutput};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS entries (
+    id INTEGER PRIMARY KEY,
+    word TEXT NOT NULL,
+    headword TEXT NOT NULL,
+    folded TEXT NOT NULL,
+    definition TEXT NOT NULL,
+    language TEXT NOT NULL,
+    source TEXT,
+    domains TEXT NOT NULL,
+    part_of_speech TEXT,
+    gender TEXT
+);
+CREATE INDEX IF NOT EXISTS entries_headword ON entries (language, headword);
+CREATE INDEX IF NOT EXISTS entries_folded ON entries (language, folded);
+CREATE VIRTUAL TABLE IF NOT EXISTS definitions USING fts5 (
+    definition,
+    content = 'entries',
+    content_rowid = 'id',
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+";
+
+/// Dictionary entries in one SQLite database, headwords in a B-tree index
+/// and definitions in an FTS5 table.
+///
+/// Supports exact, prefix, fuzzy and definition searches; the other modes
+/// and most result filters need the Tantivy backend.
+pub struct SqliteBackend {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteBackend {
+    /// Open an existing database read-only
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .context(format!("Failed to open SQLite index: {:?}", path))?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// Replace the database at `path` with the entries of `batches`,
+    /// returning the number of entries written
+    pub fn build_from_batches<I>(path: PathBuf, batches: I) -> Result<usize>
+    where
+        I: IntoIterator<Item = Vec<DictionaryEntry>>,
+    {
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+        }
+        let total = write_entries(&path, batches)?;
+        info!("SQLite index built successfully with {} entries", total);
+        Ok(total)
+    }
+
+    /// Add the entries of `batches` to the database at `path`, returning
+    /// the number of entries added
+    pub fn add_from_batches<I>(path: PathBuf, batches: I) -> Result<usize>
+    where
+        I: IntoIterator<Item = Vec<DictionaryEntry>>,
+    {
+        if !path.exists() {
+            anyhow::bail!("No SQLite index at {:?}, run a rebuild first", path);
+        }
+        let total = write_entries(&path, batches)?;
+        info!("Added {} entries to the SQLite index", total);
+        Ok(total)
+    }
+
+    /// Rows matching the query, in result order
+    fn find(&self, query: &str, options: &SearchOptions) -> Result<Vec<Row>> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| anyhow::anyhow!("SQLite connection poisoned"))?;
+        let language = options.language.as_str();
+        let (column, key) = if options.strict_diacritics {
+            ("headword", headword(query))
+        } else {
+            ("folded", fold(&headword(query)))
+        };
+
+        let rows = match options.mode {
+            SearchMode::Exact => query_rows(
+                &connection,
+                &format!(
+                    "SELECT {ROW} FROM entries WHERE language = ?1 AND {column} = ?2 ORDER BY id"
+                ),
+                params![language, key],
+            )?,
+            SearchMode::Prefix => query_rows(
+                &connection,
+                &format!(
+                    "SELECT {ROW} FROM entries WHERE language = ?1 AND {column} >= ?2 AND {column} < ?3
+                     ORDER BY length({column}), {column}, id"
+                ),
+                params![language, key, format!("{}\u{10FFFF}", key)],
+            )?,
+            SearchMode::Fuzzy => {
+                let max_distance = if options.auto_distance && key.chars().count() < SHORT_QUERY_LEN
+                {
+                    options.max_distance.min(1)
+                } else {
+                    options.max_distance
+                };
+                // Candidates share the first letter, as misspellings rarely change it
+                let first: String = key.chars().take(1).collect();
+                let mut rows = query_rows(
+                    &connection,
+                    &format!(
+                        "SELECT {ROW}, {column} FROM entries WHERE language = ?1 AND {column} >= ?2 AND {column} < ?3
+                         ORDER BY id"
+                    ),
+                    params![language, first, format!("{}\u{10FFFF}", first)],
+                )?;
+                rows.retain_mut(|row| {
+                    let distance = strsim::levenshtein(&key, &row.key);
+                    row.distance = Some(distance as u8);
+                    distance <= max_distance as usize
+                });
+                rows.sort_by_key(|row| row.distance);
+                rows
+            }
+            SearchMode::Definition => {
+                let phrase = format!("\"{}\"", query.trim().replace('"', "\"\""));
+                query_rows(
+                    &connection,
+                    &format!(
+                        "SELECT {ROW} FROM definitions JOIN entries ON entries.id = definitions.rowid
+                         WHERE definitions MATCH ?2 AND language = ?1 ORDER BY definitions.rank"
+                    ),
+                    params![language, phrase],
+                )?
+            }
+            mode => anyhow::bail!(
+                "The SQLite backend does not support {} search, use the tantivy backend",
+                mode.as_str()
+            ),
+        };
+        Ok(rows
+            .into_iter()
+            .filter(|row| {
+                options.sources.is_empty()
+                    || row
+                        .source
+                        .as_ref()
+                        .is_some_and(|source| options.sources.contains(source))
+            })
+            .collect())
+    }
+}
+
+impl Backend for SqliteBackend {
+    fn kind(&self) -> BackendKind {
+        BackendKind::Sqlite
+    }
+
+    fn search(&self, query: &str, options: &SearchOptions) -> Result<SearchOutput> {
+        let query = normalize(query);
+        let mut results: Vec<SearchResult> = Vec::new();
+        for row in self.find(&query, options)? {
+            let result = match results.iter().position(|result| result.word == row.word) {
+                Some(index) => &mut results[index],
+                None if results.len() < options.limit => {
+                    results.push(SearchResult {
+                        word: row.word.clone(),
+                        definitions: Vec::new(),
+                        language: options.language.as_str().to_string(),
+                        edit_distance: row.distance,
+                        score: None,
+                        frequency_rank: None,
+                        domains: Vec::new(),
+                        registers: Vec::new(),
+                        parts_of_speech: Vec::new(),
+                        genders: Vec::new(),
+                        audio_urls: Vec::new(),
+                        sources: Vec::new(),
+                        html: None,
+                    });
+                    results.last_mut().expect("just pushed")
+                }
+                None => continue,
+            };
+            if !result.definitions.contains(&row.definition) {
+                result.definitions.push(row.definition);
+            }
+            for domain in row.domains {
+                if !result.domains.contains(&domain) {
+                    result.domains.push(domain);
+                }
+            }
+            if let Some(part_of_speech) = row.part_of_speech
+                && !result.parts_of_speech.contains(&part_of_speech)
+            {
+                result.parts_of_speech.push(part_of_speech);
+            }
+            if let Some(gender) = row.gender
+                && !result.genders.contains(&gender)
+            {
+                result.genders.push(gender);
+            }
+        }
+        Ok(SearchOutput {
+            results,
+            ..SearchOutput::default()
+        })
+    }
+
+    fn num_docs(&self) -> u64 {
+        self.connection
+            .lock()
+            .ok()
+            .and_then(|connection| {
+                connection
+                    .query_row("SELECT COUNT(*) FROM entries", [], |row| row.get(0))
+                    .ok()
+            })
+            .unwrap_or(0)
+    }
+
+    fn get_stats(&self) -> Result<(usize, Vec<LanguageCount>)> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| anyhow::anyhow!("SQLite connection poisoned"))?;
+        let mut statement = connection.prepare(
+            "SELECT language, COUNT(*) FROM entries GROUP BY language ORDER BY language",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })?;
+        let mut languages = Vec::new();
+        for row in rows {
+            let (language, entries) = row?;
+            // Entries imported with an unrecognized language are skipped
+            if let Ok(language) = language.parse() {
+                languages.push(LanguageCount {
+                    language,
+                    entries: entries as usize,
+                });
+            }
+        }
+        let total: i64 =
+            connection.query_row("SELECT COUNT(*) FROM entries", [], |row| row.get(0))?;
+        Ok((total as usize, languages))
+    }
+}
+
+/// Columns every search selects, read by `Row::read`
+const ROW: &str = "entries.word, entries.definition, entries.source, entries.domains, entries.part_of_speech, entries.gender";
+
+struct Row {
+    word: String,
+    definition: String,
+    source: Option<String>,
+    domains: Vec<String>,
+    part_of_speech: Option<PartOfSpeech>,
+    gender: Option<String>,
+    /// Headword column the fuzzy search compares against
+    key: String,
+    distance: Option<u8>,
+}
+
+fn query_rows(
+    connection: &Connection,
+    sql: &str,
+    params: impl rusqlite::Params,
+) -> Result<Vec<Row>> {
+    let mut statement = connection.prepare(sql)?;
+    let rows = statement.query_map(params, |row| {
+        let domains: String = row.get(3)?;
+        let part_of_speech: Option<String> = row.get(4)?;
+        Ok(Row {
+            word: row.get(0)?,
+            definition: row.get(1)?,
+            source: row.get(2)?,
+            domains: serde_json::from_str(&domains).unwrap_or_default(),
+            part_of_speech: part_of_speech.and_then(|pos| pos.parse().ok()),
+            gender: row.get(5)?,
+            key: row.get(6).unwrap_or_default(),
+            distance: None,
+        })
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+/// Write batches of entries in one transaction each
+fn write_entries<I>(path: &Path, batches: I) -> Result<usize>
+where
+    I: IntoIterator<Item = Vec<DictionaryEntry>>,
+{
+    let mut connection =
+        Connection::open(path).context(format!("Failed to create SQLite index: {:?}", path))?;
+    connection.execute_batch(SCHEMA)?;
+
+    let mut total = 0;
+    for batch in batches {
+        let transaction = connection.transaction()?;
+        {
+            let mut insert = transaction.prepare(
+                "INSERT INTO entries
+                 (word, headword, folded, definition, language, source, domains, part_of_speech, gender)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            )?;
+            let mut insert_definition = transaction
+                .prepare("INSERT INTO definitions (rowid, definition) VALUES (?1, ?2)")?;
+            for entry in &batch {
+                let word = normalize(&entry.word);
+                let definition = normalize(&entry.definition);
+                let headword = headword(&word);
+                insert.execute(params![
+                    word,
+                    headword,
+                    fold(&headword),
+                    definition,
+                    entry.language,
+                    entry.source,
+                    serde_json::to_string(&entry.domains)?,
+                    entry.part_of_speech.map(|pos| pos.as_str()),
+                    entry.gender,
+                ])?;
+                insert_definition.execute(params![transaction.last_insert_rowid(), definition])?;
+            }
+        }
+        transaction.commit()?;
+        total += batch.len();
+    }
+    Ok(total)
+}
+
+/// Lookup key of a headword, matched case-insensitively
+fn headword(word: &str) -> String {
+    word.trim().to_lowercase()
+}
+
+/// A headword with diacritics removed, "ß" spelled "ss" as Tantivy's ASCII folding does
+fn fold(headword: &str) -> String {
+    headword
+        .nfd()
+        .filter(|c| !is_combining_mark(*c))
+        .collect::<String>()
+        .replace('ß', "ss")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::LanguagePair;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_sqlite_backend() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("entries.sqlite");
+        let mut haus = DictionaryEntry::new(
+            "Haus".to_string(),
+            "house, home".to_string(),
+            "de-en".to_string(),
+        );
+        haus.gender = Some("n".to_string());
+        haus.part_of_speech = Some(PartOfSpeech::Noun);
+        let entries = vec![
+            haus,
+            DictionaryEntry::new(
+                "Haus".to_string(),
+                "building".to_string(),
+                "de-en".to_string(),
+            ),
+            DictionaryEntry::new(
+                "Haustür".to_string(),
+                "front door".to_string(),
+                "de-en".to_string(),
+            ),
+            DictionaryEntry::new(
+                "Maße".to_string(),
+                "measurements".to_string(),
+                "de-en".to_string(),
+            ),
+            DictionaryEntry::new("house".to_string(), "Haus".to_string(), "en-de".to_string()),
+        ];
+        assert_eq!(
+            SqliteBackend::build_from_batches(path.clone(), vec![entries]).unwrap(),
+            5
+        );
+
+        let backend = SqliteBackend::open(&path).unwrap();
+        assert_eq!(
+            backend.get_stats().unwrap(),
+            (
+                5,
+                vec![
+                    LanguageCount {
+                        language: LanguagePair::DE_EN,
+                        entries: 4
+                    },
+                    LanguageCount {
+                        language: LanguagePair::EN_DE,
+                        entries: 1
+                    }
+                ]
+            )
+        );
+        let search = |query: &str, mode: SearchMode| {
+            let options = SearchOptions {
+                mode,
+                language: LanguagePair::DE_EN,
+                ..SearchOptions::default()
+            };
+            backend.search(query, &options).unwrap().results
+        };
+
+        let exact = search("haus", SearchMode::Exact);
+        assert_eq!(exact.len(), 1);
+        assert_eq!(exact[0].definitions, ["house, home", "building"]);
+        assert_eq!(exact[0].genders, ["n"]);
+        assert_eq!(exact[0].parts_of_speech, [PartOfSpeech::Noun]);
+
+        let words = |results: Vec<SearchResult>| -> Vec<String> {
+            results.into_iter().map(|result| result.word).collect()
+        };
+        assert_eq!(
+            words(search("Haus", SearchMode::Prefix)),
+            ["Haus", "Haustür"]
+        );
+        assert_eq!(words(search("masse", SearchMode::Exact)), ["Maße"]);
+        assert_eq!(words(search("Hasu", SearchMode::Fuzzy)), ["Haus"]);
+        assert_eq!(words(search("door", SearchMode::Definition)), ["Haustür"]);
+        assert!(
+            backend
+                .search(
+                    "h_us",
+                    &SearchOptions {
+                        mode: SearchMode::Pattern,
+                        ..SearchOptions::default()
+                    }
+                )
+                .is_err()
+        );
+
+        let added = SqliteBackend::add_from_batches(
+            path.clone(),
+            vec![vec![DictionaryEntry::new(
+                "Baum".to_string(),
+                "tree".to_string(),
+                "de-en".to_string(),
+            )]],
+        )
+        .unwrap();
+        assert_eq!(added, 1);
+        assert_eq!(SqliteBackend::open(&path).unwrap().num_docs(), 6);
+    }
+}
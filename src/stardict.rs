@@ -0,0 +1,450 @@
+use anyhow::{Context, Result};
+use flate2::read::MultiGzDecoder;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+use crate::models::DictionaryEntry;
+use crate::parser::{self, ParseStats};
+
+/// First line of every `.ifo` file
+const IFO_MAGIC: &str = "StarDict's dict ifo file";
+
+/// A parsed StarDict dictionary
+#[derive(Debug, Clone)]
+pub struct StarDictDictionary {
+    /// Dictionary name from the `bookname` of the `.ifo` file
+    pub name: Option<String>,
+    pub entries: Vec<DictionaryEntry>,
+}
+
+/// Parse a StarDict dictionary: the `.ifo` file at `path` and the `.idx`
+/// (or `.idx.gz`), `.dict` (or `.dict.dz`) and optional `.syn` files next to it.
+///
+/// StarDict files do not declare their languages. Entries are tagged with the
+/// pair named in the book name or file name when there is one, otherwise with
+/// `language`.
+pub fn parse_stardict<P: AsRef<Path>>(
+    path: P,
+    language: &str,
+    stats: &mut ParseStats,
+) -> Result<StarDictDictionary> {
+    let base = base_path(path.as_ref());
+    let ifo_path = with_suffix(&base, ".ifo");
+    let ifo = std::fs::read_to_string(&ifo_path)
+        .context(format!("Failed to read StarDict .ifo file: {:?}", ifo_path))?;
+    let info = Info::parse(&ifo).context(format!("Invalid StarDict .ifo file: {:?}", ifo_path))?;
+
+    let index = read_file(&base, &[".idx", ".idx.gz"])?;
+    let data = read_file(&base, &[".dict", ".dict.dz"])?;
+    let words = parse_idx(&index, info.offset_bits)?;
+
+    let detected = info
+        .name
+        .as_deref()
+        .and_then(parser::language_from_file_name)
+        .or_else(|| {
+            ifo_path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(parser::language_from_file_name)
+        });
+    if let Some(detected) = detected
+        && detected.as_str() != language
+    {
+        warn!(
+            "StarDict dictionary is named {}, ignoring requested {}",
+            detected, language
+        );
+    }
+    stats.detected_language = detected.map(String::from);
+    let entry_language = detected
+        .as_ref()
+        .map_or(language, |detected| detected.as_str());
+
+    let mut definitions = Vec::with_capacity(words.len());
+    for (word, offset, size) in &words {
+        let (start, end) = (*offset as usize, (*offset + *size) as usize);
+        let definition = match data.get(start..end) {
+            Some(bytes) => entry_text(bytes, info.same_type_sequence.as_deref()),
+            None => String::new(),
+        };
+        if definition.is_empty() {
+            stats.record_malformed(word);
+        }
+        definitions.push(definition);
+    }
+
+    let mut entries: Vec<DictionaryEntry> = words
+        .iter()
+        .zip(&definitions)
+        .filter(|(_, definition)| !definition.is_empty())
+        .map(|((word, _, _), definition)| {
+            DictionaryEntry::new(word.clone(), definition.clone(), entry_language.to_string())
+        })
+        .collect();
+
+    // Synonyms are further headwords for an entry, referenced by its position in the .idx
+    if let Ok(synonyms) = read_file(&base, &[".syn"]) {
+        for (word, position) in parse_syn(&synonyms)? {
+            match definitions.get(position as usize) {
+                Some(definition) if !definition.is_empty() => entries.push(DictionaryEntry::new(
+                    word,
+                    definition.clone(),
+                    entry_language.to_string(),
+                )),
+                _ => stats.record_malformed(&word),
+            }
+        }
+    }
+
+    Ok(StarDictDictionary {
+        name: info.name,
+        entries,
+    })
+}
+
+/// Whether `path` is one of the files of the StarDict dictionary whose `.ifo` is `ifo_path`
+pub fn is_part_of(ifo_path: &Path, path: &Path) -> bool {
+    base_path(ifo_path) == base_path(path)
+}
+
+/// `.ifo` fields the parser needs
+struct Info {
+    name: Option<String>,
+    /// Width of the offsets in the `.idx` file, 32 or 64
+    offset_bits: u32,
+    /// Field types shared by all entries (e.g. "m"), which are then stored without type markers
+    same_type_sequence: Option<String>,
+}
+
+impl Info {
+    fn parse(text: &str) -> Result<Self> {
+        let mut lines = text.lines();
+        if lines
+            .next()
+            .map(|line| line.trim_start_matches('\u{feff}').trim())
+            != Some(IFO_MAGIC)
+        {
+            anyhow::bail!("missing \"{}\" header", IFO_MAGIC);
+        }
+        let mut info = Info {
+            name: None,
+            offset_bits: 32,
+            same_type_sequence: None,
+        };
+        for line in lines {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim() {
+                "bookname" => info.name = Some(value.to_string()),
+                "idxoffsetbits" => {
+                    info.offset_bits = value.parse().context("invalid idxoffsetbits")?
+                }
+                "sametypesequence" if !value.is_empty() => {
+                    info.same_type_sequence = Some(value.to_string())
+                }
+                _ => {}
+            }
+        }
+        if info.offset_bits != 32 && info.offset_bits != 64 {
+            anyhow::bail!("unsupported idxoffsetbits {}", info.offset_bits);
+        }
+        Ok(info)
+    }
+}
+
+/// The path of a StarDict file without its extension, e.g. "dir/dict" for "dir/dict.idx.gz"
+fn base_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    let stem = [".ifo", ".idx.gz", ".idx", ".dict.dz", ".dict", ".syn"]
+        .iter()
+        .find_map(|extension| name.strip_suffix(extension))
+        .unwrap_or(name);
+    path.with_file_name(stem)
+}
+
+fn with_suffix(base: &Path, suffix: &str) -> PathBuf {
+    let mut path = base.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Read the first of `base` + `suffixes` that exists, decompressing `.gz` and `.dz` files
+fn read_file(base: &Path, suffixes: &[&str]) -> Result<Vec<u8>> {
+    let Some(path) = suffixes
+        .iter()
+        .map(|suffix| with_suffix(base, suffix))
+        .find(|path| path.exists())
+    else {
+        anyhow::bail!(
+            "No StarDict {} file next to {:?}",
+            suffixes.join(" or "),
+            base
+        );
+    };
+    let file = File::open(&path).context(format!("Failed to open {:?}", path))?;
+    let mut bytes = Vec::new();
+    if matches!(path.extension().and_then(|s| s.to_str()), Some("gz" | "dz")) {
+        MultiGzDecoder::new(file).read_to_end(&mut bytes)?;
+    } else {
+        let mut file = file;
+        file.read_to_end(&mut bytes)?;
+    }
+    Ok(bytes)
+}
+
+/// Split off a NUL-terminated UTF-8 string
+fn take_string(bytes: &[u8]) -> (String, &[u8]) {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    let rest = bytes.get(end + 1..).unwrap_or_default();
+    (String::from_utf8_lossy(&bytes[..end]).into_owned(), rest)
+}
+
+/// Split off a big-endian number of `width` bytes
+fn take_number(bytes: &[u8], width: usize) -> Option<(u64, &[u8])> {
+    let (number, rest) = bytes.split_at_checked(width)?;
+    let value = number.iter().fold(0u64, |value, &b| value << 8 | b as u64);
+    Some((value, rest))
+}
+
+/// Headwords with the offset and size of their data in the `.dict` file
+fn parse_idx(mut bytes: &[u8], offset_bits: u32) -> Result<Vec<(String, u64, u64)>> {
+    let mut words = Vec::new();
+    while !bytes.is_empty() {
+        let (word, rest) = take_string(bytes);
+        let (offset, rest) = take_number(rest, offset_bits as usize / 8)
+            .context(format!("Truncated .idx entry for {:?}", word))?;
+        let (size, rest) =
+            take_number(rest, 4).context(format!("Truncated .idx entry for {:?}", word))?;
+        words.push((word, offset, size));
+        bytes = rest;
+    }
+    Ok(words)
+}
+
+/// Synonyms with the position of their entry in the `.idx` file
+fn parse_syn(mut bytes: &[u8]) -> Result<Vec<(String, u64)>> {
+    let mut synonyms = Vec::new();
+    while !bytes.is_empty() {
+        let (word, rest) = take_string(bytes);
+        let (position, rest) =
+            take_number(rest, 4).context(format!("Truncated .syn entry for {:?}", word))?;
+        synonyms.push((word, position));
+        bytes = rest;
+    }
+    Ok(synonyms)
+}
+
+/// Text of one entry's data. Lowercase field types are text, uppercase ones
+/// binary (sounds, images) and skipped. Within `same_type_sequence` the last
+/// field has no terminator or size and runs to the end of the data.
+fn entry_text(mut data: &[u8], same_type_sequence: Option<&str>) -> String {
+    let mut parts = Vec::new();
+    let mut push = |kind: char, bytes: &[u8]| {
+        let text = field_text(kind, &String::from_utf8_lossy(bytes));
+        if !text.is_empty() {
+            parts.push(text);
+        }
+    };
+
+    match same_type_sequence {
+        Some(types) => {
+            let count = types.chars().count();
+            for (i, kind) in types.chars().enumerate() {
+                if i + 1 == count {
+                    push(kind, data);
+                    break;
+                }
+                if kind.is_ascii_lowercase() {
+                    let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+                    push(kind, &data[..end]);
+                    data = data.get(end + 1..).unwrap_or_default();
+                } else {
+                    let Some((size, rest)) = take_number(data, 4) else {
+                        break;
+                    };
+                    data = rest.get(size as usize..).unwrap_or_default();
+                }
+            }
+        }
+        None => {
+            while let Some((&kind, rest)) = data.split_first() {
+                let kind = kind as char;
+                if kind.is_ascii_lowercase() {
+                    let end = rest.iter().position(|&b| b == 0).unwrap_or(rest.len());
+                    push(kind, &rest[..end]);
+                    data = rest.get(end + 1..).unwrap_or_default();
+                } else {
+                    let Some((size, rest)) = take_number(rest, 4) else {
+                        break;
+                    };
+                    data = rest.get(size as usize..).unwrap_or_default();
+                }
+            }
+        }
+    }
+
+    parts.join("; ")
+}
+
+/// Plain text of a field, one definition line per line of the source joined with "; "
+fn field_text(kind: char, text: &str) -> String {
+    let text = match kind {
+        // Pango markup, XDXF and HTML
+        'g' | 'x' | 'h' => strip_tags(text),
+        // Phonetic transcriptions
+        't' | 'y' if text.trim().is_empty() => return String::new(),
+        't' | 'y' => return format!("/{}/", text.trim()),
+        // Resource file lists and WordNet data are not definitions
+        'r' | 'n' => return String::new(),
+        _ => text.to_string(),
+    };
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Drop markup tags and decode the common entities, turning line-breaking tags into newlines
+pub fn strip_tags(markup: &str) -> String {
+    let mut text = String::with_capacity(markup.len());
+    let mut chars = markup.chars();
+    while let Some(c) = chars.next() {
+        if c != '<' {
+            text.push(c);
+            continue;
+        }
+        let tag: String = chars.by_ref().take_while(|&t| t != '>').collect();
+        let name = tag
+            .trim_start_matches('/')
+            .split(|t: char| t.is_whitespace() || t == '/')
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        if matches!(name.as_str(), "br" | "p" | "div" | "li" | "tr" | "k") {
+            text.push('\n');
+        }
+    }
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    /// Write `base.ifo`, `base.idx` and `base.dict.dz` for entries of raw data
+    fn write_stardict(dir: &Path, base: &str, ifo: &str, entries: &[(&str, &[u8])]) -> PathBuf {
+        let mut idx = Vec::new();
+        let mut dict = Vec::new();
+        for (word, data) in entries {
+            idx.extend(word.as_bytes());
+            idx.push(0);
+            idx.extend((dict.len() as u32).to_be_bytes());
+            idx.extend((data.len() as u32).to_be_bytes());
+            dict.extend(*data);
+        }
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&dict).unwrap();
+
+        std::fs::write(dir.join(format!("{}.idx", base)), idx).unwrap();
+        std::fs::write(
+            dir.join(format!("{}.dict.dz", base)),
+            encoder.finish().unwrap(),
+        )
+        .unwrap();
+        let ifo_path = dir.join(format!("{}.ifo", base));
+        std::fs::write(&ifo_path, format!("{}\nversion=2.4.2\n{}", IFO_MAGIC, ifo)).unwrap();
+        ifo_path
+    }
+
+    #[test]
+    fn test_parse_stardict_with_same_type_sequence() {
+        let temp_dir = TempDir::new().unwrap();
+        let ifo_path = write_stardict(
+            temp_dir.path(),
+            "german-english",
+            "bookname=German-English\nwordcount=2\nsametypesequence=tm\n",
+            &[("Haus", b"haus\0house\nbuilding"), ("Auto", b"\0car")],
+        );
+        let mut syn = b"Gebaeude\0".to_vec();
+        syn.extend(0u32.to_be_bytes());
+        std::fs::write(temp_dir.path().join("german-english.syn"), syn).unwrap();
+
+        let mut stats = ParseStats::default();
+        let dictionary = parse_stardict(&ifo_path, "en-de", &mut stats).unwrap();
+
+        assert_eq!(dictionary.name.as_deref(), Some("German-English"));
+        assert_eq!(stats.detected_language.as_deref(), Some("de-en"));
+        let entries: Vec<(&str, &str)> = dictionary
+            .entries
+            .iter()
+            .map(|e| (e.word.as_str(), e.definition.as_str()))
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                ("Haus", "/haus/; house; building"),
+                ("Auto", "car"),
+                ("Gebaeude", "/haus/; house; building"),
+            ]
+        );
+        assert!(dictionary.entries.iter().all(|e| e.language == "de-en"));
+    }
+
+    #[test]
+    fn test_parse_stardict_with_typed_fields() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut sound = b"W".to_vec();
+        sound.extend(3u32.to_be_bytes());
+        sound.extend(b"wav");
+        let data = [
+            b"h<b>house</b><br>home &amp; hearth\0".to_vec(),
+            sound,
+            b"mbuilding\0".to_vec(),
+        ]
+        .concat();
+        let ifo_path = write_stardict(
+            temp_dir.path(),
+            "mydict",
+            "bookname=My Dictionary\n",
+            &[("Haus", &data), ("Leer", b"")],
+        );
+
+        let mut stats = ParseStats::default();
+        let dictionary = parse_stardict(&ifo_path, "de-en", &mut stats).unwrap();
+
+        assert_eq!(dictionary.entries.len(), 1);
+        assert_eq!(
+            dictionary.entries[0].definition,
+            "house; home & hearth; building"
+        );
+        assert_eq!(stats.detected_language, None);
+        assert_eq!(stats.malformed_examples, ["Leer"]);
+    }
+
+    #[test]
+    fn test_is_part_of() {
+        let ifo = Path::new("/dicts/de-en.ifo");
+        assert!(is_part_of(ifo, Path::new("/dicts/de-en.dict.dz")));
+        assert!(is_part_of(ifo, Path::new("/dicts/de-en.idx.gz")));
+        assert!(!is_part_of(ifo, Path::new("/dicts/de-en-2.dict.dz")));
+        assert!(!is_part_of(ifo, Path::new("/other/de-en.idx")));
+    }
+}
@@ -0,0 +1,48 @@
+/// Inflection endings removed by `stem`, longest first
+const SUFFIXES: &[&str] = &["ern", "es", "en", "er", "e", "s", "n"];
+
+/// Shortest stem left after removing an ending
+const MIN_STEM_LEN: usize = 3;
+
+/// Crude stem of a German or English word, used to recognise inflected forms
+/// of the same word: umlauts are folded and inflection endings are removed
+/// while at least three letters remain ("Haus", "Hauses" and "Häuser" all
+/// become "hau").
+pub fn stem(word: &str) -> String {
+    let mut stem: String = word
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'ä' => 'a',
+            'ö' => 'o',
+            'ü' => 'u',
+            c => c,
+        })
+        .collect::<String>()
+        .replace('ß', "ss");
+
+    while let Some(shorter) = SUFFIXES.iter().find_map(|suffix| {
+        stem.strip_suffix(suffix)
+            .filter(|shorter| shorter.chars().count() >= MIN_STEM_LEN)
+    }) {
+        stem = shorter.to_string();
+    }
+    stem
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stem() {
+        for word in ["Haus", "Hause", "Hauses", "Häuser", "Häusern"] {
+            assert_eq!(stem(word), "hau", "word {:?}", word);
+        }
+        assert_eq!(stem("houses"), stem("house"));
+        assert_ne!(stem("Haus"), stem("Maus"));
+        assert_eq!(stem("Ei"), "ei");
+        assert_eq!(stem("Eis"), "eis");
+        assert_eq!(stem("Maße"), stem("Masse"));
+    }
+}
@@ -0,0 +1,155 @@
+use anyhow::Result;
+use std::collections::HashMap;
+
+use crate::export::ExportRecord;
+use crate::models::{DictionaryEntry, LanguagePair, PartOfSpeech};
+use crate::search::SearchEngine;
+
+/// Criteria for sampling a study list
+#[derive(Debug, Clone)]
+pub struct StudyListCriteria {
+    pub language: LanguagePair,
+    pub part_of_speech: Option<PartOfSpeech>,
+    /// Only include words ranked within the top N most frequent
+    pub frequency_top: Option<u32>,
+    pub count: usize,
+    /// Seed for sampling, the same seed yields the same list
+    pub seed: u64,
+}
+
+/// Sample words matching the criteria into a study list.
+///
+/// Definitions of the same word are merged into one row. The sample is
+/// returned most frequent first, then alphabetically.
+pub fn build_study_list(
+    engine: &SearchEngine,
+    criteria: &StudyListCriteria,
+) -> Result<Vec<ExportRecord>> {
+    let mut rows: Vec<ExportRecord> = Vec::new();
+    let mut row_by_word: HashMap<String, usize> = HashMap::new();
+
+    for entry in engine.entries(criteria.language)? {
+        if let Some(&row) = row_by_word.get(&entry.word) {
+            let record = &mut rows[row];
+            record.definition.push_str("; ");
+            record.definition.push_str(&entry.definition);
+            if record.part_of_speech.is_none() {
+                record.part_of_speech = detect_pos(&entry);
+            }
+            continue;
+        }
+
+        row_by_word.insert(entry.word.clone(), rows.len());
+        let rank = engine.frequency_rank(criteria.language, &entry.word);
+        rows.push(ExportRecord::from_entry(entry, rank));
+    }
+
+    rows.retain(|record| {
+        let pos_matches = criteria
+            .part_of_speech
+            .is_none_or(|pos| record.part_of_speech.as_deref() == Some(pos.as_str()));
+        let frequency_matches = criteria
+            .frequency_top
+            .is_none_or(|top| record.frequency_rank.is_some_and(|rank| rank <= top));
+        pos_matches && frequency_matches
+    });
+
+    shuffle(&mut rows, criteria.seed);
+    rows.truncate(criteria.count);
+    rows.sort_by(|a, b| {
+        let rank_a = a.frequency_rank.unwrap_or(u32::MAX);
+        let rank_b = b.frequency_rank.unwrap_or(u32::MAX);
+        rank_a.cmp(&rank_b).then_with(|| a.word.cmp(&b.word))
+    });
+
+    Ok(rows)
+}
+
+fn detect_pos(entry: &DictionaryEntry) -> Option<String> {
+    entry
+        .part_of_speech
+        .or_else(|| PartOfSpeech::detect(&entry.definition))
+        .map(|pos| pos.as_str().to_string())
+}
+
+/// Fisher-Yates shuffle driven by a SplitMix64 generator
+pub(crate) fn shuffle<T>(items: &mut [T], seed: u64) {
+    let mut state = seed;
+    let mut next = move || {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    };
+
+    for i in (1..items.len()).rev() {
+        let j = (next() % (i as u64 + 1)) as usize;
+        items.swap(i, j);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frequency::FrequencyList;
+    use crate::models::DictionaryEntry;
+    use tempfile::TempDir;
+
+    fn entry(word: &str, definition: &str) -> DictionaryEntry {
+        DictionaryEntry::new(
+            word.to_string(),
+            definition.to_string(),
+            "de-en".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_build_study_list_filters_and_orders() {
+        let temp_dir = TempDir::new().unwrap();
+        let index_dir = temp_dir.path().join("index");
+        let frequency_dir = temp_dir.path().join("frequency");
+        std::fs::create_dir_all(&frequency_dir).unwrap();
+        std::fs::write(
+            frequency_dir.join("de-en.txt"),
+            "sein\nhaus\ngehen\nlaufen\n",
+        )
+        .unwrap();
+
+        let entries = vec![
+            entry("Haus", "<n, neut> house"),
+            entry("gehen", "<vi> to go"),
+            entry("gehen", "<vi> to walk"),
+            entry("laufen", "<vi> to run"),
+            entry("sein", "<v> to be"),
+            entry("schwimmen", "<vi> to swim"),
+        ];
+        SearchEngine::build_index(&index_dir, entries).unwrap();
+        let engine = SearchEngine::new(&index_dir)
+            .unwrap()
+            .with_frequencies(FrequencyList::load_dir(&frequency_dir).unwrap());
+
+        let criteria = StudyListCriteria {
+            language: LanguagePair::DE_EN,
+            part_of_speech: Some(PartOfSpeech::Verb),
+            frequency_top: Some(3),
+            count: 10,
+            seed: 42,
+        };
+        let list = build_study_list(&engine, &criteria).unwrap();
+
+        let words: Vec<&str> = list.iter().map(|r| r.word.as_str()).collect();
+        assert_eq!(words, ["sein", "gehen"]);
+        assert_eq!(list[1].definition, "<vi> to go; <vi> to walk");
+    }
+
+    #[test]
+    fn test_shuffle_is_deterministic() {
+        let mut a: Vec<u32> = (0..20).collect();
+        let mut b = a.clone();
+        shuffle(&mut a, 7);
+        shuffle(&mut b, 7);
+        assert_eq!(a, b);
+        assert_ne!(a, (0..20).collect::<Vec<_>>());
+    }
+}
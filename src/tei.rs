@@ -0,0 +1,321 @@
+use anyhow::{Context, Result};
+use flate2::read::MultiGzDecoder;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use tracing::warn;
+
+use crate::models::{DictionaryEntry, PartOfSpeech};
+use crate::parser::{self, ParseStats};
+use crate::xml::{self, Token};
+
+/// A parsed TEI P5 dictionary, as FreeDict publishes its sources
+#[derive(Debug, Clone)]
+pub struct TeiDictionary {
+    /// Title from the TEI header
+    pub name: Option<String>,
+    pub entries: Vec<DictionaryEntry>,
+}
+
+/// Parse a FreeDict TEI file (`.tei`, or gzipped as `.tei.gz`).
+///
+/// Unlike the dictd conversion, each `<sense>` becomes an entry of its own, with
+/// its translations as the definition and the part of speech, gender and
+/// domain labels kept as fields. Entries are tagged with the language pair in
+/// the file name (e.g. "deu-eng.tei") or title when there is one, otherwise
+/// with `language`.
+pub fn parse_tei<P: AsRef<Path>>(
+    path: P,
+    language: &str,
+    stats: &mut ParseStats,
+) -> Result<TeiDictionary> {
+    let path = path.as_ref();
+    let mut file = File::open(path).context(format!("Failed to open TEI file: {:?}", path))?;
+
+    let mut bytes = Vec::new();
+    if matches!(path.extension().and_then(|s| s.to_str()), Some("gz" | "dz")) {
+        MultiGzDecoder::new(file).read_to_end(&mut bytes)?;
+    } else {
+        file.read_to_end(&mut bytes)?;
+    }
+
+    let text = String::from_utf8_lossy(&bytes);
+    if !text.contains("<TEI") {
+        anyhow::bail!("Not a TEI file, it has no <TEI> element: {:?}", path);
+    }
+    let file_name = path.file_name().and_then(|name| name.to_str());
+    Ok(parse_tei_text(&text, file_name, language, stats))
+}
+
+fn parse_tei_text(
+    text: &str,
+    file_name: Option<&str>,
+    language: &str,
+    stats: &mut ParseStats,
+) -> TeiDictionary {
+    let mut name = None;
+    let mut parsed: Vec<(Vec<String>, Vec<Sense>)> = Vec::new();
+    // Open elements with their `type` attribute
+    let mut open: Vec<(&str, Option<String>)> = Vec::new();
+    let mut text_buffer = String::new();
+    let mut entry: Option<Entry> = None;
+
+    for token in xml::tokens(text) {
+        match token {
+            Token::Start {
+                name: element,
+                attributes,
+                empty,
+            } => {
+                if empty {
+                    continue;
+                }
+                match element {
+                    "entry" => entry = Some(Entry::default()),
+                    "sense" => {
+                        if let Some(entry) = &mut entry {
+                            entry.senses.push(Sense::default());
+                        }
+                    }
+                    _ => {}
+                }
+                open.push((element, xml::attribute(attributes, "type")));
+                text_buffer.clear();
+            }
+            Token::Text(text) => text_buffer.push_str(&text),
+            Token::End(element) => {
+                let Some(position) = open.iter().rposition(|(open, _)| *open == element) else {
+                    continue;
+                };
+                let kind = open[position].1.take();
+                open.truncate(position);
+                let value = text_buffer.split_whitespace().collect::<Vec<_>>().join(" ");
+                text_buffer.clear();
+
+                let in_element = |name: &str| open.iter().any(|(open, _)| *open == name);
+                if element == "title" && in_element("teiHeader") && name.is_none() {
+                    name = Some(value);
+                    continue;
+                }
+                if element == "entry" {
+                    if let Some(done) = entry.take() {
+                        parsed.push(done.finish());
+                    }
+                    continue;
+                }
+                let Some(current) = &mut entry else {
+                    continue;
+                };
+                // Examples carry translations of their own, which are not the sense's
+                let in_example = open
+                    .iter()
+                    .any(|(open, kind)| *open == "cit" && kind.as_deref() != Some("trans"));
+                let sense = if in_element("sense") {
+                    current.senses.last_mut()
+                } else {
+                    None
+                };
+                // Grammar inside a <cit> describes the translation, not the headword
+                match element {
+                    "orth" if in_element("form") && !in_element("cit") => {
+                        current.headwords.push(value)
+                    }
+                    "pos" if !in_element("cit") => {
+                        let pos = PartOfSpeech::from_tag(&value);
+                        match sense {
+                            Some(sense) => sense.part_of_speech = pos,
+                            None => current.part_of_speech = pos,
+                        }
+                    }
+                    "gen" if !in_element("cit") => {
+                        let gender = normalize_gender(&value);
+                        match sense {
+                            Some(sense) => sense.gender = gender,
+                            None => current.gender = gender,
+                        }
+                    }
+                    "quote" if !in_example && in_element("cit") => {
+                        if let Some(sense) = sense {
+                            sense.translations.push(value);
+                        }
+                    }
+                    "def" if !in_element("cit") => {
+                        if let Some(sense) = sense {
+                            sense.translations.push(value);
+                        }
+                    }
+                    "usg" if kind.as_deref() == Some("dom") => {
+                        if let Some(sense) = sense {
+                            sense.domains.push(value);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    let detected = file_name
+        .and_then(parser::language_from_file_name)
+        .or_else(|| name.as_deref().and_then(parser::language_from_file_name));
+    if let Some(detected) = detected
+        && detected.as_str() != language
+    {
+        warn!(
+            "TEI dictionary is named {}, ignoring requested {}",
+            detected, language
+        );
+    }
+    stats.detected_language = detected.map(String::from);
+    let entry_language = detected
+        .as_ref()
+        .map_or(language, |detected| detected.as_str());
+
+    let mut entries = Vec::new();
+    for (headwords, senses) in parsed {
+        let senses: Vec<Sense> = senses
+            .into_iter()
+            .filter(|sense| sense.translations.iter().any(|t| !t.is_empty()))
+            .collect();
+        let headwords: Vec<String> = headwords.into_iter().filter(|w| !w.is_empty()).collect();
+        if headwords.is_empty() || senses.is_empty() {
+            stats.record_malformed(headwords.first().map(String::as_str).unwrap_or("<entry>"));
+            continue;
+        }
+        for headword in &headwords {
+            for sense in &senses {
+                let mut entry = DictionaryEntry::new(
+                    headword.clone(),
+                    sense.translations.join(", "),
+                    entry_language.to_string(),
+                )
+                .with_domains(sense.domains.clone());
+                entry.part_of_speech = sense.part_of_speech;
+                entry.gender = sense.gender.clone();
+                entries.push(entry);
+            }
+        }
+    }
+
+    TeiDictionary { name, entries }
+}
+
+/// An `<entry>` being read. Grammar given for the whole entry applies to
+/// every sense that does not give its own.
+#[derive(Default)]
+struct Entry {
+    headwords: Vec<String>,
+    part_of_speech: Option<PartOfSpeech>,
+    gender: Option<String>,
+    senses: Vec<Sense>,
+}
+
+#[derive(Default)]
+struct Sense {
+    translations: Vec<String>,
+    part_of_speech: Option<PartOfSpeech>,
+    gender: Option<String>,
+    domains: Vec<String>,
+}
+
+impl Entry {
+    fn finish(mut self) -> (Vec<String>, Vec<Sense>) {
+        for sense in &mut self.senses {
+            sense.part_of_speech = sense.part_of_speech.or(self.part_of_speech);
+            if sense.gender.is_none() {
+                sense.gender = self.gender.clone();
+            }
+        }
+        (self.headwords, self.senses)
+    }
+}
+
+/// "m", "f" or "n" for FreeDict's `<gen>` values ("masc", "fem", "neut", ...)
+pub fn normalize_gender(gender: &str) -> Option<String> {
+    let gender = match gender.trim().to_lowercase().as_str() {
+        "m" | "masc" | "masculine" => "m",
+        "f" | "fem" | "feminine" => "f",
+        "n" | "neut" | "neuter" => "n",
+        _ => return None,
+    };
+    Some(gender.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<TEI xmlns="http://www.tei-c.org/ns/1.0">
+  <teiHeader>
+    <fileDesc><titleStmt><title>German-English FreeDict Dictionary</title></titleStmt></fileDesc>
+  </teiHeader>
+  <text><body>
+    <entry>
+      <form><orth>Haus</orth><pron>haʊs</pron></form>
+      <gramGrp><pos>n</pos><gen>neut</gen></gramGrp>
+      <sense n="1">
+        <cit type="trans" xml:lang="en"><quote>house</quote></cit>
+        <cit type="trans" xml:lang="en"><quote>building</quote></cit>
+        <cit type="example"><quote>ein großes Haus</quote>
+          <cit type="trans"><quote>a big house</quote></cit></cit>
+      </sense>
+      <sense n="2">
+        <usg type="dom">archi.</usg>
+        <cit type="trans" xml:lang="en"><quote>home</quote></cit>
+      </sense>
+    </entry>
+    <entry>
+      <form><orth>laufen</orth></form>
+      <sense><gramGrp><pos>vi</pos></gramGrp><cit type="trans"><quote>to run</quote></cit></sense>
+    </entry>
+    <entry>
+      <form><orth>Leer</orth></form>
+      <sense/>
+    </entry>
+  </body></text>
+</TEI>"#;
+
+    #[test]
+    fn test_parse_tei_keeps_senses_and_grammar() {
+        let mut stats = ParseStats::default();
+        let dictionary = parse_tei_text(SAMPLE, Some("deu-eng.tei"), "en-de", &mut stats);
+
+        assert_eq!(
+            dictionary.name.as_deref(),
+            Some("German-English FreeDict Dictionary")
+        );
+        assert_eq!(stats.detected_language.as_deref(), Some("de-en"));
+        assert_eq!(stats.malformed_examples, ["Leer"]);
+
+        let definitions: Vec<(&str, &str)> = dictionary
+            .entries
+            .iter()
+            .map(|e| (e.word.as_str(), e.definition.as_str()))
+            .collect();
+        assert_eq!(
+            definitions,
+            [
+                ("Haus", "house, building"),
+                ("Haus", "home"),
+                ("laufen", "to run")
+            ]
+        );
+        let grammar: Vec<(Option<PartOfSpeech>, Option<&str>)> = dictionary
+            .entries
+            .iter()
+            .map(|e| (e.part_of_speech, e.gender.as_deref()))
+            .collect();
+        assert_eq!(
+            grammar,
+            [
+                (Some(PartOfSpeech::Noun), Some("n")),
+                (Some(PartOfSpeech::Noun), Some("n")),
+                (Some(PartOfSpeech::Verb), None)
+            ]
+        );
+        assert!(dictionary.entries[0].domains.is_empty());
+        assert_eq!(dictionary.entries[1].domains, ["archi."]);
+        assert!(dictionary.entries.iter().all(|e| e.language == "de-en"));
+    }
+}
@@ -0,0 +1,196 @@
+use anyhow::{Context, Result};
+use ring::digest::{SHA256, digest};
+use serde::Deserialize;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::Duration;
+
+/// Longest an HTTP TTS backend may take for one word
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Text-to-speech backend for `/audio/{word}`, from the `[tts]` table of
+/// config.toml. Either `command` or `url` is set; in both, `{word}` is
+/// replaced by the word and `{lang}` by its language code ("de").
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TtsConfig {
+    /// Program and arguments run for each word, writing the audio to stdout.
+    /// It is run directly, without a shell.
+    pub command: Option<Vec<String>>,
+    /// URL fetched with GET for each word, returning the audio
+    pub url: Option<String>,
+    /// Content type of the audio the backend produces
+    #[serde(default = "default_content_type")]
+    pub content_type: String,
+}
+
+fn default_content_type() -> String {
+    "audio/wav".to_string()
+}
+
+/// Synthesized audio, cached on disk so each word is synthesized once
+#[derive(Debug, Clone)]
+pub struct Tts {
+    config: TtsConfig,
+    cache_dir: PathBuf,
+}
+
+impl Tts {
+    /// A backend caching its audio in `cache_dir`
+    pub fn new(config: TtsConfig, cache_dir: PathBuf) -> Result<Self> {
+        match (&config.command, &config.url) {
+            (Some(command), None) if !command.is_empty() => {}
+            (None, Some(_)) => {}
+            _ => anyhow::bail!("[tts] needs either a command or a url"),
+        }
+        Ok(Self { config, cache_dir })
+    }
+
+    pub fn content_type(&self) -> &str {
+        &self.config.content_type
+    }
+
+    /// Audio of `word` in the language `lang`, from the cache or the backend
+    pub fn speak(&self, lang: &str, word: &str) -> Result<Vec<u8>> {
+        let path = self.cache_path(lang, word);
+        if let Ok(audio) = fs::read(&path) {
+            return Ok(audio);
+        }
+
+        let audio = self.synthesize(lang, word)?;
+        if audio.is_empty() {
+            anyhow::bail!("The TTS backend returned no audio for '{}'", word);
+        }
+        // Written aside and renamed, so a concurrent request never reads half a file
+        let dir = path.parent().expect("cache paths have a parent");
+        fs::create_dir_all(dir).context(format!("Failed to create {:?}", dir))?;
+        let partial = path.with_extension(format!("{}.partial", std::process::id()));
+        fs::write(&partial, &audio).context(format!("Failed to write {:?}", partial))?;
+        fs::rename(&partial, &path).context(format!("Failed to write {:?}", path))?;
+        Ok(audio)
+    }
+
+    fn synthesize(&self, lang: &str, word: &str) -> Result<Vec<u8>> {
+        if let Some(url) = &self.config.url {
+            let url = fill(url, &encode(lang), &encode(word));
+            let response = reqwest::blocking::Client::builder()
+                .timeout(TIMEOUT)
+                .build()?
+                .get(&url)
+                .send()?
+                .error_for_status()?;
+            return Ok(response.bytes()?.to_vec());
+        }
+
+        let command = self.config.command.as_deref().unwrap_or_default();
+        let args: Vec<String> = command.iter().map(|arg| fill(arg, lang, word)).collect();
+        let output = Command::new(&args[0])
+            .args(&args[1..])
+            .output()
+            .context(format!("Failed to run the TTS command {}", args[0]))?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "The TTS command {} failed ({}): {}",
+                args[0],
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(output.stdout)
+    }
+
+    /// `<cache>/<lang>/<sha256 of the word>.<extension>`, safe for any word
+    fn cache_path(&self, lang: &str, word: &str) -> PathBuf {
+        let hash: String = digest(&SHA256, word.as_bytes())
+            .as_ref()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        self.cache_dir
+            .join(lang)
+            .join(hash)
+            .with_extension(extension(&self.config.content_type))
+    }
+}
+
+/// File extension for an audio content type
+fn extension(content_type: &str) -> &'static str {
+    match content_type.split(';').next().unwrap_or_default().trim() {
+        "audio/mpeg" | "audio/mp3" => "mp3",
+        "audio/ogg" | "audio/opus" => "ogg",
+        "audio/flac" => "flac",
+        "audio/aac" => "aac",
+        "audio/webm" => "webm",
+        "audio/wav" | "audio/x-wav" | "audio/wave" => "wav",
+        _ => "audio",
+    }
+}
+
+fn fill(template: &str, lang: &str, word: &str) -> String {
+    template.replace("{lang}", lang).replace("{word}", word)
+}
+
+fn encode(text: &str) -> String {
+    serde_urlencoded::to_string([("", text)])
+        .map(|pair| pair[1..].to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_command_backend_caches_audio() {
+        let temp_dir = TempDir::new().unwrap();
+        let marker = temp_dir.path().join("runs");
+        let script = format!(
+            "echo run >> {}; printf 'audio:%s:%s' \"$0\" \"$1\"",
+            marker.display()
+        );
+        let config = TtsConfig {
+            command: Some(vec![
+                "sh".to_string(),
+                "-c".to_string(),
+                script,
+                "{lang}".to_string(),
+                "{word}".to_string(),
+            ]),
+            url: None,
+            content_type: "audio/mpeg".to_string(),
+        };
+        let tts = Tts::new(config, temp_dir.path().join("audio")).unwrap();
+
+        assert_eq!(
+            tts.speak("de", "grüßen").unwrap(),
+            "audio:de:grüßen".as_bytes()
+        );
+        assert_eq!(
+            tts.speak("de", "grüßen").unwrap(),
+            "audio:de:grüßen".as_bytes()
+        );
+        assert_eq!(fs::read_to_string(&marker).unwrap(), "run\n");
+
+        let cached = tts.cache_path("de", "grüßen");
+        assert!(cached.starts_with(temp_dir.path().join("audio").join("de")));
+        assert_eq!(cached.extension().unwrap(), "mp3");
+    }
+
+    #[test]
+    fn test_config_needs_one_backend() {
+        let config = TtsConfig {
+            command: None,
+            url: None,
+            content_type: default_content_type(),
+        };
+        assert!(Tts::new(config.clone(), PathBuf::from("audio")).is_err());
+        let config = TtsConfig {
+            url: Some("http://localhost:5002/api/tts?text={word}".to_string()),
+            ..config
+        };
+        assert!(Tts::new(config, PathBuf::from("audio")).is_ok());
+        assert_eq!(encode("Straße & so"), "Stra%C3%9Fe+%26+so");
+    }
+}
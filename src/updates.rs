@@ -0,0 +1,107 @@
+use anyhow::Result;
+use std::collections::HashMap;
+
+use crate::freedict;
+use crate::manifest::Manifest;
+
+/// A newer release of an imported dictionary
+#[derive(Debug, Clone, PartialEq)]
+pub struct DictionaryUpdate {
+    pub dictionary: String,
+    pub installed: String,
+    pub available: String,
+}
+
+/// Latest dictd release version of each FreeDict dictionary, keyed by name ("deu-eng")
+pub fn latest_dictd_versions(database_json: &str) -> Result<HashMap<String, String>> {
+    Ok(freedict::parse_database(database_json)?
+        .into_iter()
+        .filter_map(|dictionary| {
+            let version = dictionary.release("dictd")?.version.clone();
+            Some((dictionary.name, version))
+        })
+        .collect())
+}
+
+/// Split an imported FreeDict source name like "freedict-deu-eng-1.9-fd1"
+/// into its dictionary name and version
+fn freedict_release(source_name: &str) -> Option<(&str, &str)> {
+    let rest = source_name.strip_prefix("freedict-")?;
+    // Dictionary names are two ISO 639-3 codes, "deu-eng"
+    let (name, version) = (rest.get(..7)?, rest.get(8..)?);
+    (rest.as_bytes().get(7) == Some(&b'-') && !version.is_empty()).then_some((name, version))
+}
+
+/// Imported FreeDict dictionaries with a newer release in `latest`
+pub fn find_updates(
+    manifest: &Manifest,
+    latest: &HashMap<String, String>,
+) -> Vec<DictionaryUpdate> {
+    manifest
+        .sources
+        .iter()
+        .filter_map(|source| {
+            let (name, installed) = freedict_release(&source.name)?;
+            let available = latest.get(name)?;
+            (available != installed).then(|| DictionaryUpdate {
+                dictionary: name.to_string(),
+                installed: installed.to_string(),
+                available: available.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Check FreeDict for newer releases of imported dictionaries
+pub async fn check_freedict_updates(manifest: &Manifest) -> Result<Vec<DictionaryUpdate>> {
+    let database = reqwest::get(freedict::DATABASE_URL)
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    Ok(find_updates(manifest, &latest_dictd_versions(&database)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::ManifestSource;
+    use crate::models::ImportFormat;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_find_updates() {
+        let database = r#"[
+            {"name": "deu-eng", "releases": [
+                {"platform": "src", "version": "1.9-fd2"},
+                {"platform": "dictd", "version": "1.9-fd2"}
+            ]},
+            {"name": "eng-deu", "releases": [{"platform": "dictd", "version": "1.9-fd1"}]},
+            {"software": {}}
+        ]"#;
+        let latest = latest_dictd_versions(database).unwrap();
+        assert_eq!(latest["deu-eng"], "1.9-fd2");
+
+        let mut manifest = Manifest::default();
+        for file in [
+            "freedict-deu-eng-1.9-fd1.dict.dz",
+            "freedict-eng-deu-1.9-fd1.dict.dz",
+            "dictcc.txt",
+        ] {
+            manifest.record(ManifestSource::new(
+                PathBuf::from(file),
+                ImportFormat::Dictd,
+                "de-en",
+            ));
+        }
+
+        assert_eq!(
+            find_updates(&manifest, &latest),
+            vec![DictionaryUpdate {
+                dictionary: "deu-eng".to_string(),
+                installed: "1.9-fd1".to_string(),
+                available: "1.9-fd2".to_string(),
+            }]
+        );
+    }
+}
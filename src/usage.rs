@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::apikeys::ApiKey;
+use crate::models::KeyUsage;
+
+const MS_PER_DAY: u64 = 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Counts {
+    total: u64,
+    today: u64,
+    /// Days since the Unix epoch that `today` counts
+    day: u64,
+}
+
+/// Counts searches per API key and enforces daily quotas
+#[derive(Debug, Default)]
+pub struct UsageTracker {
+    counts: Mutex<HashMap<String, Counts>>,
+}
+
+impl UsageTracker {
+    /// Number of keys with counted usage
+    pub fn tracked(&self) -> usize {
+        self.counts.lock().map_or(0, |counts| counts.len())
+    }
+
+    /// Count a request by `key`, or return false if its daily quota is used up
+    pub fn record(&self, key: &ApiKey) -> bool {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        self.record_at(key, now / MS_PER_DAY)
+    }
+
+    fn record_at(&self, key: &ApiKey, day: u64) -> bool {
+        let Ok(mut counts) = self.counts.lock() else {
+            return true;
+        };
+        let counts = counts.entry(key.label()).or_default();
+        if counts.day != day {
+            counts.day = day;
+            counts.today = 0;
+        }
+        if key.daily_quota.is_some_and(|quota| counts.today >= quota) {
+            return false;
+        }
+        counts.total += 1;
+        counts.today += 1;
+        true
+    }
+
+    /// Usage of every configured key, including unused ones
+    pub fn report<'a>(&self, keys: impl Iterator<Item = &'a ApiKey>) -> Vec<KeyUsage> {
+        let today = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64 / MS_PER_DAY)
+            .unwrap_or_default();
+        self.report_at(keys, today)
+    }
+
+    fn report_at<'a>(&self, keys: impl Iterator<Item = &'a ApiKey>, day: u64) -> Vec<KeyUsage> {
+        let counts = self.counts.lock().map(|c| c.clone()).unwrap_or_default();
+        let mut usage: Vec<KeyUsage> = keys
+            .map(|key| {
+                let name = key.label();
+                let counts = counts.get(&name).copied().unwrap_or_default();
+                KeyUsage {
+                    total_requests: counts.total,
+                    requests_today: if counts.day == day { counts.today } else { 0 },
+                    daily_quota: key.daily_quota,
+                    name,
+                }
+            })
+            .collect();
+        usage.sort_by(|a, b| a.name.cmp(&b.name));
+        usage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_daily_quota_resets_each_day() {
+        let key = ApiKey {
+            key: "secret".to_string(),
+            name: "classmate".to_string(),
+            languages: Vec::new(),
+            sources: Vec::new(),
+            daily_quota: Some(2),
+            admin: false,
+        };
+        let tracker = UsageTracker::default();
+
+        assert!(tracker.record_at(&key, 1));
+        assert!(tracker.record_at(&key, 1));
+        assert!(!tracker.record_at(&key, 1));
+        assert!(tracker.record_at(&key, 2));
+
+        let usage = tracker.report_at([&key].into_iter(), 2);
+        assert_eq!(usage[0].total_requests, 3);
+        assert_eq!(usage[0].requests_today, 1);
+        assert_eq!(
+            tracker.report_at([&key].into_iter(), 3)[0].requests_today,
+            0
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+use crate::index::{IndexManager, NewSource};
+use crate::manifest;
+
+/// How often `serve --watch-data` looks for new dictionaries. Polling rather
+/// than inotify, which misses files written to a network share by other machines.
+pub const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Size and modification time of a dictionary's files
+type Fingerprint = Vec<(u64, Option<SystemTime>)>;
+
+/// Imports dictionaries dropped into the data directory into the live index.
+///
+/// A file is only imported once it looks the same on two polls in a row, so
+/// a copy still in progress (e.g. onto a network share) is not read halfway.
+pub struct DataWatcher {
+    manager: IndexManager,
+    /// New files seen on the last poll
+    seen: HashMap<PathBuf, Fingerprint>,
+    /// Files that failed to import, retried only once they change
+    failed: HashMap<PathBuf, Fingerprint>,
+}
+
+impl DataWatcher {
+    pub fn new(manager: IndexManager) -> Self {
+        Self {
+            manager,
+            seen: HashMap::new(),
+            failed: HashMap::new(),
+        }
+    }
+
+    /// Import the new dictionaries that did not change since the last poll,
+    /// returning their names and entry counts
+    pub fn poll(&mut self) -> anyhow::Result<Vec<(String, usize)>> {
+        let mut seen = HashMap::new();
+        let mut imported = Vec::new();
+        for source in self.manager.new_sources()? {
+            let Some(fingerprint) = fingerprint(&source) else {
+                continue;
+            };
+            let settled = self.seen.get(&source.path) == Some(&fingerprint)
+                && self.failed.get(&source.path) != Some(&fingerprint);
+            seen.insert(source.path.clone(), fingerprint.clone());
+            if !settled {
+                continue;
+            }
+
+            let name = manifest::source_name(&source.path);
+            match self.manager.import_new_source(&source) {
+                Ok(count) => {
+                    info!(
+                        "Imported {} ({} entries) from the data directory",
+                        name, count
+                    );
+                    self.failed.remove(&source.path);
+                    imported.push((name, count));
+                }
+                Err(e) => {
+                    warn!("Could not import {:?}: {:#}", source.path, e);
+                    self.failed.insert(source.path.clone(), fingerprint);
+                }
+            }
+        }
+        self.seen = seen;
+        Ok(imported)
+    }
+
+    /// Poll every `interval` on a background thread for as long as the process runs
+    pub fn spawn(mut self, interval: Duration) -> thread::JoinHandle<()> {
+        thread::spawn(move || {
+            loop {
+                if let Err(e) = self.poll() {
+                    warn!("Watching the data directory failed: {:#}", e);
+                }
+                thread::sleep(interval);
+            }
+        })
+    }
+}
+
+fn fingerprint(source: &NewSource) -> Option<Fingerprint> {
+    std::iter::once(&source.path)
+        .chain(&source.index_path)
+        .map(|path| {
+            let metadata = fs::metadata(path).ok()?;
+            Some((metadata.len(), metadata.modified().ok()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::SearchEngine;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_poll_imports_settled_files_into_live_index() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = IndexManager::new(temp_dir.path()).unwrap();
+        SearchEngine::build_index(manager.index_dir(), Vec::new()).unwrap();
+        let engine = SearchEngine::new(manager.index_dir()).unwrap();
+        let data_dir = temp_dir.path().join("data");
+        let mut watcher = DataWatcher::new(IndexManager::new(temp_dir.path()).unwrap());
+
+        fs::write(data_dir.join("dictcc-de-en.txt"), "Haus\thouse\tnoun\n").unwrap();
+        fs::write(data_dir.join("unknown.txt"), "Auto\tcar\tnoun\n").unwrap();
+        // First sighting: the file may still be copied
+        assert!(watcher.poll().unwrap().is_empty());
+        assert_eq!(
+            watcher.poll().unwrap(),
+            vec![("dictcc-de-en".to_string(), 1)]
+        );
+        assert!(watcher.poll().unwrap().is_empty());
+
+        engine.reload().unwrap();
+        assert_eq!(engine.num_docs(), 1);
+        assert_eq!(manager.manifest().unwrap().sources.len(), 1);
+    }
+}
@@ -0,0 +1,100 @@
+use serde::Serialize;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+/// Administrative events posted to webhooks
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum WebhookEvent {
+    ImportCompleted {
+        source: String,
+        language: String,
+    },
+    RebuildCompleted {
+        indexed: usize,
+        succeeded: usize,
+        /// Dictionaries that failed to parse and were skipped
+        failed: Vec<String>,
+    },
+    RebuildFailed {
+        error: String,
+    },
+    UpdateAvailable {
+        dictionary: String,
+        installed: String,
+        available: String,
+    },
+}
+
+#[derive(Serialize)]
+struct WebhookPayload<'a> {
+    timestamp_ms: u64,
+    #[serde(flatten)]
+    event: &'a WebhookEvent,
+}
+
+/// Posts events as JSON to the configured webhook URLs
+#[derive(Debug, Clone, Default)]
+pub struct Webhooks {
+    urls: Vec<String>,
+    client: reqwest::Client,
+}
+
+impl Webhooks {
+    pub fn new(urls: Vec<String>) -> Self {
+        Self {
+            urls,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Post an event to every webhook. Delivery failures are logged, not returned,
+    /// so a broken webhook never fails the operation that triggered it.
+    pub async fn send(&self, event: &WebhookEvent) {
+        if self.urls.is_empty() {
+            return;
+        }
+        let payload = WebhookPayload {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+            event,
+        };
+
+        for url in &self.urls {
+            let delivered = self
+                .client
+                .post(url)
+                .json(&payload)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            if let Err(e) = delivered {
+                warn!("Webhook {} failed: {}", url, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload_is_tagged_with_event_name() {
+        let event = WebhookEvent::RebuildFailed {
+            error: "disk full".to_string(),
+        };
+        let payload = serde_json::to_value(WebhookPayload {
+            timestamp_ms: 1,
+            event: &event,
+        })
+        .unwrap();
+
+        assert_eq!(
+            payload,
+            serde_json::json!({"timestamp_ms": 1, "event": "rebuild_failed", "error": "disk full"})
+        );
+    }
+}
@@ -0,0 +1,909 @@
+use anyhow::{Context, Result};
+use flate2::read::MultiGzDecoder;
+use serde::Deserialize;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use crate::models::{DictionaryEntry, PartOfSpeech};
+use crate::parser::ParseStats;
+use crate::{stardict, tei, xml};
+
+/// Entries handed to the sink at a time
+const BATCH_SIZE: usize = 10_000;
+
+/// Translation templates of the English Wiktionary: `{{t+|de|Haus|n}}`
+const ENGLISH_TRANSLATIONS: &[&str] = &["t", "t+", "tt", "tt+", "t-check", "t+check"];
+/// Translation templates of the German Wiktionary: `{{Ü|en|house}}`
+const GERMAN_TRANSLATIONS: &[&str] = &["Ü", "Üt"];
+
+/// One line of a kaikki.org extract. Fields other than these are ignored.
+#[derive(Debug, Deserialize)]
+struct KaikkiWord {
+    word: String,
+    #[serde(default)]
+    lang_code: String,
+    #[serde(default)]
+    pos: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    senses: Vec<KaikkiSense>,
+    #[serde(default)]
+    translations: Vec<KaikkiTranslation>,
+    #[serde(default)]
+    sounds: Vec<KaikkiSound>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KaikkiSense {
+    #[serde(default)]
+    glosses: Vec<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    /// Newer extracts keep translations with the sense they translate
+    #[serde(default)]
+    translations: Vec<KaikkiTranslation>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KaikkiTranslation {
+    #[serde(default, alias = "lang_code")]
+    code: String,
+    #[serde(default)]
+    word: String,
+}
+
+/// A pronunciation; only those with a recording have URLs
+#[derive(Debug, Deserialize)]
+struct KaikkiSound {
+    #[serde(default)]
+    mp3_url: Option<String>,
+    #[serde(default)]
+    ogg_url: Option<String>,
+}
+
+/// Parse a Wiktionary dump or kaikki.org extract, see `parse_wiktionary_batches`
+pub fn parse_wiktionary<P: AsRef<Path>>(
+    path: P,
+    stats: &mut ParseStats,
+) -> Result<Vec<DictionaryEntry>> {
+    let mut entries = Vec::new();
+    parse_wiktionary_batches(path, stats, |batch| {
+        entries.extend(batch);
+        Ok(())
+    })?;
+    Ok(entries)
+}
+
+/// Stream German and English entries out of a Wiktionary XML dump
+/// (`pages-articles.xml`, English or German edition) or a kaikki.org JSON
+/// Lines extract, either optionally gzipped, handing them to `sink` in
+/// batches. The file is read line by line, so dumps of several gigabytes
+/// never sit in memory.
+///
+/// German words become de-en entries with their English translations, or
+/// with their English glosses when the English edition has no translation
+/// table for them. English words become en-de entries with their German
+/// translations. Every other language is skipped. Recordings of a word
+/// (`{{audio}}` templates, or `sounds` in extracts) become its `audio_urls`.
+pub fn parse_wiktionary_batches<P, F>(path: P, stats: &mut ParseStats, mut sink: F) -> Result<usize>
+where
+    P: AsRef<Path>,
+    F: FnMut(Vec<DictionaryEntry>) -> Result<()>,
+{
+    let path = path.as_ref();
+    let file = File::open(path).context(format!("Failed to open Wiktionary file: {:?}", path))?;
+    let reader: Box<dyn Read> = match path.extension().and_then(|s| s.to_str()) {
+        Some("gz") => Box::new(MultiGzDecoder::new(file)),
+        Some("bz2") => anyhow::bail!(
+            "Wiktionary dumps must be decompressed first (bunzip2 {:?}), or recompressed with gzip",
+            path
+        ),
+        _ => Box::new(file),
+    };
+
+    let mut batch = Vec::new();
+    let mut total = 0;
+    let mut emit = |entries: Vec<DictionaryEntry>| -> Result<()> {
+        batch.extend(entries);
+        if batch.len() >= BATCH_SIZE {
+            total += batch.len();
+            sink(std::mem::take(&mut batch))?;
+        }
+        Ok(())
+    };
+
+    let mut dump: Option<DumpReader> = None;
+    let mut kaikki = false;
+    for line in BufReader::new(reader).lines() {
+        let line = line?;
+        if let Some(dump) = &mut dump {
+            emit(dump.line(&line))?;
+            continue;
+        }
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if !kaikki && trimmed.starts_with('<') {
+            let mut reader = DumpReader::default();
+            emit(reader.line(&line))?;
+            dump = Some(reader);
+            continue;
+        }
+        kaikki = true;
+        match serde_json::from_str::<KaikkiWord>(trimmed) {
+            Ok(word) => emit(kaikki_entries(word))?,
+            Err(_) => stats.record_malformed(trimmed),
+        }
+    }
+    if let Some(dump) = &mut dump {
+        emit(dump.finish_page())?;
+    }
+
+    total += batch.len();
+    if !batch.is_empty() {
+        sink(batch)?;
+    }
+    Ok(total)
+}
+
+fn kaikki_entries(word: KaikkiWord) -> Vec<DictionaryEntry> {
+    let (language, target) = match word.lang_code.as_str() {
+        "de" => ("de-en", "en"),
+        "en" => ("en-de", "de"),
+        _ => return Vec::new(),
+    };
+    let headword = word.word.trim();
+    let mut translations = Vec::new();
+    for translation in word
+        .translations
+        .iter()
+        .chain(word.senses.iter().flat_map(|sense| &sense.translations))
+    {
+        let text = translation.word.trim();
+        if translation.code == target && !text.is_empty() {
+            push_unique(&mut translations, text.to_string());
+        }
+    }
+    // The English edition glosses German words in English instead
+    if translations.is_empty() && language == "de-en" {
+        for gloss in word.senses.iter().flat_map(|sense| &sense.glosses) {
+            let gloss = gloss.trim();
+            if !gloss.is_empty() {
+                push_unique(&mut translations, gloss.to_string());
+            }
+        }
+    }
+    if headword.is_empty() || translations.is_empty() {
+        return Vec::new();
+    }
+
+    let mut entry = DictionaryEntry::new(
+        headword.to_string(),
+        translations.join("; "),
+        language.to_string(),
+    );
+    entry.part_of_speech = PartOfSpeech::from_tag(&word.pos);
+    if language == "de-en" {
+        entry.gender = word
+            .tags
+            .iter()
+            .chain(word.senses.iter().flat_map(|sense| &sense.tags))
+            .find_map(|tag| tei::normalize_gender(tag));
+    }
+    // MP3 plays in every browser, the Ogg original is the fallback
+    for sound in word.sounds {
+        if let Some(url) = sound.mp3_url.or(sound.ogg_url)
+            && !url.trim().is_empty()
+        {
+            push_unique(&mut entry.audio_urls, url.trim().to_string());
+        }
+    }
+    vec![entry]
+}
+
+/// Definitions collected for one language and part of speech of a page
+#[derive(Debug)]
+struct Section {
+    language: &'static str,
+    part_of_speech: Option<PartOfSpeech>,
+    gender: Option<String>,
+    translations: Vec<String>,
+    glosses: Vec<String>,
+}
+
+/// Reads the pages of an XML dump one line at a time
+#[derive(Debug, Default)]
+struct DumpReader {
+    title: String,
+    /// Only articles (namespace 0) are dictionary entries
+    article: bool,
+    in_text: bool,
+    /// Language of the current level-2 heading, e.g. "de" for `==German==`
+    language: Option<&'static str>,
+    part_of_speech: Option<PartOfSpeech>,
+    gender: Option<String>,
+    /// Whether `#` lines are senses of the current part of speech
+    in_senses: bool,
+    sections: Vec<Section>,
+    /// Recordings of the page title by language, from `{{audio}}` templates
+    audio: Vec<(&'static str, String)>,
+}
+
+impl DumpReader {
+    fn line(&mut self, raw: &str) -> Vec<DictionaryEntry> {
+        let trimmed = raw.trim();
+        if !self.in_text {
+            if let Some(title) = element_text(trimmed, "title") {
+                let entries = self.finish_page();
+                self.title = xml::decode_entities(title);
+                return entries;
+            }
+            if let Some(namespace) = element_text(trimmed, "ns") {
+                self.article = namespace == "0";
+            } else if trimmed.starts_with("<text") && !trimmed.ends_with("/>") {
+                self.in_text = true;
+                let start = trimmed.find('>').map_or(trimmed.len(), |end| end + 1);
+                self.text_line(&trimmed[start..]);
+            }
+            return Vec::new();
+        }
+        self.text_line(trimmed);
+        Vec::new()
+    }
+
+    fn text_line(&mut self, line: &str) {
+        let line = match line.strip_suffix("</text>") {
+            Some(line) => {
+                self.in_text = false;
+                line
+            }
+            None => line,
+        };
+        if !self.article {
+            return;
+        }
+        let line = xml::decode_entities(line);
+        let line = line.trim();
+
+        if let Some(heading) = heading(line, 2) {
+            self.language = heading_language(heading);
+            self.part_of_speech = None;
+            self.gender = None;
+            self.in_senses = false;
+        } else if let Some(heading) = heading(line, 3) {
+            self.in_senses = false;
+            if let Some((part_of_speech, gender)) = heading_part_of_speech(heading) {
+                self.part_of_speech = part_of_speech;
+                self.gender = gender;
+                self.in_senses = true;
+            }
+        }
+        let Some(language) = self.language else {
+            return;
+        };
+
+        for file in audio_files(line) {
+            let url = commons_url(&file);
+            if !self.audio.contains(&(language, url.clone())) {
+                self.audio.push((language, url));
+            }
+        }
+
+        match language {
+            // English edition: glosses of German words, their gender from the headword line
+            "de" if self.in_senses && line.starts_with("{{de-noun|") => {
+                if let Some(gender) = templates(line)
+                    .first()
+                    .and_then(|params| params.get(1))
+                    .and_then(|genders| genders.split(',').next())
+                    .and_then(tei::normalize_gender)
+                {
+                    self.gender = Some(gender);
+                }
+            }
+            "de" if self.in_senses && is_sense(line) => {
+                let gloss = clean_wikitext(&line[1..]);
+                if !gloss.is_empty() {
+                    push_unique(&mut self.section().glosses, gloss);
+                }
+            }
+            // German edition: `*{{en}}: [1] {{Ü|en|house}}`
+            "de" if line.starts_with("*{{en}}:") => {
+                for word in translation_words(line, GERMAN_TRANSLATIONS, "en") {
+                    push_unique(&mut self.section().translations, word);
+                }
+            }
+            // English edition: `* German: {{t+|de|Haus|n}}`
+            "en" if line.starts_with("* German:") => {
+                for word in translation_words(line, ENGLISH_TRANSLATIONS, "de") {
+                    push_unique(&mut self.section().translations, word);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Section for the current language and part of speech
+    fn section(&mut self) -> &mut Section {
+        let language = match self.language {
+            Some("de") => "de-en",
+            _ => "en-de",
+        };
+        let position = self.sections.iter().position(|section| {
+            section.language == language && section.part_of_speech == self.part_of_speech
+        });
+        let position = position.unwrap_or_else(|| {
+            self.sections.push(Section {
+                language,
+                part_of_speech: self.part_of_speech,
+                gender: None,
+                translations: Vec::new(),
+                glosses: Vec::new(),
+            });
+            self.sections.len() - 1
+        });
+        let section = &mut self.sections[position];
+        if section.gender.is_none() && language == "de-en" {
+            section.gender = self.gender.clone();
+        }
+        section
+    }
+
+    /// Entries of the page read so far, resetting for the next page
+    fn finish_page(&mut self) -> Vec<DictionaryEntry> {
+        let title = std::mem::take(&mut self.title);
+        let sections = std::mem::take(&mut self.sections);
+        let audio = std::mem::take(&mut self.audio);
+        *self = Self::default();
+        // Pages of other namespaces were skipped already
+        sections
+            .into_iter()
+            .filter_map(|section| {
+                let definitions = if section.translations.is_empty() {
+                    section.glosses
+                } else {
+                    section.translations
+                };
+                if title.is_empty() || definitions.is_empty() {
+                    return None;
+                }
+                let mut entry = DictionaryEntry::new(
+                    title.clone(),
+                    definitions.join("; "),
+                    section.language.to_string(),
+                );
+                entry.part_of_speech = section.part_of_speech;
+                entry.gender = section.gender;
+                entry.audio_urls = audio
+                    .iter()
+                    .filter(|(language, _)| section.language.split('-').next() == Some(language))
+                    .map(|(_, url)| url.clone())
+                    .collect();
+                Some(entry)
+            })
+            .collect()
+    }
+}
+
+fn push_unique(values: &mut Vec<String>, value: String) {
+    if !values.contains(&value) {
+        values.push(value);
+    }
+}
+
+/// Text of a one-line element such as `<title>Haus</title>`
+fn element_text<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    line.strip_prefix(&format!("<{}>", name))?
+        .strip_suffix(&format!("</{}>", name))
+}
+
+/// Text of a heading of exactly `level` (`==German==` is level 2), or for
+/// level 3, of any deeper heading too
+fn heading(line: &str, level: usize) -> Option<&str> {
+    let depth = line.bytes().take_while(|&b| b == b'=').count();
+    let matches = if level == 2 {
+        depth == 2
+    } else {
+        depth >= level
+    };
+    if !matches || !line.ends_with("==") {
+        return None;
+    }
+    Some(line.trim_matches('=').trim())
+}
+
+/// `==German==` in the English edition, `== Haus ({{Sprache|Deutsch}}) ==` in the German one
+fn heading_language(heading: &str) -> Option<&'static str> {
+    match heading {
+        "German" => Some("de"),
+        "English" => Some("en"),
+        _ if heading.contains("{{Sprache|Deutsch}}") => Some("de"),
+        _ if heading.contains("{{Sprache|Englisch}}") => Some("en"),
+        _ => None,
+    }
+}
+
+/// Part of speech of a heading that introduces one, e.g. `===Noun===`, or
+/// `=== {{Wortart|Substantiv|Deutsch}}, {{n}} ===` with its gender
+fn heading_part_of_speech(heading: &str) -> Option<(Option<PartOfSpeech>, Option<String>)> {
+    const GERMAN_NAMES: &[(&str, &str)] = &[
+        ("Substantiv", "noun"),
+        ("Verb", "verb"),
+        ("Adjektiv", "adjective"),
+        ("Adverb", "adverb"),
+        ("Pronomen", "pronoun"),
+        ("Personalpronomen", "pronoun"),
+        ("Präposition", "preposition"),
+        ("Konjunktion", "conjunction"),
+        ("Interjektion", "interjection"),
+    ];
+    let parsed = templates(heading);
+    if let Some(wortart) = parsed.iter().find(|params| params[0] == "Wortart") {
+        let name = wortart.get(1).map(String::as_str).unwrap_or_default();
+        let part_of_speech = GERMAN_NAMES
+            .iter()
+            .find(|(german, _)| *german == name)
+            .and_then(|(_, tag)| PartOfSpeech::from_tag(tag));
+        let gender = parsed
+            .iter()
+            .find_map(|params| tei::normalize_gender(&params[0]));
+        return Some((part_of_speech, gender));
+    }
+    match heading {
+        "Proper noun" => Some((Some(PartOfSpeech::Noun), None)),
+        "Noun" | "Verb" | "Adjective" | "Adverb" | "Pronoun" | "Preposition" | "Conjunction"
+        | "Interjection" | "Phrase" | "Article" | "Numeral" | "Particle" => {
+            Some((PartOfSpeech::from_tag(heading), None))
+        }
+        _ => None,
+    }
+}
+
+/// Recordings on a line: `{{audio|de|De-Haus.ogg}}` in the English edition,
+/// `{{Audio|De-Haus.ogg}}` in the German one
+fn audio_files(line: &str) -> Vec<String> {
+    templates(line)
+        .into_iter()
+        .filter_map(|params| match params[0].as_str() {
+            "audio" => params.get(2).cloned(),
+            "Audio" => params.get(1).cloned(),
+            _ => None,
+        })
+        .filter(|file| !file.is_empty())
+        .collect()
+}
+
+/// URL of a file on Wikimedia Commons, redirecting to the file itself
+fn commons_url(file: &str) -> String {
+    let mut url = "https://commons.wikimedia.org/wiki/Special:FilePath/".to_string();
+    for byte in file.trim().replace(' ', "_").bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~()".contains(&byte) {
+            url.push(byte as char);
+        } else {
+            url.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    url
+}
+
+/// Recordings saved by `download_audio`
+#[derive(Debug, Default)]
+pub struct AudioDownloads {
+    pub downloaded: usize,
+    /// Saved by an earlier download
+    pub present: usize,
+    /// URLs that could not be downloaded, with the error
+    pub failed: Vec<(String, String)>,
+}
+
+/// Where the recording at `url` is saved in `dir`: its file name, e.g.
+/// `De-Haus.ogg`, with characters unsafe in paths replaced
+pub fn audio_path(dir: &Path, url: &str) -> PathBuf {
+    let name = url
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or_default();
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "-._~()%".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.is_empty() || name.starts_with('.') {
+        return dir.join(format!("audio{}", name));
+    }
+    dir.join(name)
+}
+
+/// Content type of a saved recording, from its extension
+pub fn audio_content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("mp3") => "audio/mpeg",
+        Some("ogg" | "oga" | "opus") => "audio/ogg",
+        Some("wav") => "audio/wav",
+        Some("flac") => "audio/flac",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Download recordings into `dir` for offline playback, skipping those
+/// saved before. A failed download is recorded and does not stop the others.
+pub fn download_audio<'a>(
+    dir: &Path,
+    urls: impl IntoIterator<Item = &'a str>,
+) -> Result<AudioDownloads> {
+    fs::create_dir_all(dir).context(format!("Failed to create {:?}", dir))?;
+    // Wikimedia turns away clients without a descriptive user agent
+    let client = reqwest::blocking::Client::builder()
+        .user_agent(concat!("dictv/", env!("CARGO_PKG_VERSION")))
+        .build()?;
+
+    let mut report = AudioDownloads::default();
+    for url in urls {
+        let path = audio_path(dir, url);
+        if path.exists() {
+            report.present += 1;
+            continue;
+        }
+        let audio = client
+            .get(url)
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.bytes());
+        match audio {
+            Ok(audio) => {
+                // Renamed into place once complete, so an interrupted download is retried
+                let mut partial = path.clone().into_os_string();
+                partial.push(".partial");
+                fs::write(&partial, &audio).context(format!("Failed to write {:?}", partial))?;
+                fs::rename(&partial, &path).context(format!("Failed to write {:?}", path))?;
+                report.downloaded += 1;
+            }
+            Err(e) => report.failed.push((url.to_string(), e.to_string())),
+        }
+    }
+    Ok(report)
+}
+
+/// A numbered sense, `# gloss`, but not its examples and quotations (`#:`, `#*`)
+fn is_sense(line: &str) -> bool {
+    line.starts_with('#') && !line[1..].starts_with(['#', ':', '*'])
+}
+
+/// Words of the translation templates `names` into `language` on a line
+fn translation_words(line: &str, names: &[&str], language: &str) -> Vec<String> {
+    templates(line)
+        .into_iter()
+        .filter(|params| names.contains(&params[0].as_str()))
+        .filter(|params| params.get(1).map(String::as_str) == Some(language))
+        .filter_map(|params| params.get(2).map(|word| clean_wikitext(word)))
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// Position of the `close` matching an already consumed `open`
+fn closing(text: &str, open: &str, close: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut position = 0;
+    while position < text.len() {
+        let rest = &text[position..];
+        if rest.starts_with(open) {
+            depth += 1;
+            position += open.len();
+        } else if rest.starts_with(close) {
+            if depth == 0 {
+                return Some(position);
+            }
+            depth -= 1;
+            position += close.len();
+        } else {
+            position += rest.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    None
+}
+
+/// Parameters of the templates on a line, each starting with the template name.
+/// Named parameters (`sc=Latn`) are dropped.
+fn templates(line: &str) -> Vec<Vec<String>> {
+    let mut found = Vec::new();
+    let mut rest = line;
+    while let Some(start) = rest.find("{{") {
+        let inner = &rest[start + 2..];
+        let Some(end) = closing(inner, "{{", "}}") else {
+            break;
+        };
+        found.push(template_params(&inner[..end]));
+        rest = &inner[end + 2..];
+    }
+    found
+}
+
+fn template_params(inner: &str) -> Vec<String> {
+    let mut params = Vec::new();
+    let (mut depth, mut start) = (0i32, 0);
+    let bytes = inner.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match &bytes[i..] {
+            [b'{', b'{', ..] | [b'[', b'[', ..] => {
+                depth += 1;
+                i += 2;
+                continue;
+            }
+            [b'}', b'}', ..] | [b']', b']', ..] => {
+                depth -= 1;
+                i += 2;
+                continue;
+            }
+            [b'|', ..] if depth == 0 => {
+                params.push(&inner[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    params.push(&inner[start..]);
+    params
+        .into_iter()
+        .enumerate()
+        .filter(|(position, param)| *position == 0 || !is_named(param))
+        .map(|(_, param)| param.trim().to_string())
+        .collect()
+}
+
+fn is_named(param: &str) -> bool {
+    let name_end = param.find(['{', '[']).unwrap_or(param.len());
+    param[..name_end].contains('=')
+}
+
+/// Plain text of a gloss: links and common templates reduced to their text,
+/// references, markup and other templates dropped
+fn clean_wikitext(text: &str) -> String {
+    let mut plain = String::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if let Some(inner) = rest.strip_prefix("{{") {
+            let Some(end) = closing(inner, "{{", "}}") else {
+                break;
+            };
+            plain.push_str(&expand_template(&template_params(&inner[..end])));
+            rest = &inner[end + 2..];
+        } else if let Some(inner) = rest.strip_prefix("[[") {
+            let Some(end) = closing(inner, "[[", "]]") else {
+                break;
+            };
+            let link = &inner[..end];
+            if !link.contains(':') {
+                let label = link.rsplit('|').next().unwrap_or(link);
+                plain.push_str(&clean_wikitext(label));
+            }
+            rest = &inner[end + 2..];
+        } else if let Some(inner) = rest.strip_prefix("<ref") {
+            rest = match (inner.find("/>"), inner.find("</ref>")) {
+                (Some(empty), Some(end)) if empty < end && !inner[..empty].contains('>') => {
+                    &inner[empty + 2..]
+                }
+                (_, Some(end)) => &inner[end + "</ref>".len()..],
+                (Some(empty), None) => &inner[empty + 2..],
+                (None, None) => "",
+            };
+        } else {
+            plain.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    stardict::strip_tags(&plain.replace("'''", "").replace("''", ""))
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn expand_template(params: &[String]) -> String {
+    let param = |position: usize| {
+        params
+            .get(position)
+            .map(|param| clean_wikitext(param))
+            .unwrap_or_default()
+    };
+    let name = params[0].as_str();
+    match name {
+        "l" | "m" | "l-self" | "ll" | "lang" => param(2),
+        "w" | "taxlink" | "vern" => param(1),
+        "gloss" | "gl" => format!("({})", param(1)),
+        "q" | "qual" | "qualifier" | "i" | "qf" => format!("({})", param(1)),
+        "lb" | "lbl" | "label" => {
+            let labels: Vec<String> = (2..params.len())
+                .map(param)
+                .filter(|label| !label.is_empty() && label != "_")
+                .collect();
+            if labels.is_empty() {
+                String::new()
+            } else {
+                format!("({})", labels.join(", "))
+            }
+        }
+        // {{plural of|de|Haus}}
+        _ if name.ends_with(" of") => format!("{} {}", name, param(2)),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const DUMP: &str = r#"<mediawiki xmlns="http://www.mediawiki.org/xml/export-0.10/">
+  <page>
+    <title>Haus</title>
+    <ns>0</ns>
+    <revision>
+      <text bytes="300" xml:space="preserve">==German==
+===Etymology===
+# not a sense
+===Pronunciation===
+* {{audio|de|De-Haus.ogg|Audio}}
+===Noun===
+{{de-noun|n,es|Häuser}}
+
+# [[house]], [[building]] {{q|dwelling}}&lt;ref&gt;Duden&lt;/ref&gt;
+#: {{ux|de|Das Haus ist groß.}}
+# {{lb|de|astrology}} [[house#Noun|house]]
+====Declension====
+# not a sense either</text>
+    </revision>
+  </page>
+  <page>
+    <title>house</title>
+    <ns>0</ns>
+    <revision>
+      <text bytes="200" xml:space="preserve">==English==
+===Noun===
+# A [[building]] for living in.
+====Translations====
+{{trans-top|abode}}
+* French: {{t+|fr|maison|f}}
+* German: {{t+|de|Haus|n}}, {{t|de|Heim|n}}
+*: Alemannic German: {{t|gsw|Huus|n}}
+{{trans-bottom}}</text>
+    </revision>
+  </page>
+  <page>
+    <title>Wiktionary:Main Page</title>
+    <ns>4</ns>
+    <revision>
+      <text bytes="20" xml:space="preserve">==German==
+===Noun===
+# ignored</text>
+    </revision>
+  </page>
+  <page>
+    <title>Tür</title>
+    <ns>0</ns>
+    <revision>
+      <text bytes="200" xml:space="preserve">== Tür ({{Sprache|Deutsch}}) ==
+=== {{Wortart|Substantiv|Deutsch}}, {{f}} ===
+{{Aussprache}}
+:{{Hörbeispiele}} {{Audio|De-Tür 2.ogg}}
+{{Bedeutungen}}
+:[1] Öffnung in einer Wand
+==== {{Übersetzungen}} ====
+{{Ü-Tabelle|Ü-Liste=
+*{{en}}: [1] {{Ü|en|door}}
+*{{fr}}: [1] {{Ü|fr|porte}} {{f}}
+}}</text>
+    </revision>
+  </page>
+</mediawiki>
+"#;
+
+    fn words(entries: &[DictionaryEntry]) -> Vec<(&str, &str, &str)> {
+        entries
+            .iter()
+            .map(|e| (e.word.as_str(), e.definition.as_str(), e.language.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_wiktionary_dump() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("wiktionary-pages-articles.xml");
+        std::fs::write(&path, DUMP).unwrap();
+
+        let mut stats = ParseStats::default();
+        let entries = parse_wiktionary(&path, &mut stats).unwrap();
+
+        assert_eq!(
+            words(&entries),
+            [
+                (
+                    "Haus",
+                    "house, building (dwelling); (astrology) house",
+                    "de-en"
+                ),
+                ("house", "Haus; Heim", "en-de"),
+                ("Tür", "door", "de-en"),
+            ]
+        );
+        assert_eq!(entries[0].part_of_speech, Some(PartOfSpeech::Noun));
+        assert_eq!(entries[0].gender.as_deref(), Some("n"));
+        assert_eq!(entries[1].gender, None);
+        assert_eq!(entries[2].gender.as_deref(), Some("f"));
+        assert_eq!(
+            entries[0].audio_urls,
+            ["https://commons.wikimedia.org/wiki/Special:FilePath/De-Haus.ogg"]
+        );
+        assert!(entries[1].audio_urls.is_empty());
+        assert_eq!(
+            entries[2].audio_urls,
+            ["https://commons.wikimedia.org/wiki/Special:FilePath/De-T%C3%BCr_2.ogg"]
+        );
+        assert_eq!(stats.malformed_lines, 0);
+    }
+
+    #[test]
+    fn test_parse_kaikki_extract() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("kaikki.org-dictionary-German.jsonl");
+        let lines = [
+            r#"{"word": "Haus", "lang_code": "de", "pos": "noun", "tags": ["neuter"], "senses": [{"glosses": ["house"]}, {"glosses": ["building", "house"]}], "sounds": [{"ipa": "/haʊ̯s/"}, {"audio": "De-Haus.ogg", "ogg_url": "https://upload.wikimedia.org/De-Haus.ogg", "mp3_url": "https://upload.wikimedia.org/De-Haus.ogg.mp3"}]}"#,
+            r#"{"word": "house", "lang_code": "en", "pos": "noun", "senses": [{"glosses": ["A building"], "translations": [{"lang_code": "de", "word": "Heim"}]}], "translations": [{"code": "de", "word": "Haus"}, {"code": "fr", "word": "maison"}]}"#,
+            r#"{"word": "maison", "lang_code": "fr", "senses": [{"glosses": ["house"]}]}"#,
+            r#"{"word": "leer", "lang_code": "de", "senses": []}"#,
+            "{broken",
+        ];
+        std::fs::write(&path, lines.join("\n")).unwrap();
+
+        let mut stats = ParseStats::default();
+        let mut batches = 0;
+        let mut entries = Vec::new();
+        let total = parse_wiktionary_batches(&path, &mut stats, |batch| {
+            batches += 1;
+            entries.extend(batch);
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!((total, batches), (2, 1));
+        assert_eq!(
+            words(&entries),
+            [
+                ("Haus", "house; building", "de-en"),
+                ("house", "Haus; Heim", "en-de"),
+            ]
+        );
+        assert_eq!(entries[0].gender.as_deref(), Some("n"));
+        assert_eq!(
+            entries[0].audio_urls,
+            ["https://upload.wikimedia.org/De-Haus.ogg.mp3"]
+        );
+        assert_eq!(entries[1].part_of_speech, Some(PartOfSpeech::Noun));
+        assert_eq!(stats.malformed_examples, ["{broken"]);
+    }
+
+    #[test]
+    fn test_audio_path() {
+        let dir = Path::new("audio");
+        let path = audio_path(
+            dir,
+            "https://commons.wikimedia.org/wiki/Special:FilePath/De-T%C3%BCr_2.ogg",
+        );
+        assert_eq!(path, dir.join("De-T%C3%BCr_2.ogg"));
+        assert_eq!(audio_content_type(&path), "audio/ogg");
+        assert_eq!(audio_path(dir, "https://x.org/../.."), dir.join("audio.."));
+        assert_eq!(
+            audio_content_type(&audio_path(dir, "https://x.org/a.ogg.mp3")),
+            "audio/mpeg"
+        );
+    }
+}
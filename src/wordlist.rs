@@ -0,0 +1,230 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::models::{DictionaryEntry, ImportFormat};
+use crate::parser::ParseStats;
+
+/// Header names recognized in the first row of a word list whose columns are given by number
+const HEADER_WORDS: [&str; 4] = ["word", "term", "headword", "lemma"];
+
+/// Which columns of a CSV/TSV word list hold the word and its definition.
+/// Each is a 1-based column number or a name from the header row.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Columns {
+    pub word: String,
+    pub definition: String,
+}
+
+impl Default for Columns {
+    fn default() -> Self {
+        Self {
+            word: "1".to_string(),
+            definition: "2".to_string(),
+        }
+    }
+}
+
+impl Columns {
+    /// Named columns need a header row to be looked up in
+    fn named(&self) -> bool {
+        [&self.word, &self.definition]
+            .iter()
+            .any(|column| column.parse::<usize>().is_err())
+    }
+}
+
+/// Parse a personal word list: a `.csv` file (quoted fields allowed) or a
+/// tab-separated `.tsv` file, one entry per row. Empty rows and rows starting
+/// with `#` are skipped, as is a header row.
+pub fn parse_word_list<P: AsRef<Path>>(
+    path: P,
+    format: ImportFormat,
+    columns: &Columns,
+    language: &str,
+    stats: &mut ParseStats,
+) -> Result<Vec<DictionaryEntry>> {
+    let path = path.as_ref();
+    let text =
+        std::fs::read_to_string(path).context(format!("Failed to read word list: {:?}", path))?;
+    let text = text.trim_start_matches('\u{feff}');
+
+    let mut rows: Vec<Vec<String>> = match format {
+        ImportFormat::Csv => csv_rows(text),
+        _ => text
+            .lines()
+            .map(|line| line.split('\t').map(str::to_string).collect())
+            .collect(),
+    };
+    rows.retain(|row| {
+        let first = row.first().map(|cell| cell.trim()).unwrap_or_default();
+        !(first.starts_with('#') || row.iter().all(|cell| cell.trim().is_empty()))
+    });
+
+    let (word_column, definition_column) = if columns.named() {
+        if rows.is_empty() {
+            return Ok(Vec::new());
+        }
+        let header = rows.remove(0);
+        let find = |column: &str| -> Result<usize> {
+            match column.parse::<usize>() {
+                Ok(number) => column_index(number),
+                Err(_) => header
+                    .iter()
+                    .position(|name| name.trim().eq_ignore_ascii_case(column.trim()))
+                    .context(format!(
+                        "No column named {:?} in the header of {:?}",
+                        column, path
+                    )),
+            }
+        };
+        (find(&columns.word)?, find(&columns.definition)?)
+    } else {
+        let word_column = column_index(columns.word.parse()?)?;
+        let definition_column = column_index(columns.definition.parse()?)?;
+        let is_header = rows.first().is_some_and(|row| {
+            row.get(word_column).is_some_and(|cell| {
+                HEADER_WORDS
+                    .iter()
+                    .any(|name| cell.trim().eq_ignore_ascii_case(name))
+            })
+        });
+        if is_header {
+            rows.remove(0);
+        }
+        (word_column, definition_column)
+    };
+
+    let mut entries = Vec::with_capacity(rows.len());
+    for row in rows {
+        let cell = |column: usize| row.get(column).map(|cell| cell.trim()).unwrap_or_default();
+        let (word, definition) = (cell(word_column), cell(definition_column));
+        if word.is_empty() || definition.is_empty() {
+            stats.record_malformed(&row.join(","));
+            continue;
+        }
+        entries.push(DictionaryEntry::new(
+            word.to_string(),
+            definition.to_string(),
+            language.to_string(),
+        ));
+    }
+    Ok(entries)
+}
+
+fn column_index(number: usize) -> Result<usize> {
+    if number == 0 {
+        anyhow::bail!("Column numbers start at 1");
+    }
+    Ok(number - 1)
+}
+
+/// Rows of a CSV file. Quoted fields may hold commas, line breaks and `""` for a quote.
+fn csv_rows(text: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (true, c) => field.push(c),
+            (false, '"') if field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+            }
+            (false, ',') => row.push(std::mem::take(&mut field)),
+            (false, '\n') => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            (false, '\r') => {}
+            (false, c) => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn parse(
+        name: &str,
+        content: &str,
+        format: ImportFormat,
+        columns: &Columns,
+    ) -> Result<Vec<(String, String)>> {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(name);
+        std::fs::write(&path, content).unwrap();
+        let entries = parse_word_list(&path, format, columns, "de-en", &mut ParseStats::default())?;
+        Ok(entries
+            .into_iter()
+            .map(|entry| (entry.word, entry.definition))
+            .collect())
+    }
+
+    #[test]
+    fn test_parse_csv_with_named_columns() {
+        let csv = "Notes,German,English\r\n\
+                   ,Haus,\"house, building\"\r\n\
+                   \"said \"\"hi\"\"\",grüßen,\"to greet\nto salute\"\r\n\
+                   # not a word\r\n\
+                   ,Leer,\r\n";
+        let columns = Columns {
+            word: "german".to_string(),
+            definition: "English".to_string(),
+        };
+        assert_eq!(
+            parse("words.csv", csv, ImportFormat::Csv, &columns).unwrap(),
+            [
+                ("Haus".to_string(), "house, building".to_string()),
+                ("grüßen".to_string(), "to greet\nto salute".to_string()),
+            ]
+        );
+
+        let columns = Columns {
+            word: "Deutsch".to_string(),
+            ..Columns::default()
+        };
+        let error = parse("words.csv", csv, ImportFormat::Csv, &columns).unwrap_err();
+        assert!(error.to_string().contains("No column named \"Deutsch\""));
+    }
+
+    #[test]
+    fn test_parse_tsv_with_numbered_columns() {
+        let tsv = "word\tnotes\tdefinition\nHaus\tnoun\thouse\n\nAuto\t\tcar\n";
+        let columns = Columns {
+            word: "1".to_string(),
+            definition: "3".to_string(),
+        };
+        assert_eq!(
+            parse("words.tsv", tsv, ImportFormat::Tsv, &columns).unwrap(),
+            [
+                ("Haus".to_string(), "house".to_string()),
+                ("Auto".to_string(), "car".to_string()),
+            ]
+        );
+        assert_eq!(
+            parse(
+                "words.tsv",
+                "Haus\thouse\n",
+                ImportFormat::Tsv,
+                &Columns::default()
+            )
+            .unwrap(),
+            [("Haus".to_string(), "house".to_string())]
+        );
+    }
+}
@@ -0,0 +1,244 @@
+use anyhow::Result;
+use std::collections::BTreeSet;
+
+use crate::models::{LanguagePair, SearchResult};
+use crate::search::SearchEngine;
+
+const MS_PER_DAY: u64 = 24 * 60 * 60 * 1000;
+
+/// Days covered by the Atom feed, today included
+pub const FEED_DAYS: u64 = 7;
+
+/// A day's word with its definitions
+#[derive(Debug, Clone)]
+pub struct WordOfTheDay {
+    /// Days since the Unix epoch
+    pub day: u64,
+    pub entry: SearchResult,
+}
+
+/// Days since the Unix epoch, UTC
+pub fn today() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64 / MS_PER_DAY)
+        .unwrap_or_default()
+}
+
+/// The word of `day`: the same headword for everyone all day, spread evenly
+/// over the dictionary from one day to the next. Non-empty `sources` limit
+/// the dictionaries it is picked from and its definitions come from.
+pub fn word_of_the_day(
+    engine: &SearchEngine,
+    language: LanguagePair,
+    sources: &[String],
+    day: u64,
+) -> Result<Option<WordOfTheDay>> {
+    Ok(words_of_days(engine, language, sources, &[day])?.pop())
+}
+
+/// Words of the last `FEED_DAYS` days, newest first
+pub fn recent(
+    engine: &SearchEngine,
+    language: LanguagePair,
+    sources: &[String],
+    today: u64,
+) -> Result<Vec<WordOfTheDay>> {
+    let days: Vec<u64> = (today.saturating_sub(FEED_DAYS - 1)..=today)
+        .rev()
+        .collect();
+    words_of_days(engine, language, sources, &days)
+}
+
+/// The words of several days, in the order of `days`, with the headwords
+/// counted and looked up once for all of them
+fn words_of_days(
+    engine: &SearchEngine,
+    language: LanguagePair,
+    sources: &[String],
+    days: &[u64],
+) -> Result<Vec<WordOfTheDay>> {
+    let count = engine.headword_count(language, sources)?;
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+    let positions: BTreeSet<u64> = days.iter().map(|&day| mix(day) % count).collect();
+    let headwords = engine.nth_headwords(language, sources, &positions)?;
+
+    let mut words = Vec::with_capacity(days.len());
+    for &day in days {
+        let Some(word) = headwords.get(&(mix(day) % count)) else {
+            continue;
+        };
+        if let Some(entry) = engine.headword_entry(language, word, sources)? {
+            words.push(WordOfTheDay { day, entry });
+        }
+    }
+    Ok(words)
+}
+
+/// SplitMix64 finalizer, so consecutive days land far apart
+fn mix(day: u64) -> u64 {
+    let mut z = day.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Atom feed of the given words
+pub fn atom_feed(language: LanguagePair, words: &[WordOfTheDay]) -> String {
+    let updated = words
+        .first()
+        .map(|word| timestamp(word.day))
+        .unwrap_or_else(|| timestamp(0));
+    let mut feed = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <feed xmlns=\"http://www.w3.org/2005/Atom\">\n\
+         \x20 <title>dictv word of the day ({language})</title>\n\
+         \x20 <id>urn:dictv:wotd:{language}</id>\n\
+         \x20 <updated>{updated}</updated>\n\
+         \x20 <author><name>dictv</name></author>\n",
+        language = language.as_str(),
+    );
+
+    for word in words {
+        let definitions: String = word
+            .entry
+            .definitions
+            .iter()
+            .map(|definition| format!("<li>{}</li>", escape_xml(definition)))
+            .collect();
+        feed.push_str(&format!(
+            "  <entry>\n\
+             \x20   <title>{title}</title>\n\
+             \x20   <id>urn:dictv:wotd:{language}:{date}</id>\n\
+             \x20   <updated>{updated}</updated>\n\
+             \x20   <content type=\"html\">{content}</content>\n\
+             \x20 </entry>\n",
+            title = escape_xml(&word.entry.word),
+            language = language.as_str(),
+            date = date(word.day),
+            updated = timestamp(word.day),
+            content = escape_xml(&format!("<ul>{}</ul>", definitions)),
+        ));
+    }
+
+    feed.push_str("</feed>\n");
+    feed
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// RFC 3339 timestamp of midnight UTC on `day`
+fn timestamp(day: u64) -> String {
+    format!("{}T00:00:00Z", date(day))
+}
+
+/// "YYYY-MM-DD" of a day since the Unix epoch (Howard Hinnant's civil_from_days)
+pub fn date(day: u64) -> String {
+    let z = day as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let d = day_of_year - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = year_of_era + era * 400 + i64::from(m <= 2);
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::DictionaryEntry;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_date() {
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(20_376), "2025-10-15");
+        assert_eq!(date(19_782), "2024-02-29");
+    }
+
+    #[test]
+    fn test_word_of_the_day_is_stable() {
+        let temp_dir = TempDir::new().unwrap();
+        let entries = vec![
+            DictionaryEntry::new("Haus".into(), "house & home".into(), "de-en".into()),
+            DictionaryEntry::new("Auto".into(), "car".into(), "de-en".into()),
+        ];
+        SearchEngine::build_index(temp_dir.path(), entries).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let first = word_of_the_day(&engine, LanguagePair::DE_EN, &[], 100)
+            .unwrap()
+            .unwrap();
+        let again = word_of_the_day(&engine, LanguagePair::DE_EN, &[], 100)
+            .unwrap()
+            .unwrap();
+        assert_eq!(first.entry.word, again.entry.word);
+        assert!(
+            word_of_the_day(&engine, LanguagePair::EN_DE, &[], 100)
+                .unwrap()
+                .is_none()
+        );
+
+        let words = recent(&engine, LanguagePair::DE_EN, &[], 100).unwrap();
+        assert_eq!(words.len(), FEED_DAYS as usize);
+        assert_eq!(words[0].day, 100);
+
+        let feed = atom_feed(LanguagePair::DE_EN, &words);
+        assert_eq!(feed.matches("<entry>").count(), FEED_DAYS as usize);
+        assert!(feed.contains("<updated>1970-04-11T00:00:00Z</updated>"));
+    }
+
+    #[test]
+    fn test_word_of_the_day_is_picked_from_the_searched_dictionaries() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut guten_tag =
+            DictionaryEntry::new("guten Tag".into(), "good day".into(), "de-en".into());
+        guten_tag.source = Some("ding".to_string());
+        let mut auto = DictionaryEntry::new("Auto".into(), "car".into(), "de-en".into());
+        auto.source = Some("freedict".to_string());
+        SearchEngine::build_index(temp_dir.path(), vec![guten_tag, auto]).unwrap();
+        let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+        let words = recent(&engine, LanguagePair::DE_EN, &["ding".to_string()], 100).unwrap();
+        assert_eq!(words.len(), FEED_DAYS as usize);
+        for word in &words {
+            assert_eq!(word.entry.word, "guten tag");
+            assert_eq!(word.entry.definitions, vec!["good day".to_string()]);
+        }
+    }
+
+    #[test]
+    fn test_atom_feed_escapes_definitions() {
+        let entry = SearchResult {
+            word: "haus".to_string(),
+            definitions: vec!["house & home".to_string()],
+            language: "de-en".to_string(),
+            edit_distance: None,
+            score: None,
+            frequency_rank: None,
+            domains: Vec::new(),
+            registers: Vec::new(),
+            parts_of_speech: Vec::new(),
+            genders: Vec::new(),
+            audio_urls: Vec::new(),
+            sources: Vec::new(),
+            html: None,
+        };
+        let feed = atom_feed(LanguagePair::DE_EN, &[WordOfTheDay { day: 0, entry }]);
+
+        assert!(feed.contains("<title>haus</title>"));
+        assert!(feed.contains("&lt;li&gt;house &amp;amp; home&lt;/li&gt;"));
+        assert!(feed.contains("<id>urn:dictv:wotd:de-en:1970-01-01</id>"));
+    }
+}
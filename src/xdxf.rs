@@ -0,0 +1,301 @@
+use anyhow::{Context, Result};
+use flate2::read::MultiGzDecoder;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use tracing::warn;
+
+use crate::models::DictionaryEntry;
+use crate::parser::{self, ParseStats};
+use crate::xml::{self, Token};
+
+/// A parsed XDXF dictionary
+#[derive(Debug, Clone)]
+pub struct XdxfDictionary {
+    /// Dictionary name from `<full_name>` (`<full_title>` in newer files)
+    pub name: Option<String>,
+    /// Language pair from the `lang_from`/`lang_to` attributes, e.g. "de-en"
+    pub language: Option<String>,
+    /// Abbreviations the dictionary defines, as (abbreviation, meaning)
+    pub abbreviations: Vec<(String, String)>,
+    pub entries: Vec<DictionaryEntry>,
+}
+
+/// Parse an `.xdxf` file, or a gzipped or dictzipped one (`.xdxf.gz`, `.xdxf.dz`).
+///
+/// Every `<k>` key of an article becomes an entry with the rest of the article
+/// as its definition. Entries are tagged with the language pair of the root
+/// element when it names known languages, otherwise with `language`.
+pub fn parse_xdxf<P: AsRef<Path>>(
+    path: P,
+    language: &str,
+    stats: &mut ParseStats,
+) -> Result<XdxfDictionary> {
+    let path = path.as_ref();
+    let mut file = File::open(path).context(format!("Failed to open XDXF file: {:?}", path))?;
+
+    let mut bytes = Vec::new();
+    if matches!(path.extension().and_then(|s| s.to_str()), Some("gz" | "dz")) {
+        MultiGzDecoder::new(file).read_to_end(&mut bytes)?;
+    } else {
+        file.read_to_end(&mut bytes)?;
+    }
+
+    let text = String::from_utf8_lossy(&bytes);
+    if !text.contains("<xdxf") {
+        anyhow::bail!("Not an XDXF file, it has no <xdxf> element: {:?}", path);
+    }
+    Ok(parse_xdxf_text(&text, language, stats))
+}
+
+fn parse_xdxf_text(text: &str, language: &str, stats: &mut ParseStats) -> XdxfDictionary {
+    let mut dictionary = XdxfDictionary {
+        name: None,
+        language: None,
+        abbreviations: Vec::new(),
+        entries: Vec::new(),
+    };
+    let mut articles: Vec<(Vec<String>, String)> = Vec::new();
+    // Element whose text is being collected, with the text so far
+    let mut capture: Option<(&str, String)> = None;
+    let mut abbreviation: Option<String> = None;
+    let mut article: Option<Article> = None;
+
+    for token in xml::tokens(text) {
+        if let Some(current) = &mut article {
+            match token {
+                Token::End("ar") => {
+                    let current = article.take().unwrap_or_default();
+                    articles.push((current.keys, current.body));
+                }
+                token => current.add(token),
+            }
+            continue;
+        }
+
+        match token {
+            Token::Start {
+                name: "xdxf",
+                attributes,
+                ..
+            } => {
+                let from = xml::attribute(attributes, "lang_from").unwrap_or_default();
+                let to = xml::attribute(attributes, "lang_to").unwrap_or_default();
+                dictionary.language =
+                    parser::language_from_file_name(&format!("{}-{}", from, to)).map(String::from);
+            }
+            Token::Start {
+                name: "ar",
+                empty: false,
+                ..
+            } => article = Some(Article::default()),
+            Token::Start {
+                name: name @ ("full_name" | "full_title" | "k" | "abbr_k" | "v" | "abbr_v"),
+                empty: false,
+                ..
+            } => capture = Some((name, String::new())),
+            Token::Text(text) => {
+                if let Some((_, captured)) = &mut capture {
+                    captured.push_str(&text);
+                }
+            }
+            Token::End(name) => {
+                let Some((_, captured)) = capture.take_if(|(open, _)| *open == name) else {
+                    continue;
+                };
+                let captured = collapse_whitespace(&captured);
+                match name {
+                    "full_name" | "full_title" => dictionary.name = Some(captured),
+                    // Old files define abbreviations as <abr_def><k>..</k><v>..</v></abr_def>,
+                    // newer ones with <abbr_k> and <abbr_v>
+                    "k" | "abbr_k" => abbreviation = Some(captured),
+                    "v" | "abbr_v" => {
+                        if let Some(abbreviation) = abbreviation.take() {
+                            dictionary.abbreviations.push((abbreviation, captured));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(detected) = &dictionary.language
+        && detected != language
+    {
+        warn!(
+            "XDXF file declares {}, ignoring requested {}",
+            detected, language
+        );
+    }
+    stats.detected_language = dictionary.language.clone();
+    let entry_language = dictionary.language.as_deref().unwrap_or(language);
+
+    for (keys, body) in articles {
+        let definition = body
+            .lines()
+            .map(collapse_whitespace)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("; ");
+        let keys: Vec<String> = keys
+            .iter()
+            .map(|key| collapse_whitespace(key))
+            .filter(|key| !key.is_empty())
+            .collect();
+        if keys.is_empty() || definition.is_empty() {
+            stats.record_malformed(keys.first().map(String::as_str).unwrap_or("<ar>"));
+            continue;
+        }
+        for key in keys {
+            dictionary.entries.push(DictionaryEntry::new(
+                key,
+                definition.clone(),
+                entry_language.to_string(),
+            ));
+        }
+    }
+
+    dictionary
+}
+
+/// An `<ar>` article being read: its keys and the text of everything else
+#[derive(Default)]
+struct Article {
+    keys: Vec<String>,
+    body: String,
+    /// Inside a `<k>`, and how deep inside an `<opt>` of it
+    in_key: bool,
+    optional_depth: usize,
+}
+
+impl Article {
+    fn add(&mut self, token: Token) {
+        match token {
+            Token::Start {
+                name: "k",
+                empty: false,
+                ..
+            } => {
+                self.in_key = true;
+                self.keys.push(String::new());
+            }
+            Token::End("k") => self.in_key = false,
+            // Optional parts of a key ("Haus<opt>e</opt>") are left out of the headword
+            Token::Start {
+                name: "opt",
+                empty: false,
+                ..
+            } if self.in_key => self.optional_depth += 1,
+            Token::End("opt") if self.in_key => {
+                self.optional_depth = self.optional_depth.saturating_sub(1)
+            }
+            Token::Text(text) if self.in_key => {
+                if self.optional_depth == 0
+                    && let Some(key) = self.keys.last_mut()
+                {
+                    key.push_str(&text);
+                }
+            }
+            Token::Text(text) => self.body.push_str(&text),
+            // Transcriptions read like the other formats' phonetics
+            Token::Start {
+                name: "tr",
+                empty: false,
+                ..
+            }
+            | Token::End("tr") => self.body.push('/'),
+            Token::Start {
+                name: "br" | "def" | "ex",
+                ..
+            } => self.body.push('\n'),
+            Token::End("def" | "ex") => self.body.push('\n'),
+            _ => {}
+        }
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"<?xml version="1.0" encoding="UTF-8" ?>
+<!DOCTYPE xdxf SYSTEM "http://xdxf.sourceforge.net/xdxf_lousy.dtd">
+<xdxf lang_from="DEU" lang_to="ENG" format="visual">
+<full_name>German-English Sample</full_name>
+<description>Test &amp; sample</description>
+<abbreviations>
+  <abr_def><k>f</k><v>feminine</v></abr_def>
+  <abr_def><k>n</k><v>neuter</v></abr_def>
+</abbreviations>
+<ar><k>Haus</k> <tr>haus</tr> <abr>n</abr>
+house, building
+<ex>ein großes Haus</ex></ar>
+<ar><k>Tür</k><k>Tuer</k>
+<abr>f</abr> door</ar>
+<ar><k>Leer</k></ar>
+<ar><k>Bahn<opt>hof</opt></k> railway</ar>
+</xdxf>"#;
+
+    #[test]
+    fn test_parse_xdxf() {
+        let mut stats = ParseStats::default();
+        let dictionary = parse_xdxf_text(SAMPLE, "en-de", &mut stats);
+
+        assert_eq!(dictionary.name.as_deref(), Some("German-English Sample"));
+        assert_eq!(dictionary.language.as_deref(), Some("de-en"));
+        assert_eq!(stats.detected_language.as_deref(), Some("de-en"));
+        assert_eq!(
+            dictionary.abbreviations,
+            [
+                ("f".to_string(), "feminine".to_string()),
+                ("n".to_string(), "neuter".to_string()),
+            ]
+        );
+
+        let entries: Vec<(&str, &str)> = dictionary
+            .entries
+            .iter()
+            .map(|e| (e.word.as_str(), e.definition.as_str()))
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                ("Haus", "/haus/ n; house, building; ein großes Haus"),
+                ("Tür", "f door"),
+                ("Tuer", "f door"),
+                ("Bahn", "railway"),
+            ]
+        );
+        assert!(dictionary.entries.iter().all(|e| e.language == "de-en"));
+        assert_eq!(stats.malformed_examples, ["Leer"]);
+    }
+
+    #[test]
+    fn test_parse_xdxf_logical_format() {
+        let xdxf = r#"<xdxf lang_from="ENG" lang_to="GER" format="logical" revision="33">
+<meta_info><full_title>English-German</full_title>
+<abbreviations><abbr_def><abbr_k>v</abbr_k><abbr_v>verb</abbr_v></abbr_def></abbreviations>
+</meta_info>
+<lexicon>
+<ar><k>run</k><def><gr><abbr>v</abbr></gr><def><deftext><dtrn>laufen</dtrn></deftext></def><def><deftext><dtrn>rennen</dtrn></deftext></def></def></ar>
+</lexicon>
+</xdxf>"#;
+        let mut stats = ParseStats::default();
+        let dictionary = parse_xdxf_text(xdxf, "de-en", &mut stats);
+
+        assert_eq!(dictionary.name.as_deref(), Some("English-German"));
+        assert_eq!(dictionary.language.as_deref(), Some("en-de"));
+        assert_eq!(
+            dictionary.abbreviations,
+            [("v".to_string(), "verb".to_string())]
+        );
+        assert_eq!(dictionary.entries.len(), 1);
+        assert_eq!(dictionary.entries[0].definition, "v; laufen; rennen");
+    }
+}
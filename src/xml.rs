@@ -0,0 +1,208 @@
+/// A piece of an XML document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token<'a> {
+    /// Start tag with its raw attribute text, `empty` for `<br/>`-style tags
+    Start {
+        name: &'a str,
+        attributes: &'a str,
+        empty: bool,
+    },
+    End(&'a str),
+    /// Text with entities decoded
+    Text(String),
+}
+
+/// Iterator over the tokens of an XML document, enough for the XML dictionary
+/// formats. It does not validate or resolve DTDs and namespaces.
+pub struct Tokens<'a> {
+    rest: &'a str,
+}
+
+/// Tokens of `xml`, skipping the declaration, doctype, comments and processing instructions
+pub fn tokens(xml: &str) -> Tokens<'_> {
+    Tokens { rest: xml }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        loop {
+            let rest = self.rest;
+            if rest.is_empty() {
+                return None;
+            }
+
+            if !rest.starts_with('<') {
+                let end = rest.find('<').unwrap_or(rest.len());
+                self.rest = &rest[end..];
+                return Some(Token::Text(decode_entities(&rest[..end])));
+            }
+            if let Some(comment) = rest.strip_prefix("<!--") {
+                self.rest = skip_past(comment, "-->");
+                continue;
+            }
+            if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+                let end = cdata.find("]]>").unwrap_or(cdata.len());
+                self.rest = skip_past(cdata, "]]>");
+                return Some(Token::Text(cdata[..end].to_string()));
+            }
+            if rest.starts_with("<!") || rest.starts_with("<?") {
+                // A doctype may carry an internal subset in brackets
+                let tag_end = rest.find('>').unwrap_or(rest.len());
+                self.rest = match rest.find('[') {
+                    Some(bracket) if bracket < tag_end => skip_past(&rest[bracket..], "]>"),
+                    _ => skip_past(rest, ">"),
+                };
+                continue;
+            }
+
+            let end = tag_end(rest);
+            let inner = &rest[1..end];
+            self.rest = rest.get(end + 1..).unwrap_or_default();
+            if let Some(name) = inner.strip_prefix('/') {
+                return Some(Token::End(name.trim()));
+            }
+            let (inner, empty) = match inner.strip_suffix('/') {
+                Some(inner) => (inner, true),
+                None => (inner, false),
+            };
+            let name_end = inner.find(char::is_whitespace).unwrap_or(inner.len());
+            return Some(Token::Start {
+                name: &inner[..name_end],
+                attributes: &inner[name_end..],
+                empty,
+            });
+        }
+    }
+}
+
+/// The rest of `text` after the first `marker`, or nothing when it never ends
+fn skip_past<'a>(text: &'a str, marker: &str) -> &'a str {
+    text.find(marker)
+        .map(|i| &text[i + marker.len()..])
+        .unwrap_or_default()
+}
+
+/// Position of the `>` closing the tag at the start of `text`, ignoring any in quoted values
+fn tag_end(text: &str) -> usize {
+    let mut quote = None;
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '>') => return i,
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+/// Value of the attribute `name` in the raw attribute text of a start tag
+pub fn attribute(attributes: &str, name: &str) -> Option<String> {
+    let mut rest = attributes;
+    while let Some(eq) = rest.find('=') {
+        let key = rest[..eq].trim();
+        let value = rest[eq + 1..].trim_start();
+        let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let end = value[1..].find(quote)? + 1;
+        if key == name {
+            return Some(decode_entities(&value[1..end]));
+        }
+        rest = &value[end + 1..];
+    }
+    None
+}
+
+/// Decode the predefined and numeric character entities, leaving unknown ones as written
+pub fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..]
+            .find(';')
+            .filter(|&end| end <= 10)
+            .map(|end| &rest[1..end + 1]);
+        let character = entity.and_then(|entity| match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => {
+                let code = match entity.strip_prefix("#x").or(entity.strip_prefix("#X")) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => entity.strip_prefix('#')?.parse().ok(),
+                };
+                code.and_then(char::from_u32)
+            }
+        });
+        match (entity, character) {
+            (Some(entity), Some(character)) => {
+                decoded.push(character);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokens() {
+        let xml = r#"<?xml version="1.0"?>
+<!DOCTYPE xdxf [ <!ENTITY x "y"> ]>
+<!-- comment -->
+<ar id='1' title="a > b"><k>Haus</k><br/>house &amp; home &#x2013; &#228;<![CDATA[<raw>]]></ar>"#;
+        let tokens: Vec<Token> = tokens(xml)
+            .filter(|token| !matches!(token, Token::Text(text) if text.trim().is_empty()))
+            .collect();
+        assert_eq!(
+            tokens,
+            [
+                Token::Start {
+                    name: "ar",
+                    attributes: r#" id='1' title="a > b""#,
+                    empty: false
+                },
+                Token::Start {
+                    name: "k",
+                    attributes: "",
+                    empty: false
+                },
+                Token::Text("Haus".to_string()),
+                Token::End("k"),
+                Token::Start {
+                    name: "br",
+                    attributes: "",
+                    empty: true
+                },
+                Token::Text("house & home \u{2013} ä".to_string()),
+                Token::Text("<raw>".to_string()),
+                Token::End("ar"),
+            ]
+        );
+
+        let Token::Start { attributes, .. } = &tokens[0] else {
+            unreachable!()
+        };
+        assert_eq!(attribute(attributes, "id").as_deref(), Some("1"));
+        assert_eq!(attribute(attributes, "title").as_deref(), Some("a > b"));
+        assert_eq!(attribute(attributes, "lang"), None);
+        assert_eq!(decode_entities("AT&T &unknown; &"), "AT&T &unknown; &");
+    }
+}
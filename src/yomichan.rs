@@ -0,0 +1,348 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek};
+use std::path::Path;
+use zip::ZipArchive;
+
+use crate::models::{DictionaryEntry, PartOfSpeech};
+use crate::parser::ParseStats;
+use crate::tei;
+
+/// A parsed Yomichan dictionary
+#[derive(Debug, Clone)]
+pub struct YomichanDictionary {
+    /// The `title` of index.json
+    pub name: Option<String>,
+    pub entries: Vec<DictionaryEntry>,
+}
+
+/// index.json of a Yomichan dictionary. Fields other than these are ignored.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DictionaryIndex {
+    #[serde(default)]
+    title: Option<String>,
+    /// "rank-based" (lower is more frequent, the default) or "occurrence-based"
+    #[serde(default)]
+    frequency_mode: Option<String>,
+}
+
+/// Parse a zipped Yomichan dictionary.
+///
+/// Every row of the `term_bank_*.json` files becomes an entry: the term is
+/// the headword and the glossary, with structured content reduced to its
+/// text, the definition. The part of speech comes from the definition tags
+/// (`n`, `adj`, ...) or else the deinflection rules (`v5`, `adj-i`), and a
+/// gender from the remaining tags. Rows without a term or any glossary text
+/// are counted as malformed.
+pub fn parse_yomichan<P: AsRef<Path>>(
+    path: P,
+    language: &str,
+    stats: &mut ParseStats,
+) -> Result<YomichanDictionary> {
+    let path = path.as_ref();
+    let mut archive = open(path)?;
+    let index = read_index(&mut archive).context(format!("Invalid Yomichan file: {:?}", path))?;
+
+    let mut entries = Vec::new();
+    for name in bank_names(&archive, "term_bank_") {
+        let rows: Vec<Vec<Value>> = read_json(&mut archive, &name)?;
+        for row in rows {
+            match term_entry(&row, language) {
+                Some(entry) => entries.push(entry),
+                None => stats.record_malformed(&Value::Array(row).to_string()),
+            }
+        }
+    }
+
+    Ok(YomichanDictionary {
+        name: index.title.filter(|title| !title.is_empty()),
+        entries,
+    })
+}
+
+/// Terms with frequency data in the `term_meta_bank_*.json` files of a
+/// zipped Yomichan dictionary, most frequent first
+pub fn parse_frequencies<P: AsRef<Path>>(path: P) -> Result<Vec<String>> {
+    let path = path.as_ref();
+    let mut archive = open(path)?;
+    let index = read_index(&mut archive).context(format!("Invalid Yomichan file: {:?}", path))?;
+    let by_occurrence = index.frequency_mode.as_deref() == Some("occurrence-based");
+
+    // Best value per term, over all readings
+    let mut values: HashMap<String, f64> = HashMap::new();
+    for name in bank_names(&archive, "term_meta_bank_") {
+        let rows: Vec<Vec<Value>> = read_json(&mut archive, &name)?;
+        for row in rows {
+            let (Some(Value::String(term)), Some("freq"), Some(value)) = (
+                row.first(),
+                row.get(1).and_then(Value::as_str),
+                row.get(2).and_then(frequency_value),
+            ) else {
+                continue;
+            };
+            let best = values.entry(term.clone()).or_insert(value);
+            if (by_occurrence && value > *best) || (!by_occurrence && value < *best) {
+                *best = value;
+            }
+        }
+    }
+
+    let mut terms: Vec<(String, f64)> = values.into_iter().collect();
+    terms.sort_by(|(term_a, a), (term_b, b)| {
+        let order = if by_occurrence {
+            b.total_cmp(a)
+        } else {
+            a.total_cmp(b)
+        };
+        order.then_with(|| term_a.cmp(term_b))
+    });
+    Ok(terms.into_iter().map(|(term, _)| term).collect())
+}
+
+fn open(path: &Path) -> Result<ZipArchive<File>> {
+    let file = File::open(path).context(format!("Failed to open Yomichan file: {:?}", path))?;
+    ZipArchive::new(file).context(format!("Not a zip archive: {:?}", path))
+}
+
+fn read_index<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Result<DictionaryIndex> {
+    if archive.index_for_name("index.json").is_none() {
+        anyhow::bail!("Not a Yomichan dictionary (no index.json)");
+    }
+    read_json(archive, "index.json")
+}
+
+fn read_json<R: Read + Seek, T: serde::de::DeserializeOwned>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+) -> Result<T> {
+    let mut text = String::new();
+    archive.by_name(name)?.read_to_string(&mut text)?;
+    serde_json::from_str(&text).context(format!("Invalid JSON in {}", name))
+}
+
+/// Names of the banks starting with `prefix`, in bank number order
+fn bank_names<R: Read + Seek>(archive: &ZipArchive<R>, prefix: &str) -> Vec<String> {
+    let mut banks: Vec<(usize, String)> = archive
+        .file_names()
+        .filter_map(|name| {
+            let number = name.strip_prefix(prefix)?.strip_suffix(".json")?;
+            Some((number.parse().ok()?, name.to_string()))
+        })
+        .collect();
+    banks.sort();
+    banks.into_iter().map(|(_, name)| name).collect()
+}
+
+/// Entry for one term bank row: `[term, reading, definition tags, rules,
+/// score, glossary, sequence, term tags]`, or in version 1 banks the
+/// glossary strings spread from the sixth element on
+fn term_entry(row: &[Value], language: &str) -> Option<DictionaryEntry> {
+    let word = row.first()?.as_str()?.trim();
+    let glossary: &[Value] = match row.get(5) {
+        Some(Value::Array(glossary)) => glossary,
+        _ => row.get(5..).unwrap_or_default(),
+    };
+    let definition = glossary
+        .iter()
+        .filter_map(gloss_text)
+        .collect::<Vec<_>>()
+        .join("; ");
+    if word.is_empty() || definition.is_empty() {
+        return None;
+    }
+
+    let tags: Vec<&str> = row
+        .get(2)
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .split_whitespace()
+        .collect();
+    let pos_tag = tags
+        .iter()
+        .position(|tag| PartOfSpeech::from_tag(tag).is_some());
+    let rules = row.get(3).and_then(Value::as_str).unwrap_or_default();
+
+    let mut entry = DictionaryEntry::new(word.to_string(), definition, language.to_string());
+    entry.part_of_speech = match pos_tag {
+        Some(position) => PartOfSpeech::from_tag(tags[position]),
+        None => rules.split_whitespace().find_map(rule_part_of_speech),
+    };
+    entry.gender = tags
+        .iter()
+        .enumerate()
+        .filter(|&(position, _)| Some(position) != pos_tag)
+        .find_map(|(_, tag)| tei::normalize_gender(tag));
+    Some(entry)
+}
+
+/// Part of speech implied by a deinflection rule
+fn rule_part_of_speech(rule: &str) -> Option<PartOfSpeech> {
+    if rule.starts_with("adj") {
+        Some(PartOfSpeech::Adjective)
+    } else if rule.starts_with('v') {
+        Some(PartOfSpeech::Verb)
+    } else {
+        None
+    }
+}
+
+/// Text of one glossary item: a string, or a text or structured content
+/// object. Images and deinflection references have none.
+fn gloss_text(gloss: &Value) -> Option<String> {
+    let text = match gloss {
+        Value::String(text) => text.clone(),
+        Value::Object(object) => match object.get("type").and_then(Value::as_str) {
+            Some("text") => object.get("text")?.as_str()?.to_string(),
+            Some("structured-content") => {
+                let mut text = String::new();
+                structured_text(object.get("content")?, &mut text);
+                text
+            }
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let text = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("; ");
+    (!text.is_empty()).then_some(text)
+}
+
+/// Append the text of a structured content node, one line per block element
+fn structured_text(node: &Value, out: &mut String) {
+    match node {
+        Value::String(text) => out.push_str(text),
+        Value::Array(nodes) => nodes.iter().for_each(|node| structured_text(node, out)),
+        Value::Object(element) => {
+            let tag = element.get("tag").and_then(Value::as_str).unwrap_or("");
+            match tag {
+                // Ruby readings would repeat the annotated text
+                "rt" | "rp" | "img" => {}
+                "br" => out.push('\n'),
+                _ => {
+                    let block = matches!(tag, "div" | "p" | "ul" | "ol" | "li" | "tr" | "details");
+                    if block {
+                        out.push('\n');
+                    }
+                    if let Some(content) = element.get("content") {
+                        structured_text(content, out);
+                    }
+                    if block {
+                        out.push('\n');
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Value of a frequency: a number, a string holding one, or an object with
+/// a `value` or (per reading) a `frequency`
+fn frequency_value(data: &Value) -> Option<f64> {
+    match data {
+        Value::Number(number) => number.as_f64(),
+        Value::String(text) => text.trim().parse().ok(),
+        Value::Object(object) => object
+            .get("frequency")
+            .or_else(|| object.get("value"))
+            .and_then(frequency_value),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::Write;
+    use tempfile::TempDir;
+    use zip::write::SimpleFileOptions;
+
+    #[test]
+    fn test_parse_yomichan() {
+        let files = [
+            (
+                "index.json",
+                json!({"title": "Deutsch-Englisch", "format": 3, "revision": "1"}),
+            ),
+            (
+                "term_bank_1.json",
+                json!([
+                    ["Haus", "", "n neut", "", 10, ["house", "home"], 1, ""],
+                    ["laufen", "", "", "v", 5, [{"type": "text", "text": "to run"}], 2, ""],
+                    ["Baum", "", "n m", "", 0, [{
+                        "type": "structured-content",
+                        "content": {"tag": "ul", "content": [
+                            {"tag": "li", "content": "tree"},
+                            {"tag": "li", "content": ["beam", {"tag": "img", "path": "beam.png"}]}
+                        ]}
+                    }], 3, ""],
+                    ["leer", "", "adj", "", 0, [{"type": "image", "path": "empty.png"}], 4, ""],
+                    ["", "", "", "", 0, ["nothing"], 5, ""]
+                ]),
+            ),
+            // Version 1 rows spread the glossary
+            (
+                "term_bank_2.json",
+                json!([["Auto", "", "", "", 0, "car", "automobile"]]),
+            ),
+            (
+                "term_meta_bank_1.json",
+                json!([
+                    ["Haus", "freq", 120],
+                    ["Baum", "freq", {"value": 900, "displayValue": "900"}],
+                    ["laufen", "freq", {"reading": "", "frequency": {"value": 40}}],
+                    ["Haus", "freq", 300],
+                    ["Haus", "pitch", {"reading": "", "pitches": []}]
+                ]),
+            ),
+        ];
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("de-en.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+        for (name, content) in files {
+            zip.start_file(name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(content.to_string().as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let mut stats = ParseStats::default();
+        let dictionary = parse_yomichan(&path, "de-en", &mut stats).unwrap();
+        assert_eq!(dictionary.name.as_deref(), Some("Deutsch-Englisch"));
+        let entries: Vec<(&str, &str)> = dictionary
+            .entries
+            .iter()
+            .map(|e| (e.word.as_str(), e.definition.as_str()))
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                ("Haus", "house; home"),
+                ("laufen", "to run"),
+                ("Baum", "tree; beam"),
+                ("Auto", "car; automobile"),
+            ]
+        );
+        let entry = &dictionary.entries;
+        assert_eq!(entry[0].part_of_speech, Some(PartOfSpeech::Noun));
+        assert_eq!(entry[0].gender.as_deref(), Some("n"));
+        assert_eq!(entry[1].part_of_speech, Some(PartOfSpeech::Verb));
+        assert_eq!(entry[2].gender.as_deref(), Some("m"));
+        assert_eq!(entry[3].part_of_speech, None);
+        assert_eq!(stats.malformed_lines, 2);
+
+        assert_eq!(
+            parse_frequencies(&path).unwrap(),
+            ["laufen", "Haus", "Baum"]
+        );
+    }
+}
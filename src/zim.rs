@@ -0,0 +1,543 @@
+use anyhow::{Context, Result};
+use flate2::read::ZlibDecoder;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::models::DictionaryEntry;
+use crate::parser::ParseStats;
+use crate::slob;
+
+/// First four bytes of a ZIM file
+const MAGIC: u32 = 72_173_914;
+
+/// Directory entry mime type of a redirect
+const REDIRECT: u16 = 0xffff;
+
+/// Redirects followed before giving up on a chain
+const MAX_REDIRECTS: usize = 8;
+
+/// A parsed Kiwix ZIM archive
+#[derive(Debug, Clone)]
+pub struct ZimDictionary {
+    pub entries: Vec<DictionaryEntry>,
+}
+
+/// Parse a Kiwix `.zim` archive.
+///
+/// Every HTML or plain text article in the `A` namespace (or the `C`
+/// namespace of archives using the newer namespace scheme) becomes an entry
+/// with its title as the headword and its text, stripped of markup, as the
+/// definition. Redirects add their title as another headword of the article
+/// they point to. Images, stylesheets and other content are skipped.
+/// Clusters may be stored as is or compressed with XZ, Zstandard or zlib.
+///
+/// The archive is read from disk as needed, so archives larger than memory
+/// can be imported.
+pub fn parse_zim<P: AsRef<Path>>(
+    path: P,
+    language: &str,
+    stats: &mut ParseStats,
+) -> Result<ZimDictionary> {
+    let path = path.as_ref();
+    let file = File::open(path).context(format!("Failed to open ZIM file: {:?}", path))?;
+    parse_zim_file(file, language, stats).context(format!("Invalid ZIM file: {:?}", path))
+}
+
+/// Where an article's content lives
+#[derive(Debug, Clone, Copy)]
+struct Blob {
+    cluster: u32,
+    blob: u32,
+}
+
+#[derive(Debug)]
+enum Target {
+    Article(Blob),
+    Redirect(u32),
+    /// Content of another type or namespace, or a deleted entry
+    Skipped,
+}
+
+fn parse_zim_file(file: File, language: &str, stats: &mut ParseStats) -> Result<ZimDictionary> {
+    let file_size = file.metadata()?.len();
+    let mut reader = BufReader::new(file);
+
+    let header = read_bytes(&mut reader, 80)?;
+    let mut header = Cursor(&header);
+    if header.u32()? != MAGIC {
+        anyhow::bail!("Not a ZIM file");
+    }
+    let _major_version = header.u16()?;
+    let _minor_version = header.u16()?;
+    header.skip(16)?; // UUID
+    let entry_count = header.u32()? as usize;
+    let cluster_count = header.u32()? as usize;
+    let url_pointers = header.u64()?;
+    let _title_pointers = header.u64()?;
+    let cluster_pointers = header.u64()?;
+    let mime_list = header.u64()?;
+    let _main_page = header.u32()?;
+    let _layout_page = header.u32()?;
+    let checksum = header.u64()?;
+
+    reader.seek(SeekFrom::Start(mime_list))?;
+    let mut mime_types = Vec::new();
+    loop {
+        let mime_type = read_string(&mut reader)?;
+        if mime_type.is_empty() {
+            break;
+        }
+        mime_types.push(mime_type);
+    }
+    let is_text = |mime: u16| {
+        mime_types
+            .get(mime as usize)
+            .is_some_and(|mime| mime.starts_with("text/html") || mime.starts_with("text/plain"))
+    };
+
+    ensure_in_file(url_pointers, entry_count, file_size, "URL pointer list")?;
+    reader.seek(SeekFrom::Start(url_pointers))?;
+    let pointers = read_u64s(&mut reader, entry_count)?;
+
+    // Read the directory in file order, so the reader mostly moves forward
+    let mut order: Vec<usize> = (0..entry_count).collect();
+    order.sort_by_key(|&index| pointers[index]);
+    let mut titles = vec![String::new(); entry_count];
+    let mut targets: Vec<Target> = (0..entry_count).map(|_| Target::Skipped).collect();
+    let mut position = reader.stream_position()?;
+    for index in order {
+        let offset = pointers[index];
+        if offset >= position && offset - position < 1 << 20 {
+            reader.seek_relative((offset - position) as i64)?;
+        } else {
+            reader.seek(SeekFrom::Start(offset))?;
+        }
+
+        let fixed = read_bytes(&mut reader, 8)?;
+        let mut fixed = Cursor(&fixed);
+        let mime = fixed.u16()?;
+        let parameter_length = fixed.u8()? as usize;
+        let namespace = fixed.u8()?;
+        let _revision = fixed.u32()?;
+        let target = match mime {
+            REDIRECT => Target::Redirect(read_u32(&mut reader)?),
+            // Link targets and deleted entries carry no content fields
+            0xfffe | 0xfffd => Target::Skipped,
+            _ => {
+                let cluster = read_u32(&mut reader)?;
+                let blob = read_u32(&mut reader)?;
+                if is_text(mime) {
+                    Target::Article(Blob { cluster, blob })
+                } else {
+                    Target::Skipped
+                }
+            }
+        };
+        let url = read_string(&mut reader)?;
+        let title = read_string(&mut reader)?;
+        read_bytes(&mut reader, parameter_length)?;
+        position = reader.stream_position()?;
+
+        if matches!(namespace, b'A' | b'C') {
+            titles[index] = if title.is_empty() { url } else { title };
+            targets[index] = target;
+        }
+    }
+
+    // Headwords of each article, by cluster
+    let mut clusters: BTreeMap<u32, BTreeMap<u32, Vec<String>>> = BTreeMap::new();
+    for index in 0..entry_count {
+        let mut target = &targets[index];
+        for _ in 0..MAX_REDIRECTS {
+            match target {
+                Target::Redirect(to) => {
+                    target = targets.get(*to as usize).unwrap_or(&Target::Skipped)
+                }
+                _ => break,
+            }
+        }
+        if let Target::Article(Blob { cluster, blob }) = *target
+            && !titles[index].is_empty()
+        {
+            clusters
+                .entry(cluster)
+                .or_default()
+                .entry(blob)
+                .or_default()
+                .push(std::mem::take(&mut titles[index]));
+        }
+    }
+
+    ensure_in_file(
+        cluster_pointers,
+        cluster_count,
+        file_size,
+        "cluster pointer list",
+    )?;
+    reader.seek(SeekFrom::Start(cluster_pointers))?;
+    let cluster_offsets = read_u64s(&mut reader, cluster_count)?;
+    let mut entries = Vec::new();
+    for (cluster, articles) in clusters {
+        let Some(&start) = cluster_offsets.get(cluster as usize) else {
+            for title in articles.into_values().flatten() {
+                stats.record_malformed(&title);
+            }
+            continue;
+        };
+        // A cluster runs up to the next one, the last up to the checksum,
+        // and corrupt offsets never past the end of the file
+        let end = cluster_offsets
+            .get(cluster as usize + 1)
+            .copied()
+            .unwrap_or(if checksum > start {
+                checksum
+            } else {
+                file_size
+            })
+            .min(file_size);
+        if start >= end {
+            for title in articles.into_values().flatten() {
+                stats.record_malformed(&title);
+            }
+            continue;
+        }
+        reader.seek(SeekFrom::Start(start))?;
+        let data = read_bytes(&mut reader, end.saturating_sub(start) as usize)?;
+        let Some(blobs) = decompress_cluster(&data)? else {
+            stats.record_malformed(&format!("cluster {} of {} bytes", cluster, data.len()));
+            continue;
+        };
+
+        for (blob, titles) in articles {
+            let definition = blobs
+                .blob(blob as usize)
+                .map(|content| slob::to_definition(&String::from_utf8_lossy(content)))
+                .unwrap_or_default();
+            for title in titles {
+                if definition.is_empty() {
+                    stats.record_malformed(&title);
+                    continue;
+                }
+                entries.push(DictionaryEntry::new(
+                    title,
+                    definition.clone(),
+                    language.to_string(),
+                ));
+            }
+        }
+    }
+    Ok(ZimDictionary { entries })
+}
+
+/// Blobs of a decompressed cluster: a table of offsets into the cluster,
+/// one more than there are blobs
+struct Blobs {
+    data: Vec<u8>,
+    offset_size: usize,
+}
+
+impl Blobs {
+    fn offset(&self, index: usize) -> Option<usize> {
+        let bytes = self
+            .data
+            .get(index * self.offset_size..(index + 1) * self.offset_size)?;
+        Some(match self.offset_size {
+            8 => u64::from_le_bytes(bytes.try_into().ok()?) as usize,
+            _ => u32::from_le_bytes(bytes.try_into().ok()?) as usize,
+        })
+    }
+
+    fn blob(&self, index: usize) -> Option<&[u8]> {
+        self.data.get(self.offset(index)?..self.offset(index + 1)?)
+    }
+}
+
+/// The blobs of a cluster, or None when it is corrupt
+fn decompress_cluster(cluster: &[u8]) -> Result<Option<Blobs>> {
+    let Some((&info, data)) = cluster.split_first() else {
+        return Ok(None);
+    };
+    let offset_size = if info & 0x10 != 0 { 8 } else { 4 };
+    let mut plain = Vec::new();
+    let decompressed = match info & 0x0f {
+        0 | 1 => {
+            plain.extend_from_slice(data);
+            true
+        }
+        2 => ZlibDecoder::new(data).read_to_end(&mut plain).is_ok(),
+        4 => lzma_rs::xz_decompress(&mut &data[..], &mut plain).is_ok(),
+        5 => ruzstd::decoding::StreamingDecoder::new(data)
+            .is_ok_and(|mut decoder| decoder.read_to_end(&mut plain).is_ok()),
+        other => anyhow::bail!(
+            "Unsupported ZIM cluster compression: {} (XZ, Zstandard and zlib are supported)",
+            other
+        ),
+    };
+    Ok(decompressed.then_some(Blobs {
+        data: plain,
+        offset_size,
+    }))
+}
+
+/// Fail unless a list of `count` 8-byte values at `offset` lies within the
+/// file, before anything is allocated for it
+fn ensure_in_file(offset: u64, count: usize, file_size: u64, what: &str) -> Result<()> {
+    let end = (count as u64)
+        .checked_mul(8)
+        .and_then(|length| length.checked_add(offset));
+    if end.is_none_or(|end| end > file_size) {
+        anyhow::bail!("The {} runs past the end of the file", what);
+    }
+    Ok(())
+}
+
+fn read_bytes<R: Read>(reader: &mut R, length: usize) -> Result<Vec<u8>> {
+    let mut bytes = vec![0; length];
+    reader.read_exact(&mut bytes).context("Data ends early")?;
+    Ok(bytes)
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32> {
+    Ok(u32::from_le_bytes(
+        read_bytes(reader, 4)?.try_into().unwrap_or_default(),
+    ))
+}
+
+fn read_u64s<R: Read>(reader: &mut R, count: usize) -> Result<Vec<u64>> {
+    Ok(read_bytes(reader, count * 8)?
+        .chunks_exact(8)
+        .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap_or_default()))
+        .collect())
+}
+
+/// A NUL-terminated string
+fn read_string<R: BufRead>(reader: &mut R) -> Result<String> {
+    let mut bytes = Vec::new();
+    reader.read_until(0, &mut bytes)?;
+    if bytes.pop() != Some(0) {
+        anyhow::bail!("Data ends early in a string");
+    }
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Little-endian reader over a byte slice
+struct Cursor<'a>(&'a [u8]);
+
+impl<'a> Cursor<'a> {
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8]> {
+        if self.0.len() < length {
+            anyhow::bail!("Data ends early");
+        }
+        let (bytes, rest) = self.0.split_at(length);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, length: usize) -> Result<()> {
+        self.bytes(length).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into()?))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// A cluster of `blobs` with compression `kind`
+    fn cluster(kind: u8, blobs: &[&str]) -> Vec<u8> {
+        let mut plain = Vec::new();
+        let mut offset = 4 * (blobs.len() + 1);
+        for blob in blobs {
+            plain.extend((offset as u32).to_le_bytes());
+            offset += blob.len();
+        }
+        plain.extend((offset as u32).to_le_bytes());
+        for blob in blobs {
+            plain.extend(blob.as_bytes());
+        }
+
+        let mut cluster = vec![kind];
+        match kind {
+            4 => lzma_rs::xz_compress(&mut &plain[..], &mut cluster).unwrap(),
+            5 => cluster.extend(ruzstd::encoding::compress_to_vec(
+                &plain[..],
+                ruzstd::encoding::CompressionLevel::Fastest,
+            )),
+            _ => cluster.extend(plain),
+        }
+        cluster
+    }
+
+    /// A ZIM file with the given mime types, directory entries and clusters.
+    /// Entries are (namespace, mime, url, title, content fields), with a
+    /// redirect's field being its target.
+    fn zim(
+        mime_types: &[&str],
+        entries: &[(u8, u16, &str, &str, &[u32])],
+        clusters: &[Vec<u8>],
+    ) -> Vec<u8> {
+        let mut mime_list = Vec::new();
+        for mime_type in mime_types {
+            mime_list.extend(mime_type.as_bytes());
+            mime_list.push(0);
+        }
+        mime_list.push(0);
+
+        let mut directory = Vec::new();
+        let mut entry_offsets = Vec::new();
+        for (namespace, mime, url, title, fields) in entries {
+            entry_offsets.push(directory.len());
+            directory.extend(mime.to_le_bytes());
+            directory.push(0);
+            directory.push(*namespace);
+            directory.extend(0u32.to_le_bytes());
+            for field in *fields {
+                directory.extend(field.to_le_bytes());
+            }
+            directory.extend(url.as_bytes());
+            directory.push(0);
+            directory.extend(title.as_bytes());
+            directory.push(0);
+        }
+
+        let mime_list_position = 80;
+        let directory_position = mime_list_position + mime_list.len();
+        let url_pointers = directory_position + directory.len();
+        let cluster_pointers = url_pointers + 8 * entries.len();
+        let clusters_position = cluster_pointers + 8 * clusters.len();
+        let checksum = clusters_position + clusters.iter().map(Vec::len).sum::<usize>();
+
+        let mut out = Vec::new();
+        out.extend(MAGIC.to_le_bytes());
+        out.extend(6u16.to_le_bytes());
+        out.extend(0u16.to_le_bytes());
+        out.extend([0; 16]);
+        out.extend((entries.len() as u32).to_le_bytes());
+        out.extend((clusters.len() as u32).to_le_bytes());
+        out.extend((url_pointers as u64).to_le_bytes());
+        out.extend(0u64.to_le_bytes());
+        out.extend((cluster_pointers as u64).to_le_bytes());
+        out.extend((mime_list_position as u64).to_le_bytes());
+        out.extend(u32::MAX.to_le_bytes());
+        out.extend(u32::MAX.to_le_bytes());
+        out.extend((checksum as u64).to_le_bytes());
+        out.extend(mime_list);
+        out.extend(directory);
+        for offset in entry_offsets {
+            out.extend(((directory_position + offset) as u64).to_le_bytes());
+        }
+        let mut offset = clusters_position;
+        for cluster in clusters {
+            out.extend((offset as u64).to_le_bytes());
+            offset += cluster.len();
+        }
+        for cluster in clusters {
+            out.extend(cluster);
+        }
+        out.extend([0; 16]);
+        out
+    }
+
+    #[test]
+    fn test_parse_zim() {
+        let entries: &[(u8, u16, &str, &str, &[u32])] = &[
+            (b'-', 1, "style.css", "", &[0, 1]),
+            (b'A', 0, "Baum", "", &[1, 0]),
+            (b'A', 0, "Haus", "Haus", &[0, 0]),
+            (b'A', REDIRECT, "Häuser", "Häuser", &[2]),
+            (b'A', 0, "Leer", "", &[2, 0]),
+            (b'I', 2, "haus.png", "", &[0, 2]),
+        ];
+        let clusters = [
+            cluster(
+                5,
+                &[
+                    "<html><head><style>p { color: red }</style></head><body><h1>Haus</h1><p>house, home</p></body></html>",
+                    "p { color: red }",
+                    "PNG",
+                ],
+            ),
+            cluster(4, &["<p>tree</p>"]),
+            cluster(1, &["<p></p>"]),
+        ];
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("wiktionary_de.zim");
+        std::fs::write(
+            &path,
+            zim(&["text/html", "text/css", "image/png"], entries, &clusters),
+        )
+        .unwrap();
+        let mut stats = ParseStats::default();
+        let dictionary = parse_zim(&path, "de-en", &mut stats).unwrap();
+
+        let entries: Vec<(&str, &str)> = dictionary
+            .entries
+            .iter()
+            .map(|e| (e.word.as_str(), e.definition.as_str()))
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                ("Haus", "Haus; house, home"),
+                ("Häuser", "Haus; house, home"),
+                ("Baum", "tree"),
+            ]
+        );
+        assert_eq!(stats.malformed_lines, 1);
+        assert_eq!(stats.malformed_examples, ["Leer"]);
+    }
+
+    #[test]
+    fn test_corrupt_counts_and_offsets_are_rejected() {
+        let entries: &[(u8, u16, &str, &str, &[u32])] = &[(b'A', 0, "Haus", "", &[0, 0])];
+        let valid = zim(&["text/html"], entries, &[cluster(1, &["<p>house</p>"])]);
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("corrupt.zim");
+        let parse = |bytes: &[u8]| {
+            std::fs::write(&path, bytes).unwrap();
+            parse_zim(&path, "de-en", &mut ParseStats::default())
+        };
+
+        // An entry count of 2^32 - 1 would need 34 GB of pointers
+        let mut bytes = valid.clone();
+        bytes[24..28].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(format!("{:#}", parse(&bytes).unwrap_err()).contains("Invalid ZIM file"));
+
+        // A checksum past the end of the file ends the last cluster at the end
+        let mut bytes = valid.clone();
+        bytes[72..80].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert_eq!(parse(&bytes).unwrap().entries.len(), 1);
+
+        // As does a cluster offset past it
+        let cluster_pointers = u64::from_le_bytes(bytes[48..56].try_into().unwrap()) as usize;
+        bytes[cluster_pointers..cluster_pointers + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        let mut stats = ParseStats::default();
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(
+            parse_zim(&path, "de-en", &mut stats)
+                .unwrap()
+                .entries
+                .is_empty()
+        );
+        assert_eq!(stats.malformed_examples, ["Haus"]);
+    }
+}
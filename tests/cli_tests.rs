@@ -1,5 +1,5 @@
 use dictv::index::IndexManager;
-use dictv::models::{DictionaryEntry, Language, SearchMode};
+use dictv::models::{DictionaryEntry, LanguagePair, SearchMode};
 use dictv::search::SearchEngine;
 use tempfile::TempDir;
 
@@ -25,7 +25,7 @@ fn test_cli_import_and_search() {
     // Verify search works
     let engine = SearchEngine::new(manager.index_dir()).unwrap();
     let results = engine
-        .search("Haus", SearchMode::Exact, Language::DeEn, 2, 10)
+        .search("Haus", SearchMode::Exact, LanguagePair::DE_EN, 2, 10)
         .unwrap();
 
     assert_eq!(results.len(), 1);
@@ -53,7 +53,7 @@ fn test_cli_rebuild() {
     // After rebuilding with no data files, index should be empty
     let engine = SearchEngine::new(manager.index_dir()).unwrap();
     let results = engine
-        .search("Test", SearchMode::Exact, Language::DeEn, 2, 10)
+        .search("Test", SearchMode::Exact, LanguagePair::DE_EN, 2, 10)
         .unwrap();
 
     // Empty index is expected when no dictionary files are present
@@ -75,9 +75,17 @@ fn test_cli_stats() {
     SearchEngine::build_index(manager.index_dir(), entries).unwrap();
 
     // Get stats
-    let (total, _en_de, _de_en, size) = manager.stats().unwrap();
+    let (total, languages, size) = manager.stats().unwrap();
 
     assert_eq!(total, 3);
+    let counts: Vec<(LanguagePair, usize)> = languages
+        .into_iter()
+        .map(|count| (count.language, count.entries))
+        .collect();
+    assert_eq!(
+        counts,
+        vec![(LanguagePair::DE_EN, 2), (LanguagePair::EN_DE, 1)]
+    );
     assert!(size > 0);
 }
 
@@ -114,14 +122,14 @@ fn test_fuzzy_search_via_cli() {
 
     // Test fuzzy with typo
     let results = engine
-        .search("Hauss", SearchMode::Fuzzy, Language::DeEn, 1, 10)
+        .search("Hauss", SearchMode::Fuzzy, LanguagePair::DE_EN, 1, 10)
         .unwrap();
     assert!(!results.is_empty());
     assert_eq!(results[0].word, "haus");
 
     // Test fuzzy with diacritic variation
     let results = engine
-        .search("grussen", SearchMode::Fuzzy, Language::DeEn, 2, 10)
+        .search("grussen", SearchMode::Fuzzy, LanguagePair::DE_EN, 2, 10)
         .unwrap();
     assert!(!results.is_empty());
 }
@@ -147,7 +155,7 @@ fn test_prefix_search_via_cli() {
 
     // Test prefix search
     let results = engine
-        .search("Haus", SearchMode::Prefix, Language::DeEn, 2, 10)
+        .search("Haus", SearchMode::Prefix, LanguagePair::DE_EN, 2, 10)
         .unwrap();
 
     assert!(results.len() >= 1);
@@ -170,14 +178,14 @@ fn test_language_filtering_via_cli() {
 
     // Search de-en
     let results = engine
-        .search("Haus", SearchMode::Exact, Language::DeEn, 2, 10)
+        .search("Haus", SearchMode::Exact, LanguagePair::DE_EN, 2, 10)
         .unwrap();
     assert_eq!(results.len(), 1);
     assert_eq!(results[0].language, "de-en");
 
     // Search en-de
     let results = engine
-        .search("house", SearchMode::Exact, Language::EnDe, 2, 10)
+        .search("house", SearchMode::Exact, LanguagePair::EN_DE, 2, 10)
         .unwrap();
     assert_eq!(results.len(), 1);
     assert_eq!(results[0].language, "en-de");
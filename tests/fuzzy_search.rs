@@ -1,4 +1,4 @@
-use dictv::models::{DictionaryEntry, Language, SearchMode};
+use dictv::models::{DictionaryEntry, LanguagePair, SearchMode};
 use dictv::search::SearchEngine;
 use tempfile::TempDir;
 
@@ -43,7 +43,13 @@ fn test_fuzzy_search_accuracy_matrix() {
 
     for (query, expected, max_distance) in test_cases {
         let results = engine
-            .search(query, SearchMode::Fuzzy, Language::DeEn, max_distance, 10)
+            .search(
+                query,
+                SearchMode::Fuzzy,
+                LanguagePair::DE_EN,
+                max_distance,
+                10,
+            )
             .unwrap();
 
         assert!(
@@ -98,7 +104,7 @@ fn test_diacritic_handling() {
 
     for (query, expected) in test_cases {
         let results = engine
-            .search(query, SearchMode::Fuzzy, Language::DeEn, 2, 10)
+            .search(query, SearchMode::Fuzzy, LanguagePair::DE_EN, 2, 10)
             .unwrap();
 
         assert!(
@@ -144,7 +150,7 @@ fn test_unicode_edge_cases() {
 
     // These searches should still work
     let results = engine
-        .search("Cafe", SearchMode::Fuzzy, Language::DeEn, 2, 10)
+        .search("Cafe", SearchMode::Fuzzy, LanguagePair::DE_EN, 2, 10)
         .unwrap();
 
     assert!(!results.is_empty());
@@ -165,7 +171,7 @@ fn test_special_characters() {
 
     // Search for either the full word or parts - tokenizer may split on hyphens
     let results = engine
-        .search("test", SearchMode::Fuzzy, Language::DeEn, 2, 10)
+        .search("test", SearchMode::Fuzzy, LanguagePair::DE_EN, 2, 10)
         .unwrap();
 
     assert!(!results.is_empty());
@@ -193,7 +199,7 @@ fn test_fuzzy_search_ordering() {
     let engine = SearchEngine::new(temp_dir.path()).unwrap();
 
     let results = engine
-        .search("Haus", SearchMode::Fuzzy, Language::DeEn, 2, 10)
+        .search("Haus", SearchMode::Fuzzy, LanguagePair::DE_EN, 2, 10)
         .unwrap();
 
     // Results should be ordered by edit distance
@@ -224,7 +230,7 @@ fn test_memory_stability() {
     for i in 0..100 {
         let query = format!("word{}", i % 100);
         let _ = engine
-            .search(&query, SearchMode::Fuzzy, Language::DeEn, 2, 10)
+            .search(&query, SearchMode::Fuzzy, LanguagePair::DE_EN, 2, 10)
             .unwrap();
     }
 
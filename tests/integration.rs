@@ -1,4 +1,4 @@
-use dictv::models::{DictionaryEntry, Language, SearchMode};
+use dictv::models::{DictionaryEntry, LanguagePair, SearchMode, SearchOptions};
 use dictv::search::SearchEngine;
 use tempfile::TempDir;
 
@@ -28,7 +28,7 @@ fn test_end_to_end_exact_search() {
 
     // Test exact search
     let results = engine
-        .search("Haus", SearchMode::Exact, Language::DeEn, 2, 10)
+        .search("Haus", SearchMode::Exact, LanguagePair::DE_EN, 2, 10)
         .unwrap();
 
     assert_eq!(results.len(), 1);
@@ -36,6 +36,41 @@ fn test_end_to_end_exact_search() {
     assert!(results[0].definitions[0].contains("house"));
 }
 
+#[test]
+fn test_any_language_pair() {
+    let temp_dir = TempDir::new().unwrap();
+
+    let entries = vec![
+        DictionaryEntry::new(
+            "maison".to_string(),
+            "Haus".to_string(),
+            "fr-de".to_string(),
+        ),
+        DictionaryEntry::new("Haus".to_string(), "house".to_string(), "de-en".to_string()),
+    ];
+
+    SearchEngine::build_index(temp_dir.path(), entries).unwrap();
+    let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+    let fr_de: LanguagePair = "fra-deu".parse().unwrap();
+    let results = engine
+        .search("maison", SearchMode::Exact, fr_de, 2, 10)
+        .unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].language, "fr-de");
+    assert!(
+        engine
+            .search("Haus", SearchMode::Exact, fr_de, 2, 10)
+            .unwrap()
+            .is_empty()
+    );
+
+    assert_eq!(
+        engine.languages().unwrap(),
+        vec![LanguagePair::DE_EN, fr_de]
+    );
+}
+
 #[test]
 fn test_fuzzy_search_single_typo() {
     let temp_dir = TempDir::new().unwrap();
@@ -54,7 +89,7 @@ fn test_fuzzy_search_single_typo() {
 
     // Search with single character typo
     let results = engine
-        .search("Hauss", SearchMode::Fuzzy, Language::DeEn, 1, 10)
+        .search("Hauss", SearchMode::Fuzzy, LanguagePair::DE_EN, 1, 10)
         .unwrap();
 
     assert!(!results.is_empty());
@@ -77,7 +112,7 @@ fn test_fuzzy_search_double_typo() {
 
     // Search with double typo
     let results = engine
-        .search("Haaus", SearchMode::Fuzzy, Language::DeEn, 2, 10)
+        .search("Haaus", SearchMode::Fuzzy, LanguagePair::DE_EN, 2, 10)
         .unwrap();
 
     assert!(!results.is_empty());
@@ -99,7 +134,7 @@ fn test_case_insensitive_search() {
 
     // Search with lowercase
     let results = engine
-        .search("haus", SearchMode::Exact, Language::DeEn, 2, 10)
+        .search("haus", SearchMode::Exact, LanguagePair::DE_EN, 2, 10)
         .unwrap();
 
     assert_eq!(results.len(), 1);
@@ -124,7 +159,7 @@ fn test_prefix_search() {
     let engine = SearchEngine::new(temp_dir.path()).unwrap();
 
     let results = engine
-        .search("Haus", SearchMode::Prefix, Language::DeEn, 2, 10)
+        .search("Haus", SearchMode::Prefix, LanguagePair::DE_EN, 2, 10)
         .unwrap();
 
     assert!(results.len() >= 1);
@@ -145,7 +180,7 @@ fn test_language_filtering() {
 
     // Search in German-English
     let results = engine
-        .search("Haus", SearchMode::Exact, Language::DeEn, 2, 10)
+        .search("Haus", SearchMode::Exact, LanguagePair::DE_EN, 2, 10)
         .unwrap();
 
     assert_eq!(results.len(), 1);
@@ -153,7 +188,7 @@ fn test_language_filtering() {
 
     // Search in English-German
     let results = engine
-        .search("house", SearchMode::Exact, Language::EnDe, 2, 10)
+        .search("house", SearchMode::Exact, LanguagePair::EN_DE, 2, 10)
         .unwrap();
 
     assert_eq!(results.len(), 1);
@@ -174,7 +209,7 @@ fn test_empty_query() {
     let engine = SearchEngine::new(temp_dir.path()).unwrap();
 
     let results = engine
-        .search("", SearchMode::Exact, Language::DeEn, 2, 10)
+        .search("", SearchMode::Exact, LanguagePair::DE_EN, 2, 10)
         .unwrap();
 
     // Empty query should return no results
@@ -195,7 +230,7 @@ fn test_no_matches() {
     let engine = SearchEngine::new(temp_dir.path()).unwrap();
 
     let results = engine
-        .search("xyz", SearchMode::Exact, Language::DeEn, 2, 10)
+        .search("xyz", SearchMode::Exact, LanguagePair::DE_EN, 2, 10)
         .unwrap();
 
     assert!(results.is_empty());
@@ -218,8 +253,56 @@ fn test_limit_results() {
     let engine = SearchEngine::new(temp_dir.path()).unwrap();
 
     let results = engine
-        .search("word", SearchMode::Prefix, Language::DeEn, 2, 5)
+        .search("word", SearchMode::Prefix, LanguagePair::DE_EN, 2, 5)
         .unwrap();
 
     assert!(results.len() <= 5);
 }
+
+#[test]
+fn test_other_direction_does_not_crowd_out_requested_one() {
+    let temp_dir = TempDir::new().unwrap();
+
+    // Enough en-de "ha" headwords to fill the collected window on their own
+    let mut entries: Vec<DictionaryEntry> = (0..50)
+        .map(|i| {
+            DictionaryEntry::new(
+                format!("ha{:02}", i),
+                "Füller".to_string(),
+                "en-de".to_string(),
+            )
+        })
+        .collect();
+    entries.push(DictionaryEntry::new(
+        "Hafen".to_string(),
+        "harbour, port".to_string(),
+        "de-en".to_string(),
+    ));
+    SearchEngine::build_index(temp_dir.path(), entries).unwrap();
+    let engine = SearchEngine::new(temp_dir.path()).unwrap();
+
+    for mode in [SearchMode::Prefix, SearchMode::Fuzzy] {
+        let options = SearchOptions {
+            mode,
+            language: LanguagePair::DE_EN,
+            limit: 10,
+            ..SearchOptions::default()
+        };
+        let output = engine.search_with_options("ha", &options).unwrap();
+        assert!(!output.flipped_direction, "{:?}", mode);
+        assert!(output.results.iter().all(|r| r.language == "de-en"));
+        if mode == SearchMode::Prefix {
+            assert_eq!(output.results.len(), 1);
+            assert_eq!(output.results[0].word, "hafen");
+        }
+    }
+
+    let options = SearchOptions {
+        mode: SearchMode::Exact,
+        language: LanguagePair::DE_EN,
+        ..SearchOptions::default()
+    };
+    let output = engine.search_with_options("ha00", &options).unwrap();
+    assert!(output.flipped_direction);
+    assert_eq!(output.results[0].language, "en-de");
+}
@@ -1,7 +1,9 @@
+use dictv::apikeys::{ApiKey, ApiKeys};
 use dictv::index::IndexManager;
-use dictv::models::DictionaryEntry;
+use dictv::models::{DictionaryEntry, ImportFormat, LanguagePair};
 use dictv::search::SearchEngine;
 use dictv::server;
+use dictv::tts::{Tts, TtsConfig};
 use tempfile::TempDir;
 use tokio::time::{Duration, sleep};
 
@@ -11,6 +13,13 @@ static PORT_COUNTER: AtomicU16 = AtomicU16::new(14000);
 
 /// Helper to start server in background
 async fn setup_test_server() -> (TempDir, u16) {
+    setup_test_server_with(|state| state).await
+}
+
+/// Start a server whose state is adjusted by `configure`
+async fn setup_test_server_with(
+    configure: impl FnOnce(server::AppState) -> server::AppState + Send + 'static,
+) -> (TempDir, u16) {
     let temp_dir = TempDir::new().unwrap();
     let manager = IndexManager::new(temp_dir.path()).unwrap();
 
@@ -48,7 +57,7 @@ async fn setup_test_server() -> (TempDir, u16) {
 
     // Start server in background
     tokio::spawn(async move {
-        let _ = server::serve(engine, port).await;
+        let _ = server::serve(configure(server::AppState::new(engine)), port).await;
     });
 
     // Give server time to start
@@ -72,6 +81,10 @@ async fn test_server_health_endpoint() {
 
     let json: serde_json::Value = response.json().await.unwrap();
     assert_eq!(json["status"], "ok");
+    assert_eq!(json["defaults"]["mode"], "fuzzy");
+    assert_eq!(json["defaults"]["lang"], "de-en");
+    assert_eq!(json["defaults"]["limit"], 20);
+    assert_eq!(json["defaults"]["max_distance"], 2);
 }
 
 #[tokio::test]
@@ -95,6 +108,59 @@ async fn test_server_exact_search() {
 
     let results = json["results"].as_array().unwrap();
     assert_eq!(results[0]["word"], "haus");
+    assert_eq!(json["index_generation"].as_str().unwrap().len(), 16);
+}
+
+#[tokio::test]
+async fn test_server_pinned_snapshot() {
+    let (temp_dir, port) = setup_test_server().await;
+    let client = reqwest::Client::new();
+    let search = |params: String| {
+        let client = client.clone();
+        async move {
+            client
+                .get(format!(
+                    "http://localhost:{}/search?q=Haus&mode=prefix&lang=de-en{}",
+                    port, params
+                ))
+                .send()
+                .await
+                .expect("Failed to search")
+        }
+    };
+
+    let first: serde_json::Value = search("&pin=true".to_string()).await.json().await.unwrap();
+    assert_eq!(first["total_results"], 1);
+    let token = first["snapshot"].as_str().unwrap().to_string();
+
+    // Entries added after the first page, picked up by the server's reader
+    let manager = IndexManager::new(temp_dir.path()).unwrap();
+    SearchEngine::add_to_index_from_batches(
+        manager.index_dir(),
+        [vec![DictionaryEntry::new(
+            "Hausboot".to_string(),
+            "houseboat".to_string(),
+            "de-en".to_string(),
+        )]],
+    )
+    .unwrap();
+    sleep(Duration::from_millis(1000)).await;
+
+    let current: serde_json::Value = search(String::new()).await.json().await.unwrap();
+    assert_eq!(current["total_results"], 2);
+    assert!(current.get("snapshot").is_none());
+
+    let pinned: serde_json::Value = search(format!("&snapshot={}", token))
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(pinned["total_results"], 1);
+    assert_eq!(pinned["snapshot"], token.as_str());
+    assert_eq!(pinned["index_generation"], first["index_generation"]);
+
+    let expired = search("&snapshot=unknown".to_string()).await;
+    assert_eq!(expired.status(), 410);
 }
 
 #[tokio::test]
@@ -165,6 +231,29 @@ async fn test_server_prefix_search() {
     assert!(!results.is_empty());
 }
 
+#[tokio::test]
+async fn test_server_multiple_prefixes() {
+    let (_temp_dir, port) = setup_test_server().await;
+
+    let client = reqwest::Client::new();
+    for query in ["q=ha,gr%C3%BC", "q=ha&q=gr%C3%BC"] {
+        let response = client
+            .get(format!(
+                "http://localhost:{}/search?{}&mode=prefix&lang=de-en",
+                port, query
+            ))
+            .send()
+            .await
+            .expect("Failed to search");
+        assert_eq!(response.status(), 200);
+
+        let json: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(json["total_results"], 2);
+        assert_eq!(json["prefixes"]["ha"][0]["word"], "haus");
+        assert_eq!(json["prefixes"]["grü"][0]["word"], "grüßen");
+    }
+}
+
 #[tokio::test]
 async fn test_server_language_filtering() {
     let (_temp_dir, port) = setup_test_server().await;
@@ -215,6 +304,101 @@ async fn test_server_empty_query() {
     assert_eq!(response.status(), 400);
 }
 
+#[tokio::test]
+async fn test_server_lenient_language_and_mode() {
+    let (_temp_dir, port) = setup_test_server().await;
+
+    let client = reqwest::Client::new();
+    let response = client
+        .get(format!(
+            "http://localhost:{}/search?q=Haus&mode=Exact&lang=DE-EN",
+            port
+        ))
+        .send()
+        .await
+        .expect("Failed to search");
+    assert_eq!(response.status(), 200);
+    let json: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(json["results"][0]["language"], "de-en");
+
+    let response = client
+        .get(format!("http://localhost:{}/search?q=Haus&mode=fuzy", port))
+        .send()
+        .await
+        .expect("Failed to search");
+    assert_eq!(response.status(), 400);
+    let json: serde_json::Value = response.json().await.unwrap();
+    assert!(
+        json["error"]
+            .as_str()
+            .unwrap()
+            .contains("did you mean fuzzy?")
+    );
+}
+
+#[tokio::test]
+async fn test_server_definition_search() {
+    let (_temp_dir, port) = setup_test_server().await;
+
+    let client = reqwest::Client::new();
+    let response = client
+        .get(format!(
+            "http://localhost:{}/search?q=building%20AND%20NOT%20car&mode=definition&lang=de-en",
+            port
+        ))
+        .send()
+        .await
+        .expect("Failed to search");
+    assert_eq!(response.status(), 200);
+    let json: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(json["results"][0]["word"], "haus");
+
+    // Malformed boolean queries are the client's fault
+    let response = client
+        .get(format!(
+            "http://localhost:{}/search?q=house%20AND%20(&mode=definition",
+            port
+        ))
+        .send()
+        .await
+        .expect("Failed to search");
+    assert_eq!(response.status(), 400);
+}
+
+#[tokio::test]
+async fn test_server_search_within_word_list() {
+    let (_temp_dir, port) = setup_test_server().await;
+
+    let client = reqwest::Client::new();
+    let response = client
+        .post(format!("http://localhost:{}/search/within", port))
+        .json(&serde_json::json!({
+            "q": "Hau",
+            "mode": "fuzzy",
+            "words": ["Buch", "Haus"],
+        }))
+        .send()
+        .await
+        .expect("Failed to search");
+    assert_eq!(response.status(), 200);
+    let json: serde_json::Value = response.json().await.unwrap();
+    let words: Vec<&str> = json["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["word"].as_str().unwrap())
+        .collect();
+    assert_eq!(words, ["haus"]);
+
+    let response = client
+        .post(format!("http://localhost:{}/search/within", port))
+        .json(&serde_json::json!({ "q": "Haus", "words": [] }))
+        .send()
+        .await
+        .expect("Failed to search");
+    assert_eq!(response.status(), 400);
+}
+
 #[tokio::test]
 async fn test_server_query_performance() {
     let (_temp_dir, port) = setup_test_server().await;
@@ -255,4 +439,670 @@ async fn test_server_stats_endpoint() {
 
     let json: serde_json::Value = response.json().await.unwrap();
     assert_eq!(json["total_entries"], 5);
+    assert_eq!(
+        json["languages"],
+        serde_json::json!([
+            {"language": "de-en", "entries": 4},
+            {"language": "en-de", "entries": 1}
+        ])
+    );
+    assert_eq!(json["reloads"]["failed"], 0);
+    assert!(json["memory"]["index"]["segments"].as_u64().unwrap() >= 1);
+    assert_eq!(json["memory"]["caches"]["pinned_snapshots"], 0);
+}
+
+#[tokio::test]
+async fn test_server_frequency_unknown_word() {
+    let (_temp_dir, port) = setup_test_server().await;
+
+    let client = reqwest::Client::new();
+    let response = client
+        .get(format!(
+            "http://localhost:{}/frequency/Haus?lang=de-en",
+            port
+        ))
+        .send()
+        .await
+        .expect("Failed to get frequency");
+
+    // No frequency list imported
+    assert_eq!(response.status(), 404);
+}
+
+#[tokio::test]
+async fn test_server_compare_endpoint() {
+    let (_temp_dir, port) = setup_test_server().await;
+
+    let client = reqwest::Client::new();
+    let response = client
+        .get(format!(
+            "http://localhost:{}/compare?word=Haus&lang=de-en",
+            port
+        ))
+        .send()
+        .await
+        .expect("Failed to compare");
+
+    assert_eq!(response.status(), 200);
+
+    let json: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(json["word"], "haus");
+    let sources = json["sources"].as_array().unwrap();
+    assert_eq!(sources.len(), 1);
+    assert_eq!(sources[0]["definitions"][0], "house, building");
+
+    let response = client
+        .get(format!(
+            "http://localhost:{}/compare?word=Schloss&lang=de-en",
+            port
+        ))
+        .send()
+        .await
+        .expect("Failed to compare");
+    assert_eq!(response.status(), 404);
+}
+
+#[tokio::test]
+async fn test_server_licenses_endpoint() {
+    let temp_dir = TempDir::new().unwrap();
+    let export = temp_dir.path().join("dictcc-de-en.txt");
+    std::fs::write(&export, "Haus {n}\thouse\tnoun\t\n").unwrap();
+    let manager = IndexManager::new(temp_dir.path()).unwrap();
+    manager
+        .import_file(&export, ImportFormat::DictCc, "de-en")
+        .unwrap();
+    let engine = manager.search_engine().unwrap();
+
+    let port = PORT_COUNTER.fetch_add(1, Ordering::SeqCst);
+    tokio::spawn(async move {
+        let _ = server::serve(server::AppState::new(engine), port).await;
+    });
+    sleep(Duration::from_millis(1000)).await;
+
+    let client = reqwest::Client::new();
+    let response = client
+        .get(format!("http://localhost:{}/licenses", port))
+        .send()
+        .await
+        .expect("Failed to get licenses");
+    assert_eq!(response.status(), 200);
+    let json: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(
+        json["licenses"],
+        serde_json::json!([{
+            "source": "dictcc-de-en",
+            "language": "de-en",
+            "license": "personal-use"
+        }])
+    );
+
+    let response = client
+        .get(format!(
+            "http://localhost:{}/search?q=Haus&lang=de-en&mode=exact",
+            port
+        ))
+        .send()
+        .await
+        .expect("Failed to search");
+    let json: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(
+        json["results"][0]["sources"],
+        serde_json::json!([{"name": "dictcc-de-en", "license": "personal-use"}])
+    );
+}
+
+#[tokio::test]
+async fn test_server_quiz_endpoint() {
+    let (_temp_dir, port) = setup_test_server().await;
+
+    let client = reqwest::Client::new();
+    let response = client
+        .get(format!(
+            "http://localhost:{}/quiz?lang=de-en&count=10&type=multiple-choice&seed=7",
+            port
+        ))
+        .send()
+        .await
+        .expect("Failed to get quiz");
+
+    assert_eq!(response.status(), 200);
+
+    let json: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(json["type"], "multiple-choice");
+    assert_eq!(json["seed"], 7);
+    // Four German headwords, each asked once with the other three as distractors
+    let questions = json["questions"].as_array().unwrap();
+    assert_eq!(questions.len(), 4);
+    let haus = questions.iter().find(|q| q["word"] == "haus").unwrap();
+    assert_eq!(haus["answer"], "house");
+    let mut distractors: Vec<&str> = haus["distractors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|d| d.as_str().unwrap())
+        .collect();
+    distractors.sort();
+    assert_eq!(distractors, ["book", "car", "to greet"]);
+
+    // Only one English headword, nothing to draw distractors from
+    let response = client
+        .get(format!("http://localhost:{}/quiz?lang=en-de", port))
+        .send()
+        .await
+        .expect("Failed to get quiz");
+    assert_eq!(response.status(), 404);
+
+    let response = client
+        .get(format!("http://localhost:{}/quiz?type=flashcards", port))
+        .send()
+        .await
+        .expect("Failed to get quiz");
+    assert_eq!(response.status(), 400);
+}
+
+#[tokio::test]
+async fn test_server_audio_endpoint() {
+    let cache = TempDir::new().unwrap();
+    let config = TtsConfig {
+        command: Some(vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            "printf 'RIFF:%s:%s' \"$0\" \"$1\"".to_string(),
+            "{lang}".to_string(),
+            "{word}".to_string(),
+        ]),
+        url: None,
+        content_type: "audio/wav".to_string(),
+    };
+    let tts = Tts::new(config, cache.path().join("audio")).unwrap();
+    let (_temp_dir, port) = setup_test_server_with(move |state| state.with_tts(tts)).await;
+
+    let client = reqwest::Client::new();
+    let response = client
+        .get(format!("http://localhost:{}/audio/Haus?lang=de-en", port))
+        .send()
+        .await
+        .expect("Failed to get audio");
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["content-type"], "audio/wav");
+    assert_eq!(response.text().await.unwrap(), "RIFF:de:haus");
+
+    // Only headwords are synthesized
+    let response = client
+        .get(format!(
+            "http://localhost:{}/audio/Schloss?lang=de-en",
+            port
+        ))
+        .send()
+        .await
+        .expect("Failed to get audio");
+    assert_eq!(response.status(), 404);
+}
+
+#[tokio::test]
+async fn test_server_audio_without_tts() {
+    let (_temp_dir, port) = setup_test_server().await;
+
+    let response = reqwest::get(format!("http://localhost:{}/audio/Haus", port))
+        .await
+        .expect("Failed to get audio");
+    assert_eq!(response.status(), 404);
+}
+
+#[tokio::test]
+async fn test_server_abbreviations_endpoint() {
+    let (_temp_dir, port) = setup_test_server().await;
+
+    let client = reqwest::Client::new();
+    let response = client
+        .get(format!("http://localhost:{}/abbreviations", port))
+        .send()
+        .await
+        .expect("Failed to get abbreviations");
+
+    assert_eq!(response.status(), 200);
+
+    let json: serde_json::Value = response.json().await.unwrap();
+    let abbreviations = json["abbreviations"].as_array().unwrap();
+    assert!(
+        abbreviations
+            .iter()
+            .any(|a| a["abbreviation"] == "ugs." && a["expansion"] == "umgangssprachlich")
+    );
+}
+
+#[tokio::test]
+async fn test_server_debug_timings() {
+    let (_temp_dir, port) = setup_test_server().await;
+
+    let client = reqwest::Client::new();
+    let json: serde_json::Value = client
+        .get(format!(
+            "http://localhost:{}/search?q=Haus&debug=true",
+            port
+        ))
+        .send()
+        .await
+        .expect("Failed to search")
+        .json()
+        .await
+        .unwrap();
+    for phase in ["query_build_ms", "search_ms", "fetch_ms", "group_ms"] {
+        assert!(json["timings"][phase].as_f64().unwrap() >= 0.0);
+    }
+    assert_eq!(json["merged_definitions"], 0);
+
+    let json: serde_json::Value = client
+        .get(format!("http://localhost:{}/search?q=Haus", port))
+        .send()
+        .await
+        .expect("Failed to search")
+        .json()
+        .await
+        .unwrap();
+    assert!(json.get("timings").is_none());
+    assert!(json.get("merged_definitions").is_none());
+}
+
+#[tokio::test]
+async fn test_server_tracks_zero_result_queries() {
+    let (_temp_dir, port) = setup_test_server().await;
+
+    let client = reqwest::Client::new();
+    for query in ["Zzzxq", "zzzxq", "Haus"] {
+        client
+            .get(format!(
+                "http://localhost:{}/search?q={}&mode=exact",
+                port, query
+            ))
+            .send()
+            .await
+            .expect("Failed to search");
+    }
+
+    let json: serde_json::Value = client
+        .get(format!(
+            "http://localhost:{}/analytics/misses?lang=de-en",
+            port
+        ))
+        .send()
+        .await
+        .expect("Failed to get misses")
+        .json()
+        .await
+        .unwrap();
+
+    let misses = json["misses"].as_array().unwrap();
+    assert_eq!(misses.len(), 1);
+    assert_eq!(misses[0]["query"], "zzzxq");
+    assert_eq!(misses[0]["count"], 2);
+}
+
+#[tokio::test]
+async fn test_server_suggests_popular_queries() {
+    let (_temp_dir, port) = setup_test_server().await;
+
+    let client = reqwest::Client::new();
+    for query in ["Haus", "haus", "Auto", "Hzzzxq"] {
+        client
+            .get(format!(
+                "http://localhost:{}/search?q={}&mode=exact",
+                port, query
+            ))
+            .send()
+            .await
+            .expect("Failed to search");
+    }
+
+    let json: serde_json::Value = client
+        .get(format!(
+            "http://localhost:{}/suggest/popular?prefix=h&lang=de-en",
+            port
+        ))
+        .send()
+        .await
+        .expect("Failed to get suggestions")
+        .json()
+        .await
+        .unwrap();
+
+    // Queries without results are not suggested
+    let queries = json["queries"].as_array().unwrap();
+    assert_eq!(queries.len(), 1);
+    assert_eq!(queries[0]["query"], "haus");
+    assert_eq!(queries[0]["count"], 2);
+}
+
+#[tokio::test]
+async fn test_server_session_history() {
+    let (_temp_dir, port) = setup_test_server().await;
+
+    let client = reqwest::Client::new();
+    for (query, session) in [("Haus", "alice"), ("Auto", "bob"), ("Buch", "alice")] {
+        client
+            .get(format!("http://localhost:{}/search?q={}", port, query))
+            .header("Cookie", format!("dictv_session={}", session))
+            .send()
+            .await
+            .expect("Failed to search");
+    }
+
+    let json: serde_json::Value = client
+        .get(format!("http://localhost:{}/history?session=alice", port))
+        .send()
+        .await
+        .expect("Failed to get history")
+        .json()
+        .await
+        .unwrap();
+    let lookups = json["lookups"].as_array().unwrap();
+    assert_eq!(lookups.len(), 2);
+    assert_eq!(lookups[0]["query"], "Buch");
+    assert_eq!(lookups[1]["query"], "Haus");
+
+    let response = client
+        .get(format!("http://localhost:{}/history", port))
+        .send()
+        .await
+        .expect("Failed to get history");
+    assert_eq!(response.status(), 400);
+}
+
+#[tokio::test]
+async fn test_server_api_key_scopes_search() {
+    let (_temp_dir, port) = setup_test_server_with(|state| {
+        state.with_api_keys(ApiKeys::new(vec![ApiKey {
+            key: "app-key".to_string(),
+            name: "app".to_string(),
+            languages: vec![LanguagePair::DE_EN],
+            sources: Vec::new(),
+            daily_quota: None,
+            admin: false,
+        }]))
+    })
+    .await;
+
+    let client = reqwest::Client::new();
+    let url = format!("http://localhost:{}/search?q=Haus&mode=exact", port);
+
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.status(), 401);
+
+    let response = client
+        .get(&url)
+        .header("X-API-Key", "app-key")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let json: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(json["total_results"], 1);
+
+    let response = client
+        .get(format!("{}&lang=en-de", url))
+        .header("X-API-Key", "app-key")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 403);
+}
+
+#[tokio::test]
+async fn test_server_api_key_scopes_definition_endpoints() {
+    let (_temp_dir, port) = setup_test_server_with(|state| {
+        state.with_api_keys(ApiKeys::new(vec![ApiKey {
+            key: "app-key".to_string(),
+            name: "app".to_string(),
+            languages: vec![LanguagePair::DE_EN],
+            sources: vec!["ding".to_string()],
+            daily_quota: None,
+            admin: false,
+        }]))
+    })
+    .await;
+
+    let client = reqwest::Client::new();
+    let get = |path: &str, api_key: Option<&str>| {
+        let mut request = client.get(format!("http://localhost:{}{}", port, path));
+        if let Some(api_key) = api_key {
+            request = request.header("X-API-Key", api_key);
+        }
+        request.send()
+    };
+
+    for path in [
+        "/compare?word=Haus",
+        "/browse",
+        "/wotd",
+        "/wotd/feed.xml",
+        "/quiz",
+        "/suggest/popular",
+        "/favorites",
+        "/favorites/export",
+        "/letters",
+        "/frequency/haus",
+    ] {
+        let response = get(path, None).await.unwrap();
+        assert_eq!(response.status(), 401, "{}", path);
+    }
+    for path in [
+        "/compare?word=Haus&lang=en-de",
+        "/suggest/popular?lang=en-de",
+        "/favorites?lang=en-de",
+        "/favorites/export?lang=en-de",
+        "/letters?lang=en-de",
+        "/frequency/house?lang=en-de",
+    ] {
+        let response = get(path, Some("app-key")).await.unwrap();
+        assert_eq!(response.status(), 403, "{}", path);
+    }
+    for path in [
+        "/suggest/popular",
+        "/favorites",
+        "/favorites/export",
+        "/letters",
+    ] {
+        let response = get(path, Some("app-key")).await.unwrap();
+        assert_eq!(response.status(), 200, "{}", path);
+    }
+
+    // The test entries come from no named dictionary, outside the key's "ding"
+    for path in ["/compare?word=Haus", "/wotd", "/quiz"] {
+        let response = get(path, Some("app-key")).await.unwrap();
+        assert_eq!(response.status(), 404, "{}", path);
+    }
+}
+
+#[tokio::test]
+async fn test_server_filters_favorites_by_language() {
+    let favorites_dir = TempDir::new().unwrap();
+    let favorites_path = favorites_dir.path().join("favorites.json");
+    std::fs::write(
+        &favorites_path,
+        r#"{"favorites": [
+            {"word": "haus", "language": "de-en", "definitions": ["house"], "added_ms": 0},
+            {"word": "house", "language": "en-de", "definitions": ["Haus"], "added_ms": 0}
+        ]}"#,
+    )
+    .unwrap();
+    let (_temp_dir, port) =
+        setup_test_server_with(move |state| state.with_favorites(favorites_path)).await;
+
+    let favorites = |query: &'static str| async move {
+        let json: serde_json::Value =
+            reqwest::get(format!("http://localhost:{}/favorites{}", port, query))
+                .await
+                .unwrap()
+                .json()
+                .await
+                .unwrap();
+        json["favorites"].as_array().unwrap().len()
+    };
+    assert_eq!(favorites("").await, 2);
+    assert_eq!(favorites("?lang=de-en").await, 1);
+}
+
+#[tokio::test]
+async fn test_server_enforces_daily_quota_and_reports_usage() {
+    let key = |key: &str, name: &str, daily_quota, admin| ApiKey {
+        key: key.to_string(),
+        name: name.to_string(),
+        languages: Vec::new(),
+        sources: Vec::new(),
+        daily_quota,
+        admin,
+    };
+    let (_temp_dir, port) = setup_test_server_with(move |state| {
+        state.with_api_keys(ApiKeys::new(vec![
+            key("student-key", "student", Some(1), false),
+            key("admin-key", "admin", None, true),
+        ]))
+    })
+    .await;
+
+    let client = reqwest::Client::new();
+    let search = |api_key: &'static str| {
+        client
+            .get(format!("http://localhost:{}/search?q=Haus", port))
+            .header("X-API-Key", api_key)
+            .send()
+    };
+    assert_eq!(search("student-key").await.unwrap().status(), 200);
+    assert_eq!(search("student-key").await.unwrap().status(), 429);
+
+    let usage_url = format!("http://localhost:{}/admin/usage", port);
+    let response = client
+        .get(&usage_url)
+        .header("X-API-Key", "student-key")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 403);
+
+    let response = client
+        .get(&usage_url)
+        .header("X-API-Key", "admin-key")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let json: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(json["keys"][1]["name"], "student");
+    assert_eq!(json["keys"][1]["requests_today"], 1);
+    assert_eq!(json["keys"][1]["daily_quota"], 1);
+
+    let misses_url = format!("http://localhost:{}/analytics/misses", port);
+    let response = client
+        .get(&misses_url)
+        .header("X-API-Key", "student-key")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 403);
+    let response = client
+        .get(&misses_url)
+        .header("X-API-Key", "admin-key")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+}
+
+#[tokio::test]
+async fn test_server_primes_without_recording_queries() {
+    let (_temp_dir, port) = setup_test_server().await;
+
+    let client = reqwest::Client::new();
+    let json: serde_json::Value = client
+        .post(format!("http://localhost:{}/admin/prime", port))
+        .json(&serde_json::json!({
+            "queries": ["Haus", "Zzzxq", ""],
+            "mode": "exact",
+            "lang": "de-en"
+        }))
+        .send()
+        .await
+        .expect("Failed to prime")
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(json["primed"], 2);
+    assert_eq!(json["failed"], 0);
+    assert_eq!(json["results"], 1);
+
+    // Priming queries are not analytics
+    let json: serde_json::Value = client
+        .get(format!(
+            "http://localhost:{}/analytics/misses?lang=de-en",
+            port
+        ))
+        .send()
+        .await
+        .expect("Failed to get misses")
+        .json()
+        .await
+        .unwrap();
+    assert!(json["misses"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_server_budget_returns_partial_results() {
+    let (_temp_dir, port) = setup_test_server().await;
+
+    let client = reqwest::Client::new();
+    let search = |budget_ms: u64| {
+        client
+            .get(format!(
+                "http://localhost:{}/search?q=Haus&mode=fuzzy&lang=de-en&budget_ms={}",
+                port, budget_ms
+            ))
+            .send()
+    };
+
+    let json: serde_json::Value = search(10_000).await.unwrap().json().await.unwrap();
+    assert!(json.get("partial").is_none());
+    assert!(json["total_results"].as_u64().unwrap() > 0);
+
+    let json: serde_json::Value = search(0).await.unwrap().json().await.unwrap();
+    assert_eq!(json["partial"], true);
+    assert_eq!(json["total_results"], 0);
+
+    // A search cut short is not a miss
+    let json: serde_json::Value = client
+        .get(format!(
+            "http://localhost:{}/analytics/misses?lang=de-en",
+            port
+        ))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(json["misses"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_server_html_format() {
+    let (_temp_dir, port) = setup_test_server().await;
+
+    let client = reqwest::Client::new();
+    let url = format!(
+        "http://localhost:{}/search?q=Haus&mode=exact&lang=de-en",
+        port
+    );
+    let json: serde_json::Value = client.get(&url).send().await.unwrap().json().await.unwrap();
+    assert!(json["results"][0].get("html").is_none());
+
+    let json: serde_json::Value = client
+        .get(format!("{}&format=html", url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let html = json["results"][0]["html"].as_str().unwrap();
+    assert!(html.starts_with("<article class=\"entry\" lang=\"de\"><header><dfn>"));
+    assert!(html.contains("<li class=\"sense\">house, building</li>"));
 }